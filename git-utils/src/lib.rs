@@ -1,5 +1,6 @@
 use std::error::Error;
-use std::path::Path;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Output};
 
 #[derive(Debug)]
@@ -18,9 +19,9 @@ impl Error for GitError {}
 
 pub struct GitCommand {
     program: String,
-    args: Vec<String>,
+    args: Vec<OsString>,
     env: Vec<(String, String)>,
-    dir: Option<String>,
+    dir: Option<PathBuf>,
 }
 
 #[allow(dead_code)]
@@ -34,14 +35,14 @@ impl GitCommand {
         }
     }
 
-    pub fn arg(mut self, arg: &str) -> Self {
-        self.args.push(arg.to_string());
+    pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
         self
     }
 
-    pub fn args(mut self, args: &[&str]) -> Self {
+    pub fn args<S: AsRef<OsStr>>(mut self, args: &[S]) -> Self {
         for arg in args {
-            self.args.push(arg.to_string());
+            self.args.push(arg.as_ref().to_os_string());
         }
         self
     }
@@ -52,11 +53,12 @@ impl GitCommand {
     }
 
     pub fn dir(mut self, dir: &Path) -> Self {
-        self.dir = Some(dir.to_str().unwrap().to_string());
+        self.dir = Some(dir.to_path_buf());
         self
     }
 
-    pub fn run(&self) -> Result<Output, GitError> {
+    /// Returns the `std::process::Command` this command would run.
+    pub fn to_command(&self) -> Command {
         let mut cmd = Command::new(&self.program);
         cmd.args(&self.args);
         for (key, value) in &self.env {
@@ -65,16 +67,32 @@ impl GitCommand {
         if let Some(ref dir) = self.dir {
             cmd.current_dir(dir);
         }
+        cmd
+    }
+
+    // Arguments for error messages, which need not be valid UTF-8.
+    fn command_args(&self) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
 
-        let output = cmd.output().map_err(|e| GitError {
+    pub fn run(&self) -> Result<Output, GitError> {
+        let output = self.to_command().output().map_err(|e| GitError {
             message: format!("Failed to execute command: {}", e),
-            command_args: Some(self.args.clone()),
+            command_args: Some(self.command_args()),
         })?;
 
         if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut message = format!("Command exited with non-zero status: {}", output.status);
+            if !stderr.trim().is_empty() {
+                message.push_str(&format!(": {}", stderr.trim()));
+            }
             return Err(GitError {
-                message: format!("Command exited with non-zero status: {}", output.status),
-                command_args: Some(self.args.clone()),
+                message,
+                command_args: Some(self.command_args()),
             });
         }
 
@@ -82,24 +100,15 @@ impl GitCommand {
     }
 
     pub fn run_out(&self) -> Result<(), GitError> {
-        let mut cmd = Command::new(&self.program);
-        cmd.args(&self.args);
-        for (key, value) in &self.env {
-            cmd.env(key, value);
-        }
-        if let Some(ref dir) = self.dir {
-            cmd.current_dir(dir);
-        }
-
-        let status = cmd.status().map_err(|e| GitError {
+        let status = self.to_command().status().map_err(|e| GitError {
             message: format!("Failed to execute command: {}", e),
-            command_args: Some(self.args.clone()),
+            command_args: Some(self.command_args()),
         })?;
 
         if !status.success() {
             return Err(GitError {
                 message: format!("Command exited with non-zero status: {}", status),
-                command_args: Some(self.args.clone()),
+                command_args: Some(self.command_args()),
             });
         }
         Ok(())
@@ -109,7 +118,7 @@ impl GitCommand {
         let output = self.run()?;
         let stdout = String::from_utf8(output.stdout).map_err(|e| GitError {
             message: format!("Failed to parse command output: {}", e),
-            command_args: Some(self.args.clone()),
+            command_args: Some(self.command_args()),
         })?;
         Ok(stdout)
     }
@@ -140,16 +149,53 @@ impl GitCommandBuilder {
     }
 
     pub fn git_clone(repo_url: &str, dest: &Path) -> GitCommand {
-        GitCommand::new("git")
-            .arg("clone")
-            .arg(repo_url)
-            .arg(dest.to_str().unwrap())
+        GitCommand::new("git").arg("clone").arg(repo_url).arg(dest)
     }
 
     pub fn git_checkout(branch: &str) -> GitCommand {
         GitCommand::new("git").arg("checkout").arg(branch)
     }
 
+    /// Checks `revision` out as a detached `HEAD`, discarding local
+    /// changes.
+    pub fn git_checkout_detach(revision: &str) -> GitCommand {
+        GitCommand::new("git")
+            .arg("checkout")
+            .arg("--force")
+            .arg("--detach")
+            .arg(revision)
+    }
+
+    /// Fetches `refspec` from `remote` into `FETCH_HEAD`.
+    pub fn git_fetch(remote: &str, refspec: &str) -> GitCommand {
+        GitCommand::new("git").arg("fetch").arg(remote).arg(refspec)
+    }
+
+    /// Prints the commit `revision` points to.
+    pub fn git_rev_parse(revision: &str) -> GitCommand {
+        GitCommand::new("git")
+            .arg("rev-parse")
+            .arg("--verify")
+            .arg(revision)
+    }
+
+    /// Lists the refs of the repository at `url` matching `patterns`.
+    pub fn git_ls_remote(url: &str, patterns: &[&str]) -> GitCommand {
+        GitCommand::new("git")
+            .arg("ls-remote")
+            .arg(url)
+            .args(patterns)
+    }
+
+    /// Prints the branch and changed files in the format of
+    /// `git status --porcelain=v2 --branch`.
+    pub fn git_status_porcelain() -> GitCommand {
+        GitCommand::new("git")
+            .arg("status")
+            .arg("--porcelain=v2")
+            .arg("--branch")
+    }
+
     pub fn git_pull() -> GitCommand {
         GitCommand::new("git").arg("pull")
     }
@@ -157,4 +203,12 @@ impl GitCommandBuilder {
     pub fn git_push(remote: &str, branch: &str) -> GitCommand {
         GitCommand::new("git").arg("push").arg(remote).arg(branch)
     }
+
+    /// Prints the file at `path`, relative to the repository root, as of
+    /// `revision`.
+    pub fn git_show(revision: &str, path: &str) -> GitCommand {
+        GitCommand::new("git")
+            .arg("show")
+            .arg(format!("{}:{}", revision, path))
+    }
 }
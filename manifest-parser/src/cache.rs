@@ -0,0 +1,276 @@
+use crate::include::{FileIncludeResolver, IncludeResolver, IncludedManifest};
+use crate::{Manifest, ParseOptions};
+use log::debug;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Keeps parsed manifests in memory, so that a long-running service
+/// loading the same manifests again and again parses each version once.
+///
+/// Manifests are keyed by their path and content, the parse options and
+/// the include resolver, see [`IncludeResolver::cache_key`], and an entry
+/// is only used while the manifests it includes still have the same
+/// content. The content of the manifests is kept to compare it, and
+/// checking the includes reads them again, but does not parse them. Once
+/// the cache holds its capacity of manifests, the least recently used is
+/// dropped for the next one. Loads share the cached manifest rather than
+/// copy it.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::cache::ManifestCache;
+///
+/// let cache = ManifestCache::new();
+/// let manifest = cache.load("default.xml", None, None)?;
+/// // Parsed again only if default.xml or a manifest it includes changed.
+/// let same = cache.load("default.xml", None, None)?;
+/// assert!(std::sync::Arc::ptr_eq(&manifest, &same));
+/// ```
+pub struct ManifestCache {
+    entries: Mutex<HashMap<Key, Entry>>,
+    capacity: usize,
+    // Ticks on every load, to find the least recently used entry.
+    clock: AtomicU64,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+/// How many manifests a [`ManifestCache`] holds by default.
+pub const DEFAULT_CAPACITY: usize = 64;
+
+impl Default for ManifestCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+// What a manifest was parsed from.
+#[derive(PartialEq, Eq, Hash)]
+struct Key {
+    file_path: String,
+    content: Vec<u8>,
+    default_remote: Option<String>,
+    default_revision: Option<String>,
+    options: ParseOptions,
+    resolver: String,
+}
+
+struct Entry {
+    includes: Vec<Dependency>,
+    manifest: Arc<Manifest>,
+    last_used: u64,
+}
+
+// A manifest read for an `<include>` while parsing, with its content.
+struct Dependency {
+    name: String,
+    parent: String,
+    revision: Option<String>,
+    content: Vec<u8>,
+}
+
+impl ManifestCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache holding at most `capacity` manifests.
+    pub fn with_capacity(capacity: usize) -> Self {
+        ManifestCache {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+            clock: AtomicU64::new(0),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Like [`Manifest::from_file`], returning the cached manifest if the
+    /// file was parsed before with the same content.
+    pub fn load(
+        &self,
+        file_path: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+    ) -> Result<Arc<Manifest>, Box<dyn Error>> {
+        self.load_with_resolver(
+            file_path,
+            default_remote,
+            default_revision,
+            &FileIncludeResolver,
+        )
+    }
+
+    /// Like [`ManifestCache::load`], opening `<include>` elements with
+    /// `resolver`, see [`Manifest::from_file_with_resolver`].
+    pub fn load_with_resolver(
+        &self,
+        file_path: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        resolver: &dyn IncludeResolver,
+    ) -> Result<Arc<Manifest>, Box<dyn Error>> {
+        self.load_with(
+            file_path,
+            default_remote,
+            default_revision,
+            &ParseOptions::default(),
+            resolver,
+        )
+    }
+
+    /// Like [`ManifestCache::load_with_resolver`], parsing according to
+    /// `options`, see [`Manifest::from_file_with`].
+    pub fn load_with(
+        &self,
+        file_path: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        options: &ParseOptions,
+        resolver: &dyn IncludeResolver,
+    ) -> Result<Arc<Manifest>, Box<dyn Error>> {
+        let key = Key {
+            file_path: file_path.to_string(),
+            content: fs::read(file_path)?,
+            default_remote: default_remote.map(String::from),
+            default_revision: default_revision.map(String::from),
+            options: options.clone(),
+            resolver: resolver.cache_key(),
+        };
+        let now = self.clock.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&key) {
+            if unchanged(&entry.includes, resolver) {
+                debug!("Using cached manifest {}", file_path);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                entry.last_used = now;
+                return Ok(Arc::clone(&entry.manifest));
+            }
+        }
+
+        debug!("Parsing manifest {}", file_path);
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let recorder = Recorder {
+            resolver,
+            includes: RefCell::new(Vec::new()),
+        };
+        let manifest = Arc::new(Manifest::from_reader_at(
+            key.content.as_slice(),
+            file_path,
+            default_remote,
+            default_revision,
+            options,
+            &recorder,
+        )?);
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(
+            key,
+            Entry {
+                includes: recorder.includes.into_inner(),
+                manifest: Arc::clone(&manifest),
+                last_used: now,
+            },
+        );
+        while entries.len() > self.capacity {
+            let oldest = entries.values().map(|entry| entry.last_used).min().unwrap();
+            entries.retain(|_, entry| entry.last_used != oldest);
+        }
+        Ok(manifest)
+    }
+
+    /// The number of cached manifests.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every cached manifest.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// How many loads returned a cached manifest.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// How many loads parsed the manifest.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+// Whether every include still has the content it was parsed with.
+fn unchanged(includes: &[Dependency], resolver: &dyn IncludeResolver) -> bool {
+    includes.iter().all(|include| {
+        let included = match &include.revision {
+            Some(revision) => resolver.resolve_revision(&include.name, &include.parent, revision),
+            None => resolver.resolve(&include.name, &include.parent),
+        };
+        let mut content = Vec::new();
+        match included {
+            Ok(mut included) => {
+                included.reader.read_to_end(&mut content).is_ok() && content == include.content
+            }
+            Err(_) => false,
+        }
+    })
+}
+
+// Resolves includes with `resolver`, recording what it read.
+struct Recorder<'a> {
+    resolver: &'a dyn IncludeResolver,
+    includes: RefCell<Vec<Dependency>>,
+}
+
+impl Recorder<'_> {
+    fn record(
+        &self,
+        name: &str,
+        parent: &str,
+        revision: Option<&str>,
+        mut included: IncludedManifest,
+    ) -> Result<IncludedManifest, Box<dyn Error>> {
+        let mut content = Vec::new();
+        included.reader.read_to_end(&mut content)?;
+        self.includes.borrow_mut().push(Dependency {
+            name: name.to_string(),
+            parent: parent.to_string(),
+            revision: revision.map(String::from),
+            content: content.clone(),
+        });
+        Ok(IncludedManifest {
+            location: included.location,
+            reader: Box::new(Cursor::new(content)),
+        })
+    }
+}
+
+impl IncludeResolver for Recorder<'_> {
+    fn resolve(&self, name: &str, parent: &str) -> Result<IncludedManifest, Box<dyn Error>> {
+        self.record(name, parent, None, self.resolver.resolve(name, parent)?)
+    }
+
+    fn resolve_revision(
+        &self,
+        name: &str,
+        parent: &str,
+        revision: &str,
+    ) -> Result<IncludedManifest, Box<dyn Error>> {
+        let included = self.resolver.resolve_revision(name, parent, revision)?;
+        self.record(name, parent, Some(revision), included)
+    }
+
+    fn cache_key(&self) -> String {
+        self.resolver.cache_key()
+    }
+}
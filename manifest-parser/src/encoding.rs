@@ -0,0 +1,96 @@
+use std::borrow::Cow;
+
+// Windows-1252 characters for the bytes 0x80 to 0x9f, which Latin-1 maps to
+// control characters. Unassigned bytes keep their Latin-1 meaning.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// Decodes a manifest document to text.
+///
+/// A byte order mark selects UTF-8 or UTF-16 and is dropped. Otherwise the
+/// `encoding` of the XML declaration is honored: UTF-8, US-ASCII, UTF-16,
+/// ISO-8859-1 and Windows-1252 are supported, and documents without one
+/// are UTF-8. Bytes that are not valid in the encoding, or an unsupported
+/// encoding, are an error unless `lossy` is set, which decodes them as
+/// UTF-8 with replacement characters instead.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::encoding::decode;
+///
+/// let xml = decode(&std::fs::read("legacy.xml")?, false)?;
+/// ```
+pub fn decode(bytes: &[u8], lossy: bool) -> Result<Cow<'_, str>, String> {
+    if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        return utf8(rest, lossy);
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        return utf16(rest, u16::from_le_bytes, lossy);
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        return utf16(rest, u16::from_be_bytes, lossy);
+    }
+    // "<?" without a byte order mark.
+    if bytes.starts_with(b"<\0?\0") {
+        return utf16(bytes, u16::from_le_bytes, lossy);
+    }
+    if bytes.starts_with(b"\0<\0?") {
+        return utf16(bytes, u16::from_be_bytes, lossy);
+    }
+
+    let encoding = declared_encoding(bytes).map(|encoding| encoding.to_ascii_lowercase());
+    match encoding.as_deref() {
+        None | Some("utf-8" | "utf8") => utf8(bytes, lossy),
+        Some("us-ascii" | "ascii") if bytes.is_ascii() => utf8(bytes, lossy),
+        Some("iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" | "l1") => {
+            Ok(Cow::Owned(bytes.iter().map(|&b| b as char).collect()))
+        }
+        Some("windows-1252" | "cp1252") => Ok(Cow::Owned(
+            bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9f => WINDOWS_1252[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect(),
+        )),
+        Some(_) if lossy => Ok(String::from_utf8_lossy(bytes)),
+        Some("us-ascii" | "ascii") => Err("Document declared as US-ASCII is not ASCII".to_string()),
+        Some(encoding) => Err(format!("Unsupported encoding '{}'", encoding)),
+    }
+}
+
+// The `encoding` of the XML declaration at the start of `bytes`.
+fn declared_encoding(bytes: &[u8]) -> Option<&str> {
+    let declaration = bytes.strip_prefix(b"<?xml")?;
+    let end = declaration.windows(2).position(|w| w == b"?>")?;
+    let declaration = std::str::from_utf8(&declaration[..end]).ok()?;
+    let (_, rest) = declaration.split_once("encoding")?;
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let rest = &rest[1..];
+    rest.find(quote).map(|end| &rest[..end])
+}
+
+fn utf8(bytes: &[u8], lossy: bool) -> Result<Cow<'_, str>, String> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(Cow::Borrowed(text)),
+        Err(_) if lossy => Ok(String::from_utf8_lossy(bytes)),
+        Err(e) => Err(format!("Document is not valid UTF-8: {}", e)),
+    }
+}
+
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16, lossy: bool) -> Result<Cow<'_, str>, String> {
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| unit([pair[0], pair.get(1).copied().unwrap_or(0)]))
+        .collect();
+    match String::from_utf16(&units) {
+        Ok(text) => Ok(Cow::Owned(text)),
+        Err(_) if lossy => Ok(Cow::Owned(String::from_utf16_lossy(&units))),
+        Err(e) => Err(format!("Document is not valid UTF-16: {}", e)),
+    }
+}
@@ -0,0 +1,54 @@
+use crate::writer::Element;
+use crate::Manifest;
+
+impl Manifest {
+    /// Returns a self-contained copy of the manifest, for consumers that do
+    /// not support `<include>`.
+    ///
+    /// The elements of included files are already part of a parsed
+    /// manifest, so only the `<include>` elements are dropped. Where each
+    /// element came from stays in [`Manifest::provenance`] and can be
+    /// written out as comments with
+    /// [`WriteOptions::provenance`](crate::writer::WriteOptions::provenance).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::writer::WriteOptions;
+    /// use manifest_parser::Manifest;
+    ///
+    /// let flat = Manifest::from_file("default.xml", None, None)?.flatten();
+    /// std::fs::write("flat.xml", flat.to_xml_with(&WriteOptions::new().provenance(true)))?;
+    /// ```
+    pub fn flatten(&self) -> Manifest {
+        let mut flat = self.clone();
+        flat.includes.clear();
+        flat.provenance.retain(|p| p.element != "include");
+        flat
+    }
+
+    /// The file the element `element` named `name` was read from, e.g.
+    /// `source_of("project", "platform/core")`. Later definitions win, like
+    /// they do with [`DuplicatePolicy::Override`](crate::DuplicatePolicy).
+    pub fn source_of(&self, element: &str, name: &str) -> Option<&str> {
+        self.provenance
+            .iter()
+            .rev()
+            .find(|p| p.element == element && p.name.as_deref() == Some(name))
+            .map(|p| &*p.file)
+    }
+
+    pub(crate) fn source_of_element(&self, element: &Element) -> Option<&str> {
+        let name = element.attribute("name");
+        let path = element.attribute("path");
+        self.provenance
+            .iter()
+            .rev()
+            .find(|p| {
+                p.element == element.name
+                    && p.name.as_deref() == name
+                    && (element.name != "project" || p.path.as_deref() == path)
+            })
+            .map(|p| &*p.file)
+    }
+}
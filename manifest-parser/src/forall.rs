@@ -0,0 +1,145 @@
+use crate::query::ResolvedProject;
+use crate::subset::Selection;
+use crate::sync::load_and_merge_manifests;
+use log::debug;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc;
+use threadpool::ThreadPool;
+
+/// Options of [`forall`].
+#[derive(Debug, Clone, Default)]
+pub struct ForallOptions {
+    /// The projects to run the command in, all of them if `None`.
+    pub selection: Option<Selection>,
+    /// How many projects to run the command in at once, as many as there
+    /// are CPUs if `None`.
+    pub jobs: Option<usize>,
+}
+
+/// The outcome of running the command of [`forall`] in a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForallResult {
+    pub name: String,
+    /// Checkout path, relative to the target directory.
+    pub path: String,
+    /// Exit code of the command, `None` if it did not run or was killed
+    /// by a signal.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Why the command did not run, e.g. the project is not checked out.
+    pub error: Option<String>,
+}
+
+impl ForallResult {
+    /// Whether the command ran and exited with 0.
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Runs `command` with `sh -c` in the checkout of every selected project of
+/// the manifest at `manifest_path` in `target_dir`, like `repo forall -c`,
+/// and returns the results in manifest order. Local manifests are merged
+/// like [`sync_repos`](crate::sync::sync_repos) does.
+///
+/// The command gets the variables repo sets: `REPO_PROJECT`, `REPO_PATH`,
+/// `REPO_REMOTE`, `REPO_RREV` (the revision of the manifest),
+/// `REPO_UPSTREAM`, `REPO_DEST_BRANCH`, `REPO_I` (from 1) and
+/// `REPO_COUNT`. Fails if the manifest cannot be loaded or a project
+/// cannot be selected or resolved; failures of the command are in the
+/// results.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::forall::{forall, ForallOptions};
+///
+/// let results = forall(manifest_path, ".", "git log -1 --oneline", &ForallOptions::default())?;
+/// for result in results.iter().filter(|result| !result.success()) {
+///     eprintln!("{}: {}", result.path, result.stderr);
+/// }
+/// ```
+pub fn forall(
+    manifest_path: &str,
+    target_dir: &str,
+    command: &str,
+    options: &ForallOptions,
+) -> Result<Vec<ForallResult>, Box<dyn Error>> {
+    let mut manifest = load_and_merge_manifests(manifest_path, None)?;
+    if let Some(selection) = &options.selection {
+        manifest = manifest.subset(selection)?;
+    }
+    let projects = manifest.resolve()?;
+    let count = projects.len();
+    let jobs = options
+        .jobs
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(4, |n| n.get()))
+        .max(1);
+    let pool = ThreadPool::new(jobs);
+    let (sender, receiver) = mpsc::channel();
+
+    for (index, project) in projects.into_iter().enumerate() {
+        let checkout = Path::new(target_dir).join(&project.path);
+        let command = command.to_string();
+        let sender = sender.clone();
+        pool.execute(move || {
+            let result = run_in_project(&project, &checkout, &command, index + 1, count);
+            let _ = sender.send((index, result));
+        });
+    }
+    drop(sender);
+
+    let mut results: Vec<_> = receiver.iter().collect();
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+fn run_in_project(
+    project: &ResolvedProject,
+    checkout: &Path,
+    command: &str,
+    index: usize,
+    count: usize,
+) -> ForallResult {
+    let mut result = ForallResult {
+        name: project.name.clone(),
+        path: project.path.clone(),
+        ..ForallResult::default()
+    };
+    if !checkout.is_dir() {
+        result.error = Some(format!("Project '{}' is not checked out", project.name));
+        return result;
+    }
+    debug!("Running '{}' in {}", command, project.path);
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(checkout)
+        .env("REPO_PROJECT", &project.name)
+        .env("REPO_PATH", &project.path)
+        .env("REPO_REMOTE", &project.git_remote)
+        .env("REPO_RREV", &project.revision)
+        .env(
+            "REPO_UPSTREAM",
+            project.upstream.as_deref().unwrap_or_default(),
+        )
+        .env(
+            "REPO_DEST_BRANCH",
+            project.dest_branch.as_deref().unwrap_or_default(),
+        )
+        .env("REPO_I", index.to_string())
+        .env("REPO_COUNT", count.to_string())
+        .output();
+    match output {
+        Ok(output) => {
+            result.exit_code = output.status.code();
+            result.stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+            result.stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        }
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    result
+}
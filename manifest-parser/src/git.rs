@@ -0,0 +1,98 @@
+use crate::include::GitIncludeResolver;
+use crate::Manifest;
+use git_utils::GitCommandBuilder;
+use log::debug;
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+impl Manifest {
+    /// Reads the manifest `file_name` from the branch `branch` of the
+    /// manifest repository at `url`, like `repo init -u url -b branch -m
+    /// file_name` does.
+    ///
+    /// The repository is cloned into a cache directory below
+    /// `$XDG_CACHE_HOME/gbsw/manifests`, or `~/.cache/gbsw/manifests`, and
+    /// fetched again on later calls. Includes are read from the repository
+    /// as well, see [`GitIncludeResolver`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_git("https://review.tizen.org/git/scm/manifest", "tizen", "default.xml")?;
+    /// ```
+    pub fn from_git(url: &str, branch: &str, file_name: &str) -> Result<Manifest, Box<dyn Error>> {
+        Self::from_git_with_cache(url, branch, file_name, &default_cache_dir()?)
+    }
+
+    /// Like [`Manifest::from_git`], keeping the clone below `cache_dir`.
+    ///
+    /// Calls sharing a clone, in this or other processes, wait for each
+    /// other, so that a manifest is not read while another call checks a
+    /// different branch out.
+    pub fn from_git_with_cache(
+        url: &str,
+        branch: &str,
+        file_name: &str,
+        cache_dir: &Path,
+    ) -> Result<Manifest, Box<dyn Error>> {
+        let name = cache_name(url);
+        let repo = cache_dir.join(&name);
+        std::fs::create_dir_all(cache_dir)?;
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(cache_dir.join(format!("{}.lock", name)))?;
+        lock.lock()?;
+
+        if repo.join(".git").exists() {
+            debug!("Using cached manifest repository {}", repo.display());
+        } else {
+            debug!("Cloning manifest repository {} to {}", url, repo.display());
+            GitCommandBuilder::git_clone(url, &repo)
+                .arg("--no-checkout")
+                .run()
+                .map_err(|e| format!("Cannot clone '{}': {}", url, e))?;
+        }
+        GitCommandBuilder::git_fetch(url, branch)
+            .dir(&repo)
+            .run()
+            .map_err(|e| format!("Cannot fetch '{}' from '{}': {}", branch, url, e))?;
+        GitCommandBuilder::git_checkout_detach("FETCH_HEAD")
+            .dir(&repo)
+            .run()
+            .map_err(|e| format!("Cannot check '{}' of '{}' out: {}", branch, url, e))?;
+
+        let path = repo.join(file_name);
+        let path = path.to_str().ok_or("Cache directory is not valid UTF-8")?;
+        Manifest::from_file_with_resolver(path, None, None, &GitIncludeResolver::new(&repo))
+    }
+}
+
+fn default_cache_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let cache = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").ok_or("Neither XDG_CACHE_HOME nor HOME is set")?)
+            .join(".cache"),
+    };
+    Ok(cache.join("gbsw").join("manifests"))
+}
+
+// A directory name for the clone of `url`, e.g. `review.tizen.org_git_manifest`.
+fn cache_name(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
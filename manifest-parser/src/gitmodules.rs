@@ -0,0 +1,239 @@
+use crate::{Default, Manifest, Project, Remote};
+use git_utils::GitCommand;
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+// The remote of the superproject, which relative submodule URLs refer to.
+const BASE_REMOTE_NAME: &str = "origin";
+
+#[derive(std::default::Default)]
+struct Submodule {
+    name: String,
+    path: Option<String>,
+    url: Option<String>,
+    branch: Option<String>,
+}
+
+impl Manifest {
+    /// Turns the submodules of the superproject at `path` into a manifest,
+    /// to move a workspace from submodules to repo.
+    ///
+    /// Every submodule becomes a project checked out at its submodule path
+    /// and pinned to the commit recorded in the index of the superproject,
+    /// with the branch it tracks, if any, kept in `upstream`. `base_remote`
+    /// is the URL the superproject's repository lives under, e.g.
+    /// `https://github.com/org`. It becomes the `origin` remote, which
+    /// relative submodule URLs and URLs below it are mapped to. Other hosts
+    /// get a remote of their own.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_gitmodules("/src/firmware", "https://github.com/org")?;
+    /// manifest.write_to_file("default.xml")?;
+    /// ```
+    pub fn from_gitmodules(path: &str, base_remote: &str) -> Result<Manifest, Box<dyn Error>> {
+        let base_remote = base_remote.trim_end_matches('/');
+        let submodules = read_gitmodules(Path::new(path))?;
+        let pinned = read_gitlinks(Path::new(path))?;
+
+        let mut manifest = Manifest::new();
+        manifest.remotes.push(remote(BASE_REMOTE_NAME, base_remote));
+        manifest.default = Some(Default {
+            remote: Some(BASE_REMOTE_NAME.into()),
+            revision: None,
+            dest_branch: None,
+            upstream: None,
+            sync_j: None,
+            sync_c: None,
+            sync_s: None,
+            sync_tags: None,
+        });
+
+        for submodule in submodules {
+            let submodule_path = submodule
+                .path
+                .ok_or_else(|| format!("Submodule '{}' has no path", submodule.name))?;
+            let url = submodule
+                .url
+                .ok_or_else(|| format!("Submodule '{}' has no url", submodule.name))?;
+            let sha = pinned.get(&submodule_path).ok_or_else(|| {
+                format!(
+                    "Submodule '{}' is not in the index of '{}'",
+                    submodule.name, path
+                )
+            })?;
+
+            let url = resolve_url(base_remote, &url)?;
+            let (remote_name, name) = match url.strip_prefix(&format!("{}/", base_remote)) {
+                Some(name) => (BASE_REMOTE_NAME.to_string(), name.to_string()),
+                None => {
+                    let (fetch, name) = url
+                        .rsplit_once('/')
+                        .ok_or_else(|| format!("Invalid url '{}' of '{}'", url, submodule.name))?;
+                    (add_remote(&mut manifest.remotes, fetch), name.to_string())
+                }
+            };
+            let name = name.trim_end_matches(".git").to_string();
+            debug!("Submodule {} pinned to {}", submodule_path, sha);
+
+            manifest.projects.push(Project {
+                path: (submodule_path != name).then_some(submodule_path),
+                name,
+                remote: (remote_name != BASE_REMOTE_NAME).then(|| remote_name.into()),
+                revision: Some(sha.as_str().into()),
+                dest_branch: None,
+                groups: Vec::new(),
+                sync_c: None,
+                sync_s: None,
+                sync_tags: None,
+                // "." follows the branch of the superproject, which repo does
+                // by default.
+                upstream: submodule
+                    .branch
+                    .filter(|branch| branch != ".")
+                    .map(Into::into),
+                clone_depth: None,
+                force_path: None,
+                copyfiles: Vec::new(),
+                linkfiles: Vec::new(),
+                annotations: Vec::new(),
+                source: None,
+            });
+        }
+        Ok(manifest)
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = GitCommand::new("git")
+        .args(args)
+        .dir(dir)
+        .run()
+        .map_err(|e| {
+            format!(
+                "git {} failed in '{}': {}",
+                args.join(" "),
+                dir.display(),
+                e
+            )
+        })?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Reads the submodules in the order `.gitmodules` lists them.
+fn read_gitmodules(dir: &Path) -> Result<Vec<Submodule>, Box<dyn Error>> {
+    let config = git(
+        dir,
+        &["config", "--file", ".gitmodules", "--null", "--list"],
+    )?;
+    let mut submodules: Vec<Submodule> = Vec::new();
+    for entry in config.split('\0').filter(|entry| !entry.is_empty()) {
+        let (key, value) = entry.split_once('\n').unwrap_or((entry, ""));
+        let Some((name, variable)) = key
+            .strip_prefix("submodule.")
+            .and_then(|key| key.rsplit_once('.'))
+        else {
+            continue;
+        };
+        let index = match submodules.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                submodules.push(Submodule {
+                    name: name.to_string(),
+                    ..Submodule::default()
+                });
+                submodules.len() - 1
+            }
+        };
+        let submodule = &mut submodules[index];
+        match variable {
+            "path" => submodule.path = Some(value.to_string()),
+            "url" => submodule.url = Some(value.to_string()),
+            "branch" => submodule.branch = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    Ok(submodules)
+}
+
+// Maps the paths of the submodules in the index to their commits.
+fn read_gitlinks(dir: &Path) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let stage = git(dir, &["ls-files", "--stage", "-z"])?;
+    Ok(stage
+        .split('\0')
+        .filter_map(|entry| {
+            let (info, path) = entry.split_once('\t')?;
+            let mut fields = info.split(' ');
+            let mode = fields.next()?;
+            let sha = fields.next()?;
+            (mode == "160000").then(|| (path.to_string(), sha.to_string()))
+        })
+        .collect())
+}
+
+// Resolves a URL relative to the superproject, which lives right below
+// `base_remote`, like git does.
+fn resolve_url(base_remote: &str, url: &str) -> Result<String, Box<dyn Error>> {
+    if !url.starts_with("./") && !url.starts_with("../") {
+        return Ok(url.to_string());
+    }
+    let mut resolved = base_remote.to_string();
+    let mut rest = url;
+    loop {
+        if let Some(r) = rest.strip_prefix("./") {
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("../") {
+            // One `..` leaves the superproject, every further one goes up
+            // from `base_remote`.
+            if r.starts_with("../") {
+                let (parent, _) = resolved
+                    .rsplit_once('/')
+                    .filter(|(parent, _)| !parent.ends_with('/') && !parent.is_empty())
+                    .ok_or_else(|| format!("Url '{}' leaves '{}'", url, base_remote))?;
+                resolved = parent.to_string();
+            }
+            rest = r;
+        } else {
+            break;
+        }
+    }
+    Ok(format!("{}/{}", resolved, rest))
+}
+
+// Finds or adds the remote fetching from `fetch`, named after its host.
+pub(crate) fn add_remote(remotes: &mut Vec<Remote>, fetch: &str) -> String {
+    if let Some(remote) = remotes.iter().find(|remote| remote.fetch == fetch) {
+        return remote.name.clone();
+    }
+    let without_scheme = fetch.split_once("://").map_or(fetch, |(_, rest)| rest);
+    let host = without_scheme
+        .split(['/', ':'])
+        .next()
+        .unwrap_or(without_scheme);
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let mut name = host.to_string();
+    let mut suffix = 1;
+    while remotes.iter().any(|remote| remote.name == name) {
+        suffix += 1;
+        name = format!("{}-{}", host, suffix);
+    }
+    remotes.push(remote(&name, fetch));
+    name
+}
+
+fn remote(name: &str, fetch: &str) -> Remote {
+    Remote {
+        name: name.to_string(),
+        alias: None,
+        fetch: fetch.to_string(),
+        pushurl: None,
+        review: None,
+        revision: None,
+        source: None,
+    }
+}
@@ -0,0 +1,145 @@
+use crate::intern::StringTable;
+use crate::Project;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A group a project belongs to, as listed in its `groups` attribute.
+///
+/// Besides the groups it lists, repo puts every project into `all`,
+/// `name:<name>`, `path:<path>`, and `default` unless it lists
+/// `notdefault`, see [`Project::effective_groups`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Group {
+    /// `all`, which every project is in.
+    All,
+    /// `default`, which the projects not in `notdefault` are in.
+    Default,
+    /// `notdefault`, for projects only synced when asked for.
+    NotDefault,
+    /// `name:<name>`, the project with that name.
+    Name(String),
+    /// `path:<path>`, the project checked out at that path.
+    Path(String),
+    /// `profile:<profile>`, see [`Manifest::for_profile`](crate::Manifest::for_profile).
+    Profile(Arc<str>),
+    /// Any other group.
+    Named(Arc<str>),
+}
+
+impl FromStr for Group {
+    type Err = std::convert::Infallible;
+
+    fn from_str(group: &str) -> Result<Self, Self::Err> {
+        Ok(match group {
+            "all" => Group::All,
+            "default" => Group::Default,
+            "notdefault" => Group::NotDefault,
+            _ => match group.split_once(':') {
+                Some(("name", name)) => Group::Name(name.to_string()),
+                Some(("path", path)) => Group::Path(path.to_string()),
+                Some(("profile", profile)) => Group::Profile(profile.into()),
+                _ => Group::Named(group.into()),
+            },
+        })
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Group::All => write!(f, "all"),
+            Group::Default => write!(f, "default"),
+            Group::NotDefault => write!(f, "notdefault"),
+            Group::Name(name) => write!(f, "name:{}", name),
+            Group::Path(path) => write!(f, "path:{}", path),
+            Group::Profile(profile) => write!(f, "profile:{}", profile),
+            Group::Named(group) => write!(f, "{}", group),
+        }
+    }
+}
+
+/// Parses a `groups` attribute, whose groups are separated by commas or
+/// whitespace.
+pub fn parse_groups(groups: &str) -> Vec<Group> {
+    groups
+        .split([',', ' ', '\t', '\n'])
+        .filter(|group| !group.is_empty())
+        .map(|group| group.parse().unwrap())
+        .collect()
+}
+
+// Like `parse_groups`, sharing the names of groups with the other projects
+// of the manifest through `strings`.
+pub(crate) fn parse_groups_interned(groups: &str, strings: &StringTable) -> Vec<Group> {
+    parse_groups(groups)
+        .into_iter()
+        .map(|group| match group {
+            Group::Profile(profile) => Group::Profile(strings.intern(&profile)),
+            Group::Named(group) => Group::Named(strings.intern(&group)),
+            group => group,
+        })
+        .collect()
+}
+
+/// Formats `groups` as a `groups` attribute.
+pub fn format_groups(groups: &[Group]) -> String {
+    groups
+        .iter()
+        .map(Group::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl Project {
+    /// The groups the project lists, followed by the ones repo puts every
+    /// project into.
+    pub fn effective_groups(&self) -> Vec<Group> {
+        let mut groups = self.groups.clone();
+        let mut implicit = vec![
+            Group::All,
+            Group::Name(self.name.clone()),
+            Group::Path(self.path.clone().unwrap_or_else(|| self.name.clone())),
+        ];
+        if !self.groups.contains(&Group::NotDefault) {
+            implicit.push(Group::Default);
+        }
+        for group in implicit {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    /// Whether the project is in `group`, counting the implicit groups.
+    pub fn in_group(&self, group: &Group) -> bool {
+        self.effective_groups().contains(group)
+    }
+
+    /// Whether the project is selected by a group filter like the one of
+    /// `repo sync -g`, e.g. `default,-tools,name:platform/tools`.
+    ///
+    /// The project is selected by the last entry it is in: groups select
+    /// their projects and groups prefixed with `-` deselect them. An empty
+    /// filter is `default`.
+    pub fn matches_groups(&self, filter: &str) -> bool {
+        let groups = self.effective_groups();
+        let mut filter: Vec<&str> = filter
+            .split([',', ' '])
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        if filter.is_empty() {
+            filter.push("default");
+        }
+        let mut matched = false;
+        for entry in filter {
+            match entry.strip_prefix('-') {
+                Some(group) if groups.contains(&group.parse().unwrap()) => matched = false,
+                None if groups.contains(&entry.parse().unwrap()) => matched = true,
+                _ => (),
+            }
+        }
+        matched
+    }
+}
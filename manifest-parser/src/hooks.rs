@@ -0,0 +1,133 @@
+use crate::Manifest;
+use log::debug;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+/// A hook listed in the `enabled-list` of `<repo-hooks>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HookName {
+    /// `pre-upload`, run before changes are uploaded for review.
+    PreUpload,
+    /// Any other hook.
+    Other(String),
+}
+
+impl FromStr for HookName {
+    type Err = std::convert::Infallible;
+
+    fn from_str(hook: &str) -> Result<Self, Self::Err> {
+        Ok(match hook {
+            "pre-upload" => HookName::PreUpload,
+            _ => HookName::Other(hook.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for HookName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookName::PreUpload => write!(f, "pre-upload"),
+            HookName::Other(hook) => write!(f, "{}", hook),
+        }
+    }
+}
+
+/// Parses an `enabled-list` attribute, whose hooks are separated by commas
+/// or whitespace.
+pub fn parse_hooks(hooks: &str) -> Vec<HookName> {
+    hooks
+        .split([',', ' ', '\t', '\n'])
+        .filter(|hook| !hook.is_empty())
+        .map(|hook| hook.parse().unwrap())
+        .collect()
+}
+
+/// Formats `hooks` as an `enabled-list` attribute.
+pub fn format_hooks(hooks: &[HookName]) -> String {
+    hooks
+        .iter()
+        .map(HookName::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Calls the `main` function of the hook script given first, like repo
+// does, with the projects and their checkouts given in pairs after it.
+const HOOK_DRIVER: &str = "\
+import runpy, sys
+args = sys.argv[2:]
+main = runpy.run_path(sys.argv[1])['main']
+sys.exit(main(project_list=args[0::2], worktree_list=args[1::2]) or 0)
+";
+
+/// Runs `hook` of the `<repo-hooks>` of `manifest` in the workspace at
+/// `target_dir`, for `projects`: pairs of project names and checkout
+/// paths, relative to `target_dir`.
+///
+/// Like repo, the hook is the `<hook>.py` script of the hooks project,
+/// whose `main` function is called with `project_list` and
+/// `worktree_list` by `python3`. Returns whether the hook ran: it does not
+/// if the manifest has no `<repo-hooks>`, the hook is not in its
+/// `enabled-list` or the hooks project has no script for it. Fails if the
+/// hooks project is not in the manifest or not synced, or if the hook
+/// fails.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::hooks::{run_repo_hook, HookName};
+///
+/// run_repo_hook(&manifest, Path::new("."), &HookName::PreUpload, &[("platform/core", "core")])?;
+/// ```
+pub fn run_repo_hook(
+    manifest: &Manifest,
+    target_dir: &Path,
+    hook: &HookName,
+    projects: &[(&str, &str)],
+) -> Result<bool, Box<dyn Error>> {
+    let Some(repo_hooks) = &manifest.repo_hooks else {
+        return Ok(false);
+    };
+    if !repo_hooks.enabled_list.contains(hook) {
+        return Ok(false);
+    }
+    let project = manifest
+        .find_project_by_name(&repo_hooks.in_project)
+        .ok_or_else(|| {
+            format!(
+                "Project '{}' of <repo-hooks> is not in the manifest",
+                repo_hooks.in_project
+            )
+        })?;
+    let hooks_dir = target_dir.join(project.path.as_deref().unwrap_or(&project.name));
+    if !hooks_dir.join(".git").exists() {
+        return Err(format!(
+            "Project '{}' of <repo-hooks> is not synced",
+            repo_hooks.in_project
+        )
+        .into());
+    }
+    let script = hooks_dir.join(format!("{}.py", hook));
+    if !script.is_file() {
+        debug!("No script for hook '{}' in {}", hook, hooks_dir.display());
+        return Ok(false);
+    }
+
+    debug!("Running hook '{}'", hook);
+    let output = Command::new("python3")
+        .arg("-c")
+        .arg(HOOK_DRIVER)
+        .arg(&script)
+        .args(projects.iter().flat_map(|(name, path)| [name, path]))
+        .current_dir(target_dir)
+        .output()
+        .map_err(|e| format!("Cannot run hook '{}': {}", hook, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Hook '{}' failed: {}", hook, stderr.trim()).into());
+    }
+    Ok(true)
+}
@@ -0,0 +1,300 @@
+use git_utils::GitCommandBuilder;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::{Component, Path, PathBuf};
+
+/// A manifest opened for an `<include>` element.
+pub struct IncludedManifest {
+    /// Where the manifest was found, passed back to the resolver as the
+    /// parent of the manifests it includes in turn.
+    pub location: String,
+    pub reader: Box<dyn BufRead>,
+}
+
+/// Opens the manifests named by `<include>` elements.
+///
+/// Closures taking the include name and the location of the including
+/// manifest are resolvers too, which is handy for manifests fetched over
+/// HTTP:
+///
+/// ```ignore
+/// use manifest_parser::include::IncludedManifest;
+/// use manifest_parser::Manifest;
+///
+/// let resolver = |name: &str, _parent: &str| {
+///     let location = format!("https://example.com/manifest/{}", name);
+///     let xml = fetch(&location)?;
+///     Ok(IncludedManifest {
+///         location,
+///         reader: Box::new(std::io::Cursor::new(xml)),
+///     })
+/// };
+/// let manifest = Manifest::from_str(&fetch("https://example.com/manifest/default.xml")?, None, None, &resolver)?;
+/// ```
+pub trait IncludeResolver {
+    /// Opens the manifest `name` included from the manifest at `parent`.
+    /// `parent` is empty for a manifest parsed from a string or reader.
+    fn resolve(&self, name: &str, parent: &str) -> Result<IncludedManifest, Box<dyn Error>>;
+
+    /// Opens `name` as of `revision` of the manifest repository, for an
+    /// `<include>` with a `revision`. Fails by default, for resolvers
+    /// without access to the history.
+    fn resolve_revision(
+        &self,
+        name: &str,
+        _parent: &str,
+        revision: &str,
+    ) -> Result<IncludedManifest, Box<dyn Error>> {
+        Err(format!(
+            "Cannot read '{}' at '{}': the include resolver has no access to the manifest history",
+            name, revision
+        )
+        .into())
+    }
+
+    /// Tells resolvers opening different manifests apart, e.g. by the
+    /// directory they read from, for caches of parsed manifests such as
+    /// [`ManifestCache`](crate::cache::ManifestCache). The type name by
+    /// default.
+    fn cache_key(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+impl<F> IncludeResolver for F
+where
+    F: Fn(&str, &str) -> Result<IncludedManifest, Box<dyn Error>>,
+{
+    fn resolve(&self, name: &str, parent: &str) -> Result<IncludedManifest, Box<dyn Error>> {
+        self(name, parent)
+    }
+}
+
+/// Reads included manifests from the directory of the including manifest,
+/// or from the current directory for a manifest without a path. Includes
+/// with a `revision` are read from that revision of the git repository the
+/// directory is in, e.g. `.repo/manifests`, and fail outside of one.
+pub struct FileIncludeResolver;
+
+impl IncludeResolver for FileIncludeResolver {
+    fn resolve(&self, name: &str, parent: &str) -> Result<IncludedManifest, Box<dyn Error>> {
+        let dir = Path::new(parent).parent().unwrap_or(Path::new(""));
+        let path = dir.join(name);
+        let file = File::open(&path)?;
+        Ok(IncludedManifest {
+            location: path.to_string_lossy().into_owned(),
+            reader: Box::new(BufReader::new(file)),
+        })
+    }
+
+    fn resolve_revision(
+        &self,
+        name: &str,
+        parent: &str,
+        revision: &str,
+    ) -> Result<IncludedManifest, Box<dyn Error>> {
+        let dir = match Path::new(parent).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        // `./` makes the path relative to the directory rather than to the
+        // root of the repository.
+        let output = GitCommandBuilder::git_show(revision, &format!("./{}", name))
+            .dir(dir)
+            .run()
+            .map_err(|e| format!("Cannot read '{}' at '{}': {}", name, revision, e))?;
+        Ok(IncludedManifest {
+            location: format!("{}:{}", revision, dir.join(name).display()),
+            reader: Box::new(Cursor::new(output.stdout)),
+        })
+    }
+}
+
+/// Reads included manifests from a manifest git repository, such as
+/// `.repo/manifests`, like repo does: names are relative to the root of the
+/// repository, and includes with a `revision` are read from that revision
+/// instead of the working tree.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::include::GitIncludeResolver;
+/// use manifest_parser::Manifest;
+///
+/// let resolver = GitIncludeResolver::new(".repo/manifests");
+/// let manifest = Manifest::from_file_with_resolver(".repo/manifests/default.xml", None, None, &resolver)?;
+/// ```
+pub struct GitIncludeResolver {
+    repo: PathBuf,
+}
+
+impl GitIncludeResolver {
+    pub fn new<P: Into<PathBuf>>(repo: P) -> Self {
+        GitIncludeResolver { repo: repo.into() }
+    }
+}
+
+impl IncludeResolver for GitIncludeResolver {
+    fn resolve(&self, name: &str, _parent: &str) -> Result<IncludedManifest, Box<dyn Error>> {
+        let path = self.repo.join(name);
+        let file = File::open(&path)?;
+        Ok(IncludedManifest {
+            location: path.to_string_lossy().into_owned(),
+            reader: Box::new(BufReader::new(file)),
+        })
+    }
+
+    fn resolve_revision(
+        &self,
+        name: &str,
+        _parent: &str,
+        revision: &str,
+    ) -> Result<IncludedManifest, Box<dyn Error>> {
+        let output = GitCommandBuilder::git_show(revision, name)
+            .dir(&self.repo)
+            .run()
+            .map_err(|e| format!("Cannot read '{}' at '{}': {}", name, revision, e))?;
+        Ok(IncludedManifest {
+            location: format!("{}:{}", revision, self.repo.join(name).display()),
+            reader: Box::new(Cursor::new(output.stdout)),
+        })
+    }
+
+    fn cache_key(&self) -> String {
+        format!("git:{}", self.repo.display())
+    }
+}
+
+/// Looks included manifests up in a list of directories, and keeps them
+/// from reaching outside those directories.
+///
+/// A name is looked up relative to the including manifest first, then in
+/// the manifest directory given to [`SearchPathResolver::new`], then in the
+/// [search directories](SearchPathResolver::search_dir) in the order they
+/// were added. By default, a name leading outside of all these directories,
+/// e.g. with `../`, is refused, as are absolute names. Paths are compared
+/// as written, symlinks are not followed.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::include::SearchPathResolver;
+/// use manifest_parser::Manifest;
+///
+/// let resolver = SearchPathResolver::new(".repo/manifests").search_dir("/opt/manifests/common");
+/// let manifest = Manifest::from_file_with_resolver(".repo/manifests/default.xml", None, None, &resolver)?;
+/// ```
+pub struct SearchPathResolver {
+    dirs: Vec<PathBuf>,
+    confine: bool,
+    allow_absolute: bool,
+}
+
+impl SearchPathResolver {
+    pub fn new<P: Into<PathBuf>>(manifest_dir: P) -> Self {
+        SearchPathResolver {
+            dirs: vec![manifest_dir.into()],
+            confine: true,
+            allow_absolute: false,
+        }
+    }
+
+    /// Adds a directory to look included manifests up in.
+    pub fn search_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    /// Whether to refuse names leading outside of the manifest and search
+    /// directories, on by default.
+    pub fn confine(mut self, confine: bool) -> Self {
+        self.confine = confine;
+        self
+    }
+
+    /// Whether to accept absolute names, off by default. With
+    /// [`SearchPathResolver::confine`], they must still lie in one of the
+    /// directories.
+    pub fn allow_absolute(mut self, allow_absolute: bool) -> Self {
+        self.allow_absolute = allow_absolute;
+        self
+    }
+
+    fn is_allowed(&self, path: &Path) -> bool {
+        !self.confine || self.dirs.iter().any(|dir| path.starts_with(normalize(dir)))
+    }
+}
+
+impl IncludeResolver for SearchPathResolver {
+    fn resolve(&self, name: &str, parent: &str) -> Result<IncludedManifest, Box<dyn Error>> {
+        let candidates: Vec<PathBuf> = if Path::new(name).is_absolute() {
+            if !self.allow_absolute {
+                return Err(format!("Absolute include '{}' is not allowed", name).into());
+            }
+            vec![PathBuf::from(name)]
+        } else {
+            let parent_dir = Path::new(parent).parent().unwrap_or(Path::new(""));
+            std::iter::once(parent_dir)
+                .chain(self.dirs.iter().map(PathBuf::as_path))
+                .map(|dir| normalize(&dir.join(name)))
+                .collect()
+        };
+
+        let mut allowed = candidates
+            .into_iter()
+            .filter(|path| self.is_allowed(path))
+            .peekable();
+        if allowed.peek().is_none() {
+            return Err(format!(
+                "Include '{}' leads outside of the manifest directories",
+                name
+            )
+            .into());
+        }
+        for path in allowed {
+            if let Ok(file) = File::open(&path) {
+                return Ok(IncludedManifest {
+                    location: path.to_string_lossy().into_owned(),
+                    reader: Box::new(BufReader::new(file)),
+                });
+            }
+        }
+        Err(format!("Include '{}' not found in the manifest directories", name).into())
+    }
+
+    fn cache_key(&self) -> String {
+        format!(
+            "search:{:?}:{}:{}",
+            self.dirs, self.confine, self.allow_absolute
+        )
+    }
+}
+
+// Resolves `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => (),
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Refuses every include, for manifests that must be self-contained.
+pub struct NoIncludes;
+
+impl IncludeResolver for NoIncludes {
+    fn resolve(&self, name: &str, _parent: &str) -> Result<IncludedManifest, Box<dyn Error>> {
+        Err(format!("Includes are not supported, cannot include '{}'", name).into())
+    }
+}
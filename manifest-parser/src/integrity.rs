@@ -0,0 +1,139 @@
+use crate::pin::is_commit_sha;
+use crate::query::ResolvedProject;
+use crate::sync::load_and_merge_manifests;
+use git_utils::GitCommand;
+use std::error::Error;
+use std::path::Path;
+use std::sync::mpsc;
+use threadpool::ThreadPool;
+
+/// The integrity of the checkout of a project, see [`verify`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectIntegrity {
+    pub name: String,
+    /// Checkout path, relative to the target directory.
+    pub path: String,
+    /// Whether the checkout exists. It has no problems if not.
+    pub checked_out: bool,
+    /// The commit HEAD resolves to, `None` if it does not.
+    pub head: Option<String>,
+    /// What is wrong with the checkout, e.g. the errors of `git fsck`.
+    pub problems: Vec<String>,
+}
+
+impl ProjectIntegrity {
+    /// Whether the checkout has problems, and should be cloned again.
+    pub fn is_corrupt(&self) -> bool {
+        !self.problems.is_empty()
+    }
+}
+
+/// Checks the checkouts of all projects of the manifest at
+/// `manifest_path` in `target_dir`, e.g. after a sync, for corrupt
+/// repositories to clone again.
+///
+/// The HEAD of every checkout must resolve to a commit, the revision of
+/// the manifest if it is pinned to one. With `fsck`, the objects of the
+/// repositories are checked too, with `git fsck --no-dangling`, which
+/// reads all of them. Local manifests are merged like
+/// [`sync_repos`](crate::sync::sync_repos) does.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::integrity::verify;
+///
+/// for project in verify(".repo/manifests/default.xml", ".", true)? {
+///     if project.is_corrupt() {
+///         println!("{}: {}", project.path, project.problems.join("; "));
+///     }
+/// }
+/// ```
+pub fn verify(
+    manifest_path: &str,
+    target_dir: &str,
+    fsck: bool,
+) -> Result<Vec<ProjectIntegrity>, Box<dyn Error>> {
+    let manifest = load_and_merge_manifests(manifest_path, None)?;
+    let jobs = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let pool = ThreadPool::new(jobs);
+    let (sender, receiver) = mpsc::channel();
+
+    for (index, project) in manifest.projects.iter().enumerate() {
+        let name = project.name.clone();
+        let path = project.path.clone().unwrap_or_else(|| name.clone());
+        let checkout = Path::new(target_dir).join(&path);
+        // Unresolved projects are not synced, so only checked for
+        // corruption.
+        let pinned = ResolvedProject::from_project(&manifest, project)
+            .ok()
+            .map(|resolved| resolved.revision)
+            .filter(|revision| is_commit_sha(revision));
+        let sender = sender.clone();
+        pool.execute(move || {
+            let integrity = project_integrity(name, path, &checkout, pinned, fsck);
+            let _ = sender.send((index, integrity));
+        });
+    }
+    drop(sender);
+
+    let mut integrities: Vec<_> = receiver.iter().collect();
+    integrities.sort_by_key(|(index, _)| *index);
+    Ok(integrities
+        .into_iter()
+        .map(|(_, integrity)| integrity)
+        .collect())
+}
+
+fn project_integrity(
+    name: String,
+    path: String,
+    checkout: &Path,
+    pinned: Option<String>,
+    fsck: bool,
+) -> ProjectIntegrity {
+    let mut integrity = ProjectIntegrity {
+        name,
+        path,
+        ..ProjectIntegrity::default()
+    };
+    if !checkout.join(".git").exists() {
+        return integrity;
+    }
+    integrity.checked_out = true;
+
+    match git(
+        checkout,
+        &["rev-parse", "--verify", "--quiet", "HEAD^{commit}"],
+    ) {
+        Ok(head) => {
+            if let Some(pinned) = pinned.filter(|pinned| *pinned != head) {
+                integrity.problems.push(format!(
+                    "HEAD is at {}, not at the pinned revision {}",
+                    head, pinned
+                ));
+            }
+            integrity.head = Some(head);
+        }
+        Err(e) => integrity
+            .problems
+            .push(format!("HEAD does not resolve to a commit: {}", e)),
+    }
+    if fsck {
+        if let Err(e) = git(checkout, &["fsck", "--no-dangling", "--no-progress"]) {
+            integrity.problems.push(format!("git fsck failed: {}", e));
+        }
+    }
+    integrity
+}
+
+// Runs git in `checkout`, returning its trimmed output, or its exit status
+// and error messages if it fails.
+fn git(checkout: &Path, args: &[&str]) -> Result<String, String> {
+    let output = GitCommand::new("git")
+        .args(args)
+        .dir(checkout)
+        .run()
+        .map_err(|e| e.message)?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,23 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Shares the strings repeated across the projects of a manifest, such as
+/// remote names, revisions, groups and the file they were read from, so
+/// that manifests with thousands of projects allocate each once and clone
+/// them by reference count.
+#[derive(Debug, Default)]
+pub(crate) struct StringTable(RefCell<HashSet<Arc<str>>>);
+
+impl StringTable {
+    /// The shared copy of `value`, added to the table the first time.
+    pub(crate) fn intern(&self, value: &str) -> Arc<str> {
+        let mut strings = self.0.borrow_mut();
+        if let Some(interned) = strings.get(value) {
+            return Arc::clone(interned);
+        }
+        let interned: Arc<str> = Arc::from(value);
+        strings.insert(Arc::clone(&interned));
+        interned
+    }
+}
@@ -1,10 +1,61 @@
+use groups::{parse_groups_interned, Group};
+use hooks::{parse_hooks, HookName};
+use include::{FileIncludeResolver, IncludeResolver};
+use intern::StringTable;
+use log::{debug, warn};
 use quick_xml::events::Event;
 use quick_xml::name::QName;
 use quick_xml::Reader;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::Arc;
 
+pub mod cache;
+pub mod encoding;
+pub mod flatten;
+#[cfg(feature = "sync")]
+pub mod forall;
+pub mod git;
+pub mod gitmodules;
+pub mod groups;
+pub mod hooks;
+pub mod include;
+#[cfg(feature = "sync")]
+pub mod integrity;
+mod intern;
+mod link;
+pub mod lint;
+pub mod merge;
+pub mod normalize;
+pub mod patch;
+pub mod pin;
+pub mod profile;
+#[cfg(feature = "progress")]
+pub mod progress;
+pub mod query;
+pub mod relocate;
+pub mod rename;
+pub mod revision;
+pub mod rewrite;
+pub mod schema;
+pub mod stats;
+#[cfg(feature = "sync")]
+pub mod status;
+pub mod submanifest;
+pub mod subset;
+#[cfg(feature = "sync")]
 pub mod sync;
+#[cfg(feature = "async")]
+pub mod sync_async;
+pub mod tizen;
+#[cfg(feature = "sync")]
+pub mod upload;
+pub mod urls;
+pub mod validate;
+pub mod view;
+pub mod west;
+pub mod workspace;
+pub mod writer;
 
 /// A struct representing a repo manifest.
 ///
@@ -54,6 +105,179 @@ pub struct Manifest {
     pub contactinfo: Option<ContactInfo>,
     /// This element provides the capability of including another manifest file.
     pub includes: Vec<Include>,
+    /// The file each top-level element was read from, in reading order.
+    pub provenance: Vec<Provenance>,
+    /// Comments kept with [`ParseOptions::comments`], written back before
+    /// the elements they belong to.
+    pub comments: Vec<Comment>,
+}
+
+/// What to do when a project with the same name and path is defined twice,
+/// e.g. once in the manifest and again in a file it includes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, std::default::Default)]
+pub enum DuplicatePolicy {
+    /// Fail, like `repo` does.
+    #[default]
+    Error,
+    /// Replace the earlier definition with the later one.
+    Override,
+    /// Keep the earlier definition and drop the later one.
+    Keep,
+}
+
+/// What to do with elements and attributes the manifest format does not
+/// define, e.g. a `sync_s` attribute meant to be `sync-s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, std::default::Default)]
+pub enum UnknownPolicy {
+    /// Skip them, like `repo` does.
+    #[default]
+    Ignore,
+    /// Skip them, logging a warning for each.
+    Warn,
+    /// Fail on the first one.
+    Error,
+}
+
+/// How to parse a manifest file.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::{Manifest, ParseOptions, UnknownPolicy};
+///
+/// let options = ParseOptions::new().unknown(UnknownPolicy::Error);
+/// let manifest = Manifest::from_file_with("default.xml", None, None, &options)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, std::default::Default)]
+pub struct ParseOptions {
+    duplicates: DuplicatePolicy,
+    unknown: UnknownPolicy,
+    comments: bool,
+    lossy: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How to handle projects defined more than once.
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// How to handle unknown elements and attributes.
+    pub fn unknown(mut self, policy: UnknownPolicy) -> Self {
+        self.unknown = policy;
+        self
+    }
+
+    /// Whether to keep the comments before the `<manifest>` element and
+    /// its children, e.g. license headers, so that they are written back
+    /// with the manifest. Comments inside children are dropped.
+    pub fn comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Whether to decode manifests that are not valid in their encoding,
+    /// or use an unsupported one, as UTF-8 with replacement characters
+    /// instead of failing, see [`encoding::decode`].
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+}
+
+// Where the manifest being parsed comes from and how to parse it.
+struct Source<'a> {
+    location: &'a str,
+    policy: DuplicatePolicy,
+    unknown: UnknownPolicy,
+    comments: bool,
+    lossy: bool,
+    resolver: &'a dyn IncludeResolver,
+    /// The strings shared by the manifest and those it includes.
+    strings: &'a StringTable,
+    /// Groups of the `<include>` elements the manifest was included by.
+    groups: Option<&'a str>,
+    /// The manifest including this one.
+    parent: Option<&'a Source<'a>>,
+}
+
+impl Source<'_> {
+    fn location_at(&self, line: usize) -> SourceLocation {
+        SourceLocation {
+            kind: match self.parent {
+                Some(_) => SourceKind::Include,
+                None => SourceKind::Main,
+            },
+            file: self.strings.intern(self.location),
+            line,
+        }
+    }
+}
+
+// Reads from `inner`, remembering where the lines read so far end, so that
+// elements know their line.
+struct LineReader<R> {
+    inner: R,
+    newlines: Vec<u64>,
+    consumed: u64,
+}
+
+impl<R: BufRead> LineReader<R> {
+    fn new(inner: R) -> Self {
+        LineReader {
+            inner,
+            newlines: Vec::new(),
+            consumed: 0,
+        }
+    }
+
+    // The line of the byte at `position`, starting at 1.
+    fn line(&self, position: u64) -> usize {
+        self.newlines.partition_point(|&newline| newline < position) + 1
+    }
+}
+
+fn record_newlines(newlines: &mut Vec<u64>, consumed: &mut u64, bytes: &[u8]) {
+    for (offset, byte) in bytes.iter().enumerate() {
+        if *byte == b'\n' {
+            newlines.push(*consumed + offset as u64);
+        }
+    }
+    *consumed += bytes.len() as u64;
+}
+
+impl<R: BufRead> Read for LineReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        record_newlines(&mut self.newlines, &mut self.consumed, &buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for LineReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The bytes are still buffered, so this does not read.
+        if let Ok(buf) = self.inner.fill_buf() {
+            let bytes = &buf[..amt.min(buf.len())];
+            record_newlines(&mut self.newlines, &mut self.consumed, bytes);
+        }
+        self.inner.consume(amt);
+    }
+}
+
+impl std::default::Default for Manifest {
+    fn default() -> Self {
+        Manifest::new()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -63,19 +287,22 @@ pub struct Remote {
     pub fetch: String,
     pub pushurl: Option<String>,
     pub review: Option<String>,
-    pub revision: Option<String>,
+    pub revision: Option<Arc<str>>,
+    /// Where the remote was defined, `None` for remotes not read from a
+    /// manifest.
+    pub source: Option<SourceLocation>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Default {
-    pub remote: Option<String>,
-    pub revision: Option<String>,
-    pub dest_branch: Option<String>,
-    pub upstream: Option<String>,
-    pub sync_j: Option<String>,
-    pub sync_c: Option<String>,
-    pub sync_s: Option<String>,
-    pub sync_tags: Option<String>,
+    pub remote: Option<Arc<str>>,
+    pub revision: Option<Arc<str>>,
+    pub dest_branch: Option<Arc<str>>,
+    pub upstream: Option<Arc<str>>,
+    pub sync_j: Option<u32>,
+    pub sync_c: Option<bool>,
+    pub sync_s: Option<bool>,
+    pub sync_tags: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
@@ -95,6 +322,11 @@ pub struct Submanifest {
     pub default_groups: Option<String>,
 }
 
+/// A project of the manifest.
+///
+/// The remote, revisions, groups and source file, which thousands of
+/// projects of large manifests repeat, are shared between the projects of
+/// a parsed manifest, so that cloning them only counts references.
 #[derive(Debug, Clone)]
 pub struct Project {
     // "name" must not be empty, and may not Fbe an absolute path or use "." or ".."
@@ -108,23 +340,26 @@ pub struct Project {
     // should be placed.  If not supplied, `revision` is used.
     // `path` may not be an absolute path or use "." or ".." path components.
     pub path: Option<String>,
-    pub remote: Option<String>,
-    pub revision: Option<String>,
-    pub dest_branch: Option<String>,
+    pub remote: Option<Arc<str>>,
+    pub revision: Option<Arc<str>>,
+    pub dest_branch: Option<Arc<str>>,
     // Attribute `groups`: List of additional groups to which all projects
     // in the included submanifest belong. This appends and recurses, meaning
     // all projects in submanifests carry all parent submanifest groups.
     // Same syntax as the corresponding element of `project`.
-    pub groups: Option<String>,
-    pub sync_c: Option<String>,
-    pub sync_s: Option<String>,
-    pub sync_tags: Option<String>,
-    pub upstream: Option<String>,
-    pub clone_depth: Option<String>,
+    pub groups: Vec<Group>,
+    pub sync_c: Option<bool>,
+    pub sync_s: Option<bool>,
+    pub sync_tags: Option<bool>,
+    pub upstream: Option<Arc<str>>,
+    pub clone_depth: Option<u32>,
     pub force_path: Option<String>,
     pub copyfiles: Vec<CopyFile>,
     pub linkfiles: Vec<LinkFile>,
     pub annotations: Vec<Annotation>,
+    /// Where the project was defined, `None` for projects not read from a
+    /// manifest.
+    pub source: Option<SourceLocation>,
 }
 
 #[derive(Debug, Clone)]
@@ -151,7 +386,7 @@ pub struct RemoveProject {
 #[derive(Debug, Clone)]
 pub struct RepoHooks {
     pub in_project: String,
-    pub enabled_list: String,
+    pub enabled_list: Vec<HookName>,
 }
 
 #[derive(Debug, Clone)]
@@ -173,6 +408,54 @@ pub struct Include {
     pub revision: Option<String>,
 }
 
+/// Where an element of a manifest was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// The element name, e.g. `project`.
+    pub element: String,
+    pub name: Option<String>,
+    pub path: Option<String>,
+    /// The manifest file, or the location an include resolver returned.
+    /// Empty for a manifest parsed from a string or reader.
+    pub file: Arc<str>,
+}
+
+/// A comment kept with [`ParseOptions::comments`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// The text between `<!--` and `-->`.
+    pub text: String,
+    /// The element the comment precedes, e.g. `project`, or `None` for a
+    /// comment after the last child of `<manifest>`.
+    pub element: Option<String>,
+    pub name: Option<String>,
+    pub path: Option<String>,
+}
+
+/// What kind of manifest a [`SourceLocation`] is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// The manifest that was parsed.
+    Main,
+    /// A manifest included by it.
+    Include,
+    /// A local manifest merged into it.
+    LocalManifest,
+    /// The manifest of a submanifest.
+    Submanifest,
+}
+
+/// Where a project or remote was defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub kind: SourceKind,
+    /// The manifest file, or the location an include resolver returned.
+    /// Empty for a manifest parsed from a string or reader.
+    pub file: Arc<str>,
+    /// The line of the element, starting at 1.
+    pub line: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct CopyFile {
     pub src: String,
@@ -193,6 +476,26 @@ pub struct Annotation {
 }
 
 impl Manifest {
+    /// Returns a manifest without any elements.
+    pub fn new() -> Self {
+        Manifest {
+            notice: None,
+            remotes: Vec::new(),
+            default: None,
+            manifest_server: None,
+            submanifests: Vec::new(),
+            remove_projects: Vec::new(),
+            projects: Vec::new(),
+            extend_projects: Vec::new(),
+            repo_hooks: None,
+            superproject: None,
+            contactinfo: None,
+            includes: Vec::new(),
+            provenance: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
     /// Parses a manifest XML file and returns a `Manifest` struct.
     ///
     /// # Arguments
@@ -214,28 +517,170 @@ impl Manifest {
         default_remote: Option<&str>,
         default_revision: Option<&str>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut manifest = Manifest {
-            notice: None,
-            remotes: Vec::new(),
-            default: None,
-            manifest_server: None,
-            submanifests: Vec::new(),
-            remove_projects: Vec::new(),
-            projects: Vec::new(),
-            extend_projects: Vec::new(),
-            repo_hooks: None,
-            superproject: None,
-            contactinfo: None,
-            includes: Vec::new(),
-        };
+        Self::from_file_with(
+            file_path,
+            default_remote,
+            default_revision,
+            &ParseOptions::default(),
+        )
+    }
+
+    /// Like [`Manifest::from_file`], handling projects defined more than
+    /// once according to `policy`.
+    pub fn from_file_with_policy(
+        file_path: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        policy: DuplicatePolicy,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_file_with(
+            file_path,
+            default_remote,
+            default_revision,
+            &ParseOptions::new().duplicates(policy),
+        )
+    }
+
+    /// Like [`Manifest::from_file`], parsed according to `options`.
+    pub fn from_file_with(
+        file_path: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        options: &ParseOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::parse_path(
+            file_path,
+            default_remote,
+            default_revision,
+            &Source {
+                location: file_path,
+                policy: options.duplicates,
+                unknown: options.unknown,
+                comments: options.comments,
+                lossy: options.lossy,
+                resolver: &FileIncludeResolver,
+                strings: &StringTable::default(),
+                groups: None,
+                parent: None,
+            },
+        )
+    }
+
+    /// Like [`Manifest::from_file`], opening `<include>` elements with
+    /// `resolver`, e.g. [`include::GitIncludeResolver`] to honor their
+    /// `revision`.
+    pub fn from_file_with_resolver(
+        file_path: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        resolver: &dyn IncludeResolver,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::parse_path(
+            file_path,
+            default_remote,
+            default_revision,
+            &Source {
+                location: file_path,
+                policy: DuplicatePolicy::default(),
+                unknown: UnknownPolicy::default(),
+                comments: false,
+                lossy: false,
+                resolver,
+                strings: &StringTable::default(),
+                groups: None,
+                parent: None,
+            },
+        )
+    }
 
-        manifest.parse_file(file_path)?;
+    fn parse_path(
+        file_path: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        source: &Source,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = BufReader::new(File::open(file_path)?);
+        let mut manifest = Manifest::new();
+        manifest.parse_reader(file, source)?;
+        manifest.set_default(default_remote, default_revision);
+        Ok(manifest)
+    }
 
-        // Set default values if the default element is missing
-        if manifest.default.is_none() {
-            manifest.default = Some(Default {
-                remote: default_remote.map(String::from),
-                revision: default_revision.map(String::from),
+    /// Parses manifest XML held in memory, see [`Manifest::from_reader`].
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(
+        xml: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        resolver: &dyn IncludeResolver,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_reader(xml.as_bytes(), default_remote, default_revision, resolver)
+    }
+
+    /// Parses manifest XML from `reader`, e.g. the body of an HTTP response.
+    ///
+    /// `<include>` elements are opened with `resolver`, see
+    /// [`include::IncludeResolver`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::include::FileIncludeResolver;
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_reader(std::io::stdin().lock(), None, None, &FileIncludeResolver)?;
+    /// ```
+    pub fn from_reader<R: BufRead>(
+        reader: R,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        resolver: &dyn IncludeResolver,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_reader_at(
+            reader,
+            "",
+            default_remote,
+            default_revision,
+            &ParseOptions::default(),
+            resolver,
+        )
+    }
+
+    // Like `from_reader`, for a manifest read from `location` and parsed
+    // according to `options`.
+    pub(crate) fn from_reader_at<R: BufRead>(
+        reader: R,
+        location: &str,
+        default_remote: Option<&str>,
+        default_revision: Option<&str>,
+        options: &ParseOptions,
+        resolver: &dyn IncludeResolver,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut manifest = Manifest::new();
+        manifest.parse_reader(
+            reader,
+            &Source {
+                location,
+                policy: options.duplicates,
+                unknown: options.unknown,
+                comments: options.comments,
+                lossy: options.lossy,
+                resolver,
+                strings: &StringTable::default(),
+                groups: None,
+                parent: None,
+            },
+        )?;
+        manifest.set_default(default_remote, default_revision);
+        Ok(manifest)
+    }
+
+    // Sets default values if the default element is missing
+    fn set_default(&mut self, default_remote: Option<&str>, default_revision: Option<&str>) {
+        if self.default.is_none() {
+            self.default = Some(Default {
+                remote: default_remote.map(Arc::from),
+                revision: default_revision.map(Arc::from),
                 dest_branch: None,
                 upstream: None,
                 sync_j: None,
@@ -244,26 +689,81 @@ impl Manifest {
                 sync_tags: None,
             });
         }
+    }
 
-        Ok(manifest)
+    /// Adds `project`, or handles it according to `policy` when a project
+    /// with the same name and path already exists.
+    pub fn add_project(
+        &mut self,
+        project: Project,
+        policy: DuplicatePolicy,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = project.path.as_deref().unwrap_or(&project.name);
+        let existing = self
+            .projects
+            .iter()
+            .position(|p| p.name == project.name && p.path.as_deref().unwrap_or(&p.name) == path);
+        match (existing, policy) {
+            (None, _) => self.projects.push(project),
+            (Some(index), DuplicatePolicy::Override) => self.projects[index] = project,
+            (Some(_), DuplicatePolicy::Keep) => {
+                debug!("Keeping the first definition of project '{}'", project.name)
+            }
+            (Some(_), DuplicatePolicy::Error) => {
+                return Err(
+                    format!("Duplicate project '{}' at path '{}'", project.name, path).into(),
+                )
+            }
+        }
+        Ok(())
     }
 
-    fn parse_file(&mut self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let file = File::open(file_path)?;
-        let file = BufReader::new(file);
-        let mut reader = Reader::from_reader(file);
+    /// Sets the kind of source of every project and remote read from a
+    /// manifest, e.g. to [`SourceKind::Submanifest`] for the manifest of a
+    /// submanifest.
+    pub fn set_source_kind(&mut self, kind: SourceKind) {
+        let projects = self.projects.iter_mut().filter_map(|p| p.source.as_mut());
+        let remotes = self.remotes.iter_mut().filter_map(|r| r.source.as_mut());
+        for source in projects.chain(remotes) {
+            source.kind = kind;
+        }
+    }
+
+    fn parse_reader<R: BufRead>(
+        &mut self,
+        mut reader: R,
+        source: &Source,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let xml = encoding::decode(&bytes, source.lossy).map_err(|e| match source.location {
+            "" => e,
+            location => format!("{} in '{}'", e, location),
+        })?;
+        let mut reader = Reader::from_reader(LineReader::new(xml.as_bytes()));
 
         let mut buf = Vec::new();
+        // Comments waiting for the element they precede.
+        let mut comments = Vec::new();
 
         loop {
+            let line = reader.get_ref().line(reader.buffer_position());
             match reader.read_event_into(&mut buf) {
                 Ok(Event::Start(ref e)) => {
                     let element = e.to_owned();
-                    self.parse_element(&element, &mut reader, &mut buf, file_path, false)?;
+                    self.attach_comments(&mut comments, Some(&element), source)?;
+                    self.parse_element(&element, &mut reader, &mut buf, source, false, line)?;
                 }
                 Ok(Event::Empty(ref e)) => {
                     let element = e.to_owned();
-                    self.parse_element(&element, &mut reader, &mut buf, file_path, true)?;
+                    self.attach_comments(&mut comments, Some(&element), source)?;
+                    self.parse_element(&element, &mut reader, &mut buf, source, true, line)?;
+                }
+                Ok(Event::Comment(ref e)) if source.comments => {
+                    comments.push(String::from_utf8_lossy(e).into_owned());
+                }
+                Ok(Event::End(ref e)) if e.name() == QName(b"manifest") => {
+                    self.attach_comments(&mut comments, None, source)?;
                 }
                 Ok(Event::Eof) => break,
                 Err(e) => return Err(Box::new(e)),
@@ -275,31 +775,92 @@ impl Manifest {
         Ok(())
     }
 
-    fn parse_element(
+    // Keeps `comments` as comments preceding `element`, or the end of the
+    // manifest. The ones around the root element of an included manifest
+    // are dropped, as it is not written.
+    fn attach_comments(
+        &mut self,
+        comments: &mut Vec<String>,
+        element: Option<&quick_xml::events::BytesStart>,
+        source: &Source,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if comments.is_empty() {
+            return Ok(());
+        }
+        let root = element.is_none_or(|e| e.name() == QName(b"manifest"));
+        if root && source.parent.is_some() {
+            comments.clear();
+            return Ok(());
+        }
+        let (mut name, mut path) = (None, None);
+        if let Some(element) = element {
+            for attr in element.attributes() {
+                let attr = attr?;
+                match attr.key.as_ref() {
+                    b"name" => name = Some(attr.unescape_value()?.to_string()),
+                    b"path" => path = Some(attr.unescape_value()?.to_string()),
+                    _ => (),
+                }
+            }
+        }
+        let element = element.map(|e| String::from_utf8_lossy(e.name().as_ref()).into_owned());
+        for text in comments.drain(..) {
+            self.comments.push(Comment {
+                text,
+                element: element.clone(),
+                name: name.clone(),
+                path: path.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn parse_element<R: BufRead>(
         &mut self,
         e: &quick_xml::events::BytesStart,
-        reader: &mut Reader<BufReader<File>>,
+        reader: &mut Reader<LineReader<R>>,
         buf: &mut Vec<u8>,
-        file_path: &str,
+        source: &Source,
         closed: bool,
+        line: usize,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        check_known(e, source)?;
+        let element = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+        if writer::element_order(&element).is_some() {
+            let mut provenance = Provenance {
+                element,
+                name: None,
+                path: None,
+                file: source.strings.intern(source.location),
+            };
+            for attr in e.attributes() {
+                let attr = attr?;
+                match attr.key.as_ref() {
+                    b"name" => provenance.name = Some(attr.unescape_value()?.to_string()),
+                    b"path" => provenance.path = Some(attr.unescape_value()?.to_string()),
+                    _ => (),
+                }
+            }
+            self.provenance.push(provenance);
+        }
+
         match e.name() {
             QName(b"notice") => {
                 if let Ok(Event::Text(e)) = reader.read_event_into(buf) {
                     self.notice = Some(e.unescape()?.to_string());
                 }
             }
-            QName(b"remote") => self.parse_remote(e)?,
+            QName(b"remote") => self.parse_remote(e, source.location_at(line))?,
             QName(b"default") => self.parse_default(e)?,
             QName(b"manifest-server") => self.parse_manifest_server(e)?,
             QName(b"submanifest") => self.parse_submanifest(e)?,
             QName(b"remove-project") => self.parse_remove_project(e)?,
-            QName(b"project") => self.parse_project(e, reader, closed)?,
+            QName(b"project") => self.parse_project(e, reader, closed, source, line)?,
             QName(b"extend-project") => self.parse_extend_project(e)?,
             QName(b"repo-hooks") => self.parse_repo_hooks(e)?,
             QName(b"superproject") => self.parse_superproject(e)?,
             QName(b"contactinfo") => self.parse_contactinfo(e)?,
-            QName(b"include") => self.parse_include(e, file_path)?,
+            QName(b"include") => self.parse_include(e, source)?,
             _ => (),
         }
         Ok(())
@@ -308,6 +869,7 @@ impl Manifest {
     fn parse_remote(
         &mut self,
         e: &quick_xml::events::BytesStart,
+        location: SourceLocation,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let mut remote = Remote {
             name: String::new(),
@@ -316,6 +878,7 @@ impl Manifest {
             pushurl: None,
             review: None,
             revision: None,
+            source: Some(location),
         };
         for attr in e.attributes() {
             let attr = attr?;
@@ -325,7 +888,7 @@ impl Manifest {
                 b"fetch" => remote.fetch = attr.unescape_value()?.to_string(),
                 b"pushurl" => remote.pushurl = Some(attr.unescape_value()?.to_string()),
                 b"review" => remote.review = Some(attr.unescape_value()?.to_string()),
-                b"revision" => remote.revision = Some(attr.unescape_value()?.to_string()),
+                b"revision" => remote.revision = Some(attr.unescape_value()?.into()),
                 _ => (),
             }
         }
@@ -354,14 +917,14 @@ impl Manifest {
         for attr in e.attributes() {
             let attr = attr?;
             match attr.key.as_ref() {
-                b"remote" => default.remote = Some(attr.unescape_value()?.to_string()),
-                b"revision" => default.revision = Some(attr.unescape_value()?.to_string()),
-                b"dest-branch" => default.dest_branch = Some(attr.unescape_value()?.to_string()),
-                b"upstream" => default.upstream = Some(attr.unescape_value()?.to_string()),
-                b"sync-j" => default.sync_j = Some(attr.unescape_value()?.to_string()),
-                b"sync-c" => default.sync_c = Some(attr.unescape_value()?.to_string()),
-                b"sync-s" => default.sync_s = Some(attr.unescape_value()?.to_string()),
-                b"sync-tags" => default.sync_tags = Some(attr.unescape_value()?.to_string()),
+                b"remote" => default.remote = Some(attr.unescape_value()?.into()),
+                b"revision" => default.revision = Some(attr.unescape_value()?.into()),
+                b"dest-branch" => default.dest_branch = Some(attr.unescape_value()?.into()),
+                b"upstream" => default.upstream = Some(attr.unescape_value()?.into()),
+                b"sync-j" => default.sync_j = Some(parse_number("default", &attr)?),
+                b"sync-c" => default.sync_c = Some(parse_bool("default", &attr)?),
+                b"sync-s" => default.sync_s = Some(parse_bool("default", &attr)?),
+                b"sync-tags" => default.sync_tags = Some(parse_bool("default", &attr)?),
                 _ => (),
             }
         }
@@ -445,19 +1008,47 @@ impl Manifest {
         Ok(())
     }
 
-    fn parse_project(
+    fn parse_project<R: BufRead>(
         &mut self,
         e: &quick_xml::events::BytesStart,
-        reader: &mut Reader<BufReader<File>>,
+        reader: &mut Reader<LineReader<R>>,
         closed: bool,
+        source: &Source,
+        line: usize,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        for mut project in Self::read_project(e, reader, closed, source, line, None)? {
+            let groups = source
+                .groups
+                .map(|groups| parse_groups_interned(groups, source.strings));
+            for group in groups.unwrap_or_default() {
+                if !project.groups.contains(&group) {
+                    project.groups.push(group);
+                }
+            }
+            self.add_project(project, source.policy)?;
+        }
+        Ok(())
+    }
+
+    // Reads the project `e` starts, followed by its subprojects, the
+    // projects nested in it. Like repo does, the name of a subproject is
+    // joined to the name of its `parent`, and its path, which defaults to
+    // that joined name, to the path of its parent.
+    fn read_project<R: BufRead>(
+        e: &quick_xml::events::BytesStart,
+        reader: &mut Reader<LineReader<R>>,
+        closed: bool,
+        source: &Source,
+        line: usize,
+        parent: Option<&Project>,
+    ) -> Result<Vec<Project>, Box<dyn std::error::Error>> {
         let mut project = Project {
             name: String::new(),
             path: None,
             remote: None,
             revision: None,
             dest_branch: None,
-            groups: None,
+            groups: Vec::new(),
             sync_c: None,
             sync_s: None,
             sync_tags: None,
@@ -467,21 +1058,32 @@ impl Manifest {
             copyfiles: Vec::new(),
             linkfiles: Vec::new(),
             annotations: Vec::new(),
+            source: Some(source.location_at(line)),
         };
         for attr in e.attributes() {
             let attr = attr?;
             match attr.key.as_ref() {
                 b"name" => project.name = attr.unescape_value()?.to_string(),
                 b"path" => project.path = Some(attr.unescape_value()?.to_string()),
-                b"remote" => project.remote = Some(attr.unescape_value()?.to_string()),
-                b"revision" => project.revision = Some(attr.unescape_value()?.to_string()),
-                b"dest-branch" => project.dest_branch = Some(attr.unescape_value()?.to_string()),
-                b"groups" => project.groups = Some(attr.unescape_value()?.to_string()),
-                b"sync-c" => project.sync_c = Some(attr.unescape_value()?.to_string()),
-                b"sync_s" => project.sync_s = Some(attr.unescape_value()?.to_string()),
-                b"sync-tags" => project.sync_tags = Some(attr.unescape_value()?.to_string()),
-                b"upstream" => project.upstream = Some(attr.unescape_value()?.to_string()),
-                b"clone-depth" => project.clone_depth = Some(attr.unescape_value()?.to_string()),
+                b"remote" => project.remote = Some(source.strings.intern(&attr.unescape_value()?)),
+                b"revision" => {
+                    project.revision = Some(source.strings.intern(&attr.unescape_value()?))
+                }
+                b"dest-branch" => {
+                    project.dest_branch = Some(source.strings.intern(&attr.unescape_value()?))
+                }
+                b"groups" => {
+                    project.groups = parse_groups_interned(&attr.unescape_value()?, source.strings)
+                }
+                b"sync-c" => project.sync_c = Some(parse_bool("project", &attr)?),
+                // `sync_s` is what earlier versions read, kept for the
+                // manifests relying on it.
+                b"sync-s" | b"sync_s" => project.sync_s = Some(parse_bool("project", &attr)?),
+                b"sync-tags" => project.sync_tags = Some(parse_bool("project", &attr)?),
+                b"upstream" => {
+                    project.upstream = Some(source.strings.intern(&attr.unescape_value()?))
+                }
+                b"clone-depth" => project.clone_depth = Some(parse_number("project", &attr)?),
                 b"force-path" => project.force_path = Some(attr.unescape_value()?.to_string()),
                 _ => (),
             }
@@ -489,81 +1091,55 @@ impl Manifest {
         if project.name.is_empty() {
             return Err("Missing required attribute 'name' in project element".into());
         }
+        if let Some(parent) = parent {
+            project.name = format!("{}/{}", parent.name, project.name);
+            let path = project.path.as_deref().unwrap_or(&project.name);
+            let parent_path = parent.path.as_deref().unwrap_or(&parent.name);
+            project.path = Some(format!("{}/{}", parent_path, path));
+        }
+        let mut subprojects = Vec::new();
 
         if !closed {
             let mut buf = Vec::new();
+            // Elements nested below a direct child, e.g. in an unknown
+            // element, do not belong to this project.
+            let mut depth = 0;
 
             loop {
+                let line = reader.get_ref().line(reader.buffer_position());
                 match reader.read_event_into(&mut buf) {
-                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
-                        // let element = e.to_owned();
-                        match e.name() {
-                            QName(b"copyfile") => {
-                                let mut copyfile = CopyFile {
-                                    src: String::new(),
-                                    dest: String::new(),
-                                };
-                                for attr in e.attributes() {
-                                    let attr = attr?;
-                                    match attr.key.as_ref() {
-                                        b"src" => copyfile.src = attr.unescape_value()?.to_string(),
-                                        b"dest" => {
-                                            copyfile.dest = attr.unescape_value()?.to_string()
-                                        }
-                                        _ => (),
-                                    }
-                                }
-
-                                project.copyfiles.push(copyfile);
-                            }
-                            QName(b"linkfile") => {
-                                let mut linkfile = LinkFile {
-                                    src: String::new(),
-                                    dest: String::new(),
-                                };
-                                for attr in e.attributes() {
-                                    let attr = attr?;
-                                    match attr.key.as_ref() {
-                                        b"src" => linkfile.src = attr.unescape_value()?.to_string(),
-                                        b"dest" => {
-                                            linkfile.dest = attr.unescape_value()?.to_string()
-                                        }
-                                        _ => (),
-                                    }
-                                }
-
-                                project.linkfiles.push(linkfile);
-                            }
-                            QName(b"annotation") => {
-                                let mut annotation = Annotation {
-                                    name: String::new(),
-                                    value: String::new(),
-                                    keep: true,
-                                };
-                                for attr in e.attributes() {
-                                    let attr = attr?;
-                                    match attr.key.as_ref() {
-                                        b"name" => {
-                                            annotation.name = attr.unescape_value()?.to_string()
-                                        }
-                                        b"value" => {
-                                            annotation.value = attr.unescape_value()?.to_string()
-                                        }
-                                        b"keep" => {
-                                            annotation.keep =
-                                                attr.unescape_value()?.to_string().to_lowercase()
-                                                    == "true"
-                                        }
-                                        _ => (),
-                                    }
-                                }
-                                project.annotations.push(annotation);
-                            }
-                            _ => (),
+                    Ok(Event::Start(ref e)) if depth == 0 && e.name() == QName(b"project") => {
+                        check_known(e, source)?;
+                        let nested =
+                            Self::read_project(e, reader, false, source, line, Some(&project))?;
+                        subprojects.extend(nested);
+                    }
+                    Ok(Event::Empty(ref e)) if depth == 0 && e.name() == QName(b"project") => {
+                        check_known(e, source)?;
+                        let nested =
+                            Self::read_project(e, reader, true, source, line, Some(&project))?;
+                        subprojects.extend(nested);
+                    }
+                    Ok(Event::Start(ref e)) => {
+                        if depth == 0 {
+                            Self::parse_project_child(e, &mut project, source)?;
+                        }
+                        depth += 1;
+                    }
+                    Ok(Event::Empty(ref e)) if depth == 0 => {
+                        Self::parse_project_child(e, &mut project, source)?;
+                    }
+                    Ok(Event::End(_)) => {
+                        if depth == 0 {
+                            break;
                         }
+                        depth -= 1;
+                    }
+                    Ok(Event::Eof) => {
+                        return Err(
+                            format!("Project element '{}' is not closed", project.name).into()
+                        )
                     }
-                    Ok(Event::End(ref e)) if e.name() == QName(b"project") => break,
-                    Ok(Event::Eof) => break,
                     Err(e) => return Err(Box::new(e)),
                     _ => (),
                 }
@@ -571,7 +1147,75 @@ impl Manifest {
             }
         }
 
-        self.projects.push(project);
+        subprojects.insert(0, project);
+        Ok(subprojects)
+    }
+
+    fn parse_project_child(
+        e: &quick_xml::events::BytesStart,
+        project: &mut Project,
+        source: &Source,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        check_known(e, source)?;
+        match e.name() {
+            QName(b"copyfile") => {
+                let mut copyfile = CopyFile {
+                    src: String::new(),
+                    dest: String::new(),
+                };
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    match attr.key.as_ref() {
+                        b"src" => copyfile.src = attr.unescape_value()?.to_string(),
+                        b"dest" => copyfile.dest = attr.unescape_value()?.to_string(),
+                        _ => (),
+                    }
+                }
+                if copyfile.src.is_empty() || copyfile.dest.is_empty() {
+                    return Err("Missing required attributes in copyfile element".into());
+                }
+                project.copyfiles.push(copyfile);
+            }
+            QName(b"linkfile") => {
+                let mut linkfile = LinkFile {
+                    src: String::new(),
+                    dest: String::new(),
+                };
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    match attr.key.as_ref() {
+                        b"src" => linkfile.src = attr.unescape_value()?.to_string(),
+                        b"dest" => linkfile.dest = attr.unescape_value()?.to_string(),
+                        _ => (),
+                    }
+                }
+                if linkfile.src.is_empty() || linkfile.dest.is_empty() {
+                    return Err("Missing required attributes in linkfile element".into());
+                }
+                project.linkfiles.push(linkfile);
+            }
+            QName(b"annotation") => {
+                let mut annotation = Annotation {
+                    name: String::new(),
+                    value: String::new(),
+                    keep: true,
+                };
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    match attr.key.as_ref() {
+                        b"name" => annotation.name = attr.unescape_value()?.to_string(),
+                        b"value" => annotation.value = attr.unescape_value()?.to_string(),
+                        b"keep" => {
+                            annotation.keep =
+                                attr.unescape_value()?.to_string().to_lowercase() == "true"
+                        }
+                        _ => (),
+                    }
+                }
+                project.annotations.push(annotation);
+            }
+            _ => (),
+        }
         Ok(())
     }
 
@@ -617,13 +1261,13 @@ impl Manifest {
     ) -> Result<(), Box<dyn std::error::Error>> {
         let mut repo_hooks = RepoHooks {
             in_project: String::new(),
-            enabled_list: String::new(),
+            enabled_list: Vec::new(),
         };
         for attr in e.attributes() {
             let attr = attr?;
             match attr.key.as_ref() {
                 b"in-project" => repo_hooks.in_project = attr.unescape_value()?.to_string(),
-                b"enabled-list" => repo_hooks.enabled_list = attr.unescape_value()?.to_string(),
+                b"enabled-list" => repo_hooks.enabled_list = parse_hooks(&attr.unescape_value()?),
                 _ => (),
             }
         }
@@ -672,7 +1316,7 @@ impl Manifest {
     fn parse_include(
         &mut self,
         e: &quick_xml::events::BytesStart,
-        file_path: &str,
+        source: &Source,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let mut include = Include {
             name: String::new(),
@@ -689,13 +1333,33 @@ impl Manifest {
             }
         }
         self.includes.push(include.clone());
-        let include_path = format!(
-            "{}/{}",
-            std::path::Path::new(file_path).parent().unwrap().display(),
-            include.name
-        );
-        if let Err(e) = self.parse_file(&include_path) {
-            eprintln!("Failed to parse included file '{}': {}", include_path, e);
+        // Projects of included files get the groups of every include on the way.
+        let groups = match include.groups.as_deref() {
+            Some(groups) => Some(join_groups(source.groups, groups)),
+            None => source.groups.map(String::from),
+        };
+        let included = match &include.revision {
+            Some(revision) => {
+                source
+                    .resolver
+                    .resolve_revision(&include.name, source.location, revision)
+            }
+            None => source.resolver.resolve(&include.name, source.location),
+        };
+        let result = included.and_then(|included| {
+            check_include_cycle(source, &included.location)?;
+            self.parse_reader(
+                included.reader,
+                &Source {
+                    location: &included.location,
+                    groups: groups.as_deref(),
+                    parent: Some(source),
+                    ..*source
+                },
+            )
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to parse included file '{}': {}", include.name, e);
             if !include.name.is_empty() {
                 return Err(e);
             }
@@ -704,6 +1368,180 @@ impl Manifest {
     }
 }
 
+// The attributes of each element of the manifest format.
+fn known_attributes(element: &[u8]) -> Option<&'static [&'static str]> {
+    let attributes: &[&str] = match element {
+        b"manifest" | b"notice" => &[],
+        b"remote" => &["name", "alias", "fetch", "pushurl", "review", "revision"],
+        b"default" => &[
+            "remote",
+            "revision",
+            "dest-branch",
+            "upstream",
+            "sync-j",
+            "sync-c",
+            "sync-s",
+            "sync-tags",
+        ],
+        b"manifest-server" => &["url"],
+        b"submanifest" => &[
+            "name",
+            "remote",
+            "project",
+            "manifest-name",
+            "revision",
+            "path",
+            "groups",
+            "default-groups",
+        ],
+        b"remove-project" => &["name", "path", "optional", "base-rev"],
+        b"project" => &[
+            "name",
+            "path",
+            "remote",
+            "revision",
+            "dest-branch",
+            "groups",
+            "sync-c",
+            "sync-s",
+            "sync-tags",
+            "upstream",
+            "clone-depth",
+            "force-path",
+        ],
+        b"extend-project" => &[
+            "name",
+            "path",
+            "dest-path",
+            "groups",
+            "revision",
+            "remote",
+            "dest-branch",
+            "upstream",
+            "base-rev",
+        ],
+        b"copyfile" | b"linkfile" => &["src", "dest"],
+        b"annotation" => &["name", "value", "keep"],
+        b"repo-hooks" => &["in-project", "enabled-list"],
+        b"superproject" => &["name", "remote", "revision"],
+        b"contactinfo" => &["bugurl"],
+        b"include" => &["name", "groups", "revision"],
+        _ => return None,
+    };
+    Some(attributes)
+}
+
+// Reports an element the manifest format does not define, or attributes it
+// does not define for the element, according to the unknown policy.
+fn check_known(
+    e: &quick_xml::events::BytesStart,
+    source: &Source,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if source.unknown == UnknownPolicy::Ignore {
+        return Ok(());
+    }
+    let element = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+    let location = match source.location {
+        "" => String::new(),
+        location => format!(" in '{}'", location),
+    };
+    let mut unknown = Vec::new();
+    match known_attributes(e.name().as_ref()) {
+        None => unknown.push(format!("Unknown element '{}'{}", element, location)),
+        Some(attributes) => {
+            for attr in e.attributes() {
+                let key = String::from_utf8_lossy(attr?.key.as_ref()).into_owned();
+                if !attributes.contains(&key.as_str()) {
+                    unknown.push(format!(
+                        "Unknown attribute '{}' of element '{}'{}",
+                        key, element, location
+                    ));
+                }
+            }
+        }
+    }
+    for message in unknown {
+        if source.unknown == UnknownPolicy::Error {
+            return Err(message.into());
+        }
+        warn!("{}", message);
+    }
+    Ok(())
+}
+
+// Fails when `location` is already being parsed further up the include
+// chain of `source`.
+fn check_include_cycle(source: &Source, location: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut chain = vec![location];
+    let mut current = Some(source);
+    let mut cycle = false;
+    while let Some(source) = current {
+        chain.push(source.location);
+        if source.location == location {
+            cycle = true;
+            break;
+        }
+        current = source.parent;
+    }
+    if !cycle {
+        return Ok(());
+    }
+    chain.reverse();
+    Err(format!("include cycle detected: {}", chain.join(" -> ")).into())
+}
+
+// Reads a boolean attribute, accepting the spellings repo accepts.
+fn parse_bool(
+    element: &str,
+    attr: &quick_xml::events::attributes::Attribute,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let value = attr.unescape_value()?;
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(format!(
+            "Invalid value '{}' of attribute '{}' in element '{}', expected true or false",
+            value,
+            String::from_utf8_lossy(attr.key.as_ref()),
+            element
+        )
+        .into()),
+    }
+}
+
+// Reads an attribute holding a count, which must be positive.
+fn parse_number(
+    element: &str,
+    attr: &quick_xml::events::attributes::Attribute,
+) -> Result<u32, Box<dyn std::error::Error>> {
+    let value = attr.unescape_value()?;
+    match value.trim().parse::<u32>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(format!(
+            "Invalid value '{}' of attribute '{}' in element '{}', expected a positive number",
+            value,
+            String::from_utf8_lossy(attr.key.as_ref()),
+            element
+        )
+        .into()),
+    }
+}
+
+// Appends the groups in `more` to `groups`, leaving out duplicates.
+fn join_groups(groups: Option<&str>, more: &str) -> String {
+    let mut joined: Vec<&str> = Vec::new();
+    for group in groups
+        .into_iter()
+        .chain([more])
+        .flat_map(|g| g.split([',', ' ']))
+    {
+        if !group.is_empty() && !joined.contains(&group) {
+            joined.push(group);
+        }
+    }
+    joined.join(",")
+}
+
 #[derive(Debug, Clone)]
 pub struct SyncOptions {
     pub current_branch_only: bool,
@@ -0,0 +1,71 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Creates the symlink `link` to `target`, which may be relative to the
+/// directory of `link`. On Windows, the symlink is to a directory if
+/// `target` is one.
+pub(crate) fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    {
+        let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+        if resolved.is_dir() {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+}
+
+/// Links `dest` to the file or directory `src`, as absolute paths, with a
+/// symlink where possible. Creating symlinks on Windows needs a privilege
+/// or developer mode, so otherwise directories get a junction there, and
+/// files a hard link or else a copy, which syncing again updates.
+#[cfg(feature = "sync")]
+pub(crate) fn link_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    let linked = symlink(src, dest);
+    if cfg!(unix) || linked.is_ok() {
+        return linked;
+    }
+    if src.is_dir() {
+        junction(src, dest)
+    } else {
+        fs::hard_link(src, dest).or_else(|_| fs::copy(src, dest).map(|_| ()))
+    }
+}
+
+/// Removes the link `link`, but not what it links to. Symlinks to
+/// directories and junctions are directories on Windows.
+pub(crate) fn remove_link(link: &Path) -> io::Result<()> {
+    fs::remove_file(link).or_else(|e| {
+        if cfg!(windows) {
+            fs::remove_dir(link)
+        } else {
+            Err(e)
+        }
+    })
+}
+
+// Creates the junction `dest` to the directory `src`, which unlike a
+// symlink needs no privilege.
+#[cfg(feature = "sync")]
+fn junction(src: &Path, dest: &Path) -> io::Result<()> {
+    let output = std::process::Command::new("cmd")
+        .args(["/C", "mklink", "/J"])
+        .arg(dest)
+        .arg(src)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "Cannot create junction '{}': {}",
+            dest.display(),
+            stderr.trim()
+        )));
+    }
+    Ok(())
+}
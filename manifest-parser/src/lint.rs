@@ -0,0 +1,204 @@
+use crate::Manifest;
+use std::collections::HashMap;
+use std::fmt;
+
+/// How much a [`Finding`] matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The lint rules, with their ID, default severity and description.
+pub const RULES: &[(&str, Severity, &str)] = &[
+    (
+        "missing-revision",
+        Severity::Warning,
+        "project has no revision of its own and follows its remote or the default",
+    ),
+    (
+        "insecure-fetch",
+        Severity::Warning,
+        "remote fetches over http instead of https",
+    ),
+    (
+        "missing-clone-depth",
+        Severity::Info,
+        "large project has no clone-depth",
+    ),
+];
+
+/// Something a lint rule found in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// ID of the rule, one of [`RULES`].
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// The element the finding is about, e.g. `project 'platform/core'`.
+    pub element: String,
+    pub message: String,
+}
+
+impl Finding {
+    /// The finding as a JSON object on one line, for CI tooling.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"rule":"{}","severity":"{}","element":"{}","message":"{}"}}"#,
+            escape(self.rule),
+            self.severity,
+            escape(&self.element),
+            escape(&self.message)
+        )
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            self.severity, self.rule, self.element, self.message
+        )
+    }
+}
+
+/// Which lint rules run and how severe their findings are.
+///
+/// Unlike [`Manifest::validate_paths`] or the schema checks, lint findings
+/// describe valid manifests that are likely not what their authors want.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::lint::{LintConfig, Severity};
+///
+/// let config = LintConfig::new()
+///     .severity("missing-revision", Severity::Error)
+///     .disable("insecure-fetch")
+///     .large_projects(["platform/kernel"]);
+/// let findings = manifest.lint(&config);
+/// for finding in &findings {
+///     println!("{}", finding.to_json());
+/// }
+/// if findings.iter().any(|f| f.severity == Severity::Error) {
+///     std::process::exit(1);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    severities: HashMap<String, Option<Severity>>,
+    large_projects: Vec<String>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports the findings of `rule` with `severity`.
+    pub fn severity(mut self, rule: &str, severity: Severity) -> Self {
+        self.severities.insert(rule.to_string(), Some(severity));
+        self
+    }
+
+    /// Does not run `rule`.
+    pub fn disable(mut self, rule: &str) -> Self {
+        self.severities.insert(rule.to_string(), None);
+        self
+    }
+
+    /// Names of the projects `missing-clone-depth` expects a `clone-depth`
+    /// for.
+    pub fn large_projects<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.large_projects = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // The severity of `rule`, `None` if it is disabled.
+    fn severity_of(&self, rule: &str) -> Option<Severity> {
+        match self.severities.get(rule) {
+            Some(severity) => *severity,
+            None => RULES
+                .iter()
+                .find(|(id, _, _)| *id == rule)
+                .map(|(_, severity, _)| *severity),
+        }
+    }
+}
+
+impl Manifest {
+    /// Runs the lint rules enabled in `config`, see [`RULES`], and returns
+    /// their findings in document order, rule by rule.
+    pub fn lint(&self, config: &LintConfig) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut report = |rule: &'static str, element: String, message: String| {
+            if let Some(severity) = config.severity_of(rule) {
+                findings.push(Finding {
+                    rule,
+                    severity,
+                    element,
+                    message,
+                });
+            }
+        };
+
+        for project in &self.projects {
+            if project.revision.is_none() {
+                report(
+                    "missing-revision",
+                    format!("project '{}'", project.name),
+                    "Project has no explicit revision".to_string(),
+                );
+            }
+        }
+        for remote in &self.remotes {
+            if remote.fetch.starts_with("http://") {
+                report(
+                    "insecure-fetch",
+                    format!("remote '{}'", remote.name),
+                    format!("Remote fetches from '{}' over http", remote.fetch),
+                );
+            }
+        }
+        for project in &self.projects {
+            if project.clone_depth.is_none() && config.large_projects.contains(&project.name) {
+                report(
+                    "missing-clone-depth",
+                    format!("project '{}'", project.name),
+                    "Large project has no clone-depth".to_string(),
+                );
+            }
+        }
+        findings
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,242 @@
+use crate::groups::parse_groups;
+use crate::{DuplicatePolicy, Manifest, Project, RemoveProject};
+use log::debug;
+use std::error::Error;
+
+/// How to merge a manifest into another, see [`merge_manifests`].
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::merge::{merge_manifests, MergeOptions};
+/// use manifest_parser::DuplicatePolicy;
+///
+/// let options = MergeOptions::new()
+///     .duplicates(DuplicatePolicy::Override)
+///     .override_default(false);
+/// let summary = merge_manifests(&mut base, local, &options)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeOptions {
+    duplicates: DuplicatePolicy,
+    override_default: bool,
+    pub(crate) conflicts: ConflictPolicy,
+    pub(crate) strict: bool,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        MergeOptions {
+            duplicates: DuplicatePolicy::Error,
+            override_default: true,
+            conflicts: ConflictPolicy::default(),
+            strict: false,
+        }
+    }
+}
+
+impl MergeOptions {
+    /// The options repo merges local manifests with: a project defined
+    /// again is an error, and the `<default>` of the merged manifest wins.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How to handle a project of the merged manifest that the base
+    /// manifest already defines at the same path.
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Whether the `<default>` of the merged manifest replaces the one of
+    /// the base manifest. The same goes for `<manifest-server>`,
+    /// `<repo-hooks>`, `<superproject>` and `<contactinfo>`.
+    pub fn override_default(mut self, override_default: bool) -> Self {
+        self.override_default = override_default;
+        self
+    }
+
+    /// How to resolve local manifests that change the same project
+    /// differently. Only used when merging several local manifests, e.g.
+    /// by `load_and_merge_manifests_with` of the `sync` module.
+    pub fn conflicts(mut self, policy: ConflictPolicy) -> Self {
+        self.conflicts = policy;
+        self
+    }
+
+    /// Whether to fail with `UnmatchedRemovals` when a `<remove-project>`
+    /// that is not `optional` matches no project, as the repo manifest
+    /// format requires. Only used when merging several local manifests,
+    /// like [`MergeOptions::conflicts`]; [`merge_manifests`] reports them
+    /// in [`MergeSummary::unmatched_removals`] either way.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// What to do when local manifests change the same project differently,
+/// see `MergeConflict` of the `sync` module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Fail with `MergeConflicts` once all local manifests are merged.
+    Error,
+    /// Keep the change of the local manifest merged first.
+    FirstWins,
+    /// Keep the change of the local manifest merged last, like `repo` does.
+    #[default]
+    LastWins,
+}
+
+/// What [`merge_manifests`] changed in the base manifest.
+#[derive(Debug, Clone, Default)]
+pub struct MergeSummary {
+    /// Projects added or replaced, by name.
+    pub added: Vec<String>,
+    /// Projects removed by `<remove-project>`, by name.
+    pub removed: Vec<String>,
+    /// Projects changed by `<extend-project>`, by name.
+    pub extended: Vec<String>,
+    /// The `<remove-project>` elements that are not `optional` and matched
+    /// no project.
+    pub unmatched_removals: Vec<RemoveProject>,
+}
+
+/// Merges `local` into `base` the way repo merges a local manifest: its
+/// `<remove-project>` and `<extend-project>` elements apply to the projects
+/// of `base`, then its remotes and projects are added.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::merge::{merge_manifests, MergeOptions};
+/// use manifest_parser::Manifest;
+///
+/// let mut base = Manifest::from_file("default.xml", None, None)?;
+/// let local = Manifest::from_file("local.xml", None, None)?;
+/// let summary = merge_manifests(&mut base, local, &MergeOptions::new())?;
+/// println!("added {:?}", summary.added);
+/// ```
+pub fn merge_manifests(
+    base: &mut Manifest,
+    local: Manifest,
+    options: &MergeOptions,
+) -> Result<MergeSummary, Box<dyn Error>> {
+    let mut summary = MergeSummary::default();
+
+    // Remove projects specified in remove_projects
+    for remove_project in &local.remove_projects {
+        debug!("Processing remove-project: {:?}", remove_project);
+        if !base.projects.iter().any(|p| removes(remove_project, p)) {
+            if remove_project.optional.as_deref() == Some("true") {
+                debug!(
+                    "Optional remove-project element did not match any project: {:?}",
+                    remove_project
+                );
+            } else {
+                debug!(
+                    "remove-project element did not match any project: {:?}",
+                    remove_project
+                );
+                summary.unmatched_removals.push(remove_project.clone());
+            }
+            continue;
+        }
+
+        base.projects.retain(|project| {
+            if !removes(remove_project, project) {
+                return true;
+            }
+            if let Some(base_rev) = &remove_project.base_rev {
+                if project.revision.as_deref() != Some(base_rev) {
+                    debug!(
+                        "Revision mismatch for project '{}': expected '{}', found '{}'",
+                        project.name,
+                        base_rev,
+                        project.revision.as_deref().unwrap_or("none")
+                    );
+                    return true;
+                }
+            }
+            debug!("Removing project: {:?}", project);
+            summary.removed.push(project.name.clone());
+            false
+        });
+    }
+
+    // Apply extend-project modifications
+    for extend_project in &local.extend_projects {
+        for project in &mut base.projects {
+            if project.name == extend_project.name {
+                if let Some(path) = &extend_project.path {
+                    if project.path.as_deref() != Some(path) {
+                        continue;
+                    }
+                }
+                if let Some(dest_path) = &extend_project.dest_path {
+                    project.path = Some(dest_path.clone());
+                }
+                if let Some(groups) = &extend_project.groups {
+                    project.groups = parse_groups(groups);
+                }
+                if let Some(revision) = &extend_project.revision {
+                    project.revision = Some(revision.as_str().into());
+                }
+                if let Some(remote) = &extend_project.remote {
+                    project.remote = Some(remote.as_str().into());
+                }
+                if let Some(dest_branch) = &extend_project.dest_branch {
+                    project.dest_branch = Some(dest_branch.as_str().into());
+                }
+                if let Some(upstream) = &extend_project.upstream {
+                    project.upstream = Some(upstream.as_str().into());
+                }
+                if let Some(_base_rev) = &extend_project.base_rev {
+                    // Add logic to handle base_rev if needed
+                }
+                debug!("Extended project: {:?}", project);
+                summary.extended.push(project.name.clone());
+            }
+        }
+    }
+
+    base.remotes.extend(local.remotes);
+    if options.override_default {
+        base.default = local.default.or(base.default.take());
+        base.manifest_server = local.manifest_server.or(base.manifest_server.take());
+        base.repo_hooks = local.repo_hooks.or(base.repo_hooks.take());
+        base.superproject = local.superproject.or(base.superproject.take());
+        base.contactinfo = local.contactinfo.or(base.contactinfo.take());
+    } else {
+        base.default = base.default.take().or(local.default);
+        base.manifest_server = base.manifest_server.take().or(local.manifest_server);
+        base.repo_hooks = base.repo_hooks.take().or(local.repo_hooks);
+        base.superproject = base.superproject.take().or(local.superproject);
+        base.contactinfo = base.contactinfo.take().or(local.contactinfo);
+    }
+    base.submanifests.extend(local.submanifests);
+    base.remove_projects.extend(local.remove_projects);
+    for project in local.projects {
+        let count = base.projects.len();
+        let name = project.name.clone();
+        base.add_project(project, options.duplicates)?;
+        if options.duplicates != DuplicatePolicy::Keep || base.projects.len() > count {
+            summary.added.push(name);
+        }
+    }
+    base.extend_projects.extend(local.extend_projects);
+    base.includes.extend(local.includes);
+    base.provenance.extend(local.provenance);
+    Ok(summary)
+}
+
+// Whether `remove_project` names `project`, ignoring its `base-rev`.
+fn removes(remove_project: &RemoveProject, project: &Project) -> bool {
+    match (&remove_project.name, &remove_project.path) {
+        (Some(name), Some(path)) => project.name == *name && project.path.as_ref() == Some(path),
+        (Some(name), None) => project.name == *name,
+        (None, Some(path)) => project.path.as_ref() == Some(path),
+        (None, None) => false,
+    }
+}
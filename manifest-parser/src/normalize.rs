@@ -0,0 +1,149 @@
+use crate::groups::Group;
+use crate::writer::{Element, WriteOptions};
+use crate::Manifest;
+
+impl Manifest {
+    /// Returns a copy of the manifest in a canonical form, so that
+    /// manifests describing the same workspace compare equal once written.
+    ///
+    /// Projects are sorted by path, remotes and submanifests by name, and
+    /// the files and annotations of each project by destination and name.
+    /// Remotes defined more than once are kept once, the first definition
+    /// wins. Groups are sorted and deduplicated, and a project `path` equal
+    /// to its name is dropped. Elements whose order matters, like
+    /// `<extend-project>`, keep their order.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let a = Manifest::from_file("a.xml", None, None)?;
+    /// let b = Manifest::from_file("b.xml", None, None)?;
+    /// if a.to_canonical_xml() == b.to_canonical_xml() {
+    ///     println!("same workspace");
+    /// }
+    /// ```
+    pub fn normalize(&self) -> Manifest {
+        let mut normalized = self.clone();
+
+        let mut names = Vec::new();
+        normalized.remotes.retain(|remote| {
+            let first = !names.contains(&remote.name);
+            names.push(remote.name.clone());
+            first
+        });
+        normalized.remotes.sort_by(|a, b| a.name.cmp(&b.name));
+        normalized.submanifests.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for project in &mut normalized.projects {
+            if project.path.as_ref() == Some(&project.name) {
+                project.path = None;
+            }
+            project.groups.sort_by_key(Group::to_string);
+            project.groups.dedup();
+            project
+                .copyfiles
+                .sort_by(|a, b| (&a.dest, &a.src).cmp(&(&b.dest, &b.src)));
+            project
+                .linkfiles
+                .sort_by(|a, b| (&a.dest, &a.src).cmp(&(&b.dest, &b.src)));
+            project
+                .annotations
+                .sort_by(|a, b| (&a.name, &a.value).cmp(&(&b.name, &b.value)));
+        }
+        normalized.projects.sort_by(|a, b| {
+            let a_path = a.path.as_deref().unwrap_or(&a.name);
+            let b_path = b.path.as_deref().unwrap_or(&b.name);
+            (a_path, &a.name).cmp(&(b_path, &b.name))
+        });
+        for extend_project in &mut normalized.extend_projects {
+            extend_project.groups = extend_project.groups.as_deref().map(normalize_groups);
+        }
+        for include in &mut normalized.includes {
+            include.groups = include.groups.as_deref().map(normalize_groups);
+        }
+        normalized
+    }
+
+    /// Serializes the [normalized](Manifest::normalize) manifest with its
+    /// attributes sorted by name, e.g. for hashing or comparing manifests.
+    pub fn to_canonical_xml(&self) -> String {
+        self.normalize()
+            .to_xml_with(&WriteOptions::new().sort_attributes(true))
+    }
+
+    /// A hash of what the manifest resolves to, e.g. to tell whether it
+    /// changed since the last sync or to key a cache.
+    ///
+    /// The hash is over the [canonical XML](Manifest::to_canonical_xml) of
+    /// the manifest with the remote, revision, `dest-branch` and `upstream`
+    /// of `<default>` and the revisions of remotes applied to the projects,
+    /// see [`Manifest::resolve_project`]. Formatting, comments, the order
+    /// of projects and whether values come from includes or defaults do
+    /// not change it. It is the same across runs and platforms.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let fingerprint = Manifest::from_file("default.xml", None, None)?.fingerprint();
+    /// if Some(fingerprint) != last_synced {
+    ///     sync_repos("default.xml", None, &options, "workspace")?;
+    /// }
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        let mut resolved = self.clone();
+        resolved.projects = self
+            .projects
+            .iter()
+            .map(|project| {
+                let mut project = self.resolve_project(project);
+                if let Some(default) = &self.default {
+                    project.dest_branch = project.dest_branch.or(default.dest_branch.clone());
+                    project.upstream = project.upstream.or(default.upstream.clone());
+                }
+                project
+            })
+            .collect();
+        if let Some(default) = &mut resolved.default {
+            default.remote = None;
+            default.revision = None;
+            default.dest_branch = None;
+            default.upstream = None;
+        }
+        for remote in &mut resolved.remotes {
+            remote.revision = None;
+        }
+        resolved.includes.clear();
+        resolved.provenance.clear();
+        resolved.comments.clear();
+
+        // 64-bit FNV-1a, which unlike the hashers of std is fixed.
+        resolved
+            .to_canonical_xml()
+            .bytes()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
+
+// Sorts `groups`, dropping duplicates and empty entries.
+fn normalize_groups(groups: &str) -> String {
+    let mut groups: Vec<&str> = groups
+        .split([',', ' '])
+        .filter(|group| !group.is_empty())
+        .collect();
+    groups.sort_unstable();
+    groups.dedup();
+    groups.join(",")
+}
+
+impl Element {
+    pub(crate) fn sort_attributes(&mut self) {
+        self.attributes.sort();
+        for child in &mut self.children {
+            child.sort_attributes();
+        }
+    }
+}
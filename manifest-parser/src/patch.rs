@@ -0,0 +1,90 @@
+use crate::include::NoIncludes;
+use crate::merge::{merge_manifests, MergeOptions, MergeSummary};
+use crate::{ExtendProject, Manifest, Project, RemoveProject};
+use std::error::Error;
+use std::str::FromStr;
+
+// The elements a manifest patch may contain.
+const PATCH_ELEMENTS: &[&str] = &["remove-project", "extend-project", "project"];
+
+/// Changes to the projects of a manifest, read from a file holding only
+/// `<remove-project>`, `<extend-project>` and `<project>` elements.
+///
+/// A patch is applied like a local manifest, but can be kept anywhere,
+/// e.g. next to the CI job that needs it.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::merge::MergeOptions;
+/// use manifest_parser::patch::ManifestPatch;
+/// use manifest_parser::Manifest;
+///
+/// let mut manifest = Manifest::from_file("default.xml", None, None)?;
+/// let patch = ManifestPatch::from_file("ci/emulator.xml")?;
+/// manifest.apply_patch(patch, &MergeOptions::new())?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManifestPatch {
+    pub remove_projects: Vec<RemoveProject>,
+    pub extend_projects: Vec<ExtendProject>,
+    pub projects: Vec<Project>,
+}
+
+impl ManifestPatch {
+    /// Reads a patch from `file_path`. Other elements than the ones a
+    /// patch may contain are an error.
+    pub fn from_file(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_manifest(Manifest::from_file_with_resolver(
+            file_path,
+            None,
+            None,
+            &NoIncludes,
+        )?)
+    }
+
+    fn from_manifest(manifest: Manifest) -> Result<Self, Box<dyn Error>> {
+        if let Some(provenance) = manifest
+            .provenance
+            .iter()
+            .find(|provenance| !PATCH_ELEMENTS.contains(&provenance.element.as_str()))
+        {
+            return Err(format!(
+                "Element '{}' is not allowed in a manifest patch",
+                provenance.element
+            )
+            .into());
+        }
+        Ok(ManifestPatch {
+            remove_projects: manifest.remove_projects,
+            extend_projects: manifest.extend_projects,
+            projects: manifest.projects,
+        })
+    }
+}
+
+impl FromStr for ManifestPatch {
+    type Err = Box<dyn Error>;
+
+    /// Reads a patch held in memory, see [`ManifestPatch::from_file`].
+    fn from_str(xml: &str) -> Result<Self, Self::Err> {
+        Self::from_manifest(Manifest::from_str(xml, None, None, &NoIncludes)?)
+    }
+}
+
+impl Manifest {
+    /// Applies `patch` to the manifest: its `<remove-project>` and
+    /// `<extend-project>` elements change the projects of the manifest,
+    /// then its projects are added, see [`merge_manifests`].
+    pub fn apply_patch(
+        &mut self,
+        patch: ManifestPatch,
+        options: &MergeOptions,
+    ) -> Result<MergeSummary, Box<dyn Error>> {
+        let mut local = Manifest::new();
+        local.remove_projects = patch.remove_projects;
+        local.extend_projects = patch.extend_projects;
+        local.projects = patch.projects;
+        merge_manifests(self, local, options)
+    }
+}
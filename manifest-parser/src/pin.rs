@@ -0,0 +1,133 @@
+use crate::query::ResolvedProject;
+use crate::{Manifest, Project};
+use git_utils::GitCommandBuilder;
+use log::debug;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Resolves the revision of a project to the commit it currently points to.
+pub trait RevisionResolver {
+    /// `url` is the fetch URL of `project` and `revision` its branch, tag or
+    /// ref, with the manifest defaults applied.
+    fn resolve(
+        &self,
+        project: &Project,
+        url: &str,
+        revision: &str,
+    ) -> Result<String, Box<dyn Error>>;
+}
+
+/// Reads the checked out commit of every project of a synced workspace.
+pub struct WorkspaceResolver {
+    root: PathBuf,
+}
+
+impl WorkspaceResolver {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        WorkspaceResolver { root: root.into() }
+    }
+}
+
+impl RevisionResolver for WorkspaceResolver {
+    fn resolve(
+        &self,
+        project: &Project,
+        _url: &str,
+        _revision: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let project_path = self
+            .root
+            .join(project.path.as_deref().unwrap_or(&project.name));
+        let head = GitCommandBuilder::git_rev_parse("HEAD")
+            .dir(&project_path)
+            .run_with_output()
+            .map_err(|e| format!("Failed to read HEAD of '{}': {}", project_path.display(), e))?;
+        Ok(head.trim().to_string())
+    }
+}
+
+/// Asks the remote with `git ls-remote`, so no workspace is needed.
+pub struct LsRemoteResolver;
+
+impl RevisionResolver for LsRemoteResolver {
+    fn resolve(
+        &self,
+        _project: &Project,
+        url: &str,
+        revision: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        // Annotated tags are only peeled when asked for explicitly.
+        let output =
+            GitCommandBuilder::git_ls_remote(url, &[revision, &format!("{}^{{}}", revision)])
+                .run()
+                .map_err(|e| format!("git ls-remote {} failed: {}", url, e))?;
+        parse_ls_remote(&String::from_utf8_lossy(&output.stdout), revision)
+            .ok_or_else(|| format!("Revision '{}' not found in {}", revision, url).into())
+    }
+}
+
+// Picks the commit `revision` refers to: a full ref name, then a branch,
+// then a tag, preferring the peeled commit of annotated tags.
+fn parse_ls_remote(output: &str, revision: &str) -> Option<String> {
+    let refs: Vec<(&str, &str)> = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    let candidates = [
+        format!("{}^{{}}", revision),
+        revision.to_string(),
+        format!("refs/heads/{}", revision),
+        format!("refs/tags/{}^{{}}", revision),
+        format!("refs/tags/{}", revision),
+    ];
+    candidates.iter().find_map(|candidate| {
+        refs.iter()
+            .find(|(_, name)| name == candidate)
+            .map(|(sha, _)| sha.to_string())
+    })
+}
+
+/// Whether `revision` is a full commit SHA rather than a branch or tag.
+pub fn is_commit_sha(revision: &str) -> bool {
+    matches!(revision.len(), 40 | 64) && revision.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl Manifest {
+    /// Returns a copy of the manifest with the revision of every project
+    /// resolved to an exact commit SHA, like `repo manifest -r`.
+    ///
+    /// The branch or tag a project followed is kept in `upstream`, unless
+    /// the project already sets one. Projects pinned to a SHA are left as
+    /// they are.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::pin::{LsRemoteResolver, WorkspaceResolver};
+    ///
+    /// let pinned = manifest.pin_revisions(&WorkspaceResolver::new("/src/tizen"))?;
+    /// let pinned = manifest.pin_revisions(&LsRemoteResolver)?;
+    /// pinned.write_to_file("pinned.xml")?;
+    /// ```
+    pub fn pin_revisions(
+        &self,
+        resolver: &dyn RevisionResolver,
+    ) -> Result<Manifest, Box<dyn Error>> {
+        let mut pinned = self.clone();
+        for project in &mut pinned.projects {
+            let resolved = ResolvedProject::from_project(self, project)?;
+            let revision = resolved.revision;
+            if is_commit_sha(&revision) {
+                continue;
+            }
+
+            let sha = resolver.resolve(project, &resolved.url, &revision)?;
+            debug!("Pinned {} {} to {}", project.name, revision, sha);
+            project.revision = Some(sha.into());
+            if project.upstream.is_none() {
+                project.upstream = Some(revision.into());
+            }
+        }
+        Ok(pinned)
+    }
+}
@@ -0,0 +1,64 @@
+use crate::groups::Group;
+use crate::{Manifest, Project};
+
+impl Project {
+    /// The profiles the project lists as `profile:<profile>` groups.
+    pub fn profiles(&self) -> Vec<&str> {
+        self.groups
+            .iter()
+            .filter_map(|group| match group {
+                Group::Profile(profile) => Some(&**profile),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether the project is part of `profile`. Projects listing no
+    /// profile are part of every profile.
+    pub fn in_profile(&self, profile: &str) -> bool {
+        let profiles = self.profiles();
+        profiles.is_empty() || profiles.contains(&profile)
+    }
+
+    /// The revision the project uses in `profile`, from an annotation named
+    /// `profile:<profile>:revision`.
+    pub fn profile_revision(&self, profile: &str) -> Option<&str> {
+        let name = format!("profile:{}:revision", profile);
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.name == name)
+            .map(|annotation| annotation.value.as_str())
+    }
+}
+
+impl Manifest {
+    /// Returns a copy of the manifest with only the projects of `profile`,
+    /// e.g. the projects needed to build for an emulator.
+    ///
+    /// A project is put into a profile with a `profile:<profile>` group, and
+    /// projects without such a group are in every profile. A project can
+    /// use another revision in a profile with an annotation named
+    /// `profile:<profile>:revision`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // <project name="platform/hal-qemu" groups="profile:emulator"/>
+    /// // <project name="platform/core">
+    /// //   <annotation name="profile:emulator:revision" value="emulator"/>
+    /// // </project>
+    /// let emulator = manifest.for_profile("emulator");
+    /// ```
+    pub fn for_profile(&self, profile: &str) -> Manifest {
+        let mut manifest = self.clone();
+        manifest
+            .projects
+            .retain(|project| project.in_profile(profile));
+        for project in &mut manifest.projects {
+            if let Some(revision) = project.profile_revision(profile) {
+                project.revision = Some(revision.into());
+            }
+        }
+        manifest
+    }
+}
@@ -0,0 +1,78 @@
+use crate::sync::{SyncEvent, SyncObserver};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Progress bars for [`sync_repos_with_observer`](crate::sync::sync_repos_with_observer),
+/// like the ones of `repo sync`: a spinner for every project being synced,
+/// above a bar counting the synced projects.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::progress::ProgressBars;
+/// use manifest_parser::sync::sync_repos_with_observer;
+/// use std::sync::Arc;
+///
+/// sync_repos_with_observer("default.xml", None, options, "workspace", Arc::new(ProgressBars::new()))?;
+/// ```
+pub struct ProgressBars {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    projects: Mutex<HashMap<String, ProgressBar>>,
+}
+
+impl Default for ProgressBars {
+    fn default() -> Self {
+        let multi = MultiProgress::new();
+        let overall = multi.add(ProgressBar::new(0));
+        overall.set_style(
+            ProgressStyle::with_template(
+                "Syncing: {percent:>3}% ({pos}/{len}) {wide_bar} {elapsed}",
+            )
+            .unwrap(),
+        );
+        ProgressBars {
+            multi,
+            overall,
+            projects: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ProgressBars {
+    /// Progress bars drawn on standard error.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SyncObserver for ProgressBars {
+    fn on_event(&self, event: &SyncEvent) {
+        match event {
+            SyncEvent::Started { total } => self.overall.set_length(*total as u64),
+            SyncEvent::ProjectStarted { name } => {
+                // Above the overall bar, which stays last.
+                let bar = self
+                    .multi
+                    .insert_before(&self.overall, ProgressBar::new_spinner());
+                bar.set_style(ProgressStyle::with_template("  {spinner} {msg}").unwrap());
+                bar.set_message(name.clone());
+                bar.enable_steady_tick(Duration::from_millis(100));
+                self.projects.lock().unwrap().insert(name.clone(), bar);
+            }
+            SyncEvent::ProjectFinished { name, error } => {
+                if let Some(bar) = self.projects.lock().unwrap().remove(name) {
+                    bar.finish_and_clear();
+                    self.multi.remove(&bar);
+                }
+                if let Some(error) = error {
+                    self.overall.println(format!("error: {}: {}", name, error));
+                }
+                self.overall.inc(1);
+            }
+            SyncEvent::Finished => self.overall.finish(),
+        }
+    }
+}
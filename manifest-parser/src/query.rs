@@ -0,0 +1,158 @@
+use crate::groups::Group;
+use crate::pin::is_commit_sha;
+use crate::{Manifest, Project};
+use std::error::Error;
+
+/// A project with everything the manifest leaves implicit worked out: the
+/// inputs for cloning and checking it out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedProject {
+    pub name: String,
+    /// Checkout path, relative to the top of the workspace.
+    pub path: String,
+    pub remote: String,
+    /// Name of the git remote in the checkout: the `alias` of the remote,
+    /// or else its name. Remotes fetching from different places can share
+    /// an alias, so every checkout has the same remote name.
+    pub git_remote: String,
+    /// Fetch URL of the remote, followed by the name and `.git`.
+    pub url: String,
+    pub revision: String,
+    pub dest_branch: Option<String>,
+    pub upstream: Option<String>,
+    /// The groups of the project, see [`Project::effective_groups`].
+    pub groups: Vec<Group>,
+}
+
+impl ResolvedProject {
+    /// Resolves `project` of `manifest`, failing when no remote or revision
+    /// applies to it or its remote is not defined.
+    pub fn from_project(manifest: &Manifest, project: &Project) -> Result<Self, Box<dyn Error>> {
+        let project = manifest.resolve_project(project);
+        let groups = project.effective_groups();
+        let default = manifest.default.as_ref();
+
+        let remote = project
+            .remote
+            .ok_or_else(|| format!("Project '{}' has no remote", project.name))?;
+        let manifest_remote = manifest
+            .remotes
+            .iter()
+            .find(|r| *r.name == *remote)
+            .ok_or_else(|| format!("Remote '{}' not found in manifest", remote))?;
+        let fetch = &manifest_remote.fetch;
+        let git_remote = manifest_remote
+            .alias
+            .clone()
+            .unwrap_or_else(|| remote.to_string());
+        let revision = project
+            .revision
+            .ok_or_else(|| format!("Project '{}' has no revision", project.name))?;
+        let path = project.path.unwrap_or_else(|| project.name.clone());
+
+        Ok(ResolvedProject {
+            url: format!("{}/{}.git", fetch.trim_end_matches('/'), project.name),
+            name: project.name,
+            path,
+            remote: remote.to_string(),
+            git_remote,
+            revision: revision.to_string(),
+            dest_branch: project
+                .dest_branch
+                .or_else(|| default.and_then(|d| d.dest_branch.clone()))
+                .map(|branch| branch.to_string()),
+            upstream: project
+                .upstream
+                .or_else(|| default.and_then(|d| d.upstream.clone()))
+                .map(|upstream| upstream.to_string()),
+            groups,
+        })
+    }
+
+    /// The branch changes are uploaded to for review: `dest-branch`, or
+    /// else the revision unless it is a commit SHA. Syncing does not use it.
+    pub fn upload_branch(&self) -> Option<&str> {
+        self.dest_branch
+            .as_deref()
+            .or_else(|| Some(self.revision.as_str()).filter(|r| !is_commit_sha(r)))
+    }
+
+    /// The branch a revision pinned to a commit SHA must be reachable from:
+    /// its `upstream`. Syncing fetches this branch rather than the commit,
+    /// and fails if the commit is not on it, like repo does. `None` for
+    /// revisions that are not commit SHAs.
+    pub fn pinned_upstream(&self) -> Option<&str> {
+        self.upstream
+            .as_deref()
+            .filter(|_| is_commit_sha(&self.revision))
+    }
+}
+
+impl Manifest {
+    /// The first project named `name`. A repository can be checked out at
+    /// several paths, see [`Manifest::find_project_by_path`].
+    pub fn find_project_by_name(&self, name: &str) -> Option<&Project> {
+        self.projects.iter().find(|project| project.name == name)
+    }
+
+    /// The project checked out at `path`, which defaults to its name.
+    pub fn find_project_by_path(&self, path: &str) -> Option<&Project> {
+        let path = path.trim_end_matches('/');
+        self.projects
+            .iter()
+            .find(|project| project.path.as_deref().unwrap_or(&project.name) == path)
+    }
+
+    /// Iterates over the projects with the defaults of the manifest applied,
+    /// see [`Manifest::resolve_project`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// for project in manifest.resolved_projects() {
+    ///     println!("{} {}", project.name, project.revision.unwrap_or_default());
+    /// }
+    /// ```
+    pub fn resolved_projects(&self) -> impl Iterator<Item = Project> + '_ {
+        self.projects
+            .iter()
+            .map(|project| self.resolve_project(project))
+    }
+
+    /// Resolves every project, see [`ResolvedProject::from_project`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// for project in manifest.resolve()? {
+    ///     println!("git clone -b {} {} {}", project.revision, project.url, project.path);
+    /// }
+    /// ```
+    pub fn resolve(&self) -> Result<Vec<ResolvedProject>, Box<dyn Error>> {
+        self.projects
+            .iter()
+            .map(|project| ResolvedProject::from_project(self, project))
+            .collect()
+    }
+
+    /// Returns `project` with its remote and revision filled in like repo
+    /// does: the remote from `<default>`, and the revision from its remote,
+    /// or else from `<default>`. They stay `None` when the manifest has no
+    /// value for them either.
+    pub fn resolve_project(&self, project: &Project) -> Project {
+        let mut project = project.clone();
+        let default = self.default.as_ref();
+        if project.remote.is_none() {
+            project.remote = default.and_then(|d| d.remote.clone());
+        }
+        if project.revision.is_none() {
+            project.revision = project
+                .remote
+                .as_ref()
+                .and_then(|name| self.remotes.iter().find(|r| *r.name == **name))
+                .and_then(|remote| remote.revision.clone())
+                .or_else(|| default.and_then(|d| d.revision.clone()));
+        }
+        project
+    }
+}
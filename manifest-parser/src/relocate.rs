@@ -0,0 +1,276 @@
+use crate::link;
+use log::debug;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Files and links that were rewritten while relocating a workspace.
+#[derive(Debug, Clone, Default)]
+pub struct RelocationReport {
+    /// Symlinks (e.g. from `<linkfile>`) that pointed into the old location.
+    pub symlinks: Vec<PathBuf>,
+    /// `objects/info/alternates` files that referenced the old location.
+    pub alternates: Vec<PathBuf>,
+    /// `.git` files (`gitdir: ...`) of submodules or worktrees, and the
+    /// `gitdir` files git keeps for worktrees.
+    pub gitdir_files: Vec<PathBuf>,
+    /// `config` files of repositories and submodules whose `core.worktree`
+    /// pointed into the old location.
+    pub worktrees: Vec<PathBuf>,
+    /// Files in which syncs keep the paths of checkouts and linked files,
+    /// e.g. `.repo/sync-state`.
+    pub caches: Vec<PathBuf>,
+}
+
+// The files in `.repo` in which syncs keep paths, see `sync::read_sync_state`
+// and `sync::link_files`.
+const SYNC_CACHES: &[&str] = &["sync-state", "copy-link-files"];
+
+/// Moves a synced workspace from `old_root` to `new_root` and fixes every
+/// reference to the old location.
+///
+/// The move is a rename when both paths are on the same filesystem and a
+/// copy followed by a removal otherwise.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::relocate::relocate_workspace;
+///
+/// let report = relocate_workspace("/mnt/old/ws", "/mnt/new/ws").unwrap();
+/// println!("{:#?}", report);
+/// ```
+pub fn relocate_workspace<P: AsRef<Path>, Q: AsRef<Path>>(
+    old_root: P,
+    new_root: Q,
+) -> Result<RelocationReport, Box<dyn Error>> {
+    let old_root = old_root.as_ref();
+    let new_root = new_root.as_ref();
+
+    if !old_root.is_dir() {
+        return Err(format!("Workspace '{}' does not exist", old_root.display()).into());
+    }
+    if new_root.exists() {
+        return Err(format!("Destination '{}' already exists", new_root.display()).into());
+    }
+    if let Some(parent) = new_root.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    debug!(
+        "Moving workspace from {} to {}",
+        old_root.display(),
+        new_root.display()
+    );
+    match fs::rename(old_root, new_root) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            debug!("Cross-device move, copying workspace");
+            copy_tree(old_root, new_root)?;
+            fs::remove_dir_all(old_root)?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    fix_relocated_workspace(old_root, new_root)
+}
+
+/// Rewrites references to `old_root` inside a workspace that has already
+/// been moved to `new_root` (e.g. with `rsync` or `mv`).
+pub fn fix_relocated_workspace<P: AsRef<Path>, Q: AsRef<Path>>(
+    old_root: P,
+    new_root: Q,
+) -> Result<RelocationReport, Box<dyn Error>> {
+    let (old_root, new_root) = (old_root.as_ref(), new_root.as_ref());
+    let mut report = RelocationReport::default();
+    fix_dir(old_root, new_root, new_root, &mut report)?;
+    for name in SYNC_CACHES {
+        let cache = new_root.join(".repo").join(name);
+        if rewrite_lines(&cache, |line| relocate_last_field(line, old_root, new_root))? {
+            report.caches.push(cache);
+        }
+    }
+    Ok(report)
+}
+
+fn fix_dir(
+    old_root: &Path,
+    new_root: &Path,
+    dir: &Path,
+    report: &mut RelocationReport,
+) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_symlink() {
+            let target = fs::read_link(&path)?;
+            if let Ok(suffix) = target.strip_prefix(old_root) {
+                let new_target = new_root.join(suffix);
+                debug!("Relinking {} -> {}", path.display(), new_target.display());
+                link::remove_link(&path)?;
+                link::symlink(&new_target, &path)?;
+                report.symlinks.push(path);
+            }
+        } else if file_type.is_dir() {
+            if entry.file_name() == ".git" {
+                fix_git_dir(old_root, new_root, &path, report)?;
+            } else {
+                fix_dir(old_root, new_root, &path, report)?;
+            }
+        } else if entry.file_name() == ".git"
+            && rewrite_prefix(&path, "gitdir: ", old_root, new_root)?
+        {
+            report.gitdir_files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Fixes the repository `git_dir`: its alternates, the `core.worktree` of its
+// config, the `gitdir` files of its worktrees, and the same for the
+// repositories of its submodules in `modules`.
+fn fix_git_dir(
+    old_root: &Path,
+    new_root: &Path,
+    git_dir: &Path,
+    report: &mut RelocationReport,
+) -> Result<(), Box<dyn Error>> {
+    let alternates = git_dir.join("objects").join("info").join("alternates");
+    if rewrite_prefix(&alternates, "", old_root, new_root)? {
+        report.alternates.push(alternates);
+    }
+    let config = git_dir.join("config");
+    if rewrite_prefix(&config, "worktree = ", old_root, new_root)? {
+        report.worktrees.push(config);
+    }
+    for worktree in subdirs(&git_dir.join("worktrees"))? {
+        let gitdir = worktree.join("gitdir");
+        if rewrite_prefix(&gitdir, "", old_root, new_root)? {
+            report.gitdir_files.push(gitdir);
+        }
+    }
+    fix_modules(old_root, new_root, &git_dir.join("modules"), report)
+}
+
+// Fixes the repositories of submodules below `dir`. Their names may contain
+// slashes, so a directory without `HEAD` is searched for more.
+fn fix_modules(
+    old_root: &Path,
+    new_root: &Path,
+    dir: &Path,
+    report: &mut RelocationReport,
+) -> Result<(), Box<dyn Error>> {
+    for module in subdirs(dir)? {
+        if module.join("HEAD").is_file() {
+            fix_git_dir(old_root, new_root, &module, report)?;
+        } else {
+            fix_modules(old_root, new_root, &module, report)?;
+        }
+    }
+    Ok(())
+}
+
+// The directories in `dir`, none if it does not exist.
+fn subdirs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+// Replaces `old_root` with `new_root` in every line of `file` that starts with
+// `prefix`, after any indentation, followed by a path under `old_root`.
+// Returns whether the file changed.
+fn rewrite_prefix(
+    file: &Path,
+    prefix: &str,
+    old_root: &Path,
+    new_root: &Path,
+) -> Result<bool, Box<dyn Error>> {
+    rewrite_lines(file, |line| {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let path = line.trim_start().strip_prefix(prefix)?;
+        let suffix = Path::new(path).strip_prefix(old_root).ok()?;
+        Some(format!(
+            "{}{}{}",
+            indent,
+            prefix,
+            new_root.join(suffix).display()
+        ))
+    })
+}
+
+// Replaces `old_root` with `new_root` in the path ending `line`, which
+// starts the line or follows a space, like the paths of the lines of the
+// sync caches do.
+fn relocate_last_field(line: &str, old_root: &Path, new_root: &Path) -> Option<String> {
+    let starts = std::iter::once(0).chain(line.match_indices(' ').map(|(i, _)| i + 1));
+    for start in starts {
+        if let Ok(suffix) = Path::new(&line[start..]).strip_prefix(old_root) {
+            return Some(format!(
+                "{}{}",
+                &line[..start],
+                new_root.join(suffix).display()
+            ));
+        }
+    }
+    None
+}
+
+// Replaces the lines of `file` for which `rewrite` returns a new line.
+// Returns whether the file changed.
+fn rewrite_lines<F>(file: &Path, mut rewrite: F) -> Result<bool, Box<dyn Error>>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    if !file.is_file() {
+        return Ok(false);
+    }
+    let content = fs::read_to_string(file)?;
+    let mut changed = false;
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| match rewrite(line) {
+            Some(new_line) => {
+                changed = true;
+                new_line
+            }
+            None => line.to_string(),
+        })
+        .collect();
+
+    if changed {
+        debug!("Rewriting {}", file.display());
+        fs::write(file, lines.join("\n") + "\n")?;
+    }
+    Ok(changed)
+}
+
+fn copy_tree(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let dest_path = dest.join(entry.file_name());
+        if file_type.is_symlink() {
+            link::symlink(&fs::read_link(entry.path())?, &dest_path)?;
+        } else if file_type.is_dir() {
+            copy_tree(&entry.path(), &dest_path)?;
+        } else {
+            fs::copy(entry.path(), &dest_path)?;
+        }
+    }
+    Ok(())
+}
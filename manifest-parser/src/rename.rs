@@ -0,0 +1,140 @@
+use crate::{ExtendProject, Manifest, Project, RemoveProject};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// A checkout directory that has to move, relative to the workspace root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Result of [`Manifest::rename_project`].
+#[derive(Debug, Clone)]
+pub struct RenamePlan {
+    /// Directories to move in existing workspaces.
+    pub moves: Vec<WorkspaceMove>,
+    /// A local manifest that turns the original manifest into the renamed
+    /// one, for downstream users who cannot switch manifests yet. It only
+    /// holds `remove-project`, `project` and `extend-project` elements.
+    pub overlay: Manifest,
+}
+
+impl Manifest {
+    /// Renames a project and/or moves it to a new path.
+    ///
+    /// Every reference to the project is updated: the project elements
+    /// themselves, `extend-project` and `remove-project` elements and the
+    /// `repo-hooks` project.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let plan = manifest.rename_project("platform/core", Some("platform/core-legacy"), None)?;
+    /// for m in &plan.moves {
+    ///     println!("mv {} {}", m.from.display(), m.to.display());
+    /// }
+    /// ```
+    pub fn rename_project(
+        &mut self,
+        name: &str,
+        new_name: Option<&str>,
+        new_path: Option<&str>,
+    ) -> Result<RenamePlan, Box<dyn Error>> {
+        if new_name.is_none() && new_path.is_none() {
+            return Err("Either a new name or a new path is required".into());
+        }
+        if !self.projects.iter().any(|p| p.name == name) {
+            return Err(format!("Project '{}' not found in manifest", name).into());
+        }
+        if let Some(new_name) = new_name {
+            if new_name != name && self.projects.iter().any(|p| p.name == new_name) {
+                return Err(format!("Project '{}' already exists", new_name).into());
+            }
+        }
+
+        let target_path = |p: &Project| {
+            new_path
+                .map(String::from)
+                .or_else(|| p.path.clone())
+                .unwrap_or_else(|| new_name.unwrap_or(name).to_string())
+        };
+        for project in self.projects.iter().filter(|p| p.name == name) {
+            let path = target_path(project);
+            if self
+                .projects
+                .iter()
+                .any(|p| p.name != name && p.path.as_deref().unwrap_or(&p.name) == path)
+            {
+                return Err(format!("Path '{}' is already used by another project", path).into());
+            }
+        }
+
+        let mut plan = RenamePlan {
+            moves: Vec::new(),
+            overlay: Manifest::new(),
+        };
+
+        let target_name = new_name.unwrap_or(name).to_string();
+        for project in self.projects.iter_mut().filter(|p| p.name == name) {
+            let old_path = project.path.clone().unwrap_or_else(|| project.name.clone());
+            let renamed = Project {
+                name: target_name.clone(),
+                path: new_path.map(String::from).or_else(|| project.path.clone()),
+                ..project.clone()
+            };
+            let renamed_path = renamed.path.clone().unwrap_or_else(|| renamed.name.clone());
+
+            if renamed_path != old_path {
+                plan.moves.push(WorkspaceMove {
+                    from: PathBuf::from(&old_path),
+                    to: PathBuf::from(&renamed_path),
+                });
+            }
+
+            if renamed.name != project.name {
+                plan.overlay.remove_projects.push(RemoveProject {
+                    name: Some(project.name.clone()),
+                    path: project.path.clone(),
+                    optional: None,
+                    base_rev: None,
+                });
+                plan.overlay.projects.push(renamed.clone());
+            } else {
+                plan.overlay.extend_projects.push(ExtendProject {
+                    name: project.name.clone(),
+                    path: project.path.clone(),
+                    dest_path: Some(renamed_path.clone()),
+                    groups: None,
+                    revision: None,
+                    remote: None,
+                    dest_branch: None,
+                    upstream: None,
+                    base_rev: None,
+                });
+            }
+
+            *project = renamed;
+        }
+
+        if let Some(new_name) = new_name {
+            for extend_project in self.extend_projects.iter_mut().filter(|e| e.name == name) {
+                extend_project.name = new_name.to_string();
+            }
+            for remove_project in self
+                .remove_projects
+                .iter_mut()
+                .filter(|r| r.name.as_deref() == Some(name))
+            {
+                remove_project.name = Some(new_name.to_string());
+            }
+            if let Some(repo_hooks) = self.repo_hooks.as_mut() {
+                if repo_hooks.in_project == name {
+                    repo_hooks.in_project = new_name.to_string();
+                }
+            }
+        }
+
+        Ok(plan)
+    }
+}
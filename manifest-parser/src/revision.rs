@@ -0,0 +1,112 @@
+use crate::pin::is_commit_sha;
+use crate::query::ResolvedProject;
+use crate::Project;
+use std::fmt;
+use std::str::FromStr;
+
+/// The revision of a project, by what it names. Every variant holds the
+/// revision as written in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Revision {
+    /// A branch, e.g. `main` or `refs/heads/main`. Short names of tags are
+    /// branches too, telling them apart needs the remote.
+    Branch(String),
+    /// A `refs/tags/` ref.
+    Tag(String),
+    /// A full commit SHA, see [`is_commit_sha`].
+    Sha(String),
+    /// Any other ref, e.g. `refs/changes/45/12345/2`.
+    RefsExpression(String),
+}
+
+impl Revision {
+    /// Classifies `revision` without checking that it is a valid ref name,
+    /// see [`Revision::from_str`] for that.
+    pub fn new(revision: &str) -> Self {
+        let owned = revision.to_string();
+        if is_commit_sha(revision) {
+            Revision::Sha(owned)
+        } else if revision.starts_with("refs/tags/") {
+            Revision::Tag(owned)
+        } else if revision.starts_with("refs/heads/") || !revision.starts_with("refs/") {
+            Revision::Branch(owned)
+        } else {
+            Revision::RefsExpression(owned)
+        }
+    }
+
+    /// The revision as written in the manifest.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Revision::Branch(revision)
+            | Revision::Tag(revision)
+            | Revision::Sha(revision)
+            | Revision::RefsExpression(revision) => revision,
+        }
+    }
+
+    /// The branch or tag name without its `refs/heads/` or `refs/tags/`
+    /// prefix, `None` for other revisions.
+    pub fn short_name(&self) -> Option<&str> {
+        match self {
+            Revision::Branch(revision) => {
+                Some(revision.strip_prefix("refs/heads/").unwrap_or(revision))
+            }
+            Revision::Tag(revision) => revision.strip_prefix("refs/tags/"),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Revision {
+    type Err = String;
+
+    /// Classifies `revision` like [`Revision::new`], failing for names git
+    /// does not accept as refs, see `git check-ref-format`.
+    fn from_str(revision: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Err(format!("Invalid revision '{}': {}", revision, reason));
+        if revision.is_empty() {
+            return invalid("it is empty");
+        }
+        if let Some(c) = revision
+            .chars()
+            .find(|c| c.is_ascii_control() || " ~^:?*[\\".contains(*c))
+        {
+            return invalid(&format!("it contains {:?}", c));
+        }
+        if revision.contains("..") || revision.contains("@{") || revision.contains("//") {
+            return invalid("it contains '..', '@{' or '//'");
+        }
+        if revision.starts_with('/') || revision.ends_with('/') || revision.ends_with('.') {
+            return invalid("it starts or ends with '/', or ends with '.'");
+        }
+        if revision
+            .split('/')
+            .any(|component| component.starts_with('.') || component.ends_with(".lock"))
+        {
+            return invalid("a component starts with '.' or ends with '.lock'");
+        }
+        Ok(Revision::new(revision))
+    }
+}
+
+impl fmt::Display for Revision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Project {
+    /// The revision the project sets itself, classified, see
+    /// [`Revision::new`].
+    pub fn typed_revision(&self) -> Option<Revision> {
+        self.revision.as_deref().map(Revision::new)
+    }
+}
+
+impl ResolvedProject {
+    /// The revision of the project, classified, see [`Revision::new`].
+    pub fn typed_revision(&self) -> Revision {
+        Revision::new(&self.revision)
+    }
+}
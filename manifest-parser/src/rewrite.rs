@@ -0,0 +1,406 @@
+use crate::writer::{element_order, Element, WriteOptions};
+use crate::{Manifest, SourceKind};
+use quick_xml::escape::{escape, unescape};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// A top-level element of the file being rewritten.
+struct Node {
+    element: Element,
+    /// The whole element, including its children and end tag.
+    span: Range<usize>,
+    start_tag: Range<usize>,
+    self_closing: bool,
+}
+
+struct Parsed {
+    nodes: Vec<Node>,
+    /// End of the `<manifest>` start tag.
+    open_end: usize,
+}
+
+impl Manifest {
+    /// Rewrites `original`, the XML this manifest was read from, into XML
+    /// describing this manifest with as few changes as possible.
+    ///
+    /// Unchanged elements keep their text, including comments, attribute
+    /// order, quoting and line wrapping. Changed attributes are replaced in
+    /// place, new attributes are appended, removed elements are dropped
+    /// together with their line, and new elements are inserted after the
+    /// last element of the same kind with the indentation of the file.
+    /// Elements this crate does not model are left alone.
+    ///
+    /// Read the manifest with `from_file(path, None, None)`, since the
+    /// fallback `<default>` would otherwise be written into the file.
+    /// Projects and remotes read from other files, e.g. the files it
+    /// includes, are not written into it.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let original = std::fs::read_to_string("default.xml")?;
+    /// let mut manifest = Manifest::from_file("default.xml", None, None)?;
+    /// manifest.projects[0].revision = Some("3f2c9e1".into());
+    /// std::fs::write("default.xml", manifest.rewrite_xml(&original)?)?;
+    /// ```
+    pub fn rewrite_xml(&self, original: &str) -> Result<String, Box<dyn Error>> {
+        rewrite(self, original, &[])
+    }
+
+    /// Rewrites the manifest file at `file_path` in place, see
+    /// [`Manifest::rewrite_xml`].
+    ///
+    /// Elements that come from the files it includes are not copied into it.
+    pub fn rewrite_file(&self, file_path: &str) -> Result<(), Box<dyn Error>> {
+        let original = fs::read_to_string(file_path)?;
+        let dir = Path::new(file_path).parent().unwrap_or(Path::new("."));
+
+        let mut included = Vec::new();
+        for node in parse(&original)?.nodes {
+            if let ("include", Some(name)) =
+                (node.element.name.as_str(), node.element.attribute("name"))
+            {
+                let include_path = dir.join(name);
+                let manifest = Manifest::from_file(&include_path.to_string_lossy(), None, None)?;
+                included.extend(manifest.elements());
+            }
+        }
+
+        fs::write(file_path, rewrite(self, &original, &included)?)?;
+        Ok(())
+    }
+}
+
+fn rewrite(
+    manifest: &Manifest,
+    original: &str,
+    included: &[Element],
+) -> Result<String, Box<dyn Error>> {
+    let parsed = parse(original)?;
+    let unit = parsed
+        .nodes
+        .first()
+        .map(|node| indent_of(original, node.span.start))
+        .filter(|indent| !indent.is_empty())
+        .map(String::from)
+        .unwrap_or_else(|| WriteOptions::default().indent_unit());
+
+    let mut wanted: Vec<Option<Element>> = manifest
+        .elements()
+        .into_iter()
+        .filter(|element| !from_other_file(manifest, element))
+        .map(Some)
+        .collect();
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut kept: Vec<&Node> = Vec::new();
+
+    for node in &parsed.nodes {
+        if element_order(&node.element.name).is_none() {
+            // Not modeled by `Manifest`, keep it untouched.
+            continue;
+        }
+        let found = wanted.iter().position(|element| {
+            element.as_ref().is_some_and(|element| {
+                element.name == node.element.name && element.key() == node.element.key()
+            })
+        });
+        match found {
+            Some(index) => {
+                let element = wanted[index].take().unwrap();
+                if !element.same_as(&node.element) {
+                    edits.push(update(original, node, &element, &unit));
+                }
+                kept.push(node);
+            }
+            None => edits.push((removal_range(original, &node.span), String::new())),
+        }
+    }
+
+    for element in wanted.into_iter().flatten() {
+        if included
+            .iter()
+            .any(|i| i.name == element.name && i.key() == element.key())
+        {
+            continue;
+        }
+        let order = element_order(&element.name);
+        let position = kept
+            .iter()
+            .rev()
+            .find(|node| element_order(&node.element.name) <= order)
+            .map(|node| node.span.end)
+            .unwrap_or(parsed.open_end);
+        edits.push((
+            position..position,
+            format!("\n{}{}", unit, element.render(&unit, &unit, None)),
+        ));
+    }
+
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut xml = String::new();
+    let mut cursor = 0;
+    for (range, replacement) in edits {
+        if range.start >= cursor {
+            xml.push_str(&original[cursor..range.start]);
+        }
+        xml.push_str(&replacement);
+        cursor = cursor.max(range.end);
+    }
+    xml.push_str(&original[cursor..]);
+    Ok(xml)
+}
+
+// Whether `element` is a project or remote that was read from another file
+// than the one being rewritten, e.g. from an included one.
+fn from_other_file(manifest: &Manifest, element: &Element) -> bool {
+    let name = element.attribute("name");
+    let source = match element.name.as_str() {
+        "project" => manifest
+            .projects
+            .iter()
+            .find(|p| {
+                Some(p.name.as_str()) == name && p.path.as_deref() == element.attribute("path")
+            })
+            .and_then(|p| p.source.as_ref()),
+        "remote" => manifest
+            .remotes
+            .iter()
+            .find(|r| Some(r.name.as_str()) == name)
+            .and_then(|r| r.source.as_ref()),
+        _ => None,
+    };
+    source.is_some_and(|source| source.kind != SourceKind::Main)
+}
+
+// Rewrites the start tag of `node`, and its content when that changed.
+fn update(original: &str, node: &Node, element: &Element, unit: &str) -> (Range<usize>, String) {
+    let content_changed = element.text != node.element.text
+        || element.children.len() != node.element.children.len()
+        || element
+            .children
+            .iter()
+            .zip(&node.element.children)
+            .any(|(a, b)| !a.same_as(b));
+    let raw_tag = &original[node.start_tag.clone()];
+
+    if !content_changed {
+        let tag = start_tag(raw_tag, element, node.self_closing);
+        return (node.start_tag.clone(), tag);
+    }
+
+    let tag = start_tag(raw_tag, element, element.is_empty());
+    if element.is_empty() {
+        return (node.span.clone(), tag);
+    }
+    let indent = indent_of(original, node.span.start);
+    // Indent new children like the first existing one.
+    let inner = &original[node.start_tag.end..node.span.end];
+    let child_indent = match inner.find('<') {
+        Some(child) if !node.element.children.is_empty() => indent_of(inner, child).to_string(),
+        _ => format!("{}{}", indent, unit),
+    };
+    let content = element.render_content(indent, &child_indent, unit, None);
+    (node.span.clone(), tag + &content)
+}
+
+// Builds the start tag for `element` from the original one, keeping the
+// order, quoting and spacing of the attributes that are still present.
+fn start_tag(raw: &str, element: &Element, self_closing: bool) -> String {
+    let close = if self_closing { "/>" } else { ">" };
+    let Some((attributes, trailing)) = raw_attributes(raw) else {
+        return element.open_tag("", None) + close;
+    };
+
+    let mut tag = format!("<{}", element.name);
+    for attribute in &attributes {
+        match element.attribute(attribute.key) {
+            Some(value) if unescape(attribute.value).is_ok_and(|old| old == value) => {
+                tag.push_str(attribute.space);
+                tag.push_str(attribute.text);
+            }
+            Some(value) => {
+                tag.push_str(&format!(
+                    "{}{}=\"{}\"",
+                    attribute.space,
+                    attribute.key,
+                    escape(value)
+                ));
+            }
+            None => (),
+        }
+    }
+    let space = attributes.last().map(|a| a.space).unwrap_or(" ");
+    for (key, value) in &element.attributes {
+        if !attributes.iter().any(|a| a.key == key) {
+            tag.push_str(&format!("{}{}=\"{}\"", space, key, escape(value.as_str())));
+        }
+    }
+    tag.push_str(trailing);
+    tag.push_str(close);
+    tag
+}
+
+struct RawAttribute<'a> {
+    /// The whitespace before the attribute.
+    space: &'a str,
+    key: &'a str,
+    /// The attribute as written, e.g. `name="foo"`.
+    text: &'a str,
+    /// The value as written, still escaped.
+    value: &'a str,
+}
+
+// Splits a start tag into its attributes and the whitespace before `>` or `/>`.
+fn raw_attributes(raw: &str) -> Option<(Vec<RawAttribute<'_>>, &str)> {
+    let inner = raw.strip_prefix('<')?;
+    let inner = inner.strip_suffix('>')?;
+    let inner = inner.strip_suffix('/').unwrap_or(inner);
+    let mut rest = inner.trim_start_matches(|c: char| !c.is_whitespace());
+
+    let mut attributes = Vec::new();
+    loop {
+        let text = rest.trim_start();
+        let space = &rest[..rest.len() - text.len()];
+        if text.is_empty() {
+            return Some((attributes, space));
+        }
+        let (key, after_key) = text.split_once('=')?;
+        let after_equals = after_key.trim_start();
+        let quote = after_equals.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value_len = after_equals[1..].find(quote)?;
+        let value = &after_equals[1..1 + value_len];
+        let consumed = text.len() - after_equals.len() + value_len + 2;
+        attributes.push(RawAttribute {
+            space,
+            key: key.trim(),
+            text: &text[..consumed],
+            value,
+        });
+        rest = &text[consumed..];
+    }
+}
+
+fn parse(original: &str) -> Result<Parsed, Box<dyn Error>> {
+    let mut reader = Reader::from_str(original);
+    let mut nodes = Vec::new();
+    let mut open_end = None;
+    let mut depth = 0;
+    let mut current: Option<Node> = None;
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader.read_event()?;
+        let end = reader.buffer_position() as usize;
+        match event {
+            Event::Start(e) => {
+                depth += 1;
+                match depth {
+                    1 => open_end = Some(end),
+                    2 => {
+                        current = Some(Node {
+                            element: element(&e)?,
+                            span: start..end,
+                            start_tag: start..end,
+                            self_closing: false,
+                        })
+                    }
+                    3 => {
+                        if let Some(node) = current.as_mut() {
+                            node.element.children.push(element(&e)?);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Event::Empty(e) => match depth {
+                1 => nodes.push(Node {
+                    element: element(&e)?,
+                    span: start..end,
+                    start_tag: start..end,
+                    self_closing: true,
+                }),
+                2 => {
+                    if let Some(node) = current.as_mut() {
+                        node.element.children.push(element(&e)?);
+                    }
+                }
+                _ => (),
+            },
+            Event::Text(text) if depth == 2 => {
+                if let Some(node) = current.as_mut().filter(|n| n.element.name == "notice") {
+                    node.element.text = Some(text.unescape()?.to_string());
+                }
+            }
+            Event::End(_) => {
+                if depth == 2 {
+                    if let Some(mut node) = current.take() {
+                        node.span.end = end;
+                        nodes.push(node);
+                    }
+                }
+                depth -= 1;
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    Ok(Parsed {
+        nodes,
+        open_end: open_end.ok_or("No <manifest> element found")?,
+    })
+}
+
+fn element(e: &BytesStart) -> Result<Element, Box<dyn Error>> {
+    let mut attributes = Vec::new();
+    for attr in e.attributes() {
+        let attr = attr?;
+        attributes.push((
+            String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+            attr.unescape_value()?.to_string(),
+        ));
+    }
+    Ok(Element {
+        name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+        attributes,
+        text: None,
+        children: Vec::new(),
+    })
+}
+
+// The whitespace between the start of the line and `position`, or "" when
+// other text precedes `position` on its line.
+fn indent_of(text: &str, position: usize) -> &str {
+    let line_start = text[..position].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let indent = &text[line_start..position];
+    if indent.trim().is_empty() {
+        indent
+    } else {
+        ""
+    }
+}
+
+// The span of a removed element, widened to its whole line when nothing
+// else is on it.
+fn removal_range(text: &str, span: &Range<usize>) -> Range<usize> {
+    let line_start = text[..span.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = text[span.end..]
+        .find('\n')
+        .map(|i| span.end + i + 1)
+        .unwrap_or(text.len());
+    if text[line_start..span.start].trim().is_empty() && text[span.end..line_end].trim().is_empty()
+    {
+        line_start..line_end
+    } else {
+        span.clone()
+    }
+}
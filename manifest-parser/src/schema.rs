@@ -0,0 +1,379 @@
+use crate::encoding::decode;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::sync::OnceLock;
+
+/// The DTD of the manifest format, as published in the documentation of
+/// repo.
+pub const MANIFEST_DTD: &str = include_str!("../schema/manifest.dtd");
+
+/// A place where a manifest document does not follow [`MANIFEST_DTD`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Line of the element the violation was found at, starting at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Checks a manifest document against [`MANIFEST_DTD`] and returns every
+/// violation, in document order; none means the document is valid.
+///
+/// Unlike the parser, which skips what it does not know, this reports
+/// undeclared elements and attributes, missing required attributes,
+/// elements appearing more often than allowed or where they are not
+/// allowed, duplicate remote names, and references to undefined remotes.
+/// The order of the children of an element is not checked, as repo
+/// accepts any order.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::schema::validate_schema;
+///
+/// let violations = validate_schema(&std::fs::read_to_string("default.xml")?);
+/// for violation in &violations {
+///     eprintln!("default.xml: {}", violation);
+/// }
+/// std::process::exit(if violations.is_empty() { 0 } else { 1 });
+/// ```
+pub fn validate_schema(xml: &str) -> Vec<Violation> {
+    Validator::new(xml).run()
+}
+
+/// Like [`validate_schema`], reading the document from `file_path`.
+pub fn validate_schema_file(file_path: &str) -> Result<Vec<Violation>, Box<dyn Error>> {
+    Ok(validate_schema(&decode(&fs::read(file_path)?, false)?))
+}
+
+enum Content {
+    Empty,
+    Text,
+    // Child elements, with whether each may appear at most once and
+    // whether it must appear.
+    Children(Vec<(String, bool, bool)>),
+}
+
+struct Attribute {
+    name: String,
+    kind: String,
+    required: bool,
+}
+
+struct Schema {
+    elements: HashMap<String, Content>,
+    attributes: HashMap<String, Vec<Attribute>>,
+}
+
+fn schema() -> &'static Schema {
+    static SCHEMA: OnceLock<Schema> = OnceLock::new();
+    SCHEMA.get_or_init(|| parse_dtd(MANIFEST_DTD))
+}
+
+// Reads the element and attribute list declarations of `dtd`. Enumerated
+// attribute types are not supported, the manifest DTD has none.
+fn parse_dtd(dtd: &str) -> Schema {
+    let mut schema = Schema {
+        elements: HashMap::new(),
+        attributes: HashMap::new(),
+    };
+    let mut rest = dtd;
+    while let Some(start) = rest.find("<!") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let declaration = &rest[..end];
+        if let Some(declaration) = declaration.strip_prefix("ELEMENT") {
+            let declaration = declaration.trim();
+            let (name, content) = declaration
+                .split_once(char::is_whitespace)
+                .unwrap_or((declaration, ""));
+            schema
+                .elements
+                .insert(name.to_string(), parse_content(content.trim()));
+        } else if let Some(declaration) = declaration.strip_prefix("ATTLIST") {
+            let mut tokens = declaration.split_whitespace();
+            let Some(element) = tokens.next() else {
+                continue;
+            };
+            let attributes = schema.attributes.entry(element.to_string()).or_default();
+            while let (Some(name), Some(kind), Some(default)) =
+                (tokens.next(), tokens.next(), tokens.next())
+            {
+                if default == "#FIXED" {
+                    tokens.next();
+                }
+                attributes.push(Attribute {
+                    name: name.to_string(),
+                    kind: kind.to_string(),
+                    required: default == "#REQUIRED",
+                });
+            }
+        } else {
+            // E.g. the DOCTYPE, whose declarations follow.
+            continue;
+        }
+        rest = &rest[end + 1..];
+    }
+    schema
+}
+
+fn parse_content(content: &str) -> Content {
+    if content == "EMPTY" {
+        return Content::Empty;
+    }
+    if content.contains("#PCDATA") {
+        return Content::Text;
+    }
+    Content::Children(
+        content
+            .trim_matches(['(', ')'])
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                let name = item.trim_end_matches(['?', '*', '+']);
+                let occurrence = &item[name.len()..];
+                (
+                    name.to_string(),
+                    occurrence.is_empty() || occurrence == "?",
+                    occurrence.is_empty() || occurrence == "+",
+                )
+            })
+            .collect(),
+    )
+}
+
+// An element whose end has not been read yet, with the number of times
+// each child element appeared so far.
+struct Open {
+    name: String,
+    children: HashMap<String, usize>,
+}
+
+struct Validator<'a> {
+    xml: &'a str,
+    schema: &'static Schema,
+    violations: Vec<Violation>,
+    open: Vec<Open>,
+    has_root: bool,
+    ids: HashSet<String>,
+    // Line, element, attribute and value of every IDREF attribute.
+    idrefs: Vec<(usize, String, String, String)>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(xml: &'a str) -> Self {
+        Validator {
+            xml,
+            schema: schema(),
+            violations: Vec::new(),
+            open: Vec::new(),
+            has_root: false,
+            ids: HashSet::new(),
+            idrefs: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Vec<Violation> {
+        let mut reader = Reader::from_str(self.xml);
+        loop {
+            let line = self.line(reader.buffer_position() as usize);
+            match reader.read_event() {
+                Ok(Event::Start(e)) => {
+                    self.start(&e, line);
+                    self.open.push(Open {
+                        name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+                        children: HashMap::new(),
+                    });
+                }
+                Ok(Event::Empty(e)) => {
+                    self.start(&e, line);
+                    let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                    self.end(&name, &HashMap::new(), line);
+                }
+                Ok(Event::End(_)) => {
+                    if let Some(open) = self.open.pop() {
+                        self.end(&open.name, &open.children, line);
+                    }
+                }
+                Ok(Event::Text(text)) if !String::from_utf8_lossy(&text).trim().is_empty() => {
+                    self.text(line)
+                }
+                Ok(Event::CData(_)) => self.text(line),
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    self.report(line, e.to_string());
+                    return self.violations;
+                }
+                _ => (),
+            }
+        }
+
+        if !self.has_root {
+            self.report(1, "Document has no root element".to_string());
+        }
+        for (line, element, attribute, value) in std::mem::take(&mut self.idrefs) {
+            if !self.ids.contains(&value) {
+                self.report(
+                    line,
+                    format!(
+                        "Attribute '{}' of element '{}' refers to '{}', which is not defined",
+                        attribute, element, value
+                    ),
+                );
+            }
+        }
+        self.violations
+    }
+
+    fn line(&self, position: usize) -> usize {
+        self.xml[..position.min(self.xml.len())]
+            .matches('\n')
+            .count()
+            + 1
+    }
+
+    fn report(&mut self, line: usize, message: String) {
+        self.violations.push(Violation { line, message });
+    }
+
+    fn start(&mut self, e: &BytesStart, line: usize) {
+        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+        let schema = self.schema;
+
+        match self.open.last_mut() {
+            None if self.has_root => {
+                self.report(line, format!("Element '{}' follows the root element", name));
+            }
+            None => {
+                self.has_root = true;
+                if name != "manifest" {
+                    self.report(
+                        line,
+                        format!("Root element is '{}', expected 'manifest'", name),
+                    );
+                }
+            }
+            Some(parent) => {
+                let message = match schema.elements.get(&parent.name) {
+                    Some(Content::Children(children)) => {
+                        match children.iter().find(|(child, _, _)| *child == name) {
+                            Some((_, once, _)) => {
+                                let count = parent.children.entry(name.clone()).or_insert(0);
+                                *count += 1;
+                                (*once && *count == 2).then(|| {
+                                    format!(
+                                        "Element '{}' may appear only once in '{}'",
+                                        name, parent.name
+                                    )
+                                })
+                            }
+                            None => Some(format!(
+                                "Element '{}' is not allowed in '{}'",
+                                name, parent.name
+                            )),
+                        }
+                    }
+                    Some(_) => Some(format!(
+                        "Element '{}' is not allowed in '{}'",
+                        name, parent.name
+                    )),
+                    // Reported when the parent was opened.
+                    None => None,
+                };
+                if let Some(message) = message {
+                    self.report(line, message);
+                }
+            }
+        }
+
+        if !schema.elements.contains_key(&name) {
+            self.report(line, format!("Element '{}' is not declared", name));
+            return;
+        }
+        let declared = schema
+            .attributes
+            .get(&name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut present = Vec::new();
+        for attr in e.attributes() {
+            let attr = match attr {
+                Ok(attr) => attr,
+                Err(err) => {
+                    self.report(line, err.to_string());
+                    continue;
+                }
+            };
+            let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+            let value = String::from_utf8_lossy(&attr.value).into_owned();
+            match declared.iter().find(|a| a.name == key) {
+                None => self.report(
+                    line,
+                    format!("Attribute '{}' is not declared for element '{}'", key, name),
+                ),
+                Some(a) if a.kind == "ID" => {
+                    if !self.ids.insert(value.clone()) {
+                        self.report(
+                            line,
+                            format!("'{}' of element '{}' is defined twice", value, name),
+                        );
+                    }
+                }
+                Some(a) if a.kind == "IDREF" => {
+                    self.idrefs.push((line, name.clone(), key.clone(), value));
+                }
+                Some(_) => (),
+            }
+            present.push(key);
+        }
+        for attribute in declared.iter().filter(|a| a.required) {
+            if !present.contains(&attribute.name) {
+                self.report(
+                    line,
+                    format!(
+                        "Element '{}' is missing required attribute '{}'",
+                        name, attribute.name
+                    ),
+                );
+            }
+        }
+    }
+
+    fn end(&mut self, name: &str, children: &HashMap<String, usize>, line: usize) {
+        if let Some(Content::Children(declared)) = self.schema.elements.get(name) {
+            for (child, _, required) in declared {
+                if *required && !children.contains_key(child) {
+                    self.report(
+                        line,
+                        format!("Element '{}' is missing child element '{}'", name, child),
+                    );
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, line: usize) {
+        let Some(open) = self.open.last() else {
+            return;
+        };
+        if !matches!(
+            self.schema.elements.get(&open.name),
+            Some(Content::Text) | None
+        ) {
+            let message = format!("Element '{}' may not contain text", open.name);
+            self.report(line, message);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::groups::Group;
+use crate::revision::Revision;
+use crate::Manifest;
+use std::collections::BTreeMap;
+
+/// What the revision of a project points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RevisionKind {
+    /// A full commit SHA, see [`is_commit_sha`](crate::pin::is_commit_sha).
+    Commit,
+    /// A `refs/tags/` ref.
+    Tag,
+    /// Anything else. Short names of tags count as branches, telling them
+    /// apart needs the remote.
+    Branch,
+    /// Neither the project nor the manifest defaults set a revision.
+    Unset,
+}
+
+/// Counts describing the projects of a manifest, see [`Manifest::stats`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ManifestStats {
+    pub projects: usize,
+    /// Projects per remote, with the default remote applied. `None` counts
+    /// the projects without a remote.
+    pub per_remote: BTreeMap<Option<String>, usize>,
+    /// Projects per group they list, plus `default` for the projects not in
+    /// `notdefault`.
+    pub per_group: BTreeMap<String, usize>,
+    /// Projects per kind of revision, with the defaults applied.
+    pub per_revision_kind: BTreeMap<RevisionKind, usize>,
+    /// Projects with a `clone-depth`.
+    pub shallow: usize,
+    /// Checkout paths used by more than one project, with the names of
+    /// those projects.
+    pub duplicate_paths: BTreeMap<String, Vec<String>>,
+}
+
+impl Manifest {
+    /// Summarizes the projects of the manifest, e.g. for a dashboard
+    /// tracking the hygiene of large manifests.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let stats = manifest.stats();
+    /// println!("{} projects, {} shallow", stats.projects, stats.shallow);
+    /// for (path, names) in &stats.duplicate_paths {
+    ///     println!("{} is used by {}", path, names.join(", "));
+    /// }
+    /// ```
+    pub fn stats(&self) -> ManifestStats {
+        let mut stats = ManifestStats::default();
+        let mut paths: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for project in self.resolved_projects() {
+            stats.projects += 1;
+            let remote = project.remote.as_deref().map(String::from);
+            *stats.per_remote.entry(remote).or_default() += 1;
+            for group in project.effective_groups() {
+                if let Group::Named(_) | Group::Profile(_) | Group::Default | Group::NotDefault =
+                    group
+                {
+                    *stats.per_group.entry(group.to_string()).or_default() += 1;
+                }
+            }
+            let kind = match project.typed_revision() {
+                None => RevisionKind::Unset,
+                Some(Revision::Sha(_)) => RevisionKind::Commit,
+                Some(Revision::Tag(_)) => RevisionKind::Tag,
+                Some(Revision::Branch(_) | Revision::RefsExpression(_)) => RevisionKind::Branch,
+            };
+            *stats.per_revision_kind.entry(kind).or_default() += 1;
+            if project.clone_depth.is_some() {
+                stats.shallow += 1;
+            }
+            let path = project.path.clone().unwrap_or_else(|| project.name.clone());
+            paths.entry(path).or_default().push(project.name);
+        }
+
+        stats.duplicate_paths = paths
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .collect();
+        stats
+    }
+}
@@ -0,0 +1,160 @@
+use crate::sync::load_and_merge_manifests;
+use git_utils::GitCommandBuilder;
+use std::error::Error;
+use std::path::Path;
+use std::sync::mpsc;
+use threadpool::ThreadPool;
+
+/// The state of the checkout of a project, see [`status`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectStatus {
+    pub name: String,
+    /// Checkout path, relative to the target directory.
+    pub path: String,
+    /// Whether the checkout exists. The other fields are empty if not.
+    pub checked_out: bool,
+    /// The checked out branch, `None` for a detached HEAD.
+    pub branch: Option<String>,
+    /// The branch it tracks, e.g. `origin/main`.
+    pub upstream: Option<String>,
+    /// Commits on the branch that are not on its upstream.
+    pub ahead: u32,
+    /// Commits on the upstream that are not on the branch.
+    pub behind: u32,
+    /// Changed and untracked files.
+    pub files: Vec<FileStatus>,
+    /// Why `git status` failed, if it did.
+    pub error: Option<String>,
+}
+
+impl ProjectStatus {
+    /// Whether the checkout has changed or untracked files.
+    pub fn is_dirty(&self) -> bool {
+        !self.files.is_empty()
+    }
+}
+
+/// A changed or untracked file, with the status letters of
+/// `git status --short`, e.g. `M` for modified and `?` for untracked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStatus {
+    /// Path relative to the checkout.
+    pub path: String,
+    /// Status in the index, `.` if unchanged.
+    pub index: char,
+    /// Status in the working tree, `.` if unchanged.
+    pub worktree: char,
+}
+
+/// Gets the state of the checkouts of all projects of the manifest at
+/// `manifest_path` in `target_dir`, like `repo status`, running
+/// `git status` for several projects at once. Local manifests are merged
+/// like [`sync_repos`](crate::sync::sync_repos) does.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::status::status;
+///
+/// for project in status(".repo/manifests/default.xml", ".")? {
+///     if project.is_dirty() || project.ahead > 0 {
+///         println!("{}: {} changed, {} ahead", project.path, project.files.len(), project.ahead);
+///     }
+/// }
+/// ```
+pub fn status(manifest_path: &str, target_dir: &str) -> Result<Vec<ProjectStatus>, Box<dyn Error>> {
+    let manifest = load_and_merge_manifests(manifest_path, None)?;
+    let jobs = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let pool = ThreadPool::new(jobs);
+    let (sender, receiver) = mpsc::channel();
+
+    for (index, project) in manifest.projects.iter().enumerate() {
+        let name = project.name.clone();
+        let path = project.path.clone().unwrap_or_else(|| name.clone());
+        let checkout = Path::new(target_dir).join(&path);
+        let sender = sender.clone();
+        pool.execute(move || {
+            let status = project_status(name, path, &checkout);
+            let _ = sender.send((index, status));
+        });
+    }
+    drop(sender);
+
+    let mut statuses: Vec<_> = receiver.iter().collect();
+    statuses.sort_by_key(|(index, _)| *index);
+    Ok(statuses.into_iter().map(|(_, status)| status).collect())
+}
+
+fn project_status(name: String, path: String, checkout: &Path) -> ProjectStatus {
+    let mut status = ProjectStatus {
+        name,
+        path,
+        ..ProjectStatus::default()
+    };
+    if !checkout.join(".git").exists() {
+        return status;
+    }
+    status.checked_out = true;
+    match GitCommandBuilder::git_status_porcelain()
+        .dir(checkout)
+        .run_with_output()
+    {
+        Ok(output) => parse_porcelain(&output, &mut status),
+        Err(e) => status.error = Some(e.to_string()),
+    }
+    status
+}
+
+// Fills `status` from the output of `git status --porcelain=v2 --branch`.
+fn parse_porcelain(output: &str, status: &mut ProjectStatus) {
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.head" if value != "(detached)" => status.branch = Some(value.to_string()),
+                "branch.upstream" => status.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for count in value.split(' ') {
+                        if let Some(ahead) = count.strip_prefix('+') {
+                            status.ahead = ahead.parse().unwrap_or(0);
+                        } else if let Some(behind) = count.strip_prefix('-') {
+                            status.behind = behind.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => (),
+            }
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("? ") {
+            status.files.push(FileStatus {
+                path: path.to_string(),
+                index: '?',
+                worktree: '?',
+            });
+            continue;
+        }
+        // Ordinary, renamed and unmerged entries have this many fields
+        // before the path, which may contain spaces.
+        let fields = match line.chars().next() {
+            Some('1') => 8,
+            Some('2') => 9,
+            Some('u') => 10,
+            _ => continue,
+        };
+        let parts: Vec<&str> = line.splitn(fields + 1, ' ').collect();
+        let (Some(xy), Some(path)) = (parts.get(1), parts.get(fields)) else {
+            continue;
+        };
+        // Renames end with a tab and the original path.
+        let path = path.split('\t').next().unwrap_or(path).to_string();
+        let mut letters = xy.chars();
+        let index = letters.next().unwrap_or('.');
+        let worktree = letters.next().unwrap_or('.');
+        status.files.push(FileStatus {
+            path,
+            index,
+            worktree,
+        });
+    }
+}
@@ -0,0 +1,113 @@
+use crate::groups::{parse_groups, Group};
+use crate::include::GitIncludeResolver;
+use crate::validate::check_relative_path;
+use crate::{Manifest, SourceKind, Submanifest};
+use std::error::Error;
+use std::path::Path;
+
+/// A submanifest with its manifest, see [`Manifest::load_submanifests`].
+#[derive(Debug, Clone)]
+pub struct LoadedSubmanifest {
+    pub submanifest: Submanifest,
+    pub manifest: Manifest,
+    /// The submanifests of `manifest`, loaded in turn.
+    pub submanifests: Vec<LoadedSubmanifest>,
+}
+
+impl Submanifest {
+    /// The path of the submanifest client below the parent client: `path`,
+    /// or else the name.
+    pub fn relative_path(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The file of the manifest project the submanifest reads:
+    /// `manifest-name`, or else `default.xml`.
+    pub fn manifest_file(&self) -> &str {
+        self.manifest_name.as_deref().unwrap_or("default.xml")
+    }
+}
+
+impl Manifest {
+    /// Loads the manifests of the submanifests from the repo client at
+    /// `repo_dir`, e.g. `.repo`, and the submanifests of those in turn.
+    ///
+    /// Like repo, each submanifest has its own client in
+    /// `submanifests/<path>` below the client of its parent, with its
+    /// manifest project checked out in `manifests`. The manifest read is
+    /// the [`manifest-name`](Submanifest::manifest_file) of the
+    /// submanifest, with includes resolved by
+    /// [`GitIncludeResolver`](crate::include::GitIncludeResolver). The
+    /// `groups` of a submanifest are added to all its projects, and the
+    /// projects and remotes are marked as [`SourceKind::Submanifest`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_file(".repo/manifests/default.xml", None, None)?;
+    /// for loaded in manifest.load_submanifests(".repo")? {
+    ///     println!("{}: {} projects", loaded.submanifest.name, loaded.manifest.projects.len());
+    /// }
+    /// ```
+    pub fn load_submanifests(
+        &self,
+        repo_dir: &str,
+    ) -> Result<Vec<LoadedSubmanifest>, Box<dyn Error>> {
+        load_submanifests(self, Path::new(repo_dir), &[])
+    }
+}
+
+fn load_submanifests(
+    manifest: &Manifest,
+    repo_dir: &Path,
+    parent_groups: &[Group],
+) -> Result<Vec<LoadedSubmanifest>, Box<dyn Error>> {
+    let mut loaded = Vec::new();
+    for submanifest in &manifest.submanifests {
+        let path = submanifest.relative_path();
+        check_relative_path(path).map_err(|e| {
+            format!(
+                "Invalid path '{}' of submanifest '{}': {}",
+                path, submanifest.name, e
+            )
+        })?;
+        let client = repo_dir.join("submanifests").join(path);
+        let manifests = client.join("manifests");
+        let file = manifests.join(submanifest.manifest_file());
+        let mut sub = Manifest::from_file_with_resolver(
+            &file.to_string_lossy(),
+            None,
+            None,
+            &GitIncludeResolver::new(&manifests),
+        )
+        .map_err(|e| {
+            format!(
+                "Cannot load submanifest '{}' from '{}': {}",
+                submanifest.name,
+                file.display(),
+                e
+            )
+        })?;
+        sub.set_source_kind(SourceKind::Submanifest);
+
+        let mut groups = parent_groups.to_vec();
+        groups.extend(parse_groups(submanifest.groups.as_deref().unwrap_or("")));
+        for project in &mut sub.projects {
+            for group in &groups {
+                if !project.groups.contains(group) {
+                    project.groups.push(group.clone());
+                }
+            }
+        }
+
+        let submanifests = load_submanifests(&sub, &client, &groups)?;
+        loaded.push(LoadedSubmanifest {
+            submanifest: submanifest.clone(),
+            manifest: sub,
+            submanifests,
+        });
+    }
+    Ok(loaded)
+}
@@ -0,0 +1,105 @@
+use crate::Manifest;
+use std::error::Error;
+
+/// The projects [`Manifest::subset`] keeps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// The projects with these names or paths.
+    Projects(Vec<String>),
+    /// The projects selected by a group filter, see
+    /// [`Project::matches_groups`](crate::Project::matches_groups).
+    Groups(String),
+}
+
+impl Manifest {
+    /// Returns a manifest with only the selected projects and the remotes
+    /// they and the `<default>` use, e.g. to hand a minimal manifest to a
+    /// partner.
+    ///
+    /// The notice, `<default>` and `<contactinfo>` are kept, and
+    /// `<repo-hooks>` if its project is selected. Includes, submanifests,
+    /// `<remove-project>`, `<extend-project>`, `<manifest-server>` and
+    /// `<superproject>` describe the whole manifest and are dropped. Fails
+    /// if a project of [`Selection::Projects`] is not in the manifest.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::subset::Selection;
+    ///
+    /// let partner = manifest.subset(&Selection::Groups("partner,-internal".to_string()))?;
+    /// std::fs::write("partner.xml", partner.to_xml())?;
+    /// ```
+    pub fn subset(&self, selection: &Selection) -> Result<Manifest, Box<dyn Error>> {
+        let projects = match selection {
+            Selection::Projects(names) => {
+                let mut projects = Vec::new();
+                for name in names {
+                    let project = self
+                        .find_project_by_name(name)
+                        .or_else(|| self.find_project_by_path(name))
+                        .ok_or_else(|| format!("Project '{}' is not in the manifest", name))?;
+                    if !projects.iter().any(|p| std::ptr::eq(*p, project)) {
+                        projects.push(project);
+                    }
+                }
+                // Keep the order of the manifest.
+                self.projects
+                    .iter()
+                    .filter(|p| projects.iter().any(|selected| std::ptr::eq(*p, *selected)))
+                    .cloned()
+                    .collect()
+            }
+            Selection::Groups(filter) => self
+                .projects
+                .iter()
+                .filter(|project| project.matches_groups(filter))
+                .cloned()
+                .collect::<Vec<_>>(),
+        };
+
+        let default_remote = self.default.as_ref().and_then(|d| d.remote.as_deref());
+        let remotes = self
+            .remotes
+            .iter()
+            .filter(|remote| {
+                Some(remote.name.as_str()) == default_remote
+                    || projects
+                        .iter()
+                        .any(|project| project.remote.as_deref() == Some(&remote.name))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let repo_hooks = self
+            .repo_hooks
+            .clone()
+            .filter(|hooks| projects.iter().any(|p| p.name == hooks.in_project));
+        let comments = self
+            .comments
+            .iter()
+            .filter(|comment| match comment.element.as_deref() {
+                Some("project") => projects
+                    .iter()
+                    .any(|p| Some(&p.name) == comment.name.as_ref() && p.path == comment.path),
+                Some("remote") => remotes
+                    .iter()
+                    .any(|r| Some(&r.name) == comment.name.as_ref()),
+                Some("repo-hooks") => repo_hooks.is_some(),
+                Some("notice" | "default" | "contactinfo") | None => true,
+                Some(_) => false,
+            })
+            .cloned()
+            .collect();
+
+        Ok(Manifest {
+            notice: self.notice.clone(),
+            remotes,
+            default: self.default.clone(),
+            projects,
+            repo_hooks,
+            contactinfo: self.contactinfo.clone(),
+            comments,
+            ..Manifest::default()
+        })
+    }
+}
@@ -1,20 +1,60 @@
-use crate::{Manifest, Project};
-use log::{debug, error};
+pub use crate::merge::ConflictPolicy;
+use crate::merge::{merge_manifests, MergeOptions};
+use crate::pin::is_commit_sha;
+use crate::query::ResolvedProject;
+use crate::revision::Revision;
+use crate::{link, Manifest, Project, RemoveProject, SourceKind};
+use git_utils::GitCommand;
+use log::{debug, error, warn};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs;
-use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus};
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use threadpool::ThreadPool;
 
-/// Trait for running git commands, used for mocking in tests.
+/// Trait for running git commands, used for mocking in tests or running
+/// them another way, see [`sync_repos_with_runner`].
 pub trait GitCommandRunner {
     fn run_git_command(
         &self,
         project_path: &Path,
         args: &[&str],
     ) -> Result<ExitStatus, Box<dyn Error>>;
+
+    /// Runs git like [`run_git_command`](Self::run_git_command), returning
+    /// its trimmed standard output. Runs the `git` executable unless
+    /// overridden.
+    fn git_output(&self, project_path: &Path, args: &[&str]) -> Result<String, Box<dyn Error>> {
+        let output = GitCommand::new("git")
+            .args(args)
+            .dir(project_path)
+            .run_with_output()?;
+        Ok(output.trim().to_string())
+    }
+
+    /// Runs git like [`run_git_command`](Self::run_git_command), for the
+    /// commands of a sync that reach remotes: fetches, Git LFS pulls and
+    /// `ls-remote`. Returns its trimmed standard output.
+    ///
+    /// Implementations must add `env` to the environment of git, e.g.
+    /// `GIT_SSH_COMMAND` for the SSH settings of [`GitAuth`]. Once `cancel`
+    /// is cancelled, they must stop git and the processes it started, e.g.
+    /// `git-remote-https` or `ssh`, and fail with [`SYNC_CANCELLED`], or
+    /// the sync waits for the command to end.
+    fn run_remote_git_command(
+        &self,
+        project_path: &Path,
+        args: &[&str],
+        env: &[(&str, &str)],
+        cancel: Option<&CancelToken>,
+    ) -> Result<String, Box<dyn Error>>;
 }
 
 /// Default implementation of GitCommandRunner.
@@ -31,18 +71,177 @@ impl GitCommandRunner for DefaultGitCommandRunner {
         for arg in args {
             cmd.arg(arg);
         }
-        let status = cmd.status()?;
-        if !status.success() {
+        let output = cmd.stderr(Stdio::piped()).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(
+                std::io::Error::other(format!("Git command failed: {}", stderr.trim())).into(),
+            );
+        }
+        Ok(output.status)
+    }
+
+    fn run_remote_git_command(
+        &self,
+        project_path: &Path,
+        args: &[&str],
+        env: &[(&str, &str)],
+        cancel: Option<&CancelToken>,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut command = Command::new("git");
+        command
+            .arg("-C")
+            .arg(project_path)
+            .args(args)
+            .envs(env.iter().copied())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let output = run_cancellable(&mut command, cancel)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
             return Err(
-                std::io::Error::new(std::io::ErrorKind::Other, "Git command failed").into(),
+                std::io::Error::other(format!("Git command failed: {}", stderr.trim())).into(),
             );
         }
-        Ok(status)
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// The error of a sync, or of a git command of it, stopped by a
+/// [`CancelToken`].
+pub const SYNC_CANCELLED: &str = "Sync cancelled";
+
+/// Stops the syncs given it in [`SyncOptions::cancel`], e.g. from the
+/// "cancel" button of a UI. Clones cancel the same syncs.
+///
+/// Once cancelled, a sync starts no more projects and kills the git
+/// fetches and clone bundle downloads running, then fails with
+/// [`SYNC_CANCELLED`]. On Unix they run in process groups of their own,
+/// so that the processes git starts are killed with it; a Ctrl-C in the
+/// terminal no longer reaches them directly. Checkouts running are finished, and the sync state
+/// of an incremental sync is written, so that syncing again picks up the
+/// projects that were not synced.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::sync::{sync_repos, CancelToken};
+///
+/// let cancel = CancelToken::new();
+/// let options = SyncOptions { cancel: Some(cancel.clone()), ..options };
+/// std::thread::spawn(move || sync_repos("default.xml", None, options, "workspace"));
+/// cancel.cancel();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the syncs given this token or a clone of it.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// How often commands of a sync that can be cancelled check for it.
+pub(crate) const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// Runs `command` like `Command::output` with the stdio configured on it,
+// killing it and the processes it started once `cancel` is cancelled.
+fn run_cancellable(
+    command: &mut Command,
+    cancel: Option<&CancelToken>,
+) -> Result<Output, Box<dyn Error>> {
+    let Some(cancel) = cancel else {
+        return Ok(command.spawn()?.wait_with_output()?);
+    };
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    let mut child = command.spawn()?;
+    // Read by threads, so that the child does not block on a full pipe.
+    let stdout = child.stdout.take().map(read_pipe);
+    let stderr = child.stderr.take().map(read_pipe);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if cancel.is_cancelled() {
+            kill_process_group(&mut child);
+            // The readers are left behind, as processes that left the
+            // group may keep the pipes open.
+            let _ = child.wait();
+            return Err(SYNC_CANCELLED.into());
+        }
+        std::thread::sleep(CANCEL_POLL_INTERVAL);
+    };
+    let join = |reader: Option<JoinHandle<Vec<u8>>>| {
+        reader.map_or_else(Vec::new, |reader| reader.join().unwrap_or_default())
+    };
+    Ok(Output {
+        status,
+        stdout: join(stdout),
+        stderr: join(stderr),
+    })
+}
+
+// Kills `child` and the processes of its group.
+fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: killpg only sends a signal.
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
     }
+    #[cfg(not(unix))]
+    let _ = child.kill();
+}
+
+fn read_pipe(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut content = Vec::new();
+        let _ = pipe.read_to_end(&mut content);
+        content
+    })
+}
+
+/// What [`sync_repos_with_observer`] reports while syncing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncEvent {
+    /// Syncing starts, `total` projects are to be synced.
+    Started { total: usize },
+    /// A job starts fetching or cloning the project `name`.
+    ProjectStarted { name: String },
+    /// The project `name` is synced, or failed with `error`.
+    ProjectFinished { name: String, error: Option<String> },
+    /// All jobs are done, before copyfiles and linkfiles are handled.
+    Finished,
+}
+
+/// Receives the [`SyncEvent`]s of a sync, from the sync jobs' threads.
+pub trait SyncObserver: Send + Sync {
+    fn on_event(&self, event: &SyncEvent);
+}
+
+// The observer of `sync_repos`.
+struct NoObserver;
+
+impl SyncObserver for NoObserver {
+    fn on_event(&self, _event: &SyncEvent) {}
 }
 
 /// Syncs the repositories defined in the manifest.
 ///
+/// Projects storing files with Git LFS, detected by their `.lfsconfig` or
+/// `filter=lfs` attributes, get their LFS files pulled after checkout,
+/// which needs `git lfs`. An annotation named `lfs` with the value `true`
+/// or `false` overrides the detection.
+///
 /// # Arguments
 ///
 /// * `manifest_path` - A string slice that holds the path to the manifest XML file.
@@ -53,16 +252,19 @@ impl GitCommandRunner for DefaultGitCommandRunner {
 /// # Example
 ///
 /// ```ignore
-/// use manifest_parser::sync::{sync_repos, SyncOptions};
+/// use manifest_parser::sync::{sync_repos, CloneFilter, DirtyTreePolicy, SyncOptions};
 ///
 /// let options = SyncOptions {
-///     current_branch_only: false,
-///     detach: false,
-///     force: false,
-///     jobs: None,
-///     quiet: false,
-///     smart_sync: false,
+///     force: DirtyTreePolicy::Stash,
+///     jobs_network: Some(8),
 ///     keep: true,
+///     groups: Some("default,-tools".to_string()),
+///     depth: Some(1),
+///     reference: Some("/srv/mirror".into()),
+///     filter: Some(CloneFilter::BlobNone),
+///     incremental: true,
+///     clone_bundle: true,
+///     ..Default::default()
 /// };
 /// sync_repos("path/to/manifest.xml", None, options, "path/to/target/dir").unwrap();
 /// ```
@@ -72,24 +274,129 @@ pub fn sync_repos(
     options: SyncOptions,
     target_dir: &str,
 ) -> Result<(), Box<dyn Error>> {
+    sync_repos_with_report(manifest_path, project_list, options, target_dir).map(|_| ())
+}
+
+/// Like [`sync_repos`], also returning what was done for each project.
+pub fn sync_repos_with_report(
+    manifest_path: &str,
+    project_list: Option<Vec<&str>>,
+    options: SyncOptions,
+    target_dir: &str,
+) -> Result<SyncReport, Box<dyn Error>> {
+    sync_repos_with_observer(
+        manifest_path,
+        project_list,
+        options,
+        target_dir,
+        Arc::new(NoObserver),
+    )
+}
+
+/// What a sync did for each project, in manifest order. Projects skipped
+/// after an error without [`SyncOptions::keep`] are missing.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub projects: Vec<ProjectSyncReport>,
+}
+
+/// What a sync did for a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSyncReport {
+    pub name: String,
+    /// Checkout path, relative to the target directory.
+    pub path: String,
+    /// What was done with local changes of the checkout, `None` if it had
+    /// none or was cloned.
+    pub local_changes: Option<LocalChanges>,
+    /// Whether the checkout was left alone because it is already at the
+    /// revision of the manifest, see [`SyncOptions::incremental`].
+    pub skipped: bool,
+    pub error: Option<String>,
+}
+
+/// What to do with uncommitted changes to tracked files of a checkout
+/// before updating it, see [`SyncOptions::force`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirtyTreePolicy {
+    /// Fail the project, leaving the checkout alone.
+    #[default]
+    Fail,
+    /// Stash the changes and apply them again after the update.
+    Stash,
+    /// Discard the changes, resetting the checkout to the revision
+    /// rather than rebasing local commits of its branch.
+    Discard,
+}
+
+/// What was done with the local changes of a checkout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalChanges {
+    /// Stashed and applied again after the update.
+    Reapplied,
+    /// Stashed, but applying them again conflicted with the update. The
+    /// checkout has conflicts and the changes are kept in the stash.
+    Conflicted,
+    /// Discarded.
+    Discarded,
+}
+
+/// Like [`sync_repos_with_report`], reporting progress to `observer`, e.g.
+/// the progress bars of `progress::ProgressBars` with the `progress`
+/// feature.
+pub fn sync_repos_with_observer(
+    manifest_path: &str,
+    project_list: Option<Vec<&str>>,
+    options: SyncOptions,
+    target_dir: &str,
+    observer: Arc<dyn SyncObserver>,
+) -> Result<SyncReport, Box<dyn Error>> {
+    sync_repos_with_runner(
+        manifest_path,
+        project_list,
+        options,
+        target_dir,
+        observer,
+        Arc::new(DefaultGitCommandRunner),
+    )
+}
+
+/// Like [`sync_repos_with_observer`], running the git commands of the sync
+/// with `runner`, e.g. to record them or run them on another machine.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::sync::{sync_repos_with_runner, DefaultGitCommandRunner};
+///
+/// let report = sync_repos_with_runner(
+///     "default.xml",
+///     None,
+///     options,
+///     "workspace",
+///     Arc::new(ProgressBars::new()),
+///     Arc::new(DefaultGitCommandRunner),
+/// )?;
+/// ```
+pub fn sync_repos_with_runner(
+    manifest_path: &str,
+    project_list: Option<Vec<&str>>,
+    options: SyncOptions,
+    target_dir: &str,
+    observer: Arc<dyn SyncObserver>,
+    runner: Arc<dyn GitCommandRunner + Send + Sync>,
+) -> Result<SyncReport, Box<dyn Error>> {
     debug!("sync_repos called with:");
     debug!("  manifest_path: {}", manifest_path);
     debug!("  project_list: {:#?}", project_list);
     debug!("  target_dir: {}", target_dir);
     debug!("  options: {:?}", options);
 
-    let manifest = load_and_merge_manifests(manifest_path, None)?;
+    // Shared by the jobs, as copying the projects of large manifests for
+    // every job is costly.
+    let manifest = Arc::new(load_and_merge_manifests(manifest_path, None)?);
 
-    let projects_to_sync: Vec<_> = match project_list {
-        Some(list) => manifest
-            .projects
-            .clone()
-            .into_iter()
-            .filter(|p| list.contains(&p.name.as_str()))
-            .collect(),
-        None => manifest.projects.clone(), // Sync all projects if project_list is None
-    };
-    debug!("Projects to sync: {:#?}", projects_to_sync);
+    let projects_to_sync = select_projects(&manifest, project_list.as_deref(), &options);
 
     let target_path = Path::new(target_dir);
 
@@ -97,63 +404,276 @@ pub fn sync_repos(
     if !target_path.exists() {
         fs::create_dir_all(target_path)?;
     }
+    let _credentials = write_credentials(&manifest, &options, target_path)?;
 
-    // Determine the number of jobs to use
-    let jobs = determine_jobs(&manifest, &options);
-    debug!("Number of jobs: {}", jobs);
+    let jobs_network = determine_jobs(&manifest, options.jobs_network.or(options.jobs));
+    let jobs_checkout = determine_jobs(&manifest, options.jobs_checkout.or(options.jobs));
+    debug!(
+        "Number of jobs: {} network, {} checkout",
+        jobs_network, jobs_checkout
+    );
 
+    let state_path = target_path.join(".repo").join(SYNC_STATE_FILE);
+    let state = Arc::new(Mutex::new(if options.incremental {
+        read_sync_state(&state_path)?
+    } else {
+        SyncState::new()
+    }));
     let errors = Arc::new(Mutex::new(Vec::new()));
-    let pool = ThreadPool::new(jobs);
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    // Projects fetched, and whether they were cloned, to check out.
+    let fetched = Arc::new(Mutex::new(Vec::new()));
     let stop_flag = Arc::new(AtomicBool::new(false));
+    observer.on_event(&SyncEvent::Started {
+        total: projects_to_sync.len(),
+    });
 
-    for project in projects_to_sync.clone() {
+    // Every project is fetched before any is checked out, like repo does,
+    // so that each phase runs its own number of jobs.
+    let pool = ThreadPool::new(jobs_network);
+    for &index in &projects_to_sync {
         let stop_flag = Arc::clone(&stop_flag);
-        if !options.keep && stop_flag.load(Ordering::Relaxed) {
+        if options.is_cancelled() || !options.keep && stop_flag.load(Ordering::Relaxed) {
             break;
         }
         let errors = Arc::clone(&errors);
-        let manifest = manifest.clone();
+        let reports = Arc::clone(&reports);
+        let fetched = Arc::clone(&fetched);
+        let state = Arc::clone(&state);
+        let manifest = Arc::clone(&manifest);
         let target_path = target_path.to_path_buf();
         let options = options.clone();
+        let observer = Arc::clone(&observer);
+        let runner = Arc::clone(&runner);
 
         pool.execute(move || {
-            if !options.keep && stop_flag.load(Ordering::Relaxed) {
+            let git: &dyn GitCommandRunner = &*runner;
+            if options.is_cancelled() || !options.keep && stop_flag.load(Ordering::Relaxed) {
                 return;
             }
-            if let Err(e) = process_project(&project, &manifest, &target_path, &options) {
-                let mut errors = errors.lock().unwrap();
-                errors.push((project.name.clone(), e.to_string()));
-                stop_flag.store(true, Ordering::Relaxed);
+            let project = &manifest.projects[index];
+            observer.on_event(&SyncEvent::ProjectStarted {
+                name: project.name.clone(),
+            });
+            let path = project.path.clone().unwrap_or_else(|| project.name.clone());
+            let synced = if options.incremental {
+                state.lock().unwrap().remove(&path)
+            } else {
+                None
+            };
+            if let Some(synced) = synced {
+                if is_synced(git, project, &manifest, &target_path, &options, &synced) {
+                    debug!("{} is already synced, skipping", project.name);
+                    state.lock().unwrap().insert(path, synced);
+                    finish_project(
+                        &*observer,
+                        &errors,
+                        &reports,
+                        index,
+                        project,
+                        true,
+                        Ok(None),
+                    );
+                    return;
+                }
+            }
+            match fetch_project(git, project, &manifest, &target_path, &options) {
+                Ok(cloned) => fetched.lock().unwrap().push((index, cloned)),
+                Err(e) => {
+                    stop_flag.store(true, Ordering::Relaxed);
+                    let error = e.to_string();
+                    run_failure_hook(&options, &manifest, project, &target_path, &error);
+                    finish_project(
+                        &*observer,
+                        &errors,
+                        &reports,
+                        index,
+                        project,
+                        false,
+                        Err(error),
+                    );
+                }
             }
         });
     }
+    pool.join();
+
+    // Projects fetched before an error are still checked out, as they were
+    // started.
+    let pool = ThreadPool::new(jobs_checkout);
+    for (index, cloned) in std::mem::take(&mut *fetched.lock().unwrap()) {
+        let errors = Arc::clone(&errors);
+        let reports = Arc::clone(&reports);
+        let state = Arc::clone(&state);
+        let manifest = Arc::clone(&manifest);
+        let target_path = target_path.to_path_buf();
+        let options = options.clone();
+        let observer = Arc::clone(&observer);
+        let runner = Arc::clone(&runner);
 
+        pool.execute(move || {
+            let git: &dyn GitCommandRunner = &*runner;
+            let project = &manifest.projects[index];
+            if options.is_cancelled() {
+                let error = Err(SYNC_CANCELLED.to_string());
+                finish_project(&*observer, &errors, &reports, index, project, false, error);
+                return;
+            }
+            let result = checkout_project(git, project, &manifest, &target_path, &options, cloned)
+                .map_err(|e| e.to_string());
+            if let Err(e) = &result {
+                run_failure_hook(&options, &manifest, project, &target_path, e);
+            }
+            if options.incremental && result.is_ok() {
+                if let Some(synced) = head_state(git, project, &manifest, &target_path) {
+                    let path = project.path.clone().unwrap_or_else(|| project.name.clone());
+                    state.lock().unwrap().insert(path, synced);
+                }
+            }
+            finish_project(&*observer, &errors, &reports, index, project, false, result);
+        });
+    }
     pool.join();
 
+    observer.on_event(&SyncEvent::Finished);
+    if options.incremental {
+        // Written before failing for errors, so that the projects synced
+        // are skipped when trying again.
+        write_sync_state(&state_path, &state.lock().unwrap())?;
+    }
+    if options.is_cancelled() {
+        return Err(SYNC_CANCELLED.into());
+    }
+
     handle_errors(errors, options.keep)?;
 
-    for project in projects_to_sync {
+    link_files(&manifest, &projects_to_sync, target_path)?;
+
+    let mut reports = std::mem::take(&mut *reports.lock().unwrap());
+    reports.sort_by_key(|(index, _)| *index);
+    Ok(SyncReport {
+        projects: reports.into_iter().map(|(_, report)| report).collect(),
+    })
+}
+
+// Indices of the projects of `manifest` to sync: those of `project_list`,
+// or else all of them, that match the group filter of `options`.
+pub(crate) fn select_projects(
+    manifest: &Manifest,
+    project_list: Option<&[&str]>,
+    options: &SyncOptions,
+) -> Vec<usize> {
+    let projects_to_sync: Vec<usize> = manifest
+        .projects
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| project_list.is_none_or(|list| list.contains(&p.name.as_str())))
+        .filter(|(_, p)| {
+            options
+                .groups
+                .as_ref()
+                .is_none_or(|groups| p.matches_groups(groups))
+        })
+        .map(|(index, _)| index)
+        .collect();
+    debug!(
+        "Projects to sync: {:#?}",
+        projects_to_sync
+            .iter()
+            .map(|&index| &manifest.projects[index].name)
+            .collect::<Vec<_>>()
+    );
+    projects_to_sync
+}
+
+// Where the destinations of the copyfiles and linkfiles created are kept
+// in `.repo`, one per line, relative to the target directory.
+const LINKED_FILES_FILE: &str = "copy-link-files";
+
+// Copies and links the files of the synced projects, once all of them are
+// checked out, and removes those created by earlier syncs that no project
+// declares anymore.
+pub(crate) fn link_files(
+    manifest: &Manifest,
+    projects_to_sync: &[usize],
+    target_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let state_path = target_path.join(".repo").join(LINKED_FILES_FILE);
+    let created = match fs::read_to_string(&state_path) {
+        Ok(content) => content.lines().map(str::to_string).collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let declared: HashSet<&str> = manifest
+        .projects
+        .iter()
+        .flat_map(|project| {
+            let copyfiles = project.copyfiles.iter().map(|c| c.dest.as_str());
+            copyfiles.chain(project.linkfiles.iter().map(|l| l.dest.as_str()))
+        })
+        .collect();
+    let mut linked: Vec<String> = Vec::new();
+    for dest in created {
+        if declared.contains(dest.as_str()) {
+            linked.push(dest);
+        } else {
+            debug!("Removing {}, no longer declared", dest);
+            remove_linked_file(&target_path.join(&dest), target_path)?;
+        }
+    }
+
+    for &index in projects_to_sync {
+        let project = &manifest.projects[index];
         debug!("Processing project: {:?}", project.name);
         let project_path_str = project.path.clone().unwrap_or_else(|| project.name.clone());
         let project_path = target_path.join(&project_path_str);
-        for copyfile in project.copyfiles {
-            handle_copyfiles_and_linkfiles(
-                &project_path.join(&copyfile.src),
-                &target_path.join(&copyfile.dest),
-                target_path,
-                false,
-            )?;
-        }
-        for linkfile in project.linkfiles {
+        let files = project
+            .copyfiles
+            .iter()
+            .map(|copyfile| (&copyfile.src, &copyfile.dest, false))
+            .chain(
+                project
+                    .linkfiles
+                    .iter()
+                    .map(|linkfile| (&linkfile.src, &linkfile.dest, true)),
+            );
+        for (src, dest, is_symlink) in files {
             handle_copyfiles_and_linkfiles(
-                &project_path.join(&linkfile.src),
-                &target_path.join(&linkfile.dest),
+                &project_path.join(src),
+                &target_path.join(dest),
                 target_path,
-                true,
+                is_symlink,
             )?;
+            linked.push(dest.clone());
         }
     }
 
+    linked.sort();
+    linked.dedup();
+    if let Some(parent) = state_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content: String = linked.iter().map(|dest| format!("{}\n", dest)).collect();
+    fs::write(&state_path, content)?;
+    Ok(())
+}
+
+// Removes the copyfile or linkfile `dest`, and the directories that
+// only held it, up to `target_path`.
+fn remove_linked_file(dest: &Path, target_path: &Path) -> Result<(), Box<dyn Error>> {
+    match fs::symlink_metadata(dest) {
+        Ok(metadata) if !metadata.is_dir() => link::remove_link(dest)?,
+        Ok(_) => return Err(format!("Destination '{}' is a directory", dest.display()).into()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    }
+    let mut parent = dest.parent();
+    while let Some(dir) = parent.filter(|dir| dir.starts_with(target_path) && *dir != target_path) {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
     Ok(())
 }
 
@@ -171,8 +691,16 @@ fn handle_copyfiles_and_linkfiles(
     target_path: &Path,
     is_symlink: bool,
 ) -> Result<(), Box<dyn Error>> {
-    // Ensure src and dest do not go above target_path
-    if !src.starts_with(target_path) || !dest.starts_with(target_path) {
+    // Ensure src and dest do not go above target_path, which a `..`
+    // component would do despite the prefix
+    let within = |path: &Path| {
+        path.strip_prefix(target_path).is_ok_and(|relative| {
+            relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        })
+    };
+    if !within(src) || !within(dest) {
         return Err("Source or destination path is outside the target directory".into());
     }
 
@@ -181,7 +709,9 @@ fn handle_copyfiles_and_linkfiles(
         return Err(format!("Source '{}' does not exist", src.display()).into());
     }
 
-    if dest.exists() && dest.is_dir() {
+    // Not following symlinks, which earlier syncs may have created
+    let existing = fs::symlink_metadata(dest).ok();
+    if existing.as_ref().is_some_and(|metadata| metadata.is_dir()) {
         return Err(format!("Destination '{}' is a directory", dest.display()).into());
     }
 
@@ -190,8 +720,19 @@ fn handle_copyfiles_and_linkfiles(
         fs::create_dir_all(parent)?;
     }
 
+    // Replace the symlink or copy of an earlier sync, unless it is the
+    // symlink to create
+    if let Some(metadata) = &existing {
+        if is_symlink && metadata.is_symlink() && fs::read_link(dest)? == src {
+            return Ok(());
+        }
+        if is_symlink || metadata.is_symlink() {
+            link::remove_link(dest)?;
+        }
+    }
+
     if is_symlink {
-        std::os::unix::fs::symlink(src, dest)?;
+        link::link_or_copy(src, dest)?;
     } else {
         if !src.is_file() {
             return Err(format!("Source '{}' is not a file", src.display()).into());
@@ -221,238 +762,1016 @@ pub fn load_and_merge_manifests(
     manifest_path: &str,
     local_manifests_dir: Option<&str>,
 ) -> Result<Manifest, Box<dyn Error>> {
-    let default_remote = Some("origin");
-    let default_revision = Some("main");
+    load_and_merge_manifests_with(manifest_path, local_manifests_dir, &MergeOptions::new())
+        .map(|(manifest, _)| manifest)
+}
 
-    let mut manifest = Manifest::from_file(manifest_path, default_remote, default_revision)?;
+/// Like [`load_and_merge_manifests`], merging the local manifests
+/// according to `options` and also returning what each of them changed.
+///
+/// With [`MergeOptions::conflicts`] set to [`ConflictPolicy::Error`], local
+/// manifests changing the same project differently fail with
+/// [`MergeConflicts`]; with [`MergeOptions::strict`], `<remove-project>`
+/// elements that match no project fail with [`UnmatchedRemovals`].
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::merge::MergeOptions;
+/// use manifest_parser::sync::{load_and_merge_manifests_with, ConflictPolicy};
+///
+/// let options = MergeOptions::new().conflicts(ConflictPolicy::Error).strict(true);
+/// let (manifest, report) =
+///     load_and_merge_manifests_with(".repo/manifests/default.xml", None, &options)?;
+/// for local in &report.manifests {
+///     println!("{}: added {:?}", local.file.display(), local.added);
+/// }
+/// ```
+pub fn load_and_merge_manifests_with(
+    manifest_path: &str,
+    local_manifests_dir: Option<&str>,
+    options: &MergeOptions,
+) -> Result<(Manifest, MergeReport), Box<dyn Error>> {
+    load_and_merge(manifest_path, local_manifests_dir, options)
+}
 
-    // Determine the local manifests directory
-    let local_manifests_dir = local_manifests_dir.map(PathBuf::from).unwrap_or_else(|| {
-        let manifest_dir = Path::new(manifest_path).parent().unwrap();
-        manifest_dir.join(".repo/local_manifests")
-    });
+/// What the local manifests merged into a manifest changed, in the order
+/// they were merged.
+#[derive(Debug, Clone, Default)]
+pub struct MergeReport {
+    pub manifests: Vec<LocalManifestReport>,
+    /// The changes of local manifests that conflict with an earlier one,
+    /// whatever the [`ConflictPolicy`].
+    pub conflicts: Vec<MergeConflict>,
+}
 
-    // Load and merge local manifests
-    if local_manifests_dir.exists() {
-        for entry in fs::read_dir(local_manifests_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("xml") {
-                let local_manifest =
-                    Manifest::from_file(path.to_str().unwrap(), default_remote, default_revision)?;
-                merge_manifests(&mut manifest, local_manifest);
-            }
+/// What a [`MergeConflict`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictKind {
+    /// The `revision` of a project or `<extend-project>`.
+    Revision,
+    /// The `path` of a project or `dest-path` of an `<extend-project>`.
+    Path,
+}
+
+impl std::fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConflictKind::Revision => write!(f, "revision"),
+            ConflictKind::Path => write!(f, "path"),
         }
     }
+}
+
+/// Two local manifests setting the revision or path of a project to
+/// different values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// The name of the project.
+    pub project: String,
+    pub kind: ConflictKind,
+    /// The local manifest that set the value first, and the value.
+    pub first_file: PathBuf,
+    pub first_value: String,
+    /// The local manifest that set it again, and its value.
+    pub second_file: PathBuf,
+    pub second_value: String,
+}
+
+impl std::fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' sets the {} of project '{}' to '{}', '{}' to '{}'",
+            self.first_file.display(),
+            self.kind,
+            self.project,
+            self.first_value,
+            self.second_file.display(),
+            self.second_value
+        )
+    }
+}
 
-    Ok(manifest)
+/// The conflicts between local manifests, see [`ConflictPolicy::Error`].
+#[derive(Debug)]
+pub struct MergeConflicts {
+    pub conflicts: Vec<MergeConflict>,
 }
 
-fn merge_manifests(base: &mut Manifest, local: Manifest) {
-    // Remove projects specified in remove_projects
-    for remove_project in &local.remove_projects {
-        debug!("Processing remove-project: {:?}", remove_project);
-        base.projects.retain(|project| {
-            let mut should_remove = false;
-            if let Some(name) = &remove_project.name {
-                if project.name == *name {
-                    if let Some(path) = &remove_project.path {
-                        should_remove = project.path.as_deref() == Some(path);
-                    } else {
-                        should_remove = true;
-                    }
-                }
-            } else if let Some(path) = &remove_project.path {
-                should_remove = project.path.as_deref() == Some(path);
-            }
+impl std::fmt::Display for MergeConflicts {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let conflicts: Vec<String> = self.conflicts.iter().map(|c| c.to_string()).collect();
+        write!(f, "Local manifests conflict: {}", conflicts.join("; "))
+    }
+}
+
+impl Error for MergeConflicts {}
+
+// The revisions and paths local manifests set, by project, to find the
+// ones a later local manifest sets differently.
+struct Claims {
+    policy: ConflictPolicy,
+    claims: HashMap<(String, ConflictKind), (PathBuf, String)>,
+    conflicts: Vec<MergeConflict>,
+}
 
-            if should_remove {
-                if let Some(base_rev) = &remove_project.base_rev {
-                    if project.revision.as_deref() != Some(base_rev) {
-                        debug!(
-                            "Revision mismatch for project '{}': expected '{}', found '{}'",
-                            project.name,
-                            base_rev,
-                            project.revision.as_deref().unwrap_or("none")
-                        );
-                        return true;
-                    }
+impl Claims {
+    // Records that `file` sets `kind` of `project` to `value`, replacing
+    // `value` with the earlier one if that one wins.
+    fn claim<T>(&mut self, file: &Path, project: &str, kind: ConflictKind, value: &mut Option<T>)
+    where
+        T: std::ops::Deref<Target = str> + for<'a> From<&'a str>,
+    {
+        let Some(second) = value else {
+            return;
+        };
+        let key = (project.to_string(), kind);
+        match self.claims.get(&key) {
+            Some((first_file, first)) if first_file != file && **first != **second => {
+                let conflict = MergeConflict {
+                    project: project.to_string(),
+                    kind,
+                    first_file: first_file.clone(),
+                    first_value: first.clone(),
+                    second_file: file.to_path_buf(),
+                    second_value: second.to_string(),
+                };
+                warn!("Conflicting local manifests: {}", conflict);
+                self.conflicts.push(conflict);
+                if self.policy == ConflictPolicy::FirstWins {
+                    *value = Some(T::from(first));
+                    return;
                 }
-                debug!("Removing project: {:?}", project);
-                return false;
             }
-            true
-        });
+            _ => (),
+        }
+        self.claims
+            .insert(key, (file.to_path_buf(), second.to_string()));
+    }
 
-        if remove_project.optional.as_deref() == Some("true")
-            && !base.projects.iter().any(|p| {
-                if let Some(name) = &remove_project.name {
-                    if p.name == *name {
-                        if let Some(path) = &remove_project.path {
-                            return p.path.as_deref() == Some(path);
-                        }
-                        return true;
-                    }
-                } else if let Some(path) = &remove_project.path {
-                    return p.path.as_deref() == Some(path);
-                }
-                false
-            })
-        {
-            debug!(
-                "Optional remove-project element did not match any project: {:?}",
-                remove_project
+    fn check(&mut self, file: &Path, manifest: &mut Manifest) {
+        for extend in &mut manifest.extend_projects {
+            self.claim(
+                file,
+                &extend.name,
+                ConflictKind::Revision,
+                &mut extend.revision,
+            );
+            self.claim(
+                file,
+                &extend.name,
+                ConflictKind::Path,
+                &mut extend.dest_path,
+            );
+        }
+        for project in &mut manifest.projects {
+            self.claim(
+                file,
+                &project.name,
+                ConflictKind::Revision,
+                &mut project.revision,
             );
+            self.claim(file, &project.name, ConflictKind::Path, &mut project.path);
         }
     }
+}
 
-    // Apply extend-project modifications
-    for extend_project in &local.extend_projects {
-        for project in &mut base.projects {
-            if project.name == extend_project.name {
-                if let Some(path) = &extend_project.path {
-                    if project.path.as_deref() != Some(path) {
-                        continue;
-                    }
-                }
-                if let Some(dest_path) = &extend_project.dest_path {
-                    project.path = Some(dest_path.clone());
-                }
-                if let Some(groups) = &extend_project.groups {
-                    project.groups = Some(groups.clone());
-                }
-                if let Some(revision) = &extend_project.revision {
-                    project.revision = Some(revision.clone());
-                }
-                if let Some(remote) = &extend_project.remote {
-                    project.remote = Some(remote.clone());
-                }
-                if let Some(dest_branch) = &extend_project.dest_branch {
-                    project.dest_branch = Some(dest_branch.clone());
-                }
-                if let Some(upstream) = &extend_project.upstream {
-                    project.upstream = Some(upstream.clone());
-                }
-                if let Some(_base_rev) = &extend_project.base_rev {
-                    // Add logic to handle base_rev if needed
-                }
-                debug!("Extended project: {:?}", project);
+/// The projects a local manifest added, removed or extended, by name.
+#[derive(Debug, Clone, Default)]
+pub struct LocalManifestReport {
+    pub file: PathBuf,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub extended: Vec<String>,
+}
+
+/// The `<remove-project>` elements of local manifests that matched no
+/// project, see [`MergeOptions::strict`].
+#[derive(Debug)]
+pub struct UnmatchedRemovals {
+    pub removals: Vec<RemoveProject>,
+}
+
+impl std::fmt::Display for UnmatchedRemovals {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let removals: Vec<String> = self
+            .removals
+            .iter()
+            .map(|removal| match (&removal.name, &removal.path) {
+                (Some(name), Some(path)) => format!("'{}' at '{}'", name, path),
+                (Some(name), None) => format!("'{}'", name),
+                (None, Some(path)) => format!("path '{}'", path),
+                (None, None) => "an element without name or path".to_string(),
+            })
+            .collect();
+        write!(
+            f,
+            "remove-project matched no project: {}",
+            removals.join(", ")
+        )
+    }
+}
+
+impl Error for UnmatchedRemovals {}
+
+fn load_and_merge(
+    manifest_path: &str,
+    local_manifests_dir: Option<&str>,
+    options: &MergeOptions,
+) -> Result<(Manifest, MergeReport), Box<dyn Error>> {
+    let default_remote = Some("origin");
+    let default_revision = Some("main");
+
+    let mut manifest = Manifest::from_file(manifest_path, default_remote, default_revision)?;
+
+    // Determine the local manifests directory
+    let local_manifests_dir = local_manifests_dir.map(PathBuf::from).unwrap_or_else(|| {
+        let manifest_dir = Path::new(manifest_path).parent().unwrap();
+        manifest_dir.join(".repo").join("local_manifests")
+    });
+
+    // Load and merge local manifests, sorted by file name like repo does
+    let mut unmatched = Vec::new();
+    let mut report = MergeReport::default();
+    let conflicts = options.conflicts;
+    let mut claims = Claims {
+        policy: conflicts,
+        claims: HashMap::new(),
+        conflicts: Vec::new(),
+    };
+    if local_manifests_dir.exists() {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(local_manifests_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("xml") {
+                paths.push(path);
             }
         }
+        paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        for path in paths {
+            let mut local_manifest =
+                Manifest::from_file(path.to_str().unwrap(), default_remote, default_revision)?;
+            local_manifest.set_source_kind(SourceKind::LocalManifest);
+            claims.check(&path, &mut local_manifest);
+            // A local manifest has to remove a project before redefining it.
+            let summary = merge_manifests(&mut manifest, local_manifest, options)?;
+            unmatched.extend(summary.unmatched_removals);
+            report.manifests.push(LocalManifestReport {
+                file: path,
+                added: summary.added,
+                removed: summary.removed,
+                extended: summary.extended,
+            });
+        }
     }
 
-    base.remotes.extend(local.remotes);
-    base.default = local.default.or(base.default.take());
-    base.manifest_server = local.manifest_server.or(base.manifest_server.take());
-    base.submanifests.extend(local.submanifests);
-    base.remove_projects.extend(local.remove_projects);
-    base.projects.extend(local.projects);
-    base.extend_projects.extend(local.extend_projects);
-    base.repo_hooks = local.repo_hooks.or(base.repo_hooks.take());
-    base.superproject = local.superproject.or(base.superproject.take());
-    base.contactinfo = local.contactinfo.or(base.contactinfo.take());
-    base.includes.extend(local.includes);
+    // Validated once merged, so that local manifests and the files they
+    // include are checked too.
+    manifest.validate_paths()?;
+
+    report.conflicts = claims.conflicts;
+    if conflicts == ConflictPolicy::Error && !report.conflicts.is_empty() {
+        return Err(Box::new(MergeConflicts {
+            conflicts: report.conflicts,
+        }));
+    }
+    if options.strict && !unmatched.is_empty() {
+        return Err(Box::new(UnmatchedRemovals {
+            removals: unmatched,
+        }));
+    }
+    Ok((manifest, report))
 }
 
-fn determine_jobs(manifest: &Manifest, options: &SyncOptions) -> usize {
-    options
-        .jobs
+// The number of jobs `requested`, or else the `sync-j` of the manifest.
+pub(crate) fn determine_jobs(manifest: &Manifest, requested: Option<usize>) -> usize {
+    requested
         .or_else(|| {
             manifest
                 .default
                 .as_ref()
-                .and_then(|d| d.sync_j.as_ref().map(|s| s.parse::<usize>().unwrap_or(1)))
+                .and_then(|d| d.sync_j)
+                .map(|jobs| jobs as usize)
         })
         .unwrap_or(1)
-        .clamp(1, 4)
+        .max(1)
+}
+
+// Records the outcome of syncing `project`, the one at `index` of the
+// manifest.
+pub(crate) fn finish_project(
+    observer: &dyn SyncObserver,
+    errors: &Mutex<Vec<(String, String)>>,
+    reports: &Mutex<Vec<(usize, ProjectSyncReport)>>,
+    index: usize,
+    project: &Project,
+    skipped: bool,
+    result: Result<Option<LocalChanges>, String>,
+) {
+    let (local_changes, error) = match result {
+        Ok(local_changes) => (local_changes, None),
+        Err(e) => (None, Some(e)),
+    };
+    if let Some(e) = &error {
+        errors
+            .lock()
+            .unwrap()
+            .push((project.name.clone(), e.clone()));
+    }
+    observer.on_event(&SyncEvent::ProjectFinished {
+        name: project.name.clone(),
+        error: error.clone(),
+    });
+    reports.lock().unwrap().push((
+        index,
+        ProjectSyncReport {
+            name: project.name.clone(),
+            path: project.path.clone().unwrap_or_else(|| project.name.clone()),
+            local_changes,
+            skipped,
+            error,
+        },
+    ));
 }
 
-fn process_project(
+// Clones or fetches `project`, returning whether it was cloned. A new
+// project has `revision` fetched into the repository `init_repository`
+// created. Only the repository is updated, [`checkout_project`] checks it
+// out.
+fn fetch_project(
+    git: &dyn GitCommandRunner,
     project: &Project,
     manifest: &Manifest,
     target_path: &Path,
     options: &SyncOptions,
-) -> Result<(), Box<dyn Error>> {
-    debug!("Processing project: {:?}", project.name);
+) -> Result<bool, Box<dyn Error>> {
+    let fetch = prepare_fetch(git, project, manifest, target_path, options)?;
+    if let Some(bundle_url) = &fetch.clone_bundle {
+        debug!("Downloading {}", bundle_url);
+        let downloaded = run_cancellable(
+            Command::new("curl").args(fetch.curl_args()),
+            options.cancel.as_ref(),
+        )
+        .is_ok_and(|output| output.status.success());
+        apply_clone_bundle(git, &fetch, downloaded);
+    }
+    if let Some((repository, args)) = &fetch.cache {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        debug!("Updating cache {}", repository.display());
+        let _lock = lock_cache(repository)?;
+        if let Err(e) = run_git_fetch(git, repository, &args, options) {
+            error!("Failed to fetch {} into the cache: {}", project.name, e);
+            return Err(e);
+        }
+    }
+    let args: Vec<&str> = fetch.args.iter().map(String::as_str).collect();
+    debug!("Running git fetch with args: {:?}", args);
+    if let Err(e) = run_git_fetch(git, &fetch.project_path, &args, options) {
+        error!("Failed to fetch {}: {}", project.name, e);
+        return Err(e);
+    }
+    verify_pinned(git, &fetch)?;
+    Ok(fetch.cloned)
+}
 
-    let project_path_str = project.path.clone().unwrap_or_else(|| project.name.clone());
-    let project_path = target_path.join(&project_path_str);
+/// A fetch of a project whose repository [`prepare_fetch`] set up.
+#[derive(Debug, Clone)]
+pub(crate) struct PreparedFetch {
+    pub(crate) project_path: PathBuf,
+    /// Whether the repository is new.
+    pub(crate) cloned: bool,
+    pub(crate) git_remote: String,
+    pub(crate) tags: bool,
+    /// URL of the clone bundle to try before fetching, for new
+    /// repositories, see [`SyncOptions::clone_bundle`].
+    pub(crate) clone_bundle: Option<String>,
+    /// Arguments of the git fetch of the revision, or of the upstream for
+    /// revisions pinned to a commit. Branches deleted from the remote are
+    /// pruned, unless the repository is new.
+    pub(crate) args: Vec<String>,
+    /// The revision pinned to a commit and the upstream it must be on.
+    pub(crate) pinned: Option<(String, String)>,
+    /// The repository of the cache to fetch the remote into before `args`,
+    /// and the arguments of that fetch, see [`SyncOptions::cache`].
+    pub(crate) cache: Option<(PathBuf, Vec<String>)>,
+}
 
-    // Find the corresponding remote fetch URL
-    let remote_name = project
-        .remote
-        .clone()
-        .or_else(|| manifest.default.as_ref().and_then(|d| d.remote.clone()))
-        .unwrap_or_else(|| "origin".to_string());
-    debug!("Searching for remote: {}", remote_name);
+impl PreparedFetch {
+    // Where the clone bundle is downloaded to.
+    pub(crate) fn bundle_path(&self) -> PathBuf {
+        self.project_path.join(".git").join("clone.bundle")
+    }
 
-    let remote = manifest
-        .remotes
-        .iter()
-        .find(|r| r.name == remote_name)
-        .ok_or_else(|| {
-            let error_message = format!("Remote '{}' not found in manifest", remote_name);
-            error!("{}", error_message);
-            error_message
-        })?;
-    let repo_url = format!("{}/{}.git", remote.fetch, project.name);
-
-    debug!("Repo URL: {}", repo_url);
-
-    // Determine the revision to use
-    let revision = project
-        .revision
-        .clone()
-        .or_else(|| manifest.default.as_ref().and_then(|d| d.revision.clone()))
-        .ok_or_else(|| {
-            if manifest.default.is_none() {
-                "Default element is missing and project does not specify a revision".to_string()
-            } else {
-                "Default element does not specify a revision and project does not specify a revision".to_string()
-            }
-        })?;
+    // Arguments of curl downloading the clone bundle.
+    pub(crate) fn curl_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = ["--fail", "--silent", "--location", "--output"]
+            .map(String::from)
+            .to_vec();
+        args.push(self.bundle_path().to_string_lossy().into_owned());
+        args.extend(self.clone_bundle.clone());
+        args
+    }
+}
 
-    debug!("Revision: {}", revision);
+// Sets up the repository of `project`, creating it if needed, and works
+// out how to fetch it. Only runs local git commands, so that the fetch
+// can be run separately.
+pub(crate) fn prepare_fetch(
+    git: &dyn GitCommandRunner,
+    project: &Project,
+    manifest: &Manifest,
+    target_path: &Path,
+    options: &SyncOptions,
+) -> Result<PreparedFetch, Box<dyn Error>> {
+    debug!("Fetching project: {:?}", project.name);
 
-    if project_path.exists() {
-        debug!("Project path exists, fetching and rebasing...");
-        fetch_and_rebase(&project_path, &revision, options)?;
-    } else {
+    let resolved = ResolvedProject::from_project(manifest, project).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+    let project_path = target_path.join(&resolved.path);
+    let depth = project.clone_depth.or(options.depth);
+    let tags = !options.no_tags
+        && project
+            .sync_tags
+            .or_else(|| manifest.default.as_ref().and_then(|d| d.sync_tags))
+            .unwrap_or(true);
+
+    debug!("Repo URL: {}", resolved.url);
+    debug!("Revision: {}", resolved.revision);
+    debug!("Depth: {:?}", depth);
+    debug!("Tags: {}", tags);
+
+    let cache = match &options.cache {
+        Some(cache) => Some(prepare_cache(git, cache, &resolved, options, target_path)?),
+        None => None,
+    };
+    let cloned = !project_path.exists();
+    let mut clone_bundle = None;
+    if cloned {
         debug!("Project path does not exist, cloning repository...");
-        clone_repository(&project_path, &repo_url, &revision)?;
+        run_project_hook(
+            options,
+            "before_clone",
+            |hooks| hooks.before_clone.as_ref(),
+            &resolved,
+            &project_path,
+        )?;
+        let reference = match &cache {
+            Some((repository, _)) => Some(repository.join("objects")),
+            None => options
+                .reference
+                .as_deref()
+                .and_then(|reference| find_reference(reference, &project.name)),
+        };
+        init_repository(
+            git,
+            &project_path,
+            &resolved.git_remote,
+            &resolved.url,
+            reference.as_deref(),
+            options.filter.as_ref(),
+        )?;
+        // A bundle has the whole history, and a reference the objects.
+        if options.clone_bundle
+            && depth.is_none()
+            && options.filter.is_none()
+            && reference.is_none()
+        {
+            clone_bundle = Some(format!(
+                "{}/clone.bundle",
+                resolved.url.trim_end_matches('/')
+            ));
+        }
     }
 
-    if options.detach {
-        debug!("Detaching to revision: {}", revision);
-        checkout_revision(&project_path, &revision)?;
+    let tags_arg = if tags { "--tags" } else { "--no-tags" };
+    let mut args = auth_args(options, target_path);
+    if let Some((repository, _)) = &cache {
+        // Fetches from the cache, with the remote unchanged for pushes.
+        args.push("-c".to_string());
+        args.push(format!(
+            "url.{}.insteadOf={}",
+            repository.display(),
+            resolved.url
+        ));
+    }
+    args.extend([
+        "fetch".to_string(),
+        resolved.git_remote.clone(),
+        tags_arg.to_string(),
+    ]);
+    let pinned = resolved
+        .pinned_upstream()
+        .map(|upstream| (resolved.revision.clone(), upstream.to_string()));
+    if let Some((revision, upstream)) = &pinned {
+        debug!(
+            "Fetching upstream {} of pinned revision {}",
+            upstream, revision
+        );
+        args.push(upstream.clone());
+    } else {
+        if !cloned {
+            args.push("--prune".to_string());
+        }
+        args.extend(depth_args(&project_path, depth));
+        args.push(resolved.revision.clone());
     }
 
+    Ok(PreparedFetch {
+        project_path,
+        cloned,
+        git_remote: resolved.git_remote,
+        tags,
+        clone_bundle,
+        args,
+        pinned,
+        cache,
+    })
+}
+
+// Creates the repository of `cache` for the remote of `resolved` if
+// needed, returning its absolute path and the arguments of its fetch: all
+// branches and tags of the remote, and the revision if it is another ref
+// or a commit.
+fn prepare_cache(
+    git: &dyn GitCommandRunner,
+    cache: &Path,
+    resolved: &ResolvedProject,
+    options: &SyncOptions,
+    target_path: &Path,
+) -> Result<(PathBuf, Vec<String>), Box<dyn Error>> {
+    let repository = cache_repository(cache, &resolved.url);
+    fs::create_dir_all(&repository)?;
+    // Alternates and URLs are resolved from the checkout, so the path
+    // must be absolute.
+    let repository = fs::canonicalize(&repository)?;
+    {
+        let _lock = lock_cache(&repository)?;
+        if !repository.join("HEAD").exists() {
+            debug!("Creating cache repository {}", repository.display());
+            run_git_command(git, &repository, &["init", "-q", "--bare"])?;
+            run_git_command(git, &repository, &["config", "gc.auto", "0"])?;
+        }
+    }
+
+    let mut args = auth_args(options, target_path);
+    args.extend(
+        [
+            "fetch",
+            "--prune",
+            "--no-tags",
+            &resolved.url,
+            "+refs/heads/*:refs/heads/*",
+            "+refs/tags/*:refs/tags/*",
+        ]
+        .map(String::from),
+    );
+    match Revision::new(&resolved.revision) {
+        Revision::RefsExpression(revision) => args.push(format!("+{}:{}", revision, revision)),
+        Revision::Sha(sha) if resolved.pinned_upstream().is_none() => args.push(sha),
+        _ => (),
+    }
+    Ok((repository, args))
+}
+
+// The bare repository of `cache` for the remote repository at `url`, at
+// its host and path, e.g. `example.com/platform/core.git` for
+// `https://user@example.com/platform/core`.
+fn cache_repository(cache: &Path, url: &str) -> PathBuf {
+    let location = url.split_once("://").map_or(url, |(_, location)| location);
+    let location = match location.split_once('@') {
+        Some((user, location)) if !user.contains('/') => location,
+        _ => location,
+    };
+    let mut repository = cache.to_path_buf();
+    for component in location.split(['/', ':']) {
+        if !component.is_empty() && component != "." && component != ".." {
+            repository.push(component);
+        }
+    }
+    let name = repository.file_name().unwrap_or_default().to_string_lossy();
+    let name = format!("{}.git", name.trim_end_matches(".git"));
+    repository.set_file_name(name);
+    repository
+}
+
+// Locks the cache `repository` until the returned file is dropped, waiting
+// for syncs of this and other processes that hold it.
+pub(crate) fn lock_cache(repository: &Path) -> Result<fs::File, Box<dyn Error>> {
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(repository.join("gbsw-sync.lock"))?;
+    lock.lock()?;
+    Ok(lock)
+}
+
+// Checks out the revision of `project` that [`fetch_project`] fetched.
+pub(crate) fn checkout_project(
+    git: &dyn GitCommandRunner,
+    project: &Project,
+    manifest: &Manifest,
+    target_path: &Path,
+    options: &SyncOptions,
+    cloned: bool,
+) -> Result<Option<LocalChanges>, Box<dyn Error>> {
+    debug!("Checking out project: {:?}", project.name);
+
+    let resolved = ResolvedProject::from_project(manifest, project)?;
+    let project_path = target_path.join(&resolved.path);
+    let pinned = resolved.pinned_upstream().is_some();
+    let branch = local_branch(&resolved);
+    let revision = resolved.revision.clone();
+    let git_remote = resolved.git_remote.clone();
+
+    let mut local_changes = None;
+    if cloned {
+        if options.reference.is_some() && options.cache.is_none() && options.dissociate {
+            dissociate(git, &project_path)?;
+        }
+        let target = if pinned {
+            revision.as_str()
+        } else {
+            "FETCH_HEAD"
+        };
+        debug!("Checking out revision: {}", target);
+        if let Err(e) = checkout_revision(git, &project_path, target) {
+            error!("Failed to checkout revision: {}", e);
+            return Err(e);
+        }
+    } else {
+        let set_aside = set_local_changes_aside(git, &project_path, &project.name, options.force)?;
+        if set_aside == Some(LocalChanges::Discarded) {
+            local_changes = set_aside;
+        }
+        let synced = synced_commit(
+            git,
+            target_path,
+            &resolved.path,
+            &git_remote,
+            branch.as_ref().and_then(|(_, tracked)| tracked.as_deref()),
+        );
+        update_checkout(
+            git,
+            &project_path,
+            &revision,
+            pinned,
+            synced.as_deref(),
+            options,
+        )?;
+        if set_aside == Some(LocalChanges::Reapplied) {
+            debug!("Applying stashed local changes");
+            local_changes = match run_git_command(git, &project_path, &["stash", "pop", "-q"]) {
+                Ok(()) => Some(LocalChanges::Reapplied),
+                Err(e) => {
+                    warn!("Local changes of {} conflict: {}", project.name, e);
+                    Some(LocalChanges::Conflicted)
+                }
+            };
+        }
+    }
+
+    // Checkouts are left detached at the revision fetched otherwise.
+    if let Some((branch, tracked)) = branch.as_ref().filter(|_| !options.detach) {
+        track_branch(git, &project_path, &git_remote, branch, tracked.as_deref())?;
+    }
+
+    if let Some(keyring) = &options.verify_signatures {
+        verify_signature(&project_path, &revision, keyring).map_err(|e| {
+            format!(
+                "Project '{}' failed signature verification: {}",
+                project.name, e
+            )
+        })?;
+    }
+
+    if uses_lfs(project, &project_path) {
+        let auth = auth_args(options, target_path);
+        pull_lfs(git, &project_path, &git_remote, &auth, options).map_err(|e| {
+            format!(
+                "Project '{}' uses Git LFS, but its files cannot be pulled: {}",
+                project.name, e
+            )
+        })?;
+    }
+
+    run_project_hook(
+        options,
+        "after_checkout",
+        |hooks| hooks.after_checkout.as_ref(),
+        &resolved,
+        &project_path,
+    )?;
+    Ok(local_changes)
+}
+
+// Verifies the signature of the tag `revision`, or of the commit checked
+// out for other revisions, against the keyring of the GnuPG home directory
+// `keyring`. Runs git itself rather than by the runner, as gpg finds the
+// keyring by its environment.
+fn verify_signature(
+    project_path: &Path,
+    revision: &str,
+    keyring: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let args = match Revision::new(revision) {
+        Revision::Tag(tag) => ["verify-tag".to_string(), tag],
+        _ => ["verify-commit".to_string(), "HEAD".to_string()],
+    };
+    debug!("Verifying signature with git {:?}", args);
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(project_path)
+        .args(&args)
+        .env("GNUPGHOME", keyring)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.trim().into());
+    }
     Ok(())
 }
 
-fn fetch_and_rebase(
+// Whether the checkout of `project` stores files with Git LFS: it has an
+// `.lfsconfig` or LFS attributes, unless an `lfs` annotation of the
+// project says otherwise.
+fn uses_lfs(project: &Project, project_path: &Path) -> bool {
+    let annotation = project
+        .annotations
+        .iter()
+        .find(|annotation| annotation.name == "lfs");
+    if let Some(annotation) = annotation {
+        return annotation.value == "true";
+    }
+    project_path.join(".lfsconfig").exists()
+        || fs::read_to_string(project_path.join(".gitattributes"))
+            .is_ok_and(|attributes| attributes.contains("filter=lfs"))
+}
+
+// Replaces the LFS pointer files of the checkout with their content, and
+// installs the hooks that keep them up to date.
+fn pull_lfs(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    git_remote: &str,
+    auth: &[String],
+    options: &SyncOptions,
+) -> Result<(), Box<dyn Error>> {
+    debug!("Pulling LFS files of {}", project_path.display());
+    run_git_command(git, project_path, &["lfs", "install", "--local"])?;
+    let mut args: Vec<&str> = auth.iter().map(String::as_str).collect();
+    args.extend(["lfs", "pull", git_remote]);
+    run_remote_git_command(git, project_path, &args, options).map(|_| ())
+}
+
+// Where incremental syncs keep the state of the checkouts, in `.repo` of
+// the target directory.
+pub(crate) const SYNC_STATE_FILE: &str = "sync-state";
+
+// Revision of the manifest and commit checked out, by checkout path.
+pub(crate) type SyncState = HashMap<String, (String, String)>;
+
+// The state file holds one line per checkout: `<commit> <revision> <path>`.
+pub(crate) fn read_sync_state(path: &Path) -> Result<SyncState, Box<dyn Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SyncState::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut state = SyncState::new();
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        let mut fields = line.splitn(3, ' ');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(sha), Some(revision), Some(checkout)) => {
+                state.insert(
+                    checkout.to_string(),
+                    (revision.to_string(), sha.to_string()),
+                );
+            }
+            _ => return Err(format!("Invalid line in sync state file: '{}'", line).into()),
+        }
+    }
+    Ok(state)
+}
+
+pub(crate) fn write_sync_state(path: &Path, state: &SyncState) -> Result<(), Box<dyn Error>> {
+    let mut checkouts: Vec<_> = state.iter().collect();
+    checkouts.sort();
+    let content: String = checkouts
+        .into_iter()
+        .map(|(checkout, (revision, sha))| format!("{} {} {}\n", sha, revision, checkout))
+        .collect();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Renamed into place, so that a sync stopped while writing leaves the
+    // previous state rather than part of the new one.
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// The revision of `project` and the commit its checkout is at.
+pub(crate) fn head_state(
+    git: &dyn GitCommandRunner,
+    project: &Project,
+    manifest: &Manifest,
+    target_path: &Path,
+) -> Option<(String, String)> {
+    let resolved = ResolvedProject::from_project(manifest, project).ok()?;
+    let sha = git
+        .git_output(&target_path.join(&resolved.path), &["rev-parse", "HEAD"])
+        .ok()?;
+    Some((resolved.revision, sha))
+}
+
+// Whether the checkout of `project` is still at the commit `synced` says
+// the previous sync left it at, and its revision still names that commit.
+pub(crate) fn is_synced(
+    git: &dyn GitCommandRunner,
+    project: &Project,
+    manifest: &Manifest,
+    target_path: &Path,
+    options: &SyncOptions,
+    synced: &(String, String),
+) -> bool {
+    let Some(current) = head_state(git, project, manifest, target_path) else {
+        return false;
+    };
+    if current != *synced {
+        return false;
+    }
+    let (revision, sha) = current;
+    if is_commit_sha(&revision) {
+        return true;
+    }
+    let Ok(resolved) = ResolvedProject::from_project(manifest, project) else {
+        return false;
+    };
+    let remote_ref = match resolved.typed_revision() {
+        Revision::Branch(branch) if !branch.starts_with("refs/") => {
+            format!("refs/heads/{}", branch)
+        }
+        revision => revision.as_str().to_string(),
+    };
+    // Annotated tags are listed with the commit they point to as well.
+    let peeled = format!("{}^{{}}", remote_ref);
+    let auth = auth_args(options, target_path);
+    let mut args: Vec<&str> = auth.iter().map(String::as_str).collect();
+    args.extend(["ls-remote", &resolved.url, &remote_ref, &peeled]);
+    let Ok(output) = run_remote_git_command(git, &target_path.join(&resolved.path), &args, options)
+    else {
+        return false;
+    };
+    let mut tip = None;
+    for line in output.lines() {
+        if let Some((commit, name)) = line.split_once('\t') {
+            if name == peeled || (name == remote_ref && tip.is_none()) {
+                tip = Some(commit);
+            }
+        }
+    }
+    tip == Some(sha.as_str())
+}
+
+// Handles uncommitted changes to tracked files of the checkout of
+// `project` according to `policy`: returns `Reapplied` once they are
+// stashed, to be applied again, and `Discarded` once they are discarded.
+fn set_local_changes_aside(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    project: &str,
+    policy: DirtyTreePolicy,
+) -> Result<Option<LocalChanges>, Box<dyn Error>> {
+    let dirty =
+        run_git_command(git, project_path, &["diff-index", "--quiet", "HEAD", "--"]).is_err();
+    if !dirty {
+        return Ok(None);
+    }
+    match policy {
+        DirtyTreePolicy::Fail => {
+            Err(format!("Project '{}' has uncommitted changes", project).into())
+        }
+        DirtyTreePolicy::Stash => {
+            debug!("Stashing local changes of {}", project);
+            run_git_command(git, project_path, &["stash", "push", "-q"])?;
+            Ok(Some(LocalChanges::Reapplied))
+        }
+        DirtyTreePolicy::Discard => {
+            debug!("Discarding local changes of {}", project);
+            run_git_command(git, project_path, &["reset", "-q", "--hard"])?;
+            Ok(Some(LocalChanges::Discarded))
+        }
+    }
+}
+
+// The local branch to check a project out on, named after its dest-branch
+// or else its revision, and the remote branch it tracks. `None` for
+// projects at a tag or commit without an upstream.
+fn local_branch(resolved: &ResolvedProject) -> Option<(String, Option<String>)> {
+    let strip = |branch: &str| {
+        branch
+            .strip_prefix("refs/heads/")
+            .unwrap_or(branch)
+            .to_string()
+    };
+    let tracked = match resolved.typed_revision() {
+        Revision::Branch(revision) => Some(strip(&revision)),
+        Revision::Sha(_) => resolved.upstream.as_deref().map(strip),
+        _ => None,
+    };
+    let branch = resolved
+        .dest_branch
+        .as_deref()
+        .map(strip)
+        .or(tracked.clone())?;
+    Some((branch, tracked))
+}
+
+// Checks `branch` out at the fetched commit unless HEAD is already on a
+// branch, and makes it track `tracked` of `git_remote` like a branch made
+// with `git checkout --track` does.
+fn track_branch(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    git_remote: &str,
+    branch: &str,
+    tracked: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    if !on_branch(git, project_path) {
+        debug!("Checking out local branch: {}", branch);
+        run_git_command(git, project_path, &["checkout", "-q", "-B", branch])?;
+    }
+    let Some(tracked) = tracked else {
+        return Ok(());
+    };
+    // The fetched commit is the tip of the tracked branch.
+    let remote_ref = format!("refs/remotes/{}/{}", git_remote, tracked);
+    run_git_command(
+        git,
+        project_path,
+        &["update-ref", &remote_ref, "FETCH_HEAD"],
+    )?;
+    run_git_command(
+        git,
+        project_path,
+        &["config", &format!("branch.{}.remote", branch), git_remote],
+    )?;
+    run_git_command(
+        git,
+        project_path,
+        &[
+            "config",
+            &format!("branch.{}.merge", branch),
+            &format!("refs/heads/{}", tracked),
+        ],
+    )
+}
+
+// Whether HEAD is on a branch rather than detached.
+fn on_branch(git: &dyn GitCommandRunner, project_path: &Path) -> bool {
+    run_git_command(git, project_path, &["symbolic-ref", "-q", "HEAD"]).is_ok()
+}
+
+// Moves an existing checkout to the fetched revision, or `revision` if
+// pinned: local commits of its branch, those since the `synced` commit the
+// previous sync left it at, are rebased onto it. It is reset to it when
+// detaching, discarding local changes or without local commits to keep.
+fn update_checkout(
+    git: &dyn GitCommandRunner,
     project_path: &Path,
     revision: &str,
-    _options: &SyncOptions,
+    pinned: bool,
+    synced: Option<&str>,
+    options: &SyncOptions,
 ) -> Result<(), Box<dyn Error>> {
-    debug!(
-        "Fetching and rebasing project at: {}",
-        project_path.display()
-    );
+    debug!("Updating checkout at: {}", project_path.display());
     debug!("Revision: {}", revision);
 
-    // Fetch the latest changes with depth 1
-    let fetch_args = vec!["fetch", "origin", "--prune", "--depth", "1", revision];
-
-    debug!("Running git fetch with args: {:?}", fetch_args);
-    if let Err(e) = run_git_command(project_path, &fetch_args) {
-        error!("Failed to fetch: {}", e);
-        return Err(e);
+    let target = if pinned { revision } else { "FETCH_HEAD" };
+    if options.detach {
+        // Leaves the local branch, and any commits of it, as it is.
+        debug!("Detaching to: {}", target);
+        return run_git_command(git, project_path, &["checkout", "-q", "--detach", target]);
+    }
+    // Without a synced commit, commits the target does not have are taken
+    // as local.
+    let base = synced.unwrap_or(target);
+    let keep_commits = options.force != DirtyTreePolicy::Discard
+        && on_branch(git, project_path)
+        && has_local_commits(git, project_path, base);
+    if keep_commits {
+        debug!("Rebasing local branch onto: {}", target);
+        return rebase_local_commits(git, project_path, revision, target, base);
     }
 
     // Reset the repository to the fetched revision
-    debug!("Resetting repository to fetched revision");
-    if let Err(e) = run_git_command(project_path, &["reset", "--hard", "FETCH_HEAD"]) {
+    debug!("Resetting repository to: {}", target);
+    if let Err(e) = run_git_command(git, project_path, &["reset", "--hard", target]) {
         error!("Failed to reset repository: {}", e);
         return Err(e);
     }
@@ -460,14 +1779,81 @@ fn fetch_and_rebase(
     Ok(())
 }
 
-fn clone_repository(
+// The commit the previous sync left the checkout at `checkout` at, like
+// repo counts local commits from: the tip of the remote branch it tracks,
+// which is only moved once checked out, or else the commit in the sync
+// state of incremental syncs.
+fn synced_commit(
+    git: &dyn GitCommandRunner,
+    target_path: &Path,
+    checkout: &str,
+    git_remote: &str,
+    tracked: Option<&str>,
+) -> Option<String> {
+    if let Some(tracked) = tracked {
+        let remote_ref = format!("refs/remotes/{}/{}", git_remote, tracked);
+        let sha = git.git_output(
+            &target_path.join(checkout),
+            &["rev-parse", "--verify", "-q", &remote_ref],
+        );
+        if let Some(sha) = sha.ok().filter(|sha| !sha.is_empty()) {
+            return Some(sha);
+        }
+    }
+    let state = read_sync_state(&target_path.join(".repo").join(SYNC_STATE_FILE)).ok()?;
+    state.get(checkout).map(|(_, sha)| sha.clone())
+}
+
+// Whether HEAD has commits that `base` does not.
+fn has_local_commits(git: &dyn GitCommandRunner, project_path: &Path, base: &str) -> bool {
+    let range = format!("{}..HEAD", base);
+    git.git_output(project_path, &["rev-list", "--count", &range])
+        .is_ok_and(|count| count != "0")
+}
+
+// Rebases the local commits of the checked out branch, those since
+// `base`, onto `target`, aborting the rebase and naming the conflicting
+// files if it fails.
+fn rebase_local_commits(
+    git: &dyn GitCommandRunner,
     project_path: &Path,
-    repo_url: &str,
     revision: &str,
+    target: &str,
+    base: &str,
+) -> Result<(), Box<dyn Error>> {
+    let args = ["rebase", "-q", "--onto", target, base];
+    let Err(e) = run_git_command(git, project_path, &args) else {
+        return Ok(());
+    };
+    let conflicts = git
+        .git_output(project_path, &["diff", "--name-only", "--diff-filter=U"])
+        .unwrap_or_default();
+    let _ = run_git_command(git, project_path, &["rebase", "--abort"]);
+    error!("Failed to rebase local branch: {}", e);
+    if conflicts.is_empty() {
+        return Err(e);
+    }
+    Err(format!(
+        "Local commits conflict with {} in: {}; resolve them or sync with force",
+        revision,
+        conflicts.lines().collect::<Vec<_>>().join(", ")
+    )
+    .into())
+}
+
+// Creates an empty repository at `project_path` fetching from `repo_url`,
+// borrowing objects from the `reference` objects directory and fetching
+// only the objects `filter` lets through, if given.
+fn init_repository(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    git_remote: &str,
+    repo_url: &str,
+    reference: Option<&Path>,
+    filter: Option<&CloneFilter>,
 ) -> Result<(), Box<dyn Error>> {
     debug!("Cloning repository from: {}", repo_url);
     debug!("Target path: {}", project_path.display());
-    debug!("Revision: {}", revision);
 
     // Create the target directory if it does not exist
     if !project_path.exists() {
@@ -483,46 +1869,177 @@ fn clone_repository(
         "Initializing new git repository at: {}",
         project_path.display()
     );
-    if let Err(e) = run_git_command(project_path, &["init"]) {
+    if let Err(e) = run_git_command(git, project_path, &["init"]) {
         error!("Failed to initialize git repository: {}", e);
         return Err(e);
     }
 
-    // Add the remote origin
-    debug!("Adding remote origin: {}", repo_url);
-    if let Err(e) = run_git_command(project_path, &["remote", "add", "origin", repo_url]) {
-        error!("Failed to add remote origin: {}", e);
+    // Add the remote under its alias, if it has one
+    debug!("Adding remote {}: {}", git_remote, repo_url);
+    if let Err(e) = run_git_command(git, project_path, &["remote", "add", git_remote, repo_url]) {
+        error!("Failed to add remote {}: {}", git_remote, e);
         return Err(e);
     }
 
-    // Fetch the specific revision with depth 1
-    debug!("Fetching revision with depth 1: {}", revision);
-    if let Err(e) = run_git_command(project_path, &["fetch", "--depth", "1", "origin", revision]) {
-        error!("Failed to fetch revision: {}", e);
-        return Err(e);
+    // Borrow objects from the mirror, like `git clone --reference`
+    if let Some(reference) = reference {
+        debug!("Using objects of reference: {}", reference.display());
+        fs::write(
+            project_path
+                .join(".git")
+                .join("objects")
+                .join("info")
+                .join("alternates"),
+            format!("{}\n", reference.display()),
+        )?;
     }
 
-    // Checkout the fetched revision
-    debug!("Checking out revision: {}", revision);
-    if let Err(e) = run_git_command(project_path, &["checkout", "FETCH_HEAD"]) {
-        error!("Failed to checkout revision: {}", e);
-        return Err(e);
+    // Make the remote a promisor, like `git clone --filter` does, so that
+    // every fetch is filtered and checkouts fetch missing objects
+    if let Some(filter) = filter {
+        debug!("Filtering fetches from {} with {}", git_remote, filter);
+        let promisor = format!("remote.{}.promisor", git_remote);
+        let partial_clone_filter = format!("remote.{}.partialclonefilter", git_remote);
+        run_git_command(git, project_path, &["config", &promisor, "true"])?;
+        run_git_command(
+            git,
+            project_path,
+            &["config", &partial_clone_filter, filter.as_str()],
+        )?;
     }
 
     Ok(())
 }
 
-fn checkout_revision(project_path: &Path, revision: &str) -> Result<(), Box<dyn Error>> {
-    run_git_command(project_path, &["checkout", revision])
+// Fetches the branches and tags of the clone bundle of `fetch`, if it was
+// `downloaded`, into the new repository, like repo does to spare git
+// servers the cost of a full clone. The revision is fetched afterwards,
+// which only transfers the commits made since the bundle. Failing to use
+// the bundle is not an error: it is an optimization servers may not offer.
+pub(crate) fn apply_clone_bundle(
+    git: &dyn GitCommandRunner,
+    fetch: &PreparedFetch,
+    downloaded: bool,
+) {
+    let bundle = fetch.bundle_path();
+    let bundle_url = fetch.clone_bundle.as_deref().unwrap_or_default();
+    if !downloaded {
+        debug!("No clone bundle at {}", bundle_url);
+        let _ = fs::remove_file(&bundle);
+        return;
+    }
+    let heads = format!("+refs/heads/*:refs/remotes/{}/*", fetch.git_remote);
+    let bundle_path = bundle.to_string_lossy();
+    let mut fetch_args = vec!["fetch", "-q", "--no-tags", &bundle_path, &heads];
+    if fetch.tags {
+        fetch_args.push("+refs/tags/*:refs/tags/*");
+    }
+    if let Err(e) = run_git_command(git, &fetch.project_path, &fetch_args) {
+        warn!("Cannot use clone bundle {}: {}", bundle_url, e);
+    }
+    let _ = fs::remove_file(&bundle);
 }
 
-fn run_git_command(project_path: &Path, args: &[&str]) -> Result<(), Box<dyn Error>> {
-    DefaultGitCommandRunner
-        .run_git_command(project_path, args)
-        .map(|_| ())
+// Checks that the commit a revision of `fetch` is pinned to is on the
+// fetched upstream, like repo does for revisions pinned to a commit SHA.
+pub(crate) fn verify_pinned(
+    git: &dyn GitCommandRunner,
+    fetch: &PreparedFetch,
+) -> Result<(), Box<dyn Error>> {
+    let Some((revision, upstream)) = &fetch.pinned else {
+        return Ok(());
+    };
+    run_git_command(
+        git,
+        &fetch.project_path,
+        &["merge-base", "--is-ancestor", revision, "FETCH_HEAD"],
+    )
+    .map_err(|_| {
+        format!(
+            "Revision '{}' is not reachable from upstream '{}'",
+            revision, upstream
+        )
+        .into()
+    })
 }
 
-fn handle_errors(
+// The objects directory of the mirror of the project `name` in the mirror
+// tree `reference`: a bare `<name>.git`, as `repo init --mirror` creates,
+// or else a checkout at `<name>`.
+fn find_reference(reference: &Path, name: &str) -> Option<PathBuf> {
+    let objects = [
+        reference.join(format!("{}.git", name)).join("objects"),
+        reference.join(name).join(".git").join("objects"),
+    ]
+    .into_iter()
+    .find(|objects| objects.is_dir());
+    if objects.is_none() {
+        debug!("No mirror of {} in {}", name, reference.display());
+    }
+    // Alternates are resolved from the checkout, so they must be absolute.
+    objects.map(|objects| fs::canonicalize(&objects).unwrap_or(objects))
+}
+
+// Copies the objects borrowed from a reference into the repository and
+// stops borrowing, like `git clone --dissociate`.
+fn dissociate(git: &dyn GitCommandRunner, project_path: &Path) -> Result<(), Box<dyn Error>> {
+    debug!("Dissociating {} from its reference", project_path.display());
+    run_git_command(git, project_path, &["repack", "-a", "-d", "-q"])?;
+    fs::remove_file(
+        project_path
+            .join(".git")
+            .join("objects")
+            .join("info")
+            .join("alternates"),
+    )?;
+    Ok(())
+}
+
+// The fetch arguments for `depth` commits of history, `None` for all of it,
+// which a shallow checkout only gets with `--unshallow`.
+fn depth_args(project_path: &Path, depth: Option<u32>) -> Vec<String> {
+    match depth {
+        Some(depth) => vec!["--depth".to_string(), depth.to_string()],
+        None if project_path.join(".git").join("shallow").exists() => {
+            vec!["--unshallow".to_string()]
+        }
+        None => Vec::new(),
+    }
+}
+
+fn checkout_revision(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    revision: &str,
+) -> Result<(), Box<dyn Error>> {
+    run_git_command(git, project_path, &["checkout", revision])
+}
+
+fn run_git_command(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    args: &[&str],
+) -> Result<(), Box<dyn Error>> {
+    git.run_git_command(project_path, args).map(|_| ())
+}
+
+// Runs the git command `args` reaching remotes in `project_path`, with the
+// SSH settings of `options`, until the sync is cancelled.
+fn run_remote_git_command(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    args: &[&str],
+    options: &SyncOptions,
+) -> Result<String, Box<dyn Error>> {
+    let env = auth_env(options);
+    let env: Vec<(&str, &str)> = env
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .collect();
+    git.run_remote_git_command(project_path, args, &env, options.cancel.as_ref())
+}
+
+pub(crate) fn handle_errors(
     errors: Arc<Mutex<Vec<(String, String)>>>,
     keep: bool,
 ) -> Result<(), Box<dyn Error>> {
@@ -538,13 +2055,570 @@ fn handle_errors(
     Ok(())
 }
 
-#[derive(Debug, Clone)]
+/// Options of [`sync_repos`]. The defaults sync every project like a plain
+/// `repo sync`; set the fields that differ and fill in the rest with
+/// `..Default::default()`, so that fields added later keep it compiling.
+#[derive(Debug, Clone, Default)]
 pub struct SyncOptions {
     pub current_branch_only: bool,
+    /// Leaves checkouts on a detached HEAD, and their local branches as
+    /// they are. Otherwise they are on a local branch named after the
+    /// `dest-branch` or revision and tracking it, with local commits
+    /// rebased when syncing again.
     pub detach: bool,
-    pub force: bool,
+    /// What to do with checkouts with uncommitted changes.
+    pub force: DirtyTreePolicy,
+    /// How many projects to sync at once, `sync-j` of the `<default>` if
+    /// `None`, like `repo sync -j`. Applies to the phases without their own
+    /// number of jobs.
     pub jobs: Option<usize>,
+    /// How many projects to fetch at once, like `repo sync --jobs-network`.
+    pub jobs_network: Option<usize>,
+    /// How many projects to check out at once, once all are fetched, like
+    /// `repo sync --jobs-checkout`.
+    pub jobs_checkout: Option<usize>,
     pub quiet: bool,
     pub smart_sync: bool,
     pub keep: bool,
+    /// Group filter like `repo sync -g`, e.g. `default,-tools`, see
+    /// [`Project::matches_groups`]. `None` syncs every project.
+    pub groups: Option<String>,
+    /// How fetches failing for network reasons are retried.
+    pub retry: RetryPolicy,
+    /// How many commits of history to fetch for projects without a
+    /// `clone-depth`, `None` for all of it. Shallow checkouts are faster,
+    /// but break bisecting and describing by tags.
+    pub depth: Option<u32>,
+    /// A mirror tree, e.g. made with `repo init --mirror`, that new
+    /// checkouts borrow objects from instead of fetching them, like with
+    /// `git clone --reference`. Projects missing from it are fetched as
+    /// usual.
+    pub reference: Option<PathBuf>,
+    /// Copies the objects borrowed from `reference` into new checkouts,
+    /// so that they keep working without the mirror, like with
+    /// `git clone --dissociate`.
+    pub dissociate: bool,
+    /// Directory of bare repositories shared by the workspaces of a host,
+    /// one per remote URL. Projects are fetched into their repository of
+    /// the cache, whose objects new checkouts borrow like with
+    /// `reference`, then checkouts fetch from it rather than the remote,
+    /// so that each object is downloaded and stored once. The cache
+    /// repositories are complete, whatever the depth or filter of the
+    /// checkouts, and never pruned by `git gc`, which would break the
+    /// checkouts borrowing from them. Syncs of other processes using the
+    /// cache wait for each other's fetches.
+    pub cache: Option<PathBuf>,
+    /// Credentials for private remotes, given to the git commands of the
+    /// sync that reach remotes, rather than configured on the machine.
+    pub auth: Option<GitAuth>,
+    /// Makes new checkouts partial clones, fetching only the objects the
+    /// filter lets through and the rest when a checkout needs them.
+    pub filter: Option<CloneFilter>,
+    /// Skips projects that are still at the commit the previous sync
+    /// left them at, when that is also the commit their revision names
+    /// now. Branches and tags are looked up with `git ls-remote`, which is
+    /// much faster than fetching. What each sync leaves the checkouts at
+    /// is kept in `.repo/sync-state` of the target directory.
+    pub incremental: bool,
+    /// Initializes new checkouts from the `clone.bundle` the server
+    /// offers next to the repository, if any, before fetching the rest,
+    /// like repo does. Not used for shallow or partial clones, or those
+    /// borrowing from a reference.
+    pub clone_bundle: bool,
+    /// Fetches no tags, like `repo sync --no-tags`. Otherwise all tags are
+    /// fetched, except for projects whose `sync-tags`, or else that of
+    /// the `<default>`, is false.
+    pub no_tags: bool,
+    /// GnuPG home directory whose keyring the checkouts must be signed
+    /// with. If set, the signature of a tag revision is verified with
+    /// `git verify-tag`, which needs its tags fetched, and that of the
+    /// commit checked out for other revisions with `git verify-commit`.
+    /// Projects whose signature is missing or not from the keyring fail.
+    pub verify_signatures: Option<PathBuf>,
+    /// Stops the sync once cancelled, see [`CancelToken`].
+    pub cancel: Option<CancelToken>,
+    /// Callbacks run for every project synced, see [`SyncHooks`].
+    pub hooks: Option<SyncHooks>,
+}
+
+impl SyncOptions {
+    // Whether the sync was cancelled, see [`SyncOptions::cancel`].
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+}
+
+// A hook of `SyncHooks` run for a project and its checkout.
+type ProjectHook = Arc<dyn Fn(&ResolvedProject, &Path) -> Result<(), Box<dyn Error>> + Send + Sync>;
+
+// The `on_failure` hook of `SyncHooks`.
+type FailureHook = Arc<dyn Fn(&ResolvedProject, &Path, &str) + Send + Sync>;
+
+/// Callbacks run by the sync jobs for each project, see
+/// [`SyncOptions::hooks`], e.g. to generate configuration files in
+/// checkouts. They are given the project and the path of its checkout.
+/// Hooks failing fail the project, like a failed fetch does.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::sync::SyncHooks;
+///
+/// let hooks = SyncHooks::new()
+///     .after_checkout(|project, path| {
+///         std::fs::write(path.join("local.conf"), format!("name={}\n", project.name))?;
+///         Ok(())
+///     })
+///     .on_failure(|project, _, error| eprintln!("{} failed: {}", project.name, error));
+/// ```
+#[derive(Clone, Default)]
+pub struct SyncHooks {
+    before_clone: Option<ProjectHook>,
+    after_checkout: Option<ProjectHook>,
+    on_failure: Option<FailureHook>,
+}
+
+impl SyncHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `hook` before a project without a checkout is cloned, once
+    /// its directory is known not to exist.
+    pub fn before_clone(
+        mut self,
+        hook: impl Fn(&ResolvedProject, &Path) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
+    ) -> Self {
+        self.before_clone = Some(Arc::new(hook));
+        self
+    }
+
+    /// Runs `hook` once a project is checked out, before its copyfiles
+    /// and linkfiles are created.
+    pub fn after_checkout(
+        mut self,
+        hook: impl Fn(&ResolvedProject, &Path) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
+    ) -> Self {
+        self.after_checkout = Some(Arc::new(hook));
+        self
+    }
+
+    /// Runs `hook` with the error of a project that fails to fetch or
+    /// check out. Projects whose remote or revision cannot be resolved
+    /// fail without it.
+    pub fn on_failure(
+        mut self,
+        hook: impl Fn(&ResolvedProject, &Path, &str) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_failure = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl std::fmt::Debug for SyncHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SyncHooks")
+            .field("before_clone", &self.before_clone.is_some())
+            .field("after_checkout", &self.after_checkout.is_some())
+            .field("on_failure", &self.on_failure.is_some())
+            .finish()
+    }
+}
+
+// Runs the hook of `options` chosen by `hook` for `resolved`, whose
+// checkout is at `project_path`.
+fn run_project_hook(
+    options: &SyncOptions,
+    name: &str,
+    hook: fn(&SyncHooks) -> Option<&ProjectHook>,
+    resolved: &ResolvedProject,
+    project_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let Some(hook) = options.hooks.as_ref().and_then(hook) else {
+        return Ok(());
+    };
+    debug!("Running hook {} of {}", name, resolved.name);
+    hook(resolved, project_path)
+        .map_err(|e| format!("Hook {} of project '{}' failed: {}", name, resolved.name, e).into())
+}
+
+// Runs the `on_failure` hook of `options`, if any, for `project`, which
+// failed with `error`.
+pub(crate) fn run_failure_hook(
+    options: &SyncOptions,
+    manifest: &Manifest,
+    project: &Project,
+    target_path: &Path,
+    error: &str,
+) {
+    let Some(hook) = options
+        .hooks
+        .as_ref()
+        .and_then(|hooks| hooks.on_failure.as_ref())
+    else {
+        return;
+    };
+    if let Ok(resolved) = ResolvedProject::from_project(manifest, project) {
+        hook(&resolved, &target_path.join(&resolved.path), error);
+    }
+}
+
+/// Credentials for the remotes of a sync, see [`SyncOptions::auth`].
+///
+/// HTTPS credentials are answered by git's `store` credential helper from
+/// `.repo/git-credentials` in the target directory, which only the user
+/// can read and which is deleted when the sync finishes, so that they do
+/// not show in the arguments of processes. SSH settings are given as
+/// `GIT_SSH_COMMAND`, replacing the one of the environment.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::sync::GitAuth;
+///
+/// let auth = GitAuth {
+///     https_token: Some(std::env::var("GERRIT_TOKEN")?),
+///     ssh_key: Some("/etc/ci/id_ed25519".into()),
+///     ..GitAuth::default()
+/// };
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct GitAuth {
+    /// Username for HTTPS remotes, `git` if only `https_token` is set,
+    /// which hosts taking tokens as passwords, like GitHub, accept.
+    pub https_username: Option<String>,
+    /// Token, or password, for the HTTPS remotes of the manifest.
+    pub https_token: Option<String>,
+    /// Private key for SSH remotes, used rather than the keys of
+    /// `~/.ssh` or of the agent.
+    pub ssh_key: Option<PathBuf>,
+    /// Socket of the SSH agent to use rather than that of `SSH_AUTH_SOCK`.
+    pub ssh_agent: Option<PathBuf>,
+}
+
+impl std::fmt::Debug for GitAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GitAuth")
+            .field("https_username", &self.https_username)
+            .field(
+                "https_token",
+                &self.https_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("ssh_key", &self.ssh_key)
+            .field("ssh_agent", &self.ssh_agent)
+            .finish()
+    }
+}
+
+// Where the HTTPS credentials of a sync are kept, in `.repo` of the
+// target directory.
+const CREDENTIALS_FILE: &str = "git-credentials";
+
+// The credentials file written by [`write_credentials`], deleted when
+// dropped so that the token does not outlive the sync.
+pub(crate) struct CredentialsFile(Option<PathBuf>);
+
+impl Drop for CredentialsFile {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0 {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+// Writes the HTTPS credentials of `options` for the hosts of the HTTP and
+// HTTPS remotes of `manifest`, for as long as the returned file is kept.
+// Removes those left by an interrupted sync if there are none.
+pub(crate) fn write_credentials(
+    manifest: &Manifest,
+    options: &SyncOptions,
+    target_path: &Path,
+) -> Result<CredentialsFile, Box<dyn Error>> {
+    let path = target_path.join(".repo").join(CREDENTIALS_FILE);
+    let auth = options.auth.as_ref();
+    let Some(token) = auth.and_then(|auth| auth.https_token.as_deref()) else {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(CredentialsFile(None));
+    };
+    let username = auth
+        .and_then(|auth| auth.https_username.as_deref())
+        .unwrap_or("git");
+    let mut hosts: Vec<String> = manifest
+        .projects
+        .iter()
+        .filter_map(|project| ResolvedProject::from_project(manifest, project).ok())
+        .filter_map(|resolved| {
+            let (scheme, location) = resolved.url.split_once("://")?;
+            if scheme != "https" && scheme != "http" {
+                return None;
+            }
+            let host = location.split('/').next()?;
+            let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+            Some(format!("{}://{}", scheme, host))
+        })
+        .collect();
+    hosts.sort();
+    hosts.dedup();
+    let content: String = hosts
+        .iter()
+        .map(|host| {
+            let (scheme, host) = host.split_once("://").unwrap_or_default();
+            format!(
+                "{}://{}:{}@{}\n",
+                scheme,
+                percent_encode(username),
+                percent_encode(token),
+                host
+            )
+        })
+        .collect();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new();
+    file.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut file, 0o600);
+    let mut file = file.open(&path)?;
+    let credentials = CredentialsFile(Some(path));
+    std::io::Write::write_all(&mut file, content.as_bytes())?;
+    Ok(credentials)
+}
+
+// Encodes `value` for the user or password of a URL.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// The `-c` options giving the HTTPS credentials of `options` to the git
+// commands of a sync of `target_path` that reach remotes, see [`GitAuth`].
+pub(crate) fn auth_args(options: &SyncOptions, target_path: &Path) -> Vec<String> {
+    let Some(auth) = &options.auth else {
+        return Vec::new();
+    };
+    let mut config = Vec::new();
+    if auth.https_token.is_some() {
+        // Git runs the helper from the checkout, so the path must be
+        // absolute.
+        let target_path = fs::canonicalize(target_path).unwrap_or(target_path.to_path_buf());
+        let path = target_path.join(".repo").join(CREDENTIALS_FILE);
+        // An empty helper drops the helpers configured on the machine.
+        config.push("credential.helper=".to_string());
+        config.push(format!(
+            "credential.helper=store --file={}",
+            shell_quote(&path.to_string_lossy())
+        ));
+    }
+    config
+        .into_iter()
+        .flat_map(|config| ["-c".to_string(), config])
+        .collect()
+}
+
+// The environment giving the SSH settings of `options` to the git commands
+// of a sync that reach remotes, see [`GitAuth`].
+pub(crate) fn auth_env(options: &SyncOptions) -> Vec<(&'static str, String)> {
+    let Some(auth) = &options.auth else {
+        return Vec::new();
+    };
+    let mut env = Vec::new();
+    if auth.ssh_key.is_some() || auth.ssh_agent.is_some() {
+        let mut command = "ssh".to_string();
+        if let Some(key) = &auth.ssh_key {
+            command.push_str(&format!(
+                " -i {} -o IdentitiesOnly=yes",
+                shell_quote(&key.to_string_lossy())
+            ));
+        }
+        if let Some(agent) = &auth.ssh_agent {
+            command.push_str(&format!(
+                " -o IdentityAgent={}",
+                shell_quote(&agent.to_string_lossy())
+            ));
+        }
+        env.push(("GIT_SSH_COMMAND", command));
+    }
+    env
+}
+
+// Quotes `value` for the shell git runs helpers and SSH commands with.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// What a partial clone leaves out, see [`SyncOptions::filter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloneFilter {
+    /// `blob:none`: file contents, fetched when checked out. History is
+    /// complete for `git log`, but `git blame` or `git diff` fetch more.
+    BlobNone,
+    /// `tree:0`: trees and file contents, for builds that only check out
+    /// a single commit.
+    Treeless,
+    /// Any other `git rev-list --filter` spec, e.g. `blob:limit=1m`.
+    Spec(String),
+}
+
+impl CloneFilter {
+    /// The filter spec, as given to `git fetch --filter`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            CloneFilter::BlobNone => "blob:none",
+            CloneFilter::Treeless => "tree:0",
+            CloneFilter::Spec(spec) => spec,
+        }
+    }
+}
+
+impl std::fmt::Display for CloneFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How to retry git fetches that fail for a reason that may go away, see
+/// [`is_transient_git_error`]. Other failures are not retried.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::sync::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let retry = RetryPolicy::new()
+///     .attempts(5)
+///     .base_delay(Duration::from_secs(2))
+///     .jitter(Duration::ZERO);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub(crate) attempts: u32,
+    base_delay: Duration,
+    jitter: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+            jitter: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Three attempts, waiting about one then two seconds in between.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A single attempt, never retrying.
+    pub fn none() -> Self {
+        Self::default().attempts(1)
+    }
+
+    /// How many times a fetch is tried in all, at least once.
+    pub fn attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// The wait after the first failed attempt, doubled after each
+    /// further one.
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// The most that is added at random to every wait, so that jobs
+    /// failing together do not retry together.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The wait after the failed attempt `attempt`, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        if self.jitter.is_zero() {
+            return backoff;
+        }
+        // Random enough for spreading retries, without a dependency.
+        let random = RandomState::new().build_hasher().finish();
+        let jitter = self.jitter.as_nanos() as u64;
+        backoff + Duration::from_nanos(random % jitter)
+    }
+}
+
+/// Whether a git error message tells of a failure that may go away when
+/// retried: timeouts, dropped connections and server errors. Errors like a
+/// missing repository or revision are permanent.
+pub fn is_transient_git_error(message: &str) -> bool {
+    const TRANSIENT: &[&str] = &[
+        "timed out",
+        "timeout",
+        "early eof",
+        "unexpected disconnect",
+        "remote end hung up unexpectedly",
+        "connection reset",
+        "temporary failure in name resolution",
+        "rpc failed",
+        "the requested url returned error: 5",
+        "http 5",
+        "gnutls recv error",
+        "ssl_read",
+    ];
+    let message = message.to_ascii_lowercase();
+    TRANSIENT.iter().any(|pattern| message.contains(pattern))
+}
+
+// Runs the fetch `args`, retrying transient failures according to the
+// retry policy of `options`, until the sync is cancelled.
+pub(crate) fn run_git_fetch(
+    git: &dyn GitCommandRunner,
+    project_path: &Path,
+    args: &[&str],
+    options: &SyncOptions,
+) -> Result<(), Box<dyn Error>> {
+    let retry = &options.retry;
+    let mut attempt = 1;
+    loop {
+        if options.is_cancelled() {
+            return Err(SYNC_CANCELLED.into());
+        }
+        let result = run_remote_git_command(git, project_path, args, options).map(|_| ());
+        match result {
+            Err(e) if attempt < retry.attempts && is_transient_git_error(&e.to_string()) => {
+                let delay = retry.delay(attempt);
+                warn!(
+                    "Fetch in {} failed, retrying in {:?}: {}",
+                    project_path.display(),
+                    delay,
+                    e
+                );
+                let deadline = Instant::now() + delay;
+                while Instant::now() < deadline && !options.is_cancelled() {
+                    std::thread::sleep(CANCEL_POLL_INTERVAL.min(delay));
+                }
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
 }
@@ -0,0 +1,433 @@
+use crate::sync::{
+    apply_clone_bundle, auth_env, checkout_project, determine_jobs, finish_project, handle_errors,
+    head_state, is_synced, is_transient_git_error, link_files, load_and_merge_manifests,
+    lock_cache, prepare_fetch, read_sync_state, run_failure_hook, select_projects, verify_pinned,
+    write_credentials, write_sync_state, DefaultGitCommandRunner, GitCommandRunner, SyncEvent,
+    SyncObserver, SyncOptions, SyncReport, SyncState, CANCEL_POLL_INTERVAL, SYNC_CANCELLED,
+    SYNC_STATE_FILE,
+};
+use crate::Manifest;
+use log::{debug, error, warn};
+use std::error::Error;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Like [`sync_repos_with_observer`](crate::sync::sync_repos_with_observer),
+/// on a tokio runtime, for services syncing many workspaces at once.
+///
+/// Projects are cloned and fetched by tasks running git and the clone bundle
+/// downloads with `tokio::process`, so that long fetches do not hold
+/// threads, and checked out on the blocking threads of the runtime with
+/// [`DefaultGitCommandRunner`]. Semaphores bound how many fetch and check
+/// out at once, by the job counts of `options`. A
+/// [`CancelToken`](crate::sync::CancelToken) in `options` stops the sync
+/// like it stops `sync_repos`, and [`SyncHooks`](crate::sync::SyncHooks)
+/// run on the blocking threads too.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::sync_async::sync_repos_async;
+///
+/// let report = sync_repos_async("default.xml", None, options, "workspace", observer).await?;
+/// ```
+pub async fn sync_repos_async(
+    manifest_path: &str,
+    project_list: Option<Vec<&str>>,
+    options: SyncOptions,
+    target_dir: &str,
+    observer: Arc<dyn SyncObserver>,
+) -> Result<SyncReport, Box<dyn Error + Send + Sync>> {
+    sync_repos_async_with_runner(
+        manifest_path,
+        project_list,
+        options,
+        target_dir,
+        observer,
+        Arc::new(DefaultGitCommandRunner),
+    )
+    .await
+}
+
+/// Like [`sync_repos_async`], running the git commands of the sync with
+/// `runner`, like [`sync_repos_with_runner`](crate::sync::sync_repos_with_runner),
+/// except for the fetches, which run with `tokio::process`.
+pub async fn sync_repos_async_with_runner(
+    manifest_path: &str,
+    project_list: Option<Vec<&str>>,
+    options: SyncOptions,
+    target_dir: &str,
+    observer: Arc<dyn SyncObserver>,
+    runner: Arc<dyn GitCommandRunner + Send + Sync>,
+) -> Result<SyncReport, Box<dyn Error + Send + Sync>> {
+    debug!("sync_repos_async called with:");
+    debug!("  manifest_path: {}", manifest_path);
+    debug!("  target_dir: {}", target_dir);
+
+    let manifest = load_and_merge_manifests(manifest_path, None).map_err(|e| e.to_string())?;
+    let manifest = Arc::new(manifest);
+    let projects_to_sync = select_projects(&manifest, project_list.as_deref(), &options);
+    let target_path = PathBuf::from(target_dir);
+    fs::create_dir_all(&target_path)?;
+    let _credentials =
+        write_credentials(&manifest, &options, &target_path).map_err(|e| e.to_string())?;
+
+    let jobs_network = determine_jobs(&manifest, options.jobs_network.or(options.jobs));
+    let jobs_checkout = determine_jobs(&manifest, options.jobs_checkout.or(options.jobs));
+    debug!(
+        "Number of jobs: {} network, {} checkout",
+        jobs_network, jobs_checkout
+    );
+    let network = Arc::new(Semaphore::new(jobs_network));
+    let checkout = Arc::new(Semaphore::new(jobs_checkout));
+
+    let state_path = target_path.join(".repo").join(SYNC_STATE_FILE);
+    let state = if options.incremental {
+        read_sync_state(&state_path).map_err(|e| e.to_string())?
+    } else {
+        SyncState::new()
+    };
+    let state = Arc::new(Mutex::new(state));
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let options = Arc::new(options);
+    observer.on_event(&SyncEvent::Started {
+        total: projects_to_sync.len(),
+    });
+
+    // Every project is fetched before any is checked out, like
+    // `sync_repos` does.
+    let mut fetches = JoinSet::new();
+    for &index in &projects_to_sync {
+        let network = Arc::clone(&network);
+        let manifest = Arc::clone(&manifest);
+        let target_path = target_path.clone();
+        let options = Arc::clone(&options);
+        let state = Arc::clone(&state);
+        let errors = Arc::clone(&errors);
+        let reports = Arc::clone(&reports);
+        let stop_flag = Arc::clone(&stop_flag);
+        let observer = Arc::clone(&observer);
+        let runner = Arc::clone(&runner);
+
+        fetches.spawn(async move {
+            let _permit = network.acquire_owned().await.ok()?;
+            if options.is_cancelled() || !options.keep && stop_flag.load(Ordering::Relaxed) {
+                return None;
+            }
+            let project = &manifest.projects[index];
+            observer.on_event(&SyncEvent::ProjectStarted {
+                name: project.name.clone(),
+            });
+            let path = project.path.clone().unwrap_or_else(|| project.name.clone());
+            let synced = if options.incremental {
+                state.lock().unwrap().remove(&path)
+            } else {
+                None
+            };
+            if let Some(synced) = synced {
+                let (manifest, target_path, options, checked, git) = (
+                    Arc::clone(&manifest),
+                    target_path.clone(),
+                    Arc::clone(&options),
+                    synced.clone(),
+                    Arc::clone(&runner),
+                );
+                let up_to_date = blocking(move || {
+                    let project = &manifest.projects[index];
+                    Ok(is_synced(
+                        &*git,
+                        project,
+                        &manifest,
+                        &target_path,
+                        &options,
+                        &checked,
+                    ))
+                })
+                .await;
+                if up_to_date == Ok(true) {
+                    debug!("{} is already synced, skipping", project.name);
+                    state.lock().unwrap().insert(path, synced);
+                    finish_project(
+                        &*observer,
+                        &errors,
+                        &reports,
+                        index,
+                        project,
+                        true,
+                        Ok(None),
+                    );
+                    return None;
+                }
+            }
+            match fetch_project(index, &manifest, &target_path, &options, &runner).await {
+                Ok(cloned) => Some((index, cloned)),
+                Err(e) => {
+                    stop_flag.store(true, Ordering::Relaxed);
+                    if options.hooks.is_some() {
+                        let (manifest, target_path, options, error) = (
+                            Arc::clone(&manifest),
+                            target_path.clone(),
+                            Arc::clone(&options),
+                            e.clone(),
+                        );
+                        let _ = blocking(move || {
+                            let project = &manifest.projects[index];
+                            run_failure_hook(&options, &manifest, project, &target_path, &error);
+                            Ok(())
+                        })
+                        .await;
+                    }
+                    finish_project(&*observer, &errors, &reports, index, project, false, Err(e));
+                    None
+                }
+            }
+        });
+    }
+    let mut fetched = Vec::new();
+    while let Some(result) = fetches.join_next().await {
+        fetched.extend(result?);
+    }
+
+    // Projects fetched before an error are still checked out, as they were
+    // started.
+    let mut checkouts = JoinSet::new();
+    for (index, cloned) in fetched {
+        let checkout = Arc::clone(&checkout);
+        let manifest = Arc::clone(&manifest);
+        let target_path = target_path.clone();
+        let options = Arc::clone(&options);
+        let state = Arc::clone(&state);
+        let errors = Arc::clone(&errors);
+        let reports = Arc::clone(&reports);
+        let observer = Arc::clone(&observer);
+        let runner = Arc::clone(&runner);
+
+        checkouts.spawn(async move {
+            let _permit = checkout.acquire_owned().await;
+            tokio::task::spawn_blocking(move || {
+                let git = &*runner;
+                let project = &manifest.projects[index];
+                if options.is_cancelled() {
+                    let error = Err(SYNC_CANCELLED.to_string());
+                    finish_project(&*observer, &errors, &reports, index, project, false, error);
+                    return;
+                }
+                let result =
+                    checkout_project(git, project, &manifest, &target_path, &options, cloned)
+                        .map_err(|e| e.to_string());
+                if let Err(e) = &result {
+                    run_failure_hook(&options, &manifest, project, &target_path, e);
+                }
+                if options.incremental && result.is_ok() {
+                    if let Some(synced) = head_state(git, project, &manifest, &target_path) {
+                        let path = project.path.clone().unwrap_or_else(|| project.name.clone());
+                        state.lock().unwrap().insert(path, synced);
+                    }
+                }
+                finish_project(&*observer, &errors, &reports, index, project, false, result);
+            })
+            .await
+        });
+    }
+    while let Some(result) = checkouts.join_next().await {
+        result??;
+    }
+
+    observer.on_event(&SyncEvent::Finished);
+    if options.incremental {
+        // Written before failing for errors, so that the projects synced
+        // are skipped when trying again.
+        write_sync_state(&state_path, &state.lock().unwrap()).map_err(|e| e.to_string())?;
+    }
+    if options.is_cancelled() {
+        return Err(SYNC_CANCELLED.into());
+    }
+    handle_errors(errors, options.keep).map_err(|e| e.to_string())?;
+    link_files(&manifest, &projects_to_sync, &target_path).map_err(|e| e.to_string())?;
+
+    let mut reports = std::mem::take(&mut *reports.lock().unwrap());
+    reports.sort_by_key(|(index, _)| *index);
+    Ok(SyncReport {
+        projects: reports.into_iter().map(|(_, report)| report).collect(),
+    })
+}
+
+// Clones or fetches the project at `index` of `manifest` with `runner`,
+// returning whether it was cloned, like `sync_repos` does but with the
+// clone bundle downloaded asynchronously.
+async fn fetch_project(
+    index: usize,
+    manifest: &Arc<Manifest>,
+    target_path: &Path,
+    options: &Arc<SyncOptions>,
+    runner: &Arc<dyn GitCommandRunner + Send + Sync>,
+) -> Result<bool, String> {
+    let fetch = {
+        let (manifest, target_path, options, git) = (
+            Arc::clone(manifest),
+            target_path.to_path_buf(),
+            Arc::clone(options),
+            Arc::clone(runner),
+        );
+        blocking(move || {
+            let project = &manifest.projects[index];
+            prepare_fetch(&*git, project, &manifest, &target_path, &options)
+        })
+        .await?
+    };
+    if let Some(bundle_url) = &fetch.clone_bundle {
+        debug!("Downloading {}", bundle_url);
+        let download = Command::new("curl")
+            .args(fetch.curl_args())
+            .kill_on_drop(true)
+            .status();
+        let downloaded = unless_cancelled(download, options)
+            .await
+            .is_ok_and(|status| status.is_ok_and(|status| status.success()));
+        let (bundled, git) = (fetch.clone(), Arc::clone(runner));
+        blocking(move || {
+            apply_clone_bundle(&*git, &bundled, downloaded);
+            Ok(())
+        })
+        .await?;
+    }
+    if let Some((repository, args)) = &fetch.cache {
+        debug!("Updating cache {}", repository.display());
+        let locked = repository.clone();
+        let _lock = blocking(move || lock_cache(&locked)).await?;
+        if let Err(e) = fetch_async(repository, args, options).await {
+            error!(
+                "Failed to fetch {} into the cache: {}",
+                repository.display(),
+                e
+            );
+            return Err(e);
+        }
+    }
+    debug!("Running git fetch with args: {:?}", fetch.args);
+    if let Err(e) = fetch_async(&fetch.project_path, &fetch.args, options).await {
+        error!("Failed to fetch {}: {}", fetch.project_path.display(), e);
+        return Err(e);
+    }
+    let cloned = fetch.cloned;
+    let git = Arc::clone(runner);
+    blocking(move || verify_pinned(&*git, &fetch)).await?;
+    Ok(cloned)
+}
+
+// Runs git fetch in `project_path` with `tokio::process`, retrying like
+// `sync_repos` does.
+async fn fetch_async(
+    project_path: &Path,
+    args: &[String],
+    options: &SyncOptions,
+) -> Result<(), String> {
+    let retry = &options.retry;
+    let mut attempt = 1;
+    loop {
+        let mut command = Command::new("git");
+        command
+            .arg("-C")
+            .arg(project_path)
+            .args(args)
+            .envs(auth_env(options));
+        match run_git(&mut command, options).await {
+            Err(e) if attempt < retry.attempts && is_transient_git_error(&e) => {
+                let delay = retry.delay(attempt);
+                warn!(
+                    "Fetch in {} failed, retrying in {:?}: {}",
+                    project_path.display(),
+                    delay,
+                    e
+                );
+                let _ = unless_cancelled(tokio::time::sleep(delay), options).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Runs the git `command` until the sync is cancelled. With a cancel token,
+// git runs in a process group of its own, killed as a whole on cancelling
+// so that the processes git started, e.g. `ssh`, stop with it.
+async fn run_git(command: &mut Command, options: &SyncOptions) -> Result<(), String> {
+    if options.is_cancelled() {
+        return Err(SYNC_CANCELLED.to_string());
+    }
+    #[cfg(unix)]
+    if options.cancel.is_some() {
+        command.process_group(0);
+    }
+    let child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let pid = child.id();
+    let mut output = std::pin::pin!(child.wait_with_output());
+    let output = loop {
+        if options.is_cancelled() {
+            #[cfg(unix)]
+            if let Some(pid) = pid {
+                // SAFETY: killpg only sends a signal.
+                unsafe {
+                    libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+                }
+            }
+            // Elsewhere, git is killed as the output is dropped.
+            #[cfg(not(unix))]
+            let _ = pid;
+            return Err(SYNC_CANCELLED.to_string());
+        }
+        if let Ok(output) = tokio::time::timeout(CANCEL_POLL_INTERVAL, &mut output).await {
+            break output.map_err(|e| e.to_string())?;
+        }
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Git command failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+// Awaits `future`, dropping it if the sync is cancelled first.
+async fn unless_cancelled<F: Future>(
+    future: F,
+    options: &SyncOptions,
+) -> Result<F::Output, String> {
+    if options.cancel.is_none() {
+        return Ok(future.await);
+    }
+    let mut future = std::pin::pin!(future);
+    loop {
+        if options.is_cancelled() {
+            return Err(SYNC_CANCELLED.to_string());
+        }
+        if let Ok(output) = tokio::time::timeout(CANCEL_POLL_INTERVAL, &mut future).await {
+            return Ok(output);
+        }
+    }
+}
+
+// Runs `f`, which runs local git commands, on the blocking threads of the
+// runtime.
+async fn blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Box<dyn Error>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || f().map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,105 @@
+use crate::include::NoIncludes;
+use crate::pin::is_commit_sha;
+use crate::{Manifest, Project, Remote};
+use std::error::Error;
+use std::fs;
+
+// The remote added to snapshot manifests, which do not define one.
+const SNAPSHOT_REMOTE_NAME: &str = "tizen";
+
+impl Manifest {
+    /// Reads a manifest published with a Tizen snapshot under
+    /// `builddata/manifest/`, to reconstruct the workspace the snapshot
+    /// was built from.
+    ///
+    /// Snapshot manifests list every project with its path and the commit
+    /// it was built at, but no remote. Unless the file defines remotes, a
+    /// `tizen` remote fetching from `fetch`, e.g.
+    /// `https://review.tizen.org/git`, becomes the default remote.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_tizen_snapshot(
+    ///     "builddata/manifest/tizen-unified_20240101.1_arm-wayland.xml",
+    ///     "https://review.tizen.org/git",
+    /// )?;
+    /// manifest.write_to_file("default.xml")?;
+    /// ```
+    pub fn from_tizen_snapshot(file_path: &str, fetch: &str) -> Result<Manifest, Box<dyn Error>> {
+        let xml = fs::read_to_string(file_path)?;
+        Self::from_tizen_snapshot_str(&xml, fetch)
+    }
+
+    /// Reads a Tizen snapshot manifest held in memory, see
+    /// [`Manifest::from_tizen_snapshot`].
+    pub fn from_tizen_snapshot_str(xml: &str, fetch: &str) -> Result<Manifest, Box<dyn Error>> {
+        let mut manifest = Manifest::from_str(xml, None, None, &NoIncludes)?;
+        if manifest.remotes.is_empty() {
+            manifest.remotes.push(Remote {
+                name: SNAPSHOT_REMOTE_NAME.to_string(),
+                alias: None,
+                fetch: fetch.to_string(),
+                pushurl: None,
+                review: None,
+                revision: None,
+                source: None,
+            });
+            match &mut manifest.default {
+                Some(default) => {
+                    default.remote.get_or_insert(SNAPSHOT_REMOTE_NAME.into());
+                }
+                None => manifest.set_default(Some(SNAPSHOT_REMOTE_NAME), None),
+            }
+        }
+        for project in &manifest.projects {
+            snapshot_revision(project)?;
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest in the format of Tizen snapshot manifests: one
+    /// `<project>` per project with its name, path and commit.
+    ///
+    /// Fails for projects not pinned to a commit, see
+    /// [`Manifest::pin_revisions`].
+    pub fn to_tizen_snapshot(&self) -> Result<String, Box<dyn Error>> {
+        let mut snapshot = Manifest::new();
+        for project in &self.projects {
+            snapshot.projects.push(Project {
+                name: project.name.clone(),
+                path: Some(project.path.clone().unwrap_or_else(|| project.name.clone())),
+                remote: None,
+                revision: Some(snapshot_revision(project)?.into()),
+                dest_branch: None,
+                groups: Vec::new(),
+                sync_c: None,
+                sync_s: None,
+                sync_tags: None,
+                upstream: None,
+                clone_depth: None,
+                force_path: None,
+                copyfiles: Vec::new(),
+                linkfiles: Vec::new(),
+                annotations: Vec::new(),
+                source: None,
+            });
+        }
+        Ok(snapshot.to_xml())
+    }
+}
+
+// The commit `project` is pinned to.
+fn snapshot_revision(project: &Project) -> Result<&str, Box<dyn Error>> {
+    match project.revision.as_deref() {
+        Some(revision) if is_commit_sha(revision) => Ok(revision),
+        Some(revision) => Err(format!(
+            "Project '{}' is at '{}', not pinned to a commit",
+            project.name, revision
+        )
+        .into()),
+        None => Err(format!("Project '{}' is not pinned to a commit", project.name).into()),
+    }
+}
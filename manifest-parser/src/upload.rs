@@ -0,0 +1,243 @@
+use crate::hooks::{run_repo_hook, HookName};
+use crate::query::ResolvedProject;
+use crate::subset::Selection;
+use crate::sync::load_and_merge_manifests;
+use crate::Manifest;
+use git_utils::{GitCommand, GitCommandBuilder};
+use log::{debug, info};
+use std::error::Error;
+use std::path::Path;
+
+/// Options of [`upload`].
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    /// The projects to upload, all of them if `None`.
+    pub selection: Option<Selection>,
+    /// Reviewers to add to the changes.
+    pub reviewers: Vec<String>,
+    /// Users to CC on the changes.
+    pub cc: Vec<String>,
+    /// Topic to set on the changes.
+    pub topic: Option<String>,
+    /// List what would be uploaded without pushing.
+    pub dry_run: bool,
+    /// Skips the `pre-upload` hook of the manifest, like
+    /// `repo upload --no-verify`.
+    pub no_verify: bool,
+}
+
+/// What [`upload`] did, or would do, for a project with local commits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Upload {
+    pub name: String,
+    /// Checkout path, relative to the target directory.
+    pub path: String,
+    /// Where the commits are pushed, see [`review_url`].
+    pub url: String,
+    /// The refspec pushed, e.g. `HEAD:refs/for/main%r=alice@example.com`.
+    pub refspec: String,
+    /// The local commits, newest first, as `<short SHA> <subject>`.
+    pub commits: Vec<String>,
+    /// Whether the commits were pushed; never for a dry run.
+    pub uploaded: bool,
+    /// Why the commits could not be listed or pushed.
+    pub error: Option<String>,
+}
+
+/// The URL to push the changes of the project named `name` to for review
+/// on `review`, the `review` attribute of its remote. A review server
+/// without a scheme is reached over SSH on Gerrit's port, 29418.
+pub fn review_url(review: &str, name: &str) -> String {
+    let review = review.trim_end_matches('/');
+    if review.contains("://") {
+        format!("{}/{}", review, name)
+    } else {
+        format!("ssh://{}:29418/{}", review, name)
+    }
+}
+
+/// Pushes the local commits of the selected projects of the manifest at
+/// `manifest_path` in `target_dir` for review, like `repo upload`: to
+/// `refs/for/<branch>` on the review server of their remote, where the
+/// branch is the [upload branch](ResolvedProject::upload_branch).
+///
+/// Local commits are those of HEAD that are not on its upstream branch,
+/// which [`sync_repos`](crate::sync::sync_repos) sets up, or else not on
+/// the upload branch of the remote. Projects without local commits are
+/// left out of the result, and projects that cannot be uploaded, e.g.
+/// because their remote has no review server, get an error.
+///
+/// The `pre-upload` hook of the manifest, see
+/// [`run_repo_hook`], checks the projects with local commits first,
+/// even for a dry run. Fails if it does, or if the manifest cannot be
+/// loaded or a project cannot be selected or resolved.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::upload::{upload, UploadOptions};
+///
+/// let options = UploadOptions {
+///     reviewers: vec!["alice@example.com".to_string()],
+///     dry_run: true,
+///     ..UploadOptions::default()
+/// };
+/// for upload in upload(manifest_path, ".", &options)? {
+///     println!("{}: {} commits to {}", upload.path, upload.commits.len(), upload.refspec);
+/// }
+/// ```
+pub fn upload(
+    manifest_path: &str,
+    target_dir: &str,
+    options: &UploadOptions,
+) -> Result<Vec<Upload>, Box<dyn Error>> {
+    // Subsets drop the hooks unless their project is selected.
+    let full = load_and_merge_manifests(manifest_path, None)?;
+    let subset = options
+        .selection
+        .as_ref()
+        .map(|selection| full.subset(selection))
+        .transpose()?;
+    let manifest = subset.as_ref().unwrap_or(&full);
+    let mut uploads = Vec::new();
+    let mut pending = Vec::new();
+    for project in manifest.resolve()? {
+        let checkout = Path::new(target_dir).join(&project.path);
+        if !checkout.is_dir() {
+            continue;
+        }
+        let commits = match local_commits(&project, &checkout) {
+            Ok(commits) if commits.is_empty() => continue,
+            Ok(commits) => commits,
+            Err(e) => {
+                uploads.push(Upload {
+                    name: project.name.clone(),
+                    path: project.path.clone(),
+                    error: Some(e.to_string()),
+                    ..Upload::default()
+                });
+                continue;
+            }
+        };
+        uploads.push(Upload {
+            name: project.name.clone(),
+            path: project.path.clone(),
+            commits,
+            ..Upload::default()
+        });
+        pending.push((uploads.len() - 1, project, checkout));
+    }
+
+    if !options.no_verify {
+        let projects: Vec<(&str, &str)> = pending
+            .iter()
+            .map(|(_, project, _)| (project.name.as_str(), project.path.as_str()))
+            .collect();
+        if !projects.is_empty() {
+            run_repo_hook(
+                &full,
+                Path::new(target_dir),
+                &HookName::PreUpload,
+                &projects,
+            )?;
+        }
+    }
+    for (index, project, checkout) in pending {
+        let upload = &mut uploads[index];
+        if let Err(e) = push(manifest, &project, &checkout, options, upload) {
+            upload.error = Some(e.to_string());
+        }
+    }
+    Ok(uploads)
+}
+
+// The commits of HEAD that are not on its upstream, or else not on the
+// upload branch of the remote.
+fn local_commits(
+    project: &ResolvedProject,
+    checkout: &Path,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let upstream = GitCommand::new("git")
+        .args(&["rev-parse", "--verify", "-q", "HEAD@{upstream}"])
+        .dir(checkout)
+        .run_with_output();
+    let base = match (upstream, project.upload_branch()) {
+        (Ok(_), _) => "HEAD@{upstream}".to_string(),
+        (Err(_), Some(branch)) => format!(
+            "refs/remotes/{}/{}",
+            project.git_remote,
+            branch.strip_prefix("refs/heads/").unwrap_or(branch)
+        ),
+        (Err(_), None) => {
+            return Err(format!("Project '{}' has no branch to upload to", project.name).into())
+        }
+    };
+    let output = GitCommand::new("git")
+        .args(&["log", "--format=%h %s", &format!("{}..HEAD", base)])
+        .dir(checkout)
+        .run_with_output()
+        .map_err(|e| format!("Cannot list the local commits of '{}': {}", project.name, e))?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+fn push(
+    manifest: &Manifest,
+    project: &ResolvedProject,
+    checkout: &Path,
+    options: &UploadOptions,
+    upload: &mut Upload,
+) -> Result<(), Box<dyn Error>> {
+    let review = manifest
+        .remotes
+        .iter()
+        .find(|remote| remote.name == project.remote)
+        .and_then(|remote| remote.review.as_deref())
+        .ok_or_else(|| {
+            format!(
+                "Remote '{}' of project '{}' has no review server",
+                project.remote, project.name
+            )
+        })?;
+    let branch = project
+        .upload_branch()
+        .ok_or_else(|| format!("Project '{}' has no branch to upload to", project.name))?;
+    upload.url = review_url(review, &project.name);
+    upload.refspec = refspec(branch, options);
+    if options.dry_run {
+        info!(
+            "Would upload {} commits of {} to {} {}",
+            upload.commits.len(),
+            project.name,
+            upload.url,
+            upload.refspec
+        );
+        return Ok(());
+    }
+    debug!(
+        "Pushing {} to {} {}",
+        project.name, upload.url, upload.refspec
+    );
+    GitCommandBuilder::git_push(&upload.url, &upload.refspec)
+        .dir(checkout)
+        .run()?;
+    upload.uploaded = true;
+    Ok(())
+}
+
+// `HEAD:refs/for/<branch>`, followed by the Gerrit push options for the
+// reviewers, CCs and topic.
+fn refspec(branch: &str, options: &UploadOptions) -> String {
+    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+    let push_options: Vec<String> = options
+        .reviewers
+        .iter()
+        .map(|reviewer| format!("r={}", reviewer))
+        .chain(options.cc.iter().map(|cc| format!("cc={}", cc)))
+        .chain(options.topic.iter().map(|topic| format!("topic={}", topic)))
+        .collect();
+    if push_options.is_empty() {
+        format!("HEAD:refs/for/{}", branch)
+    } else {
+        format!("HEAD:refs/for/{}%{}", branch, push_options.join(","))
+    }
+}
@@ -0,0 +1,77 @@
+use crate::Manifest;
+
+impl Manifest {
+    /// Resolves relative remote fetch URLs such as `..` against
+    /// `manifest_url`, the URL the manifest repository was cloned from, the
+    /// way repo does.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // <remote name="aosp" fetch=".."/>
+    /// manifest.resolve_fetch_urls("https://android.googlesource.com/platform/manifest");
+    /// assert_eq!(manifest.remotes[0].fetch, "https://android.googlesource.com");
+    /// ```
+    pub fn resolve_fetch_urls(&mut self, manifest_url: &str) {
+        for remote in &mut self.remotes {
+            remote.fetch = resolve_url(manifest_url, &remote.fetch);
+        }
+    }
+}
+
+/// Resolves `url` relative to `base` like a relative link on a web page,
+/// without a trailing slash. Absolute URLs, scp-like `host:path` addresses
+/// and absolute paths are returned as they are.
+///
+/// ```ignore
+/// assert_eq!(resolve_url("ssh://host/org/manifest", "../mirror"), "ssh://host/mirror");
+/// assert_eq!(resolve_url("git@host:org/manifest", "."), "git@host:org");
+/// ```
+pub fn resolve_url(base: &str, url: &str) -> String {
+    if is_absolute(url) {
+        return url.to_string();
+    }
+
+    let base = base.trim_end_matches('/');
+    let (prefix, path) = match base.find("://") {
+        Some(scheme_end) => {
+            let path_start = base[scheme_end + 3..]
+                .find('/')
+                .map_or(base.len(), |i| scheme_end + 3 + i);
+            (&base[..path_start], &base[path_start..])
+        }
+        None => match scp_host(base) {
+            Some(host_end) => (&base[..=host_end], &base[host_end + 1..]),
+            None => ("", base),
+        },
+    };
+
+    // The last segment of the base names the manifest repository itself.
+    let mut segments: Vec<&str> = path.split('/').collect();
+    segments.pop();
+    for segment in url.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => {
+                // Never climb above the root.
+                if segments.len() > 1 || segments.first().is_some_and(|s| !s.is_empty()) {
+                    segments.pop();
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+    format!("{}{}", prefix, segments.join("/"))
+}
+
+fn is_absolute(url: &str) -> bool {
+    url.contains("://") || url.starts_with('/') || scp_host(url).is_some()
+}
+
+// The position of the colon ending the host of an scp-like address, e.g.
+// `git@host:org/repo`.
+fn scp_host(url: &str) -> Option<usize> {
+    let colon = url.find(':')?;
+    let slash = url.find('/').unwrap_or(url.len());
+    (colon < slash).then_some(colon)
+}
@@ -0,0 +1,71 @@
+use crate::Manifest;
+use std::error::Error;
+
+/// Checks that `path` stays below the directory it is relative to: it must
+/// not be empty or absolute, nor contain `.` or `..` components.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::validate::check_relative_path;
+///
+/// assert!(check_relative_path("platform/core").is_ok());
+/// assert!(check_relative_path("../outside").is_err());
+/// ```
+pub fn check_relative_path(path: &str) -> Result<(), String> {
+    if path.is_empty() {
+        return Err("path is empty".to_string());
+    }
+    if path.starts_with(['/', '\\']) || path.chars().nth(1) == Some(':') {
+        return Err("path is absolute".to_string());
+    }
+    match path.split(['/', '\\']).find(|c| *c == "." || *c == "..") {
+        Some(component) => Err(format!("path contains a '{}' component", component)),
+        None => Ok(()),
+    }
+}
+
+impl Manifest {
+    /// Checks the names and checkout paths of the projects, and the
+    /// sources and destinations of their `<copyfile>` and `<linkfile>`
+    /// elements, with [`check_relative_path`], before they are used to
+    /// build paths in the workspace.
+    ///
+    /// This is meant for the manifest once local manifests are merged into
+    /// it, since they add projects too.
+    pub fn validate_paths(&self) -> Result<(), Box<dyn Error>> {
+        for project in &self.projects {
+            check_relative_path(&project.name)
+                .map_err(|e| format!("Invalid name of project '{}': {}", project.name, e))?;
+            if let Some(path) = &project.path {
+                check_relative_path(path).map_err(|e| {
+                    format!(
+                        "Invalid path '{}' of project '{}': {}",
+                        path, project.name, e
+                    )
+                })?;
+            }
+            let files = project
+                .copyfiles
+                .iter()
+                .map(|copyfile| ("copyfile", &copyfile.src, &copyfile.dest))
+                .chain(
+                    project
+                        .linkfiles
+                        .iter()
+                        .map(|linkfile| ("linkfile", &linkfile.src, &linkfile.dest)),
+                );
+            for (element, src, dest) in files {
+                for (attribute, path) in [("src", src), ("dest", dest)] {
+                    check_relative_path(path).map_err(|e| {
+                        format!(
+                            "Invalid {} {} '{}' of project '{}': {}",
+                            element, attribute, path, project.name, e
+                        )
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use quick_xml::events::attributes::Attributes;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::borrow::Cow;
+use std::error::Error;
+
+/// A read-only view of the remotes, default and projects of a manifest,
+/// borrowing from the XML it was parsed from.
+///
+/// Parsing a view allocates little more than its vectors: attribute
+/// values are only copied when they contain escapes. It suits analysis of
+/// large manifests that does not need the full [`Manifest`](crate::Manifest)
+/// model. Includes are not followed, and the children of projects and the
+/// other elements are skipped.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::view::ManifestView;
+///
+/// let xml = std::fs::read_to_string("default.xml")?;
+/// let view = ManifestView::parse(&xml)?;
+/// let unpinned = view.projects.iter().filter(|p| p.revision.is_none()).count();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManifestView<'a> {
+    pub remotes: Vec<RemoteView<'a>>,
+    pub default: Option<DefaultView<'a>>,
+    pub projects: Vec<ProjectView<'a>>,
+}
+
+/// A `<remote>` of a [`ManifestView`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteView<'a> {
+    pub name: Cow<'a, str>,
+    pub alias: Option<Cow<'a, str>>,
+    pub fetch: Cow<'a, str>,
+    pub review: Option<Cow<'a, str>>,
+    pub revision: Option<Cow<'a, str>>,
+}
+
+/// The `<default>` of a [`ManifestView`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefaultView<'a> {
+    pub remote: Option<Cow<'a, str>>,
+    pub revision: Option<Cow<'a, str>>,
+    pub dest_branch: Option<Cow<'a, str>>,
+    pub upstream: Option<Cow<'a, str>>,
+}
+
+/// A `<project>` of a [`ManifestView`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectView<'a> {
+    pub name: Cow<'a, str>,
+    pub path: Option<Cow<'a, str>>,
+    pub remote: Option<Cow<'a, str>>,
+    pub revision: Option<Cow<'a, str>>,
+    pub dest_branch: Option<Cow<'a, str>>,
+    pub upstream: Option<Cow<'a, str>>,
+    /// The `groups` attribute, see [`parse_groups`](crate::groups::parse_groups).
+    pub groups: Option<Cow<'a, str>>,
+    pub clone_depth: Option<Cow<'a, str>>,
+}
+
+impl ProjectView<'_> {
+    /// The checkout path: the `path`, or else the name.
+    pub fn path_or_name(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl<'a> ManifestView<'a> {
+    /// Parses the manifest XML `xml`, failing on malformed XML and on
+    /// remotes or projects without their required attributes.
+    pub fn parse(xml: &'a str) -> Result<Self, Box<dyn Error>> {
+        let mut view = ManifestView::default();
+        let mut reader = Reader::from_str(xml);
+        // Depth of the elements read, so that only children of the
+        // root element are taken.
+        let mut depth = 0;
+
+        loop {
+            let (e, closed) = match reader.read_event()? {
+                Event::Start(e) => (e, false),
+                Event::Empty(e) => (e, true),
+                Event::End(_) => {
+                    depth -= 1;
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            if depth == 1 {
+                // The start tag without `<` and `>` or `/>`, from the
+                // input rather than the event to borrow from `xml`.
+                let end = reader.buffer_position() as usize - if closed { 2 } else { 1 };
+                let content = xml
+                    .get(end - e.len()..end)
+                    .ok_or("Cannot locate element in the input")?;
+                let name_len = e.name().as_ref().len();
+                let attributes = Attributes::new(content, name_len);
+                match e.name().as_ref() {
+                    b"remote" => view.remotes.push(remote(attributes)?),
+                    b"default" => view.default = Some(default(attributes)?),
+                    b"project" => view.projects.push(project(attributes)?),
+                    _ => (),
+                }
+            }
+            if !closed {
+                depth += 1;
+            }
+        }
+        Ok(view)
+    }
+
+    /// The project named `name`, the first one if several are.
+    pub fn find_project(&self, name: &str) -> Option<&ProjectView<'a>> {
+        self.projects.iter().find(|project| project.name == name)
+    }
+}
+
+fn remote(attributes: Attributes) -> Result<RemoteView, Box<dyn Error>> {
+    let mut remote = RemoteView::default();
+    for attr in attributes {
+        let attr = attr?;
+        let value = attr.unescape_value()?;
+        match attr.key.as_ref() {
+            b"name" => remote.name = value,
+            b"alias" => remote.alias = Some(value),
+            b"fetch" => remote.fetch = value,
+            b"review" => remote.review = Some(value),
+            b"revision" => remote.revision = Some(value),
+            _ => (),
+        }
+    }
+    if remote.name.is_empty() || remote.fetch.is_empty() {
+        return Err("Missing required attributes in remote element".into());
+    }
+    Ok(remote)
+}
+
+fn default(attributes: Attributes) -> Result<DefaultView, Box<dyn Error>> {
+    let mut default = DefaultView::default();
+    for attr in attributes {
+        let attr = attr?;
+        let value = attr.unescape_value()?;
+        match attr.key.as_ref() {
+            b"remote" => default.remote = Some(value),
+            b"revision" => default.revision = Some(value),
+            b"dest-branch" => default.dest_branch = Some(value),
+            b"upstream" => default.upstream = Some(value),
+            _ => (),
+        }
+    }
+    Ok(default)
+}
+
+fn project(attributes: Attributes) -> Result<ProjectView, Box<dyn Error>> {
+    let mut project = ProjectView::default();
+    for attr in attributes {
+        let attr = attr?;
+        let value = attr.unescape_value()?;
+        match attr.key.as_ref() {
+            b"name" => project.name = value,
+            b"path" => project.path = Some(value),
+            b"remote" => project.remote = Some(value),
+            b"revision" => project.revision = Some(value),
+            b"dest-branch" => project.dest_branch = Some(value),
+            b"upstream" => project.upstream = Some(value),
+            b"groups" => project.groups = Some(value),
+            b"clone-depth" => project.clone_depth = Some(value),
+            _ => (),
+        }
+    }
+    if project.name.is_empty() {
+        return Err("Missing required attribute 'name' in project element".into());
+    }
+    Ok(project)
+}
@@ -0,0 +1,505 @@
+use crate::groups::Group;
+use crate::{Default, Manifest, Project, Remote};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use yaml_rust2::{Yaml, YamlLoader};
+
+// West checks out projects without a revision at `master`.
+const WEST_DEFAULT_REVISION: &str = "master";
+
+impl Manifest {
+    /// Converts the manifest into a west manifest (`west.yml`), so Zephyr
+    /// workspaces can be kept in sync with the repo manifest.
+    ///
+    /// Every project keeps its repository name as `repo-path` and its
+    /// checkout path as `path`. West project names cannot be paths, so the
+    /// checkout path with `/` replaced by `-` is used as the name, with a
+    /// `-2`, `-3`, ... suffix when paths like `a/b-c` and `a-b/c` would
+    /// give several projects the same name. The
+    /// revision of a remote is written to the projects that use it, since
+    /// west remotes have no revision. `copyfile`, `linkfile`, `dest-branch`
+    /// and `upstream` have no west equivalent and are dropped, and projects
+    /// in the `notdefault` group are disabled with a `group-filter`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_file("default.xml", None, None)?;
+    /// manifest.write_west_file("west.yml")?;
+    /// ```
+    pub fn to_west_yaml(&self) -> Result<String, Box<dyn Error>> {
+        let mut yaml = String::from("manifest:\n");
+
+        if let Some(default) = &self.default {
+            if default.remote.is_some() || default.revision.is_some() {
+                yaml.push_str("  defaults:\n");
+                if let Some(remote) = &default.remote {
+                    yaml.push_str(&format!("    remote: {}\n", scalar(remote)));
+                }
+                if let Some(revision) = &default.revision {
+                    yaml.push_str(&format!("    revision: {}\n", scalar(revision)));
+                }
+                yaml.push('\n');
+            }
+        }
+
+        if !self.remotes.is_empty() {
+            yaml.push_str("  remotes:\n");
+            for remote in &self.remotes {
+                if !remote.fetch.contains("://") && !remote.fetch.contains('@') {
+                    return Err(format!(
+                        "Remote '{}' has a relative fetch URL '{}', which west cannot express",
+                        remote.name, remote.fetch
+                    )
+                    .into());
+                }
+                yaml.push_str(&format!("    - name: {}\n", scalar(&remote.name)));
+                yaml.push_str(&format!(
+                    "      url-base: {}\n",
+                    scalar(remote.fetch.trim_end_matches('/'))
+                ));
+            }
+            yaml.push('\n');
+        }
+
+        let mut notdefault = false;
+        let names = west_names(&self.projects);
+        yaml.push_str("  projects:\n");
+        for (project, name) in self.projects.iter().zip(names) {
+            let path = project.path.as_deref().unwrap_or(&project.name);
+            yaml.push_str(&format!("    - name: {}\n", scalar(&name)));
+            yaml.push_str(&format!("      repo-path: {}\n", scalar(&project.name)));
+            if let Some(remote) = &project.remote {
+                yaml.push_str(&format!("      remote: {}\n", scalar(remote)));
+            }
+            if let Some(revision) = self.west_revision(project) {
+                yaml.push_str(&format!("      revision: {}\n", scalar(revision)));
+            }
+            yaml.push_str(&format!("      path: {}\n", scalar(path)));
+            if let Some(depth) = &project.clone_depth {
+                yaml.push_str(&format!("      clone-depth: {}\n", depth));
+            }
+            if !project.groups.is_empty() {
+                notdefault |= project.groups.contains(&Group::NotDefault);
+                let groups: Vec<String> = project
+                    .groups
+                    .iter()
+                    .map(|group| scalar(&group.to_string()))
+                    .collect();
+                yaml.push_str(&format!("      groups: [{}]\n", groups.join(", ")));
+            }
+        }
+
+        if notdefault {
+            yaml.push_str("\n  group-filter: [-notdefault]\n");
+        }
+        Ok(yaml)
+    }
+
+    /// Writes the manifest to `file_path` as a west manifest, see
+    /// [`Manifest::to_west_yaml`].
+    pub fn write_west_file(&self, file_path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(file_path, self.to_west_yaml()?)?;
+        Ok(())
+    }
+
+    // The revision of `project` unless west picks it from the defaults.
+    fn west_revision<'a>(&'a self, project: &'a Project) -> Option<&'a str> {
+        if let Some(revision) = &project.revision {
+            return Some(revision);
+        }
+        let default = self.default.as_ref();
+        let remote_name = project
+            .remote
+            .as_ref()
+            .or_else(|| default.and_then(|d| d.remote.as_ref()))?;
+        self.remotes
+            .iter()
+            .find(|remote| *remote.name == **remote_name)
+            .and_then(|remote| remote.revision.as_deref())
+    }
+}
+
+// Unique west names for `projects`, derived from their checkout paths.
+fn west_names(projects: &[Project]) -> Vec<String> {
+    let bases: Vec<String> = projects
+        .iter()
+        .map(|project| {
+            project
+                .path
+                .as_deref()
+                .unwrap_or(&project.name)
+                .replace('/', "-")
+        })
+        .collect();
+    let taken: HashSet<&str> = bases.iter().map(String::as_str).collect();
+    let mut used = HashSet::new();
+    bases
+        .iter()
+        .map(|base| {
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while used.contains(&name) || (name != *base && taken.contains(name.as_str())) {
+                name = format!("{}-{}", base, suffix);
+                suffix += 1;
+            }
+            used.insert(name.clone());
+            name
+        })
+        .collect()
+}
+
+impl Manifest {
+    /// Reads a west manifest (`west.yml`), so gbsw can sync west based
+    /// workspaces.
+    ///
+    /// The manifest is expected in its repository directory right below
+    /// the workspace top directory, e.g. `zephyrproject/zephyr/west.yml`,
+    /// which is where the files of imported projects are looked up.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_west_file("zephyrproject/zephyr/west.yml")?;
+    /// ```
+    pub fn from_west_file(file_path: &str) -> Result<Manifest, Box<dyn Error>> {
+        let yaml = fs::read_to_string(file_path)?;
+        let doc = load_yaml(&yaml, file_path)?;
+        let dir = Path::new(file_path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        let depth = doc["manifest"]["self"]["path"]
+            .as_str()
+            .map(|path| Path::new(path).components().count())
+            .unwrap_or(1);
+        let topdir = dir.ancestors().nth(depth).map(Path::to_path_buf);
+        from_west(&doc, Some(dir), topdir)
+    }
+
+    /// Reads a west manifest held in memory, see
+    /// [`Manifest::from_west_file`].
+    ///
+    /// Imports are read relative to `topdir`, the workspace top directory,
+    /// and fail without one.
+    pub fn from_west_str(yaml: &str, topdir: Option<&Path>) -> Result<Manifest, Box<dyn Error>> {
+        let doc = load_yaml(yaml, "west manifest")?;
+        let dir = topdir.and_then(|topdir| {
+            doc["manifest"]["self"]["path"]
+                .as_str()
+                .map(|path| topdir.join(path))
+        });
+        from_west(&doc, dir, topdir.map(Path::to_path_buf))
+    }
+}
+
+// The projects and remotes of a west manifest and of everything it imports.
+struct WestManifest {
+    remotes: Vec<Remote>,
+    /// Projects with their west names, in order of precedence.
+    projects: Vec<(String, Project)>,
+    group_filter: Vec<String>,
+}
+
+fn from_west(
+    doc: &Yaml,
+    dir: Option<PathBuf>,
+    topdir: Option<PathBuf>,
+) -> Result<Manifest, Box<dyn Error>> {
+    let west = load_west(doc, Some(dir.as_deref()), topdir.as_deref())?;
+
+    let mut disabled = HashSet::new();
+    for entry in &west.group_filter {
+        if let Some(group) = entry.strip_prefix('-') {
+            disabled.insert(group);
+        } else if let Some(group) = entry.strip_prefix('+') {
+            disabled.remove(group);
+        }
+    }
+
+    let defaults = &doc["manifest"]["defaults"];
+    let mut manifest = Manifest::new();
+    manifest.remotes = west.remotes;
+    manifest.default = Some(Default {
+        remote: string(&defaults["remote"]).map(Into::into),
+        revision: string(&defaults["revision"]).map(Into::into),
+        dest_branch: None,
+        upstream: None,
+        sync_j: None,
+        sync_c: None,
+        sync_s: None,
+        sync_tags: None,
+    });
+    for (_, mut project) in west.projects {
+        if project
+            .groups
+            .iter()
+            .any(|group| disabled.contains(group.to_string().as_str()))
+        {
+            project.groups.push(Group::NotDefault);
+        }
+        manifest.projects.push(project);
+    }
+    Ok(manifest)
+}
+
+fn load_yaml(yaml: &str, location: &str) -> Result<Yaml, Box<dyn Error>> {
+    let doc = YamlLoader::load_from_str(yaml)
+        .map_err(|e| format!("Failed to parse '{}': {}", location, e))?
+        .into_iter()
+        .next()
+        .unwrap_or(Yaml::BadValue);
+    if !doc["manifest"].is_hash() {
+        return Err(format!("'{}' is not a west manifest", location).into());
+    }
+    Ok(doc)
+}
+
+// Loads the projects of `doc` followed by the projects it imports. Every
+// project gets an explicit remote and revision, since the defaults of an
+// imported manifest only apply within it. `dir` is the manifest directory
+// of the top-level manifest, the only one whose `self: import` counts.
+fn load_west(
+    doc: &Yaml,
+    dir: Option<Option<&Path>>,
+    topdir: Option<&Path>,
+) -> Result<WestManifest, Box<dyn Error>> {
+    let manifest = &doc["manifest"];
+    let default_remote = string(&manifest["defaults"]["remote"]);
+    let default_revision = string(&manifest["defaults"]["revision"])
+        .unwrap_or_else(|| WEST_DEFAULT_REVISION.to_string());
+
+    let mut west = WestManifest {
+        remotes: Vec::new(),
+        projects: Vec::new(),
+        group_filter: list(&manifest["group-filter"]),
+    };
+    for remote in manifest["remotes"].as_vec().into_iter().flatten() {
+        let name = string(&remote["name"]).ok_or("West remote without a name")?;
+        let fetch = string(&remote["url-base"])
+            .ok_or_else(|| format!("West remote '{}' has no url-base", name))?;
+        add_remote(&mut west.remotes, &name, &fetch);
+    }
+
+    let mut imports = Vec::new();
+    for project in manifest["projects"].as_vec().into_iter().flatten() {
+        let west_name = string(&project["name"]).ok_or("West project without a name")?;
+        if west.projects.iter().any(|(name, _)| *name == west_name) {
+            continue;
+        }
+
+        let (remote, repo_name) = match string(&project["url"]) {
+            Some(url) => {
+                let url = url.trim_end_matches('/');
+                let (fetch, repo) = url
+                    .rsplit_once('/')
+                    .ok_or_else(|| format!("Invalid url '{}' of project '{}'", url, west_name))?;
+                let remote = match west.remotes.iter().find(|remote| remote.fetch == fetch) {
+                    Some(remote) => remote.name.clone(),
+                    None => add_remote(&mut west.remotes, &west_name, fetch),
+                };
+                (remote, repo.trim_end_matches(".git").to_string())
+            }
+            None => {
+                let remote = string(&project["remote"])
+                    .or_else(|| default_remote.clone())
+                    .ok_or_else(|| format!("West project '{}' has no remote", west_name))?;
+                let repo = string(&project["repo-path"]).unwrap_or_else(|| west_name.clone());
+                (remote, repo)
+            }
+        };
+        let path = string(&project["path"]).unwrap_or_else(|| west_name.clone());
+        let groups = list(&project["groups"]);
+
+        west.projects.push((
+            west_name.clone(),
+            Project {
+                path: (path != repo_name).then_some(path.clone()),
+                name: repo_name,
+                remote: Some(remote.into()),
+                revision: Some(
+                    string(&project["revision"])
+                        .unwrap_or_else(|| default_revision.clone())
+                        .into(),
+                ),
+                dest_branch: None,
+                groups: groups.iter().map(|group| group.parse().unwrap()).collect(),
+                sync_c: None,
+                sync_s: None,
+                sync_tags: None,
+                upstream: None,
+                clone_depth: string(&project["clone-depth"])
+                    .map(|depth| {
+                        depth.parse().map_err(|_| {
+                            format!(
+                                "Invalid clone-depth '{}' of west project '{}'",
+                                depth, west_name
+                            )
+                        })
+                    })
+                    .transpose()?,
+                force_path: None,
+                copyfiles: Vec::new(),
+                linkfiles: Vec::new(),
+                annotations: Vec::new(),
+                source: None,
+            },
+        ));
+        if !project["import"].is_badvalue() {
+            imports.push((west_name, path, &project["import"]));
+        }
+    }
+
+    // Projects defined by the manifest itself take precedence over imported
+    // ones, and projects imported from the manifest repository over those
+    // imported from other projects.
+    let import = &manifest["self"]["import"];
+    if let (Some(dir), false) = (dir, import.is_badvalue()) {
+        let dir = dir.ok_or("Cannot import from 'self' without the manifest directory")?;
+        import_into(&mut west, import, dir, topdir, "self")?;
+    }
+    for (west_name, path, import) in imports {
+        let topdir = topdir.ok_or_else(|| {
+            format!(
+                "Cannot import from project '{}' without the workspace directory",
+                west_name
+            )
+        })?;
+        import_into(
+            &mut west,
+            import,
+            &topdir.join(path),
+            Some(topdir),
+            &west_name,
+        )?;
+    }
+    Ok(west)
+}
+
+// Reads the manifests an `import` names, relative to `dir`, and adds their
+// projects that are not defined yet.
+fn import_into(
+    west: &mut WestManifest,
+    import: &Yaml,
+    dir: &Path,
+    topdir: Option<&Path>,
+    importer: &str,
+) -> Result<(), Box<dyn Error>> {
+    let file = match import {
+        Yaml::Boolean(false) => return Ok(()),
+        Yaml::Boolean(true) => "west.yml".to_string(),
+        Yaml::Hash(_) => string(&import["file"]).unwrap_or_else(|| "west.yml".to_string()),
+        _ => string(import).ok_or_else(|| format!("Invalid import of '{}'", importer))?,
+    };
+    let allowlist = list(&import["name-allowlist"]);
+    let blocklist = list(&import["name-blocklist"]);
+    let path_prefix = string(&import["path-prefix"]);
+
+    // A directory imports all of its YAML files in order.
+    let path = dir.join(&file);
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(&path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        files.retain(|file| file.extension().is_some_and(|ext| ext == "yml"));
+        files.sort();
+        files
+    } else {
+        vec![path]
+    };
+
+    for file in files {
+        let location = file.to_string_lossy();
+        let yaml = fs::read_to_string(&file)
+            .map_err(|e| format!("Cannot import '{}' from '{}': {}", location, importer, e))?;
+        let imported = load_west(&load_yaml(&yaml, &location)?, None, topdir)?;
+
+        for remote in imported.remotes {
+            add_remote(&mut west.remotes, &remote.name, &remote.fetch);
+        }
+        for (name, mut project) in imported.projects {
+            if (!allowlist.is_empty() && !allowlist.contains(&name))
+                || blocklist.contains(&name)
+                || west.projects.iter().any(|(defined, _)| *defined == name)
+            {
+                continue;
+            }
+            if let Some(prefix) = &path_prefix {
+                let path = project.path.take().unwrap_or_else(|| project.name.clone());
+                project.path = Some(format!("{}/{}", prefix.trim_end_matches('/'), path));
+            }
+            west.projects.push((name, project));
+        }
+        west.group_filter.extend(imported.group_filter);
+    }
+    Ok(())
+}
+
+// Adds a remote unless one of that name exists, which then wins like the
+// projects defined first do. Returns the name of the remote.
+fn add_remote(remotes: &mut Vec<Remote>, name: &str, fetch: &str) -> String {
+    if remotes.iter().any(|remote| remote.name == name) {
+        return name.to_string();
+    }
+    remotes.push(Remote {
+        name: name.to_string(),
+        alias: None,
+        fetch: fetch.to_string(),
+        pushurl: None,
+        review: None,
+        revision: None,
+        source: None,
+    });
+    name.to_string()
+}
+
+// Reads a scalar as a string, since YAML reads e.g. `revision: 1234567` as
+// a number.
+fn string(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+// Reads a list of scalars, or a single comma separated scalar.
+fn list(value: &Yaml) -> Vec<String> {
+    match value.as_vec() {
+        Some(values) => values.iter().filter_map(string).collect(),
+        None => string(value)
+            .map(|s| {
+                s.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+// Quotes `value` when YAML would not read it back as the same string.
+fn scalar(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:+@".contains(c))
+        && !value.starts_with(['-', ':', '@'])
+        && !matches!(
+            value.to_ascii_lowercase().as_str(),
+            "true" | "false" | "yes" | "no" | "on" | "off" | "null" | "~"
+        )
+        && value.parse::<f64>().is_err();
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
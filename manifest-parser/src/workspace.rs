@@ -0,0 +1,113 @@
+use crate::gitmodules::add_remote;
+use crate::{Default, Manifest, Project};
+use git_utils::GitCommandBuilder;
+use log::{debug, warn};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+impl Manifest {
+    /// Describes the git repositories below `root` as a manifest, to turn
+    /// a workspace put together by hand into one that can be synced again.
+    ///
+    /// Every repository becomes a project at its path relative to `root`,
+    /// fetched from its `origin` URL and pinned to the commit of its `HEAD`.
+    /// Repositories are searched below other repositories too, but not in
+    /// `.git` and `.repo` directories. Repositories without an `origin` are
+    /// skipped with a warning. Projects are named after the last component
+    /// of their URL, which is fetched from a remote named after its host;
+    /// the first remote is the default.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let manifest = Manifest::from_workspace("/src/tizen")?;
+    /// manifest.write_to_file("default.xml")?;
+    /// ```
+    pub fn from_workspace<P: AsRef<Path>>(root: P) -> Result<Manifest, Box<dyn Error>> {
+        let root = root.as_ref();
+        let mut repositories = Vec::new();
+        find_repositories(root, &mut repositories)?;
+
+        let mut manifest = Manifest::new();
+        for dir in repositories {
+            let path = dir
+                .strip_prefix(root)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let Ok(url) = GitCommandBuilder::git_config_get("remote.origin.url")
+                .dir(&dir)
+                .run_with_output()
+            else {
+                warn!("Skipping '{}', which has no origin", path);
+                continue;
+            };
+            let url = url.trim().trim_end_matches('/');
+            let sha = GitCommandBuilder::git_rev_parse("HEAD")
+                .dir(&dir)
+                .run_with_output()
+                .map_err(|e| format!("Cannot read the HEAD of '{}': {}", path, e))?;
+            let (fetch, name) = url
+                .rsplit_once('/')
+                .ok_or_else(|| format!("Invalid url '{}' of '{}'", url, path))?;
+            let name = name.trim_end_matches(".git").to_string();
+            let remote = add_remote(&mut manifest.remotes, fetch);
+            debug!("Repository {} at {}", path, sha.trim());
+
+            manifest.projects.push(Project {
+                path: (path != name).then_some(path),
+                name,
+                remote: (remote != manifest.remotes[0].name).then(|| remote.into()),
+                revision: Some(sha.trim().into()),
+                dest_branch: None,
+                groups: Vec::new(),
+                sync_c: None,
+                sync_s: None,
+                sync_tags: None,
+                upstream: None,
+                clone_depth: None,
+                force_path: None,
+                copyfiles: Vec::new(),
+                linkfiles: Vec::new(),
+                annotations: Vec::new(),
+                source: None,
+            });
+        }
+
+        if let Some(remote) = manifest.remotes.first() {
+            manifest.default = Some(Default {
+                remote: Some(remote.name.as_str().into()),
+                revision: None,
+                dest_branch: None,
+                upstream: None,
+                sync_j: None,
+                sync_c: None,
+                sync_s: None,
+                sync_tags: None,
+            });
+        }
+        Ok(manifest)
+    }
+}
+
+// Collects the repositories below `dir`, in path order.
+fn find_repositories(dir: &Path, repositories: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        if name == ".git" || name == ".repo" || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        if path.join(".git").exists() {
+            repositories.push(path.clone());
+        }
+        find_repositories(&path, repositories)?;
+    }
+    Ok(())
+}
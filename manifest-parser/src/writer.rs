@@ -0,0 +1,567 @@
+use crate::groups::format_groups;
+use crate::hooks::format_hooks;
+use crate::{Comment, Manifest, Project};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::Writer;
+use std::error::Error;
+use std::fs;
+use std::io;
+
+/// Formatting of generated manifest XML.
+///
+/// # Example
+///
+/// ```ignore
+/// use manifest_parser::writer::WriteOptions;
+///
+/// let xml = manifest.to_xml_with(&WriteOptions::new().indent(4).wrap_attributes(100));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    indent: usize,
+    wrap_width: Option<usize>,
+    provenance: bool,
+    sort_attributes: bool,
+}
+
+impl std::default::Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            indent: 2,
+            wrap_width: None,
+            provenance: false,
+            sort_attributes: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of spaces per nesting level.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Puts every attribute on its own line, aligned with the first one,
+    /// when an element would not fit into `width` columns.
+    pub fn wrap_attributes(mut self, width: usize) -> Self {
+        self.wrap_width = Some(width);
+        self
+    }
+
+    /// Precedes the elements read from each file with a comment naming the
+    /// file, see [`Manifest::provenance`].
+    pub fn provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Writes the attributes of every element sorted by name instead of in
+    /// the order the repo documentation lists them.
+    pub fn sort_attributes(mut self, sort_attributes: bool) -> Self {
+        self.sort_attributes = sort_attributes;
+        self
+    }
+
+    pub(crate) fn indent_unit(&self) -> String {
+        " ".repeat(self.indent)
+    }
+
+    pub(crate) fn wrap_width(&self) -> Option<usize> {
+        self.wrap_width
+    }
+}
+
+/// An XML element of a manifest, as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) text: Option<String>,
+    pub(crate) children: Vec<Element>,
+}
+
+impl Element {
+    fn new(name: &str, attributes: &[(&str, Option<&str>)]) -> Element {
+        Element {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .filter_map(|(key, value)| value.map(|value| (key.to_string(), value.to_string())))
+                .collect(),
+            text: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub(crate) fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The attributes identifying the element among its siblings, e.g. the
+    /// name and path of a project. Elements that occur once have none.
+    pub(crate) fn key(&self) -> Vec<Option<&str>> {
+        match self.name.as_str() {
+            "project" => vec![
+                self.attribute("name"),
+                self.attribute("path").or(self.attribute("name")),
+            ],
+            "remove-project" | "extend-project" => {
+                vec![self.attribute("name"), self.attribute("path")]
+            }
+            "remote" | "submanifest" | "include" => vec![self.attribute("name")],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether `other` describes the same element, ignoring attribute order.
+    pub(crate) fn same_as(&self, other: &Element) -> bool {
+        let sorted = |element: &Element| {
+            let mut attributes = element.attributes.clone();
+            attributes.sort();
+            attributes
+        };
+        self.name == other.name
+            && sorted(self) == sorted(other)
+            && self.text == other.text
+            && self.children.len() == other.children.len()
+            && self
+                .children
+                .iter()
+                .zip(&other.children)
+                .all(|(a, b)| a.same_as(b))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.text.is_none() && self.children.is_empty()
+    }
+
+    /// Formats the element at `indent`, without a trailing newline.
+    pub(crate) fn render(&self, indent: &str, unit: &str, wrap_width: Option<usize>) -> String {
+        to_string(|writer| self.write(writer, indent, unit, wrap_width))
+    }
+
+    /// Formats the start tag up to, but not including, `>` or `/>`.
+    pub(crate) fn open_tag(&self, indent: &str, wrap_width: Option<usize>) -> String {
+        format!(
+            "<{}",
+            String::from_utf8_lossy(&self.start_tag(indent, wrap_width))
+        )
+    }
+
+    /// Formats what follows the start tag: the text or the children at
+    /// `child_indent`, and the end tag.
+    pub(crate) fn render_content(
+        &self,
+        indent: &str,
+        child_indent: &str,
+        unit: &str,
+        wrap_width: Option<usize>,
+    ) -> String {
+        to_string(|writer| self.write_content(writer, indent, child_indent, unit, wrap_width))
+    }
+
+    // Writes the element at `indent`, its children indented by `unit` more.
+    fn write<W: io::Write>(
+        &self,
+        writer: &mut Writer<W>,
+        indent: &str,
+        unit: &str,
+        wrap_width: Option<usize>,
+    ) -> io::Result<()> {
+        let start = self.start_tag(indent, wrap_width);
+        if self.is_empty() {
+            writer.write_event(Event::Empty(start))?;
+            return Ok(());
+        }
+        writer.write_event(Event::Start(start))?;
+        let child_indent = format!("{}{}", indent, unit);
+        self.write_content(writer, indent, &child_indent, unit, wrap_width)
+    }
+
+    fn write_content<W: io::Write>(
+        &self,
+        writer: &mut Writer<W>,
+        indent: &str,
+        child_indent: &str,
+        unit: &str,
+        wrap_width: Option<usize>,
+    ) -> io::Result<()> {
+        if let Some(text) = &self.text {
+            writer.write_event(Event::Text(BytesText::new(text)))?;
+        }
+        if !self.children.is_empty() {
+            for child in &self.children {
+                line_break(writer, child_indent)?;
+                child.write(writer, child_indent, unit, wrap_width)?;
+            }
+            line_break(writer, indent)?;
+        }
+        writer.write_event(Event::End(BytesEnd::new(self.name.as_str())))?;
+        Ok(())
+    }
+
+    // The start tag, with every attribute on its own line, aligned with the
+    // first one, when it does not fit into `wrap_width` columns.
+    fn start_tag(&self, indent: &str, wrap_width: Option<usize>) -> BytesStart<'static> {
+        let attributes: Vec<String> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape(value.as_str())))
+            .collect();
+        let line_length = indent.len()
+            + self.name.len()
+            + 3
+            + attributes.iter().map(|a| a.len() + 1).sum::<usize>();
+        match wrap_width {
+            Some(width) if line_length > width && attributes.len() > 1 => {
+                let separator = format!("\n{}{}", indent, " ".repeat(self.name.len() + 2));
+                let content = format!("{} {}", self.name, attributes.join(&separator));
+                BytesStart::from_content(content, self.name.len())
+            }
+            _ => BytesStart::new(self.name.clone()).with_attributes(
+                self.attributes
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str())),
+            ),
+        }
+    }
+}
+
+// Writes a line break followed by `indent`.
+fn line_break<W: io::Write>(writer: &mut Writer<W>, indent: &str) -> io::Result<()> {
+    writer.write_event(Event::Text(BytesText::from_escaped(format!(
+        "\n{}",
+        indent
+    ))))?;
+    Ok(())
+}
+
+// Runs `write` on a writer into memory, returning what it wrote.
+fn to_string(write: impl FnOnce(&mut Writer<Vec<u8>>) -> io::Result<()>) -> String {
+    let mut writer = Writer::new(Vec::new());
+    write(&mut writer).expect("writing XML to memory cannot fail");
+    String::from_utf8(writer.into_inner()).expect("XML output is UTF-8")
+}
+
+/// Position of an element kind in the order the repo documentation lists
+/// them, or `None` for elements [`Manifest`] does not model.
+pub(crate) fn element_order(name: &str) -> Option<usize> {
+    const ORDER: &[&str] = &[
+        "notice",
+        "remote",
+        "default",
+        "manifest-server",
+        "submanifest",
+        "remove-project",
+        "project",
+        "extend-project",
+        "repo-hooks",
+        "superproject",
+        "contactinfo",
+        "include",
+    ];
+    ORDER.iter().position(|n| *n == name)
+}
+
+impl Manifest {
+    /// Serializes the manifest to repo manifest XML.
+    ///
+    /// Elements are written in the order the repo documentation lists them.
+    /// A manifest read with [`Manifest::from_file`] already holds the
+    /// projects of its included files, so clear `includes` to write a
+    /// self-contained manifest instead of one that includes them twice.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use manifest_parser::Manifest;
+    ///
+    /// let mut manifest = Manifest::from_file("default.xml", None, None).unwrap();
+    /// manifest.projects[0].revision = Some("3f2c9e1".into());
+    /// println!("{}", manifest.to_xml());
+    /// ```
+    pub fn to_xml(&self) -> String {
+        self.to_xml_with(&WriteOptions::default())
+    }
+
+    /// Like [`Manifest::to_xml`], formatted according to `options`.
+    pub fn to_xml_with(&self, options: &WriteOptions) -> String {
+        to_string(|writer| self.write_xml(writer, options))
+    }
+
+    fn write_xml(&self, writer: &mut Writer<Vec<u8>>, options: &WriteOptions) -> io::Result<()> {
+        let unit = options.indent_unit();
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        for comment in self.comments_before(Some("manifest"), None, None) {
+            line_break(writer, "")?;
+            write_comment(writer, &comment.text)?;
+        }
+        line_break(writer, "")?;
+        writer.write_event(Event::Start(BytesStart::new("manifest")))?;
+
+        let mut last_file = None;
+        for mut element in self.elements() {
+            let file = self.source_of_element(&element);
+            if options.provenance && file != last_file {
+                if let Some(file) = file.filter(|file| !file.is_empty()) {
+                    // "--" may not appear in XML comments.
+                    let comment = file.replace("--", "- -");
+                    line_break(writer, &unit)?;
+                    write_comment(writer, &format!(" {} ", comment))?;
+                }
+                last_file = file;
+            }
+            let path = match element.name.as_str() {
+                "project" => Some(element.attribute("path")),
+                _ => None,
+            };
+            for comment in
+                self.comments_before(Some(&element.name), element.attribute("name"), path)
+            {
+                line_break(writer, &unit)?;
+                write_comment(writer, &comment.text)?;
+            }
+            if options.sort_attributes {
+                element.sort_attributes();
+            }
+            line_break(writer, &unit)?;
+            element.write(writer, &unit, &unit, options.wrap_width())?;
+        }
+        for comment in self.comments_before(None, None, None) {
+            line_break(writer, &unit)?;
+            write_comment(writer, &comment.text)?;
+        }
+
+        line_break(writer, "")?;
+        writer.write_event(Event::End(BytesEnd::new("manifest")))?;
+        line_break(writer, "")
+    }
+
+    // The comments kept for the element named `element`, or for the end of
+    // the manifest. `path` is compared for projects only.
+    fn comments_before<'a>(
+        &'a self,
+        element: Option<&'a str>,
+        name: Option<&'a str>,
+        path: Option<Option<&'a str>>,
+    ) -> impl Iterator<Item = &'a Comment> + 'a {
+        self.comments.iter().filter(move |comment| {
+            comment.element.as_deref() == element
+                && (element.is_none() || comment.name.as_deref() == name)
+                && path.is_none_or(|path| comment.path.as_deref() == path)
+        })
+    }
+
+    /// Writes the manifest to `file_path`, see [`Manifest::to_xml`].
+    pub fn write_to_file(&self, file_path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(file_path, self.to_xml())?;
+        Ok(())
+    }
+
+    /// The elements of the manifest in the order they are written.
+    pub(crate) fn elements(&self) -> Vec<Element> {
+        let mut elements = Vec::new();
+
+        if let Some(notice) = &self.notice {
+            elements.push(Element {
+                text: Some(notice.clone()),
+                ..Element::new("notice", &[])
+            });
+        }
+        for remote in &self.remotes {
+            elements.push(Element::new(
+                "remote",
+                &[
+                    ("name", Some(&remote.name)),
+                    ("alias", remote.alias.as_deref()),
+                    ("fetch", Some(&remote.fetch)),
+                    ("pushurl", remote.pushurl.as_deref()),
+                    ("review", remote.review.as_deref()),
+                    ("revision", remote.revision.as_deref()),
+                ],
+            ));
+        }
+        if let Some(default) = &self.default {
+            let sync_j = text(default.sync_j);
+            let sync_c = text(default.sync_c);
+            let sync_s = text(default.sync_s);
+            let sync_tags = text(default.sync_tags);
+            let default = Element::new(
+                "default",
+                &[
+                    ("remote", default.remote.as_deref()),
+                    ("revision", default.revision.as_deref()),
+                    ("dest-branch", default.dest_branch.as_deref()),
+                    ("upstream", default.upstream.as_deref()),
+                    ("sync-j", sync_j.as_deref()),
+                    ("sync-c", sync_c.as_deref()),
+                    ("sync-s", sync_s.as_deref()),
+                    ("sync-tags", sync_tags.as_deref()),
+                ],
+            );
+            // `from_file` fills in an empty default when the file has none.
+            if !default.attributes.is_empty() {
+                elements.push(default);
+            }
+        }
+        if let Some(manifest_server) = &self.manifest_server {
+            elements.push(Element::new(
+                "manifest-server",
+                &[("url", Some(&manifest_server.url))],
+            ));
+        }
+        for submanifest in &self.submanifests {
+            elements.push(Element::new(
+                "submanifest",
+                &[
+                    ("name", Some(&submanifest.name)),
+                    ("remote", submanifest.remote.as_deref()),
+                    ("project", submanifest.project.as_deref()),
+                    ("manifest-name", submanifest.manifest_name.as_deref()),
+                    ("revision", submanifest.revision.as_deref()),
+                    ("path", submanifest.path.as_deref()),
+                    ("groups", submanifest.groups.as_deref()),
+                    ("default-groups", submanifest.default_groups.as_deref()),
+                ],
+            ));
+        }
+        for remove_project in &self.remove_projects {
+            elements.push(Element::new(
+                "remove-project",
+                &[
+                    ("name", remove_project.name.as_deref()),
+                    ("path", remove_project.path.as_deref()),
+                    ("optional", remove_project.optional.as_deref()),
+                    ("base-rev", remove_project.base_rev.as_deref()),
+                ],
+            ));
+        }
+        for project in &self.projects {
+            elements.push(project_element(project));
+        }
+        for extend_project in &self.extend_projects {
+            elements.push(Element::new(
+                "extend-project",
+                &[
+                    ("name", Some(&extend_project.name)),
+                    ("path", extend_project.path.as_deref()),
+                    ("dest-path", extend_project.dest_path.as_deref()),
+                    ("groups", extend_project.groups.as_deref()),
+                    ("revision", extend_project.revision.as_deref()),
+                    ("remote", extend_project.remote.as_deref()),
+                    ("dest-branch", extend_project.dest_branch.as_deref()),
+                    ("upstream", extend_project.upstream.as_deref()),
+                    ("base-rev", extend_project.base_rev.as_deref()),
+                ],
+            ));
+        }
+        if let Some(repo_hooks) = &self.repo_hooks {
+            let enabled_list = format_hooks(&repo_hooks.enabled_list);
+            elements.push(Element::new(
+                "repo-hooks",
+                &[
+                    ("in-project", Some(&repo_hooks.in_project)),
+                    ("enabled-list", Some(&enabled_list)),
+                ],
+            ));
+        }
+        if let Some(superproject) = &self.superproject {
+            elements.push(Element::new(
+                "superproject",
+                &[
+                    ("name", Some(&superproject.name)),
+                    ("remote", superproject.remote.as_deref()),
+                    ("revision", superproject.revision.as_deref()),
+                ],
+            ));
+        }
+        if let Some(contactinfo) = &self.contactinfo {
+            elements.push(Element::new(
+                "contactinfo",
+                &[("bugurl", Some(&contactinfo.bugurl))],
+            ));
+        }
+        for include in &self.includes {
+            elements.push(Element::new(
+                "include",
+                &[
+                    ("name", Some(&include.name)),
+                    ("groups", include.groups.as_deref()),
+                    ("revision", include.revision.as_deref()),
+                ],
+            ));
+        }
+
+        elements
+    }
+}
+
+// Writes `text`, which must not contain "--", as an XML comment.
+fn write_comment<W: io::Write>(writer: &mut Writer<W>, text: &str) -> io::Result<()> {
+    writer.write_event(Event::Comment(BytesText::from_escaped(text)))?;
+    Ok(())
+}
+
+// Formats a typed attribute value.
+fn text<T: ToString>(value: Option<T>) -> Option<String> {
+    value.map(|value| value.to_string())
+}
+
+fn project_element(project: &Project) -> Element {
+    let groups = (!project.groups.is_empty()).then(|| format_groups(&project.groups));
+    let sync_c = text(project.sync_c);
+    let sync_s = text(project.sync_s);
+    let sync_tags = text(project.sync_tags);
+    let clone_depth = text(project.clone_depth);
+    let mut element = Element::new(
+        "project",
+        &[
+            ("name", Some(&project.name)),
+            ("path", project.path.as_deref()),
+            ("remote", project.remote.as_deref()),
+            ("revision", project.revision.as_deref()),
+            ("dest-branch", project.dest_branch.as_deref()),
+            ("groups", groups.as_deref()),
+            ("sync-c", sync_c.as_deref()),
+            ("sync-s", sync_s.as_deref()),
+            ("sync-tags", sync_tags.as_deref()),
+            ("upstream", project.upstream.as_deref()),
+            ("clone-depth", clone_depth.as_deref()),
+            ("force-path", project.force_path.as_deref()),
+        ],
+    );
+    for copyfile in &project.copyfiles {
+        element.children.push(Element::new(
+            "copyfile",
+            &[("src", Some(&copyfile.src)), ("dest", Some(&copyfile.dest))],
+        ));
+    }
+    for linkfile in &project.linkfiles {
+        element.children.push(Element::new(
+            "linkfile",
+            &[("src", Some(&linkfile.src)), ("dest", Some(&linkfile.dest))],
+        ));
+    }
+    let keep_false = "false".to_string();
+    for annotation in &project.annotations {
+        element.children.push(Element::new(
+            "annotation",
+            &[
+                ("name", Some(&annotation.name)),
+                ("value", Some(&annotation.value)),
+                // `keep` defaults to true.
+                ("keep", (!annotation.keep).then_some(&keep_false)),
+            ],
+        ));
+    }
+    element
+}
@@ -0,0 +1,116 @@
+use manifest_parser::cache::ManifestCache;
+use manifest_parser::include::{FileIncludeResolver, SearchPathResolver};
+use manifest_parser::{DuplicatePolicy, ParseOptions};
+use std::fs;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+#[test]
+fn test_cache_reuses_unchanged_manifests() {
+    // Test that a manifest is parsed again only when it or an include changed, and shared otherwise
+    let dir = tempdir().unwrap();
+    let default = dir.path().join("default.xml");
+    let default = default.to_str().unwrap();
+    fs::write(
+        default,
+        r#"<manifest>
+            <remote name="origin" fetch="https://example.com"/>
+            <include name="apps.xml"/>
+            <project name="platform/core"/>
+        </manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("apps.xml"),
+        r#"<manifest><project name="platform/app" revision="v1"/></manifest>"#,
+    )
+    .unwrap();
+
+    let cache = ManifestCache::new();
+    let first = cache.load(default, Some("origin"), Some("main")).unwrap();
+    let second = cache.load(default, Some("origin"), Some("main")).unwrap();
+    assert_eq!(first.to_xml(), second.to_xml());
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+    fs::write(
+        dir.path().join("apps.xml"),
+        r#"<manifest><project name="platform/app" revision="v2"/></manifest>"#,
+    )
+    .unwrap();
+    let changed = cache.load(default, Some("origin"), Some("main")).unwrap();
+    let app = changed.find_project_by_name("platform/app").unwrap();
+    assert_eq!(app.revision.as_deref(), Some("v2"));
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+    assert_eq!(cache.len(), 1);
+
+    cache.load(default, None, None).unwrap();
+    assert_eq!(cache.misses(), 3);
+    assert_eq!(cache.len(), 2);
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_cache_evicts_least_recently_used() {
+    // Test that a full cache drops the manifest used the longest time ago
+    let dir = tempdir().unwrap();
+    let paths: Vec<String> = ["a.xml", "b.xml", "c.xml"]
+        .iter()
+        .map(|name| {
+            let path = dir.path().join(name);
+            fs::write(
+                &path,
+                r#"<manifest><project name="platform/core"/></manifest>"#,
+            )
+            .unwrap();
+            path.to_str().unwrap().to_string()
+        })
+        .collect();
+
+    let cache = ManifestCache::with_capacity(2);
+    cache.load(&paths[0], None, None).unwrap();
+    cache.load(&paths[1], None, None).unwrap();
+    cache.load(&paths[0], None, None).unwrap();
+    cache.load(&paths[2], None, None).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert_eq!((cache.hits(), cache.misses()), (1, 3));
+
+    // b.xml was dropped, a.xml was kept.
+    cache.load(&paths[0], None, None).unwrap();
+    assert_eq!(cache.hits(), 2);
+    cache.load(&paths[1], None, None).unwrap();
+    assert_eq!(cache.misses(), 4);
+}
+
+#[test]
+fn test_cache_keys_parse_options_and_resolver() {
+    // Test that manifests parsed differently are cached apart
+    let dir = tempdir().unwrap();
+    let default = dir.path().join("default.xml");
+    let default = default.to_str().unwrap();
+    fs::write(
+        default,
+        r#"<manifest>
+            <project name="platform/core" revision="v1"/>
+            <project name="platform/core" revision="v2"/>
+        </manifest>"#,
+    )
+    .unwrap();
+
+    let cache = ManifestCache::new();
+    assert!(cache.load(default, None, None).is_err());
+    let options = ParseOptions::new().duplicates(DuplicatePolicy::Override);
+    let manifest = cache
+        .load_with(default, None, None, &options, &FileIncludeResolver)
+        .unwrap();
+    assert_eq!(manifest.projects[0].revision.as_deref(), Some("v2"));
+    assert_eq!(cache.misses(), 2);
+
+    let resolver = SearchPathResolver::new(dir.path());
+    cache
+        .load_with(default, None, None, &options, &resolver)
+        .unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (0, 3));
+    assert_eq!(cache.len(), 2);
+}
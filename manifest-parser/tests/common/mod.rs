@@ -0,0 +1,14 @@
+use std::path::Path;
+use std::process::Command;
+
+// Runs git in `dir`, failing the test if it fails.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=gbsw", "-c", "user.email=gbsw@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
@@ -0,0 +1,65 @@
+use manifest_parser::encoding::decode;
+use manifest_parser::{Manifest, ParseOptions};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_decode_encodings() {
+    // Test decoding byte order marks and declared encodings
+    assert_eq!(
+        decode(b"\xef\xbb\xbf<manifest/>", false).unwrap(),
+        "<manifest/>"
+    );
+    let utf16: Vec<u8> = "\u{feff}<manifest/>"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    assert_eq!(decode(&utf16, false).unwrap(), "<manifest/>");
+    assert_eq!(
+        decode(
+            b"<?xml version='1.0' encoding='ISO-8859-1'?><a b=\"caf\xe9\"/>",
+            false
+        )
+        .unwrap(),
+        "<?xml version='1.0' encoding='ISO-8859-1'?><a b=\"café\"/>"
+    );
+    assert_eq!(
+        decode(
+            b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>\x80",
+            false
+        )
+        .unwrap(),
+        "<?xml version=\"1.0\" encoding=\"windows-1252\"?>€"
+    );
+    assert_eq!(
+        decode(b"<?xml version=\"1.0\" encoding=\"EBCDIC\"?>", false).unwrap_err(),
+        "Unsupported encoding 'ebcdic'"
+    );
+}
+
+#[test]
+fn test_parse_latin1_manifest() {
+    // Test parsing manifests in Latin-1, and invalid UTF-8 with the lossy option
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    let file_path = file_path.to_str().unwrap();
+    fs::write(
+        file_path,
+        b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<manifest>\n  <project name=\"caf\xe9\"/>\n</manifest>\n",
+    )
+    .unwrap();
+    let manifest = Manifest::from_file(file_path, None, None).unwrap();
+    assert_eq!(manifest.projects[0].name, "café");
+
+    fs::write(
+        file_path,
+        b"<manifest>\n  <project name=\"caf\xe9\"/>\n</manifest>\n",
+    )
+    .unwrap();
+    let err = Manifest::from_file(file_path, None, None).unwrap_err();
+    assert!(err.to_string().starts_with("Document is not valid UTF-8"));
+    assert!(err.to_string().ends_with(&format!("in '{}'", file_path)));
+    let options = ParseOptions::new().lossy(true);
+    let manifest = Manifest::from_file_with(file_path, None, None, &options).unwrap();
+    assert_eq!(manifest.projects[0].name, "caf\u{fffd}");
+}
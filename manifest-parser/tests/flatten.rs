@@ -0,0 +1,78 @@
+use manifest_parser::writer::WriteOptions;
+use manifest_parser::{Manifest, Provenance};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_flatten() {
+    // Test flattening includes into one manifest that remembers where elements came from
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("common")).unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(
+        &file_path,
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <include name="common/projects.xml"/>
+        <project name="platform/app"/>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    let included_path = dir.path().join("common/projects.xml");
+    fs::write(
+        &included_path,
+        r#"
+    <manifest>
+        <project name="platform/core" path="core"/>
+        <project name="platform/tools"/>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    let file_path = file_path.to_str().unwrap();
+    let included_path = included_path.to_str().unwrap();
+
+    let manifest = Manifest::from_file(file_path, None, None).unwrap();
+    assert_eq!(manifest.source_of("remote", "origin"), Some(file_path));
+    assert_eq!(
+        manifest.source_of("project", "platform/core"),
+        Some(included_path)
+    );
+    assert_eq!(manifest.source_of("project", "platform/none"), None);
+    assert_eq!(
+        manifest.provenance[3],
+        Provenance {
+            element: "project".to_string(),
+            name: Some("platform/core".to_string()),
+            path: Some("core".to_string()),
+            file: included_path.into(),
+        }
+    );
+
+    let flat = manifest.flatten();
+    assert!(flat.includes.is_empty());
+    assert_eq!(flat.projects.len(), 3);
+
+    assert_eq!(
+        flat.to_xml_with(&WriteOptions::new().provenance(true)),
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <manifest>\n  \
+             <!-- {main} -->\n  \
+             <remote name=\"origin\" fetch=\"https://example.com\"/>\n  \
+             <default remote=\"origin\" revision=\"main\"/>\n  \
+             <!-- {included} -->\n  \
+             <project name=\"platform/core\" path=\"core\"/>\n  \
+             <project name=\"platform/tools\"/>\n  \
+             <!-- {main} -->\n  \
+             <project name=\"platform/app\"/>\n\
+             </manifest>\n",
+            main = file_path,
+            included = included_path
+        )
+    );
+    assert!(!flat.to_xml().contains("<!--"));
+}
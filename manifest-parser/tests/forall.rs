@@ -0,0 +1,65 @@
+#![cfg(feature = "sync")]
+
+use manifest_parser::forall::{forall, ForallOptions};
+use manifest_parser::subset::Selection;
+use tempfile::tempdir;
+
+#[test]
+fn test_forall() {
+    // Test running a command in the checkouts of the selected projects
+    let dir = tempdir().unwrap();
+    let target_dir = dir.path().join("target");
+    std::fs::create_dir_all(target_dir.join("core")).unwrap();
+    std::fs::create_dir_all(target_dir.join("platform/app")).unwrap();
+    let manifest_path = dir.path().join("manifest.xml");
+    std::fs::write(
+        &manifest_path,
+        r#"<manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core" upstream="stable"/>
+        <project name="platform/app"/>
+        <project name="platform/docs" groups="docs"/>
+    </manifest>"#,
+    )
+    .unwrap();
+    let run = |command: &str, options: &ForallOptions| {
+        forall(
+            manifest_path.to_str().unwrap(),
+            target_dir.to_str().unwrap(),
+            command,
+            options,
+        )
+        .unwrap()
+    };
+
+    let results = run(
+        r#"echo "$REPO_I/$REPO_COUNT $REPO_PROJECT $REPO_PATH $REPO_REMOTE $REPO_RREV $REPO_UPSTREAM" && basename "$PWD""#,
+        &ForallOptions::default(),
+    );
+    assert_eq!(results.len(), 3);
+    assert!(results[0].success());
+    assert_eq!(
+        results[0].stdout,
+        "1/3 platform/core core origin main stable\ncore\n"
+    );
+    assert_eq!(
+        results[1].stdout,
+        "2/3 platform/app platform/app origin main \napp\n"
+    );
+    assert_eq!(results[2].exit_code, None);
+    assert_eq!(
+        results[2].error.as_deref(),
+        Some("Project 'platform/docs' is not checked out")
+    );
+
+    let options = ForallOptions {
+        selection: Some(Selection::Projects(vec!["core".to_string()])),
+        jobs: Some(2),
+    };
+    let results = run("echo failed >&2; exit 3", &options);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "platform/core");
+    assert_eq!(results[0].exit_code, Some(3));
+    assert_eq!(results[0].stderr, "failed\n");
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::git;
+use manifest_parser::Manifest;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_from_git() {
+    // Test reading a manifest from a branch of a manifest repository through a cached clone
+    let dir = tempdir().unwrap();
+    let upstream = dir.path().join("manifest");
+    let cache = dir.path().join("cache");
+    fs::create_dir(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    fs::write(
+        upstream.join("default.xml"),
+        r#"<manifest><project name="platform/core"/><include name="common/apps.xml"/></manifest>"#,
+    )
+    .unwrap();
+    fs::create_dir(upstream.join("common")).unwrap();
+    fs::write(
+        upstream.join("common/apps.xml"),
+        r#"<manifest><project name="platform/app"/></manifest>"#,
+    )
+    .unwrap();
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-q", "-m", "Add manifest"]);
+    git(&upstream, &["checkout", "-q", "-b", "tizen"]);
+    fs::write(
+        upstream.join("tizen.xml"),
+        r#"<manifest><project name="platform/tizen"/></manifest>"#,
+    )
+    .unwrap();
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-q", "-m", "Add tizen manifest"]);
+    let url = upstream.to_str().unwrap();
+
+    let manifest = Manifest::from_git_with_cache(url, "main", "default.xml", &cache).unwrap();
+    let names: Vec<&str> = manifest.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["platform/core", "platform/app"]);
+
+    // The cached clone is fetched again for other branches and new commits.
+    let manifest = Manifest::from_git_with_cache(url, "tizen", "tizen.xml", &cache).unwrap();
+    assert_eq!(manifest.projects[0].name, "platform/tizen");
+    let clones = fs::read_dir(&cache)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().is_dir());
+    assert_eq!(clones.count(), 1);
+
+    let err = Manifest::from_git_with_cache(url, "missing", "default.xml", &cache).unwrap_err();
+    assert!(err.to_string().starts_with("Cannot fetch 'missing'"));
+
+    // Calls sharing the clone read the manifest of their own branch.
+    std::thread::scope(|s| {
+        let readers: Vec<_> = (0..8)
+            .map(|i| {
+                let (branch, file_name) = [("main", "default.xml"), ("tizen", "tizen.xml")][i % 2];
+                let cache = &cache;
+                s.spawn(move || {
+                    let manifest =
+                        Manifest::from_git_with_cache(url, branch, file_name, cache).unwrap();
+                    (branch, manifest.projects[0].name.clone())
+                })
+            })
+            .collect();
+        for reader in readers {
+            match reader.join().unwrap() {
+                ("main", name) => assert_eq!(name, "platform/core"),
+                (_, name) => assert_eq!(name, "platform/tizen"),
+            }
+        }
+    });
+}
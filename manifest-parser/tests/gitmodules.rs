@@ -0,0 +1,118 @@
+mod common;
+
+use common::git;
+use manifest_parser::Manifest;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_from_gitmodules() {
+    // Test turning the submodules of a superproject into pinned projects
+    let dir = tempdir().unwrap();
+    let superproject = dir.path();
+    git(superproject, &["init", "-q"]);
+    fs::write(
+        superproject.join(".gitmodules"),
+        r#"[submodule "lib"]
+	path = third_party/lib
+	url = ../lib.git
+	branch = stable
+[submodule "tools.v2"]
+	path = tools
+	url = https://github.com/org/tools
+	branch = .
+[submodule "zlib"]
+	path = third_party/zlib
+	url = https://gitlab.example.com/mirrors/zlib.git
+"#,
+    )
+    .unwrap();
+    let shas = [
+        "1111111111111111111111111111111111111111",
+        "2222222222222222222222222222222222222222",
+        "3333333333333333333333333333333333333333",
+    ];
+    for (path, sha) in ["third_party/lib", "tools", "third_party/zlib"]
+        .iter()
+        .zip(shas)
+    {
+        git(
+            superproject,
+            &[
+                "update-index",
+                "--add",
+                "--cacheinfo",
+                &format!("160000,{},{}", sha, path),
+            ],
+        );
+    }
+
+    let manifest =
+        Manifest::from_gitmodules(superproject.to_str().unwrap(), "https://github.com/org/")
+            .unwrap();
+
+    let remotes: Vec<_> = manifest
+        .remotes
+        .iter()
+        .map(|r| (r.name.as_str(), r.fetch.as_str()))
+        .collect();
+    assert_eq!(
+        remotes,
+        vec![
+            ("origin", "https://github.com/org"),
+            ("gitlab.example.com", "https://gitlab.example.com/mirrors"),
+        ]
+    );
+    let projects: Vec<_> = manifest
+        .projects
+        .iter()
+        .map(|p| {
+            (
+                p.name.as_str(),
+                p.path.as_deref(),
+                p.remote.as_deref(),
+                p.revision.as_deref().unwrap(),
+                p.upstream.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        projects,
+        vec![
+            (
+                "lib",
+                Some("third_party/lib"),
+                None,
+                shas[0],
+                Some("stable")
+            ),
+            ("tools", None, None, shas[1], None),
+            (
+                "zlib",
+                Some("third_party/zlib"),
+                Some("gitlab.example.com"),
+                shas[2],
+                None
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_from_gitmodules_missing_gitlink() {
+    // Test that a submodule missing from the index is reported
+    let dir = tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    fs::write(
+        dir.path().join(".gitmodules"),
+        "[submodule \"lib\"]\n\tpath = lib\n\turl = ../lib.git\n",
+    )
+    .unwrap();
+
+    let path = dir.path().to_str().unwrap();
+    let err = Manifest::from_gitmodules(path, "https://github.com/org").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Submodule 'lib' is not in the index of '{}'", path)
+    );
+}
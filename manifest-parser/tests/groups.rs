@@ -0,0 +1,58 @@
+use manifest_parser::groups::{format_groups, parse_groups, Group};
+use manifest_parser::include::NoIncludes;
+use manifest_parser::Manifest;
+
+#[test]
+fn test_parse_groups() {
+    // Test parsing groups attributes into typed groups and back
+    let groups = parse_groups("base, notdefault,name:platform/core path:core,all");
+    assert_eq!(
+        groups,
+        vec![
+            Group::Named("base".into()),
+            Group::NotDefault,
+            Group::Name("platform/core".to_string()),
+            Group::Path("core".to_string()),
+            Group::All,
+        ]
+    );
+    assert_eq!(
+        format_groups(&groups),
+        "base,notdefault,name:platform/core,path:core,all"
+    );
+    assert!(parse_groups(" , ").is_empty());
+}
+
+#[test]
+fn test_group_membership() {
+    // Test implicit groups and group filters like those of repo sync -g
+    let manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <project name="platform/core" path="core" groups="base"/>
+        <project name="platform/tools" groups="tools,notdefault"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+    let core = &manifest.projects[0];
+    let tools = &manifest.projects[1];
+
+    assert_eq!(
+        format_groups(&core.effective_groups()),
+        "base,all,name:platform/core,path:core,default"
+    );
+    assert!(core.in_group(&Group::Default));
+    assert!(core.in_group(&Group::Path("core".to_string())));
+    assert!(!tools.in_group(&Group::Default));
+
+    assert!(core.matches_groups(""));
+    assert!(!tools.matches_groups(""));
+    assert!(tools.matches_groups("default,tools"));
+    assert!(!core.matches_groups("all,-base"));
+    assert!(core.matches_groups("-base,name:platform/core"));
+    assert!(!tools.matches_groups("tools,-notdefault"));
+}
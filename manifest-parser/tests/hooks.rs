@@ -0,0 +1,74 @@
+use manifest_parser::hooks::{format_hooks, parse_hooks, run_repo_hook, HookName};
+use manifest_parser::include::NoIncludes;
+use manifest_parser::Manifest;
+use tempfile::tempdir;
+
+#[test]
+fn test_parse_hooks() {
+    // Test parsing an enabled-list separated by commas and whitespace
+    let hooks = parse_hooks("pre-upload, post-sync  lint");
+    assert_eq!(
+        hooks,
+        [
+            HookName::PreUpload,
+            HookName::Other("post-sync".to_string()),
+            HookName::Other("lint".to_string()),
+        ]
+    );
+    assert_eq!(format_hooks(&hooks), "pre-upload post-sync lint");
+    assert!(parse_hooks("").is_empty());
+}
+
+#[test]
+fn test_run_repo_hook() {
+    // Test running an enabled hook from the checkout of the hooks project
+    let dir = tempdir().unwrap();
+    let manifest = Manifest::from_str(
+        r#"<manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="tools/repohooks" path="hooks"/>
+        <project name="platform/core" path="core"/>
+        <repo-hooks in-project="tools/repohooks" enabled-list="pre-upload"/>
+    </manifest>"#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+    let projects = [("platform/core", "core")];
+    let run = |hook: &HookName| run_repo_hook(&manifest, dir.path(), hook, &projects);
+
+    assert_eq!(
+        run(&HookName::PreUpload).unwrap_err().to_string(),
+        "Project 'tools/repohooks' of <repo-hooks> is not synced"
+    );
+    let hooks = dir.path().join("hooks");
+    std::fs::create_dir_all(hooks.join(".git")).unwrap();
+    assert!(!run(&HookName::PreUpload).unwrap());
+
+    std::fs::write(
+        hooks.join("pre-upload.py"),
+        "def main(project_list, worktree_list=None, **kwargs):\n    \
+         open('hook.log', 'w').write(' '.join(project_list + worktree_list))\n",
+    )
+    .unwrap();
+    assert!(run(&HookName::PreUpload).unwrap());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("hook.log")).unwrap(),
+        "platform/core core"
+    );
+    // Hooks not in the enabled-list are not run.
+    std::fs::write(hooks.join("post-sync.py"), "raise SystemExit(1)\n").unwrap();
+    assert!(!run(&HookName::Other("post-sync".to_string())).unwrap());
+
+    std::fs::write(
+        hooks.join("pre-upload.py"),
+        "def main(**kwargs):\n    raise SystemExit('style check failed')\n",
+    )
+    .unwrap();
+    assert_eq!(
+        run(&HookName::PreUpload).unwrap_err().to_string(),
+        "Hook 'pre-upload' failed: style check failed"
+    );
+}
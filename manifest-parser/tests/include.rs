@@ -0,0 +1,213 @@
+mod common;
+
+use common::git;
+use manifest_parser::include::{GitIncludeResolver, IncludeResolver, SearchPathResolver};
+use manifest_parser::Manifest;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_include_revision_from_git() {
+    // Test that includes with a revision are read from the manifest repository history
+    let dir = tempdir().unwrap();
+    let repo = dir.path();
+    git(repo, &["init", "-q", "-b", "main"]);
+    fs::create_dir(repo.join("common")).unwrap();
+    fs::write(
+        repo.join("common/apps.xml"),
+        r#"<manifest><project name="platform/app" revision="v1"/></manifest>"#,
+    )
+    .unwrap();
+    git(repo, &["add", "."]);
+    git(repo, &["commit", "-q", "-m", "apps"]);
+    git(repo, &["tag", "release-1"]);
+
+    fs::write(
+        repo.join("common/apps.xml"),
+        r#"<manifest><project name="platform/app" revision="v2"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        repo.join("common/tools.xml"),
+        r#"<manifest><project name="platform/tools"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        repo.join("common/default.xml"),
+        r#"
+    <manifest>
+        <include name="common/apps.xml" revision="release-1"/>
+        <include name="common/tools.xml"/>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    let file_path = repo.join("common/default.xml");
+    let file_path = file_path.to_str().unwrap();
+
+    let manifest =
+        Manifest::from_file_with_resolver(file_path, None, None, &GitIncludeResolver::new(repo))
+            .unwrap();
+    let projects: Vec<_> = manifest
+        .projects
+        .iter()
+        .map(|p| (p.name.as_str(), p.revision.as_deref()))
+        .collect();
+    assert_eq!(
+        projects,
+        vec![("platform/app", Some("v1")), ("platform/tools", None)]
+    );
+
+    // Manifest files in a git work tree read the history too, relative to
+    // their directory.
+    fs::write(
+        repo.join("common/default.xml"),
+        r#"<manifest><include name="apps.xml" revision="release-1"/></manifest>"#,
+    )
+    .unwrap();
+    let manifest = Manifest::from_file(file_path, None, None).unwrap();
+    assert_eq!(manifest.projects[0].revision.as_deref(), Some("v1"));
+
+    // Resolvers without history refuse revisions rather than read the
+    // working tree.
+    let err = Manifest::from_file_with_resolver(
+        file_path,
+        None,
+        None,
+        &SearchPathResolver::new(repo.join("common")),
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Cannot read 'apps.xml' at 'release-1': the include resolver has no access"));
+
+    // So do manifest files outside of a git work tree.
+    let plain = tempdir().unwrap();
+    let plain_path = plain.path().join("default.xml");
+    fs::write(
+        &plain_path,
+        r#"<manifest><include name="apps.xml" revision="release-1"/></manifest>"#,
+    )
+    .unwrap();
+    let err = Manifest::from_file(plain_path.to_str().unwrap(), None, None).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Cannot read 'apps.xml' at 'release-1'"));
+
+    fs::write(
+        repo.join("common/default.xml"),
+        r#"<manifest><include name="common/apps.xml" revision="missing"/></manifest>"#,
+    )
+    .unwrap();
+    let err =
+        Manifest::from_file_with_resolver(file_path, None, None, &GitIncludeResolver::new(repo))
+            .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Cannot read 'common/apps.xml' at 'missing'"));
+}
+
+#[test]
+fn test_include_cycle() {
+    // Test that manifests including themselves are rejected with the include chain
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("a.xml"),
+        r#"<manifest><project name="platform/a"/><include name="b.xml"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("b.xml"),
+        r#"<manifest><include name="a.xml"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("self.xml"),
+        r#"<manifest><include name="self.xml"/></manifest>"#,
+    )
+    .unwrap();
+    let a = dir.path().join("a.xml");
+    let b = dir.path().join("b.xml");
+    let a = a.to_str().unwrap();
+    let b = b.to_str().unwrap();
+
+    let err = Manifest::from_file(a, None, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("include cycle detected: {} -> {} -> {}", a, b, a)
+    );
+
+    let own = dir.path().join("self.xml");
+    let own = own.to_str().unwrap();
+    let err = Manifest::from_file(own, None, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("include cycle detected: {} -> {}", own, own)
+    );
+}
+
+#[test]
+fn test_search_path_resolver() {
+    // Test looking includes up in search directories without leaving them
+    let dir = tempdir().unwrap();
+    let manifests = dir.path().join("manifests");
+    let common = dir.path().join("common");
+    fs::create_dir_all(manifests.join("sub")).unwrap();
+    fs::create_dir(&common).unwrap();
+    fs::write(
+        manifests.join("default.xml"),
+        r#"<manifest><include name="sub/apps.xml"/><include name="shared.xml"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        manifests.join("sub/apps.xml"),
+        r#"<manifest><project name="platform/app"/><include name="../base.xml"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        manifests.join("base.xml"),
+        r#"<manifest><project name="platform/base"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        common.join("shared.xml"),
+        r#"<manifest><project name="platform/shared"/></manifest>"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("secret.xml"),
+        r#"<manifest><project name="secret"/></manifest>"#,
+    )
+    .unwrap();
+    let default = manifests.join("default.xml");
+    let default = default.to_str().unwrap();
+
+    let resolver = SearchPathResolver::new(&manifests).search_dir(&common);
+    let manifest = Manifest::from_file_with_resolver(default, None, None, &resolver).unwrap();
+    let names: Vec<&str> = manifest.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["platform/app", "platform/base", "platform/shared"]
+    );
+
+    let escaping = resolver.resolve("../secret.xml", default);
+    assert_eq!(
+        escaping.err().unwrap().to_string(),
+        "Include '../secret.xml' leads outside of the manifest directories"
+    );
+    let unconfined = SearchPathResolver::new(&manifests).confine(false);
+    assert!(unconfined.resolve("../secret.xml", default).is_ok());
+
+    let secret = dir.path().join("secret.xml");
+    let secret = secret.to_str().unwrap();
+    assert_eq!(
+        resolver.resolve(secret, default).err().unwrap().to_string(),
+        format!("Absolute include '{}' is not allowed", secret)
+    );
+    let absolute = SearchPathResolver::new(&manifests).allow_absolute(true);
+    assert!(absolute.resolve(secret, default).is_err());
+    let shared = common.join("shared.xml");
+    let absolute = absolute.search_dir(&common);
+    assert!(absolute.resolve(shared.to_str().unwrap(), default).is_ok());
+    assert!(absolute.confine(false).resolve(secret, default).is_ok());
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "sync")]
+
+mod common;
+
+use common::git;
+use manifest_parser::integrity::verify;
+use std::path::Path;
+use tempfile::tempdir;
+
+// Creates a checkout at `path` with a commit adding `README`.
+fn create_checkout(path: &Path) -> String {
+    std::fs::create_dir_all(path).unwrap();
+    git(path, &["init", "-q", "-b", "main"]);
+    std::fs::write(path.join("README"), "core\n").unwrap();
+    git(path, &["add", "README"]);
+    git(path, &["commit", "-q", "-m", "one"]);
+    git(path, &["rev-parse", "HEAD"])
+}
+
+#[test]
+fn test_verify() {
+    // Test that corrupt checkouts and checkouts off their pinned revision are reported
+    let dir = tempdir().unwrap();
+    let target_dir = dir.path().join("target");
+    let head = create_checkout(&target_dir.join("core"));
+    create_checkout(&target_dir.join("pinned"));
+    create_checkout(&target_dir.join("corrupt"));
+    let blob = git(&target_dir.join("corrupt"), &["rev-parse", "HEAD:README"]);
+    std::fs::remove_file(
+        target_dir
+            .join("corrupt/.git/objects")
+            .join(&blob[..2])
+            .join(&blob[2..]),
+    )
+    .unwrap();
+
+    let manifest_path = dir.path().join("manifest.xml");
+    std::fs::write(
+        &manifest_path,
+        format!(
+            r#"<manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core" revision="{}"/>
+        <project name="platform/pinned" path="pinned" revision="{}"/>
+        <project name="platform/corrupt" path="corrupt"/>
+        <project name="platform/app"/>
+    </manifest>"#,
+            head,
+            "a".repeat(40)
+        ),
+    )
+    .unwrap();
+    let verify_all = |fsck| {
+        verify(
+            manifest_path.to_str().unwrap(),
+            target_dir.to_str().unwrap(),
+            fsck,
+        )
+        .unwrap()
+    };
+
+    let integrities = verify_all(true);
+    assert_eq!(integrities.len(), 4);
+    assert_eq!(integrities[0].head.as_deref(), Some(head.as_str()));
+    assert!(!integrities[0].is_corrupt());
+    assert_eq!(
+        integrities[1].problems,
+        [format!(
+            "HEAD is at {}, not at the pinned revision {}",
+            integrities[1].head.as_deref().unwrap(),
+            "a".repeat(40)
+        )]
+    );
+    assert_eq!(integrities[2].problems.len(), 1);
+    assert!(integrities[2].problems[0].starts_with("git fsck failed: "));
+    assert!(!integrities[3].checked_out);
+    assert!(!integrities[3].is_corrupt());
+
+    assert!(!verify_all(false)[2].is_corrupt());
+}
@@ -1,9 +1,14 @@
-use manifest_parser::Manifest;
-use std::fs::File;
+use manifest_parser::groups::{format_groups, Group};
+use manifest_parser::hooks::HookName;
+use manifest_parser::include::{IncludedManifest, NoIncludes};
+use manifest_parser::{DuplicatePolicy, Manifest, ParseOptions, SourceKind, UnknownPolicy};
+use std::fs::{self, File};
 use std::io::Write;
+use std::sync::Arc;
 use tempfile::tempdir;
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_parse_valid_manifest() {
     // Test parsing a valid manifest with various elements
     let dir = tempdir().unwrap();
@@ -58,8 +63,8 @@ fn test_parse_valid_manifest() {
     assert_eq!(manifest.remotes.len(), 1);
     assert_eq!(manifest.remotes[0].name, "origin");
     assert_eq!(
-        manifest.default.as_ref().unwrap().remote,
-        Some("origin".to_string())
+        manifest.default.as_ref().unwrap().remote.as_deref(),
+        Some("origin")
     );
     assert_eq!(
         manifest.manifest_server.as_ref().unwrap().url,
@@ -82,6 +87,10 @@ fn test_parse_valid_manifest() {
     assert_eq!(manifest.extend_projects.len(), 1);
     assert_eq!(manifest.remove_projects.len(), 1);
     assert_eq!(manifest.repo_hooks.as_ref().unwrap().in_project, "hooks");
+    assert_eq!(
+        manifest.repo_hooks.as_ref().unwrap().enabled_list,
+        [HookName::PreUpload]
+    );
     assert_eq!(manifest.superproject.as_ref().unwrap().name, "super");
     assert_eq!(
         manifest.contactinfo.as_ref().unwrap().bugurl,
@@ -218,6 +227,61 @@ fn test_invalid_element() {
     assert!(manifest.includes.is_empty());
 }
 
+#[test]
+fn test_unknown_policy() {
+    // Test that strict parsing rejects unknown elements and misspelled attributes
+    let dir = tempdir().unwrap();
+    let typo_path = dir.path().join("typo.xml");
+    fs::write(
+        &typo_path,
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <project name="platform/core" sync_s="true">
+            <copyfile src="Makefile" dest="Makefile"/>
+        </project>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    let element_path = dir.path().join("element.xml");
+    fs::write(
+        &element_path,
+        r#"
+    <manifest>
+        <project name="platform/core">
+            <linkfiles src="tools" dest="tools"/>
+        </project>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    let typo = typo_path.to_str().unwrap();
+    let element = element_path.to_str().unwrap();
+
+    // Lenient parsing stays the default.
+    let manifest = Manifest::from_file(typo, None, None).unwrap();
+    assert_eq!(manifest.projects[0].sync_s, Some(true));
+    let warn = ParseOptions::new().unknown(UnknownPolicy::Warn);
+    let manifest = Manifest::from_file_with(element, None, None, &warn).unwrap();
+    assert!(manifest.projects[0].linkfiles.is_empty());
+
+    let strict = ParseOptions::new().unknown(UnknownPolicy::Error);
+    let err = Manifest::from_file_with(typo, None, None, &strict).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Unknown attribute 'sync_s' of element 'project' in '{}'",
+            typo
+        )
+    );
+    let err = Manifest::from_file_with(element, None, None, &strict).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Unknown element 'linkfiles' in '{}'", element)
+    );
+}
+
 #[test]
 fn test_multiple_remotes() {
     // Test parsing a manifest with multiple remote elements
@@ -271,7 +335,61 @@ fn test_project_with_annotations() {
         manifest.projects[0].path.as_deref(),
         Some("annotated_project")
     );
-    // Annotations are not directly parsed into the main projects list
+    assert_eq!(manifest.projects[0].annotations.len(), 2);
+    assert_eq!(manifest.projects[0].annotations[1].name, "key2");
+}
+
+#[test]
+fn test_project_children_belong_to_their_project() {
+    // Test that copyfile, linkfile and annotation stay with the project they are nested in
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("nested.xml");
+    let mut file = File::create(&file_path).unwrap();
+
+    writeln!(
+        file,
+        r#"
+    <manifest>
+        <project name="build" path="build">
+            <copyfile src="core/root.mk" dest="Makefile"/>
+            <project name="soong">
+                <linkfile src="soong.bash" dest="soong.bash"/>
+            </project>
+            <annotation name="owner" value="build-team"/>
+        </project>
+        <project name="tools">
+            <linkfile src="repo" dest="repo"/>
+        </project>
+    </manifest>
+    "#
+    )
+    .unwrap();
+
+    let manifest =
+        Manifest::from_file(file_path.to_str().unwrap(), Some("origin"), Some("main")).unwrap();
+    assert_eq!(manifest.projects.len(), 3);
+    let build = &manifest.projects[0];
+    assert_eq!(build.copyfiles.len(), 1);
+    assert_eq!(build.copyfiles[0].dest, "Makefile");
+    assert!(build.linkfiles.is_empty());
+    assert_eq!(build.annotations[0].value, "build-team");
+    // Nested projects are subprojects, named and placed below their parent.
+    let soong = &manifest.projects[1];
+    assert_eq!(soong.name, "build/soong");
+    assert_eq!(soong.path.as_deref(), Some("build/build/soong"));
+    assert_eq!(soong.linkfiles[0].src, "soong.bash");
+    let tools = &manifest.projects[2];
+    assert!(tools.copyfiles.is_empty());
+    assert_eq!(tools.linkfiles.len(), 1);
+    assert_eq!(tools.linkfiles[0].src, "repo");
+
+    let missing_dest = dir.path().join("missing_dest.xml");
+    std::fs::write(
+        &missing_dest,
+        r#"<manifest><project name="build"><copyfile src="Makefile"/></project></manifest>"#,
+    )
+    .unwrap();
+    assert!(Manifest::from_file(missing_dest.to_str().unwrap(), None, None).is_err());
 }
 
 #[test]
@@ -318,8 +436,8 @@ fn test_parse_valid_manifest_with_include() {
     assert_eq!(manifest.remotes.len(), 1);
     assert_eq!(manifest.remotes[0].name, "origin");
     assert_eq!(
-        manifest.default.as_ref().unwrap().remote,
-        Some("origin".to_string())
+        manifest.default.as_ref().unwrap().remote.as_deref(),
+        Some("origin")
     );
     assert_eq!(
         manifest.manifest_server.as_ref().unwrap().url,
@@ -373,8 +491,8 @@ fn test_parse_valid_manifest_without_include() {
     assert_eq!(manifest.remotes.len(), 1);
     assert_eq!(manifest.remotes[0].name, "origin");
     assert_eq!(
-        manifest.default.as_ref().unwrap().remote,
-        Some("origin".to_string())
+        manifest.default.as_ref().unwrap().remote.as_deref(),
+        Some("origin")
     );
     assert_eq!(
         manifest.manifest_server.as_ref().unwrap().url,
@@ -389,3 +507,288 @@ fn test_parse_valid_manifest_without_include() {
     assert!(manifest.contactinfo.is_some());
     assert!(manifest.includes.is_empty());
 }
+
+#[test]
+fn test_duplicate_project_from_include() {
+    // Test that a project defined again by an included file follows the duplicate policy
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    let included_file_path = dir.path().join("included.xml");
+    let mut file = File::create(&file_path).unwrap();
+    let mut included_file = File::create(&included_file_path).unwrap();
+
+    writeln!(
+        file,
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <project name="platform/core" path="core" revision="main"/>
+        <project name="platform/app" revision="main"/>
+        <include name="included.xml"/>
+    </manifest>
+    "#
+    )
+    .unwrap();
+    writeln!(
+        included_file,
+        r#"
+    <manifest>
+        <project name="platform/core" path="core" revision="develop"/>
+        <project name="platform/core" path="core-mirror" revision="main"/>
+    </manifest>
+    "#
+    )
+    .unwrap();
+    let file_path = file_path.to_str().unwrap();
+
+    let err = Manifest::from_file(file_path, None, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Duplicate project 'platform/core' at path 'core'"
+    );
+
+    let manifest =
+        Manifest::from_file_with_policy(file_path, None, None, DuplicatePolicy::Override).unwrap();
+    let projects: Vec<_> = manifest
+        .projects
+        .iter()
+        .map(|p| (p.path.as_deref().unwrap_or(&p.name), p.revision.as_deref()))
+        .collect();
+    assert_eq!(
+        projects,
+        vec![
+            ("core", Some("develop")),
+            ("platform/app", Some("main")),
+            ("core-mirror", Some("main")),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_manifest_from_str() {
+    // Test parsing a manifest held in memory, with includes served from memory too
+    let xml = r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <project name="platform/core" path="core"/>
+        <include name="apps.xml"/>
+    </manifest>
+    "#;
+
+    let err = Manifest::from_str(xml, Some("origin"), Some("main"), &NoIncludes).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Includes are not supported, cannot include 'apps.xml'"
+    );
+
+    let resolver =
+        |name: &str, parent: &str| -> Result<IncludedManifest, Box<dyn std::error::Error>> {
+            let xml = match (name, parent) {
+                ("apps.xml", "") => r#"<manifest><include name="more/apps.xml"/></manifest>"#,
+                ("more/apps.xml", "https://example.com/apps.xml") => {
+                    r#"<manifest><project name="platform/app"/></manifest>"#
+                }
+                _ => return Err(format!("unexpected include '{}' from '{}'", name, parent).into()),
+            };
+            Ok(IncludedManifest {
+                location: format!("https://example.com/{}", name),
+                reader: Box::new(xml.as_bytes()),
+            })
+        };
+    let manifest = Manifest::from_str(xml, Some("origin"), Some("main"), &resolver).unwrap();
+
+    let names: Vec<_> = manifest.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["platform/core", "platform/app"]);
+    assert_eq!(manifest.includes.len(), 2);
+    assert_eq!(manifest.default.unwrap().revision.as_deref(), Some("main"));
+}
+
+#[test]
+fn test_parse_manifest_from_reader() {
+    // Test parsing a manifest from a reader
+    let xml = br#"<manifest><project name="platform/core"/></manifest>"#;
+
+    let manifest = Manifest::from_reader(
+        std::io::BufReader::new(&xml[..]),
+        None,
+        None,
+        &manifest_parser::include::FileIncludeResolver,
+    )
+    .unwrap();
+
+    assert_eq!(manifest.projects.len(), 1);
+    assert_eq!(manifest.projects[0].name, "platform/core");
+}
+
+#[test]
+fn test_include_groups() {
+    // Test that the groups of an include are added to the projects it pulls in
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(
+        &file_path,
+        r#"
+    <manifest>
+        <project name="platform/core" groups="base"/>
+        <include name="apps.xml" groups="apps"/>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("apps.xml"),
+        r#"
+    <manifest>
+        <project name="platform/app" groups="ui,apps"/>
+        <include name="tools.xml" groups="tools notdefault"/>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("tools.xml"),
+        r#"<manifest><project name="platform/tools"/></manifest>"#,
+    )
+    .unwrap();
+
+    let manifest = Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap();
+
+    let groups: Vec<_> = manifest
+        .projects
+        .iter()
+        .map(|p| (p.name.as_str(), format_groups(&p.groups)))
+        .collect();
+    let groups: Vec<_> = groups
+        .iter()
+        .map(|(name, groups)| (*name, Some(groups.as_str())))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            ("platform/core", Some("base")),
+            ("platform/app", Some("ui,apps")),
+            ("platform/tools", Some("apps,tools,notdefault")),
+        ]
+    );
+}
+
+#[test]
+fn test_typed_attributes() {
+    // Test that numeric and boolean attributes are parsed and validated
+    let parse = |xml: &str| Manifest::from_str(xml, None, None, &NoIncludes);
+
+    let manifest = parse(
+        r#"
+    <manifest>
+        <default sync-j="8" sync-c="yes" sync-tags="0"/>
+        <project name="platform/core" clone-depth="1" sync-s="True"/>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    let default = manifest.default.unwrap();
+    assert_eq!(default.sync_j, Some(8));
+    assert_eq!(default.sync_c, Some(true));
+    assert_eq!(default.sync_tags, Some(false));
+    assert!(default.sync_s.is_none());
+    assert_eq!(manifest.projects[0].clone_depth, Some(1));
+    assert_eq!(manifest.projects[0].sync_s, Some(true));
+
+    let err = parse(r#"<manifest><default sync-j="four"/></manifest>"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid value 'four' of attribute 'sync-j' in element 'default', expected a positive number"
+    );
+    let err = parse(r#"<manifest><project name="a" clone-depth="0"/></manifest>"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid value '0' of attribute 'clone-depth' in element 'project', expected a positive number"
+    );
+    let err = parse(r#"<manifest><project name="a" sync-c="maybe"/></manifest>"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid value 'maybe' of attribute 'sync-c' in element 'project', expected true or false"
+    );
+}
+
+#[test]
+fn test_source_locations() {
+    // Test that projects and remotes record the file and line they were defined at
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(
+        &file_path,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <remote name="origin"
+          fetch="https://example.com"/>
+  <project name="platform/core">
+    <annotation name="owner" value="core"/>
+  </project>
+  <include name="apps.xml"/>
+</manifest>
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("apps.xml"),
+        "<manifest>\n\n  <project name=\"platform/app\"/>\n</manifest>\n",
+    )
+    .unwrap();
+    let file_path = file_path.to_str().unwrap();
+
+    let manifest = Manifest::from_file(file_path, None, None).unwrap();
+    let remote = manifest.remotes[0].source.as_ref().unwrap();
+    assert_eq!(
+        (remote.kind, &*remote.file, remote.line),
+        (SourceKind::Main, file_path, 3)
+    );
+    let core = manifest.projects[0].source.as_ref().unwrap();
+    assert_eq!((core.kind, core.line), (SourceKind::Main, 5));
+    let app = manifest.projects[1].source.as_ref().unwrap();
+    assert_eq!((app.kind, app.line), (SourceKind::Include, 3));
+    assert!(app.file.ends_with("apps.xml"));
+}
+
+#[test]
+fn test_repeated_strings_shared() {
+    // Test that the strings repeated by the projects of a manifest and its includes are allocated once
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(
+        &file_path,
+        r#"<manifest>
+  <remote name="origin" fetch="https://example.com"/>
+  <project name="platform/core" remote="origin" revision="main" groups="base"/>
+  <include name="apps.xml"/>
+</manifest>
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("apps.xml"),
+        r#"<manifest>
+  <project name="platform/app" remote="origin" revision="main" groups="base"/>
+  <project name="platform/tools" remote="origin" revision="main" groups="base"/>
+</manifest>
+"#,
+    )
+    .unwrap();
+
+    let manifest = Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap();
+    let [core, app, tools] = &manifest.projects[..] else {
+        panic!("expected 3 projects");
+    };
+    let shared = |field: fn(&manifest_parser::Project) -> &Arc<str>| {
+        Arc::ptr_eq(field(core), field(app)) && Arc::ptr_eq(field(app), field(tools))
+    };
+    assert!(shared(|project| project.remote.as_ref().unwrap()));
+    assert!(shared(|project| project.revision.as_ref().unwrap()));
+    assert!(shared(|project| match &project.groups[0] {
+        Group::Named(group) => group,
+        group => panic!("unexpected group {}", group),
+    }));
+    let file =
+        |project: &manifest_parser::Project| Arc::clone(&project.source.as_ref().unwrap().file);
+    assert!(Arc::ptr_eq(&file(app), &file(tools)));
+}
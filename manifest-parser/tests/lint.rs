@@ -0,0 +1,48 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::lint::{LintConfig, Severity};
+use manifest_parser::Manifest;
+
+const MANIFEST: &str = r#"
+<manifest>
+    <remote name="origin" fetch="https://example.com"/>
+    <remote name="mirror" fetch="http://mirror.example.com"/>
+    <default remote="origin" revision="main"/>
+    <project name="platform/core"/>
+    <project name="platform/kernel" revision="v6.1"/>
+    <project name="platform/app" revision="main" clone-depth="1"/>
+</manifest>
+"#;
+
+#[test]
+fn test_lint_default_rules() {
+    // Test the findings of the default rules
+    let manifest = Manifest::from_str(MANIFEST, None, None, &NoIncludes).unwrap();
+    let findings =
+        manifest.lint(&LintConfig::new().large_projects(["platform/kernel", "platform/app"]));
+    let lines: Vec<String> = findings.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        lines,
+        [
+            "warning[missing-revision] project 'platform/core': Project has no explicit revision",
+            "warning[insecure-fetch] remote 'mirror': Remote fetches from 'http://mirror.example.com' over http",
+            "info[missing-clone-depth] project 'platform/kernel': Large project has no clone-depth",
+        ]
+    );
+    assert_eq!(
+        findings[0].to_json(),
+        r#"{"rule":"missing-revision","severity":"warning","element":"project 'platform/core'","message":"Project has no explicit revision"}"#
+    );
+}
+
+#[test]
+fn test_lint_config() {
+    // Test changing the severity of a rule and disabling one
+    let manifest = Manifest::from_str(MANIFEST, None, None, &NoIncludes).unwrap();
+    let config = LintConfig::new()
+        .severity("missing-revision", Severity::Error)
+        .disable("insecure-fetch");
+    let findings = manifest.lint(&config);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].rule, "missing-revision");
+    assert_eq!(findings[0].severity, Severity::Error);
+}
@@ -0,0 +1,79 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::merge::{merge_manifests, MergeOptions};
+use manifest_parser::{DuplicatePolicy, Manifest};
+
+fn parse(xml: &str) -> Manifest {
+    Manifest::from_str(xml, None, None, &NoIncludes).unwrap()
+}
+
+const BASE: &str = r#"
+<manifest>
+    <remote name="origin" fetch="https://example.com"/>
+    <default remote="origin" revision="main"/>
+    <project name="platform/core" revision="v1"/>
+    <project name="platform/app"/>
+</manifest>
+"#;
+
+const LOCAL: &str = r#"
+<manifest>
+    <default remote="origin" revision="devel"/>
+    <remove-project name="platform/app"/>
+    <remove-project name="platform/missing"/>
+    <project name="platform/core" revision="v2"/>
+    <project name="vendor/extra"/>
+</manifest>
+"#;
+
+#[test]
+fn test_merge_duplicate_error() {
+    // Test that a project defined again fails the merge by default
+    let mut base = parse(BASE);
+    let err = merge_manifests(&mut base, parse(LOCAL), &MergeOptions::new()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Duplicate project 'platform/core' at path 'platform/core'"
+    );
+}
+
+#[test]
+fn test_merge_duplicate_override() {
+    // Test that the merged manifest replaces projects with last-wins
+    let mut base = parse(BASE);
+    let options = MergeOptions::new().duplicates(DuplicatePolicy::Override);
+    let summary = merge_manifests(&mut base, parse(LOCAL), &options).unwrap();
+
+    let core = base.find_project_by_name("platform/core").unwrap();
+    assert_eq!(core.revision.as_deref(), Some("v2"));
+    assert!(base.find_project_by_name("platform/app").is_none());
+    assert_eq!(summary.added, ["platform/core", "vendor/extra"]);
+    assert_eq!(summary.removed, ["platform/app"]);
+    assert_eq!(summary.unmatched_removals.len(), 1);
+    assert_eq!(
+        summary.unmatched_removals[0].name.as_deref(),
+        Some("platform/missing")
+    );
+    assert_eq!(
+        base.default.as_ref().unwrap().revision.as_deref(),
+        Some("devel")
+    );
+}
+
+#[test]
+fn test_merge_duplicate_keep() {
+    // Test that first-wins keeps the base project and the base defaults
+    let mut base = parse(BASE);
+    let options = MergeOptions::new()
+        .duplicates(DuplicatePolicy::Keep)
+        .override_default(false);
+    let summary = merge_manifests(&mut base, parse(LOCAL), &options).unwrap();
+
+    let core = base.find_project_by_name("platform/core").unwrap();
+    assert_eq!(core.revision.as_deref(), Some("v1"));
+    assert_eq!(summary.added, ["vendor/extra"]);
+    assert_eq!(base.projects.len(), 2);
+    assert_eq!(
+        base.default.as_ref().unwrap().revision.as_deref(),
+        Some("main")
+    );
+}
@@ -0,0 +1,94 @@
+use manifest_parser::groups::format_groups;
+use manifest_parser::include::NoIncludes;
+use manifest_parser::Manifest;
+
+fn parse(xml: &str) -> Manifest {
+    Manifest::from_str(xml, None, None, &NoIncludes).unwrap()
+}
+
+#[test]
+fn test_normalize() {
+    // Test that equivalent manifests written differently serialize identically
+    let a = parse(
+        r#"
+    <manifest>
+        <remote name="tizen" fetch="https://review.tizen.org"/>
+        <remote name="origin" fetch="https://example.com"/>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/tools" path="tools" groups="tools,base, base"/>
+        <project name="platform/core" path="platform/core">
+            <linkfile src="b" dest="b"/>
+            <linkfile src="a" dest="a"/>
+        </project>
+    </manifest>
+    "#,
+    );
+    let b = parse(
+        r#"
+    <manifest>
+        <remote fetch="https://example.com" name="origin"/>
+        <remote fetch="https://review.tizen.org" name="tizen"/>
+        <default revision="main" remote="origin"/>
+        <project name="platform/core">
+            <linkfile src="a" dest="a"/>
+            <linkfile src="b" dest="b"/>
+        </project>
+        <project groups="base,tools" path="tools" name="platform/tools"/>
+    </manifest>
+    "#,
+    );
+
+    assert_ne!(a.to_xml(), b.to_xml());
+    assert_eq!(a.to_canonical_xml(), b.to_canonical_xml());
+
+    let normalized = a.normalize();
+    let remotes: Vec<&str> = normalized.remotes.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(remotes, vec!["origin", "tizen"]);
+    assert_eq!(normalized.projects[0].name, "platform/core");
+    assert!(normalized.projects[0].path.is_none());
+    assert_eq!(normalized.projects[0].linkfiles[0].dest, "a");
+    assert_eq!(format_groups(&normalized.projects[1].groups), "base,tools");
+
+    assert_eq!(
+        a.to_canonical_xml(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <manifest>\n  \
+         <remote fetch=\"https://example.com\" name=\"origin\"/>\n  \
+         <remote fetch=\"https://review.tizen.org\" name=\"tizen\"/>\n  \
+         <default remote=\"origin\" revision=\"main\"/>\n  \
+         <project name=\"platform/core\">\n    \
+         <linkfile dest=\"a\" src=\"a\"/>\n    \
+         <linkfile dest=\"b\" src=\"b\"/>\n  \
+         </project>\n  \
+         <project groups=\"base,tools\" name=\"platform/tools\" path=\"tools\"/>\n\
+         </manifest>\n"
+    );
+}
+
+#[test]
+fn test_fingerprint() {
+    // Test that the fingerprint only changes when the resolved content does
+    let a = parse(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <!-- core first -->
+        <project name="platform/core"/>
+        <project name="platform/app" path="app"/>
+    </manifest>
+    "#,
+    );
+    let b = parse(
+        r#"<manifest><remote name="origin" fetch="https://example.com"/>
+        <project path="app" name="platform/app" remote="origin" revision="main"/>
+        <project name="platform/core" remote="origin" revision="main"/></manifest>"#,
+    );
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.fingerprint(), a.clone().fingerprint());
+
+    let mut c = a.clone();
+    c.projects[1].revision = Some("stable".into());
+    assert_ne!(a.fingerprint(), c.fingerprint());
+}
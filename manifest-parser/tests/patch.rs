@@ -0,0 +1,63 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::merge::MergeOptions;
+use manifest_parser::patch::ManifestPatch;
+use manifest_parser::Manifest;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_apply_patch() {
+    // Test applying a patch file to a manifest
+    let mut manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core"/>
+        <project name="platform/app"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("emulator.xml");
+    fs::write(
+        &file_path,
+        r#"<manifest>
+            <remove-project name="platform/app"/>
+            <extend-project name="platform/core" revision="emulator"/>
+            <project name="platform/hal-qemu"/>
+        </manifest>"#,
+    )
+    .unwrap();
+
+    let patch = ManifestPatch::from_file(file_path.to_str().unwrap()).unwrap();
+    let summary = manifest.apply_patch(patch, &MergeOptions::new()).unwrap();
+    assert_eq!(summary.removed, ["platform/app"]);
+    assert_eq!(summary.extended, ["platform/core"]);
+    assert_eq!(summary.added, ["platform/hal-qemu"]);
+
+    let names: Vec<&str> = manifest.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["platform/core", "platform/hal-qemu"]);
+    assert_eq!(manifest.projects[0].revision.as_deref(), Some("emulator"));
+    let default = manifest.default.as_ref().unwrap();
+    assert_eq!(default.revision.as_deref(), Some("main"));
+}
+
+#[test]
+fn test_patch_rejects_other_elements() {
+    // Test that a patch may only change projects
+    let err = r#"<manifest>
+            <remote name="origin" fetch="https://example.com"/>
+            <project name="platform/core"/>
+        </manifest>"#
+        .parse::<ManifestPatch>()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Element 'remote' is not allowed in a manifest patch"
+    );
+}
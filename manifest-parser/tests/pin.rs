@@ -0,0 +1,100 @@
+mod common;
+
+use common::git;
+use manifest_parser::pin::{is_commit_sha, LsRemoteResolver, WorkspaceResolver};
+use manifest_parser::Manifest;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+// Creates a repository with a `main` branch and an annotated `v1.0` tag on
+// its first commit, and returns the SHAs of both commits.
+fn create_repo(path: &Path) -> (String, String) {
+    fs::create_dir_all(path).unwrap();
+    git(path, &["init", "-q", "-b", "main"]);
+    git(path, &["commit", "-q", "--allow-empty", "-m", "first"]);
+    git(path, &["tag", "-a", "v1.0", "-m", "release"]);
+    let first = git(path, &["rev-parse", "HEAD"]);
+    git(path, &["commit", "-q", "--allow-empty", "-m", "second"]);
+    (first, git(path, &["rev-parse", "HEAD"]))
+}
+
+fn manifest(dir: &Path, fetch: &Path) -> Manifest {
+    let file_path = dir.join("default.xml");
+    fs::write(
+        &file_path,
+        format!(
+            r#"
+    <manifest>
+        <remote name="origin" fetch="{}"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core"/>
+        <project name="platform/app" path="app" revision="v1.0" upstream="release"/>
+        <project name="platform/fixed" path="fixed" revision="0123456789abcdef0123456789abcdef01234567"/>
+    </manifest>
+    "#,
+            fetch.display()
+        ),
+    )
+    .unwrap();
+    Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap()
+}
+
+#[test]
+fn test_pin_revisions_ls_remote() {
+    // Test pinning branches and annotated tags through git ls-remote
+    let dir = tempdir().unwrap();
+    let remote = dir.path().join("remote");
+    let (_, core_head) = create_repo(&remote.join("platform/core.git"));
+    let (app_tagged, _) = create_repo(&remote.join("platform/app.git"));
+
+    let manifest = manifest(dir.path(), &remote);
+    let pinned = manifest.pin_revisions(&LsRemoteResolver).unwrap();
+
+    assert_eq!(
+        pinned.projects[0].revision.as_deref(),
+        Some(core_head.as_str())
+    );
+    assert_eq!(pinned.projects[0].upstream.as_deref(), Some("main"));
+    assert_eq!(
+        pinned.projects[1].revision.as_deref(),
+        Some(app_tagged.as_str())
+    );
+    assert_eq!(pinned.projects[1].upstream.as_deref(), Some("release"));
+    assert_eq!(pinned.projects[2].revision, manifest.projects[2].revision);
+    assert!(pinned
+        .projects
+        .iter()
+        .all(|p| is_commit_sha(p.revision.as_deref().unwrap())));
+    // The original manifest is left as it was.
+    assert_eq!(manifest.projects[0].revision, None);
+}
+
+#[test]
+fn test_pin_revisions_workspace() {
+    // Test pinning to the commits checked out in a workspace
+    let dir = tempdir().unwrap();
+    let workspace = dir.path().join("workspace");
+    let (core_first, _) = create_repo(&workspace.join("core"));
+    git(&workspace.join("core"), &["checkout", "-q", &core_first]);
+    let (_, app_head) = create_repo(&workspace.join("app"));
+
+    let manifest = manifest(dir.path(), Path::new("https://example.com"));
+    let pinned = manifest
+        .pin_revisions(&WorkspaceResolver::new(&workspace))
+        .unwrap();
+
+    assert_eq!(
+        pinned.projects[0].revision.as_deref(),
+        Some(core_first.as_str())
+    );
+    assert_eq!(
+        pinned.projects[1].revision.as_deref(),
+        Some(app_head.as_str())
+    );
+
+    fs::remove_dir_all(workspace.join("app")).unwrap();
+    assert!(manifest
+        .pin_revisions(&WorkspaceResolver::new(&workspace))
+        .is_err());
+}
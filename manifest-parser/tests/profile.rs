@@ -0,0 +1,44 @@
+use manifest_parser::groups::Group;
+use manifest_parser::include::NoIncludes;
+use manifest_parser::Manifest;
+
+#[test]
+fn test_for_profile() {
+    // Test selecting the projects of a profile with its revision overrides
+    let manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core">
+            <annotation name="profile:emulator:revision" value="emulator"/>
+        </project>
+        <project name="platform/hal-qemu" groups="base,profile:emulator"/>
+        <project name="platform/hal-board" groups="profile:device,profile:lab"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+    assert_eq!(
+        manifest.projects[1].groups,
+        [
+            Group::Named("base".into()),
+            Group::Profile("emulator".into())
+        ]
+    );
+
+    let emulator = manifest.for_profile("emulator");
+    let names: Vec<&str> = emulator.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["platform/core", "platform/hal-qemu"]);
+    assert_eq!(emulator.projects[0].revision.as_deref(), Some("emulator"));
+    assert_eq!(emulator.projects[1].revision, None);
+
+    let lab = manifest.for_profile("lab");
+    let names: Vec<&str> = lab.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["platform/core", "platform/hal-board"]);
+    assert_eq!(lab.projects[0].revision, None);
+    assert_eq!(manifest.projects.len(), 3);
+}
@@ -0,0 +1,190 @@
+use manifest_parser::groups::{format_groups, Group};
+use manifest_parser::include::NoIncludes;
+use manifest_parser::query::ResolvedProject;
+use manifest_parser::Manifest;
+
+fn parse(xml: &str) -> Manifest {
+    Manifest::from_str(xml, None, None, &NoIncludes).unwrap()
+}
+
+#[test]
+fn test_find_project() {
+    // Test looking projects up by name and by checkout path
+    let manifest = parse(
+        r#"
+    <manifest>
+        <project name="platform/core" path="core"/>
+        <project name="platform/core" path="core-mirror"/>
+        <project name="platform/app"/>
+    </manifest>
+    "#,
+    );
+
+    let core = manifest.find_project_by_name("platform/core").unwrap();
+    assert_eq!(core.path.as_deref(), Some("core"));
+    let mirror = manifest.find_project_by_path("core-mirror/").unwrap();
+    assert_eq!(mirror.name, "platform/core");
+    let app = manifest.find_project_by_path("platform/app").unwrap();
+    assert_eq!(app.name, "platform/app");
+    assert!(manifest.find_project_by_name("core").is_none());
+    assert!(manifest.find_project_by_path("platform/core").is_none());
+}
+
+#[test]
+fn test_resolved_projects() {
+    // Test that projects fall back to the revision of their remote, then to the defaults
+    let manifest = parse(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <remote name="tizen" fetch="https://review.tizen.org" revision="tizen"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core"/>
+        <project name="platform/app" remote="tizen"/>
+        <project name="platform/tools" remote="tizen" revision="v1.0"/>
+    </manifest>
+    "#,
+    );
+
+    let resolved: Vec<_> = manifest
+        .resolved_projects()
+        .map(|p| {
+            (
+                p.name,
+                p.remote.unwrap().to_string(),
+                p.revision.unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        resolved,
+        vec![
+            (
+                "platform/core".to_string(),
+                "origin".to_string(),
+                "main".to_string()
+            ),
+            (
+                "platform/app".to_string(),
+                "tizen".to_string(),
+                "tizen".to_string()
+            ),
+            (
+                "platform/tools".to_string(),
+                "tizen".to_string(),
+                "v1.0".to_string()
+            ),
+        ]
+    );
+    // The manifest itself is left alone.
+    assert!(manifest.projects[0].revision.is_none());
+
+    let bare = parse(r#"<manifest><project name="platform/core"/></manifest>"#);
+    let project = bare.resolve_project(&bare.projects[0]);
+    assert!(project.remote.is_none());
+    assert!(project.revision.is_none());
+}
+
+#[test]
+fn test_resolve() {
+    // Test computing clone URLs, revisions and groups of every project
+    let manifest = parse(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com/"/>
+        <default remote="origin" revision="main" dest-branch="review" upstream="main"/>
+        <project name="platform/core" path="core" groups="base,notdefault"/>
+        <project name="platform/app" revision="v1.0" upstream="release"/>
+    </manifest>
+    "#,
+    );
+
+    let resolved = manifest.resolve().unwrap();
+    assert_eq!(
+        resolved[0],
+        ResolvedProject {
+            name: "platform/core".to_string(),
+            path: "core".to_string(),
+            remote: "origin".to_string(),
+            git_remote: "origin".to_string(),
+            url: "https://example.com/platform/core.git".to_string(),
+            revision: "main".to_string(),
+            dest_branch: Some("review".to_string()),
+            upstream: Some("main".to_string()),
+            groups: vec![
+                Group::Named("base".into()),
+                Group::NotDefault,
+                Group::All,
+                Group::Name("platform/core".to_string()),
+                Group::Path("core".to_string()),
+            ],
+        }
+    );
+    assert_eq!(resolved[1].path, "platform/app");
+    assert_eq!(resolved[1].revision, "v1.0");
+    assert_eq!(resolved[1].upstream.as_deref(), Some("release"));
+    assert_eq!(
+        format_groups(&resolved[1].groups),
+        "all,name:platform/app,path:platform/app,default"
+    );
+
+    let missing = parse(
+        r#"<manifest><default remote="tizen" revision="main"/><project name="platform/core"/></manifest>"#,
+    );
+    let err = missing.resolve().unwrap_err();
+    assert_eq!(err.to_string(), "Remote 'tizen' not found in manifest");
+}
+
+#[test]
+fn test_resolve_remote_alias() {
+    // Test that checkouts name their remote after its alias but fetch from the remote itself
+    let manifest = parse(
+        r#"
+    <manifest>
+        <remote name="public" alias="origin" fetch="https://example.com"/>
+        <remote name="mirror" alias="origin" fetch="https://mirror.example.com"/>
+        <remote name="tizen" fetch="https://review.tizen.org"/>
+        <default remote="public" revision="main"/>
+        <project name="platform/core"/>
+        <project name="platform/app" remote="mirror"/>
+        <project name="platform/tools" remote="tizen"/>
+    </manifest>
+    "#,
+    );
+
+    let resolved = manifest.resolve().unwrap();
+    assert_eq!(resolved[0].remote, "public");
+    assert_eq!(resolved[0].git_remote, "origin");
+    assert_eq!(resolved[0].url, "https://example.com/platform/core.git");
+    assert_eq!(resolved[1].remote, "mirror");
+    assert_eq!(resolved[1].git_remote, "origin");
+    assert_eq!(
+        resolved[1].url,
+        "https://mirror.example.com/platform/app.git"
+    );
+    assert_eq!(resolved[2].git_remote, "tizen");
+}
+
+#[test]
+fn test_resolve_dest_branch_and_upstream() {
+    // Test that dest-branch and upstream fall back to the default and pin checks
+    let manifest = parse(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main" upstream="main"/>
+        <project name="platform/core" dest-branch="review"/>
+        <project name="platform/app" revision="0123456789abcdef0123456789abcdef01234567"/>
+        <project name="platform/tools" revision="0123456789abcdef0123456789abcdef01234567" upstream="stable"/>
+    </manifest>
+    "#,
+    );
+
+    let resolved = manifest.resolve().unwrap();
+    assert_eq!(resolved[0].upload_branch(), Some("review"));
+    assert_eq!(resolved[0].pinned_upstream(), None);
+    assert_eq!(resolved[1].upstream.as_deref(), Some("main"));
+    assert_eq!(resolved[1].upload_branch(), None);
+    assert_eq!(resolved[1].pinned_upstream(), Some("main"));
+    assert_eq!(resolved[2].pinned_upstream(), Some("stable"));
+}
@@ -0,0 +1,152 @@
+use manifest_parser::relocate::relocate_workspace;
+use std::fs;
+use tempfile::tempdir;
+
+#[cfg(unix)]
+#[test]
+fn test_relocate_workspace() {
+    // Test moving a workspace with linkfiles, alternates and a submodule gitdir file
+    let dir = tempdir().unwrap();
+    let old_root = dir.path().join("old");
+    let new_root = dir.path().join("disk2").join("new");
+
+    let project = old_root.join("platform/core");
+    fs::create_dir_all(project.join(".git/objects/info")).unwrap();
+    fs::write(project.join("Makefile"), "all:").unwrap();
+    fs::write(
+        project.join(".git/objects/info/alternates"),
+        format!("{}/.mirror/core.git/objects\n", old_root.display()),
+    )
+    .unwrap();
+    std::os::unix::fs::symlink(project.join("Makefile"), old_root.join("Makefile")).unwrap();
+    std::os::unix::fs::symlink("/usr/share/doc", old_root.join("doc")).unwrap();
+
+    let submodule = project.join("third_party/lib");
+    fs::create_dir_all(&submodule).unwrap();
+    fs::write(
+        submodule.join(".git"),
+        format!(
+            "gitdir: {}/platform/core/.git/modules/lib\n",
+            old_root.display()
+        ),
+    )
+    .unwrap();
+
+    let report = relocate_workspace(&old_root, &new_root).unwrap();
+
+    assert!(!old_root.exists());
+    assert_eq!(report.symlinks, vec![new_root.join("Makefile")]);
+    assert_eq!(
+        fs::read_link(new_root.join("Makefile")).unwrap(),
+        new_root.join("platform/core/Makefile")
+    );
+    assert_eq!(
+        fs::read_link(new_root.join("doc")).unwrap(),
+        std::path::Path::new("/usr/share/doc")
+    );
+    assert_eq!(
+        fs::read_to_string(new_root.join("platform/core/.git/objects/info/alternates")).unwrap(),
+        format!("{}/.mirror/core.git/objects\n", new_root.display())
+    );
+    assert_eq!(report.gitdir_files.len(), 1);
+    assert_eq!(
+        fs::read_to_string(new_root.join("platform/core/third_party/lib/.git")).unwrap(),
+        format!(
+            "gitdir: {}/platform/core/.git/modules/lib\n",
+            new_root.display()
+        )
+    );
+}
+
+#[test]
+fn test_relocate_workspace_to_existing_destination() {
+    // Test that an existing destination is never overwritten
+    let dir = tempdir().unwrap();
+    let old_root = dir.path().join("old");
+    let new_root = dir.path().join("new");
+    fs::create_dir_all(&old_root).unwrap();
+    fs::create_dir_all(&new_root).unwrap();
+
+    assert!(relocate_workspace(&old_root, &new_root).is_err());
+    assert!(old_root.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_relocate_workspace_git_metadata_and_caches() {
+    // Test that submodule worktrees, worktree gitdirs and the sync caches follow the move
+    let dir = tempdir().unwrap();
+    let old_root = dir.path().join("old");
+    let new_root = dir.path().join("new");
+
+    let git_dir = old_root.join("platform/core/.git");
+    for module in ["modules/lib", "modules/vendor/blobs"] {
+        fs::create_dir_all(git_dir.join(module)).unwrap();
+        fs::write(git_dir.join(module).join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    }
+    fs::write(
+        git_dir.join("modules/lib/config"),
+        format!(
+            "[core]\n\tbare = false\n\tworktree = {}/platform/core/lib\n",
+            old_root.display()
+        ),
+    )
+    .unwrap();
+    fs::write(
+        git_dir.join("modules/vendor/blobs/config"),
+        "[core]\n\tworktree = ../../../../vendor/blobs\n",
+    )
+    .unwrap();
+    fs::create_dir_all(git_dir.join("worktrees/review")).unwrap();
+    fs::write(
+        git_dir.join("worktrees/review/gitdir"),
+        format!("{}/review/.git\n", old_root.display()),
+    )
+    .unwrap();
+    fs::create_dir_all(old_root.join(".repo")).unwrap();
+    fs::write(
+        old_root.join(".repo/sync-state"),
+        format!(
+            "0123abcd main platform/core\n0123abcd main {}/out of tree\n",
+            old_root.display()
+        ),
+    )
+    .unwrap();
+    fs::write(old_root.join(".repo/copy-link-files"), "Makefile\n").unwrap();
+
+    let report = relocate_workspace(&old_root, &new_root).unwrap();
+
+    let git_dir = new_root.join("platform/core/.git");
+    assert_eq!(report.worktrees, vec![git_dir.join("modules/lib/config")]);
+    assert_eq!(
+        fs::read_to_string(git_dir.join("modules/lib/config")).unwrap(),
+        format!(
+            "[core]\n\tbare = false\n\tworktree = {}/platform/core/lib\n",
+            new_root.display()
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(git_dir.join("modules/vendor/blobs/config")).unwrap(),
+        "[core]\n\tworktree = ../../../../vendor/blobs\n"
+    );
+    assert_eq!(
+        report.gitdir_files,
+        vec![git_dir.join("worktrees/review/gitdir")]
+    );
+    assert_eq!(
+        fs::read_to_string(git_dir.join("worktrees/review/gitdir")).unwrap(),
+        format!("{}/review/.git\n", new_root.display())
+    );
+    assert_eq!(report.caches, vec![new_root.join(".repo/sync-state")]);
+    assert_eq!(
+        fs::read_to_string(new_root.join(".repo/sync-state")).unwrap(),
+        format!(
+            "0123abcd main platform/core\n0123abcd main {}/out of tree\n",
+            new_root.display()
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(new_root.join(".repo/copy-link-files")).unwrap(),
+        "Makefile\n"
+    );
+}
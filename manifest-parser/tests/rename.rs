@@ -0,0 +1,86 @@
+use manifest_parser::rename::WorkspaceMove;
+use manifest_parser::Manifest;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+fn parse(xml: &str) -> Manifest {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("manifest.xml");
+    let mut file = File::create(&file_path).unwrap();
+    writeln!(file, "{}", xml).unwrap();
+    Manifest::from_file(file_path.to_str().unwrap(), Some("origin"), Some("main")).unwrap()
+}
+
+#[test]
+fn test_rename_project_name() {
+    // Test renaming a project without an explicit path
+    let mut manifest = parse(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <project name="tools/hooks" revision="main"/>
+        <extend-project name="tools/hooks" revision="develop"/>
+        <repo-hooks in-project="tools/hooks" enabled-list="pre-upload"/>
+    </manifest>
+    "#,
+    );
+
+    let plan = manifest
+        .rename_project("tools/hooks", Some("infra/hooks"), None)
+        .unwrap();
+
+    assert_eq!(manifest.projects[0].name, "infra/hooks");
+    assert_eq!(manifest.extend_projects[0].name, "infra/hooks");
+    assert_eq!(
+        manifest.repo_hooks.as_ref().unwrap().in_project,
+        "infra/hooks"
+    );
+    assert_eq!(
+        plan.moves,
+        vec![WorkspaceMove {
+            from: PathBuf::from("tools/hooks"),
+            to: PathBuf::from("infra/hooks"),
+        }]
+    );
+    assert_eq!(
+        plan.overlay.remove_projects[0].name.as_deref(),
+        Some("tools/hooks")
+    );
+    assert_eq!(plan.overlay.projects[0].name, "infra/hooks");
+    assert_eq!(plan.overlay.projects[0].revision.as_deref(), Some("main"));
+}
+
+#[test]
+fn test_rename_project_path() {
+    // Test moving a project to a new path, and rejecting path collisions
+    let mut manifest = parse(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <project name="core" path="src/core"/>
+        <project name="util" path="src/util"/>
+    </manifest>
+    "#,
+    );
+
+    assert!(manifest
+        .rename_project("core", None, Some("src/util"))
+        .is_err());
+    assert_eq!(manifest.projects[0].path.as_deref(), Some("src/core"));
+
+    let plan = manifest
+        .rename_project("core", None, Some("platform/core"))
+        .unwrap();
+    assert_eq!(manifest.projects[0].path.as_deref(), Some("platform/core"));
+    assert_eq!(plan.moves[0].from, PathBuf::from("src/core"));
+    assert_eq!(plan.moves[0].to, PathBuf::from("platform/core"));
+    assert!(plan.overlay.remove_projects.is_empty());
+    assert_eq!(
+        plan.overlay.extend_projects[0].dest_path.as_deref(),
+        Some("platform/core")
+    );
+
+    assert!(manifest.rename_project("missing", Some("x"), None).is_err());
+}
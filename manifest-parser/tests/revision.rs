@@ -0,0 +1,67 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::query::ResolvedProject;
+use manifest_parser::revision::Revision;
+use manifest_parser::Manifest;
+
+#[test]
+fn test_revision_kinds() {
+    // Test classifying revisions by what they name
+    let sha = "3f2c9e1a7b4d5c6e8f901234567890abcdef1234";
+    assert_eq!(Revision::new(sha), Revision::Sha(sha.to_string()));
+    assert_eq!(Revision::new("main"), Revision::Branch("main".to_string()));
+    let branch = Revision::new("refs/heads/tizen");
+    assert_eq!(branch, Revision::Branch("refs/heads/tizen".to_string()));
+    assert_eq!(branch.short_name(), Some("tizen"));
+    let tag = Revision::new("refs/tags/v1.0");
+    assert_eq!(tag, Revision::Tag("refs/tags/v1.0".to_string()));
+    assert_eq!(tag.short_name(), Some("v1.0"));
+    let change = Revision::new("refs/changes/45/12345/2");
+    assert_eq!(
+        change,
+        Revision::RefsExpression("refs/changes/45/12345/2".to_string())
+    );
+    assert_eq!(change.short_name(), None);
+    assert_eq!(change.to_string(), "refs/changes/45/12345/2");
+}
+
+#[test]
+fn test_revision_validation() {
+    // Test that revisions git does not accept as refs are rejected
+    assert_eq!("main".parse(), Ok(Revision::Branch("main".to_string())));
+    assert_eq!(
+        "feature..x".parse::<Revision>().unwrap_err(),
+        "Invalid revision 'feature..x': it contains '..', '@{' or '//'"
+    );
+    assert_eq!(
+        "my branch".parse::<Revision>().unwrap_err(),
+        "Invalid revision 'my branch': it contains ' '"
+    );
+    assert!("".parse::<Revision>().is_err());
+    assert!("refs/heads/main.lock".parse::<Revision>().is_err());
+    assert!("refs/heads/".parse::<Revision>().is_err());
+}
+
+#[test]
+fn test_typed_revision_of_projects() {
+    // Test the typed revision of a project and of the resolved project
+    let manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="refs/tags/v2"/>
+        <project name="platform/core"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+    let project = &manifest.projects[0];
+    assert_eq!(project.typed_revision(), None);
+    let resolved = ResolvedProject::from_project(&manifest, project).unwrap();
+    assert_eq!(
+        resolved.typed_revision(),
+        Revision::Tag("refs/tags/v2".to_string())
+    );
+}
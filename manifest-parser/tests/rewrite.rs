@@ -0,0 +1,128 @@
+use manifest_parser::groups::Group;
+use manifest_parser::{CopyFile, Manifest};
+use std::fs;
+use tempfile::tempdir;
+
+const ORIGINAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Tizen platform -->
+<manifest>
+    <remote name="origin" fetch="https://example.com"/>
+    <default remote="origin" revision="main"/>
+
+    <!-- core -->
+    <project path="core" name="platform/core"
+             groups="base" />
+    <project name="platform/app" revision='develop'>
+        <copyfile src="a" dest="b"/>
+    </project>
+    <project name="platform/old"/>
+    <custom-element foo="bar"/>
+</manifest>
+"#;
+
+#[test]
+fn test_rewrite_unchanged() {
+    // Test that rewriting an unmodified manifest keeps the file as it is
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(&file_path, ORIGINAL).unwrap();
+    let manifest = Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap();
+
+    assert_eq!(manifest.rewrite_xml(ORIGINAL).unwrap(), ORIGINAL);
+}
+
+#[test]
+fn test_rewrite_minimal_diff() {
+    // Test that only modified, added and removed elements change
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(&file_path, ORIGINAL).unwrap();
+    let mut manifest = Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap();
+
+    manifest.projects[0].revision = Some("3f2c9e1".into());
+    manifest.projects[1].copyfiles.push(CopyFile {
+        src: "c".to_string(),
+        dest: "d".to_string(),
+    });
+    let mut new = manifest.projects.remove(2);
+    new.name = "platform/new".to_string();
+    manifest.projects.push(new);
+
+    manifest.rewrite_file(file_path.to_str().unwrap()).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Tizen platform -->
+<manifest>
+    <remote name="origin" fetch="https://example.com"/>
+    <default remote="origin" revision="main"/>
+
+    <!-- core -->
+    <project path="core" name="platform/core"
+             groups="base"
+             revision="3f2c9e1" />
+    <project name="platform/app" revision='develop'>
+        <copyfile src="a" dest="b"/>
+        <copyfile src="c" dest="d"/>
+    </project>
+    <project name="platform/new"/>
+    <custom-element foo="bar"/>
+</manifest>
+"#
+    );
+}
+
+#[test]
+fn test_rewrite_skips_included_elements() {
+    // Test that projects from included files are not copied into the including file
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    let original = r#"<manifest>
+  <project name="platform/core"/>
+  <include name="vendor.xml"/>
+</manifest>
+"#;
+    fs::write(&file_path, original).unwrap();
+    fs::write(
+        dir.path().join("vendor.xml"),
+        r#"<manifest><project name="vendor/blobs"/></manifest>"#,
+    )
+    .unwrap();
+    let mut manifest = Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap();
+    assert_eq!(manifest.projects.len(), 2);
+
+    manifest.projects[0].groups = vec![Group::Named("base".into())];
+    manifest.rewrite_file(file_path.to_str().unwrap()).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        r#"<manifest>
+  <project name="platform/core" groups="base"/>
+  <include name="vendor.xml"/>
+</manifest>
+"#
+    );
+}
+
+#[test]
+fn test_rewrite_xml_skips_included_projects() {
+    // Test that rewriting a string leaves out the projects of included files
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    let original = r#"<manifest>
+  <project name="platform/core"/>
+  <include name="vendor.xml"/>
+</manifest>
+"#;
+    fs::write(&file_path, original).unwrap();
+    fs::write(
+        dir.path().join("vendor.xml"),
+        r#"<manifest><project name="vendor/blobs"/></manifest>"#,
+    )
+    .unwrap();
+    let manifest = Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap();
+    assert_eq!(manifest.projects.len(), 2);
+
+    assert_eq!(manifest.rewrite_xml(original).unwrap(), original);
+}
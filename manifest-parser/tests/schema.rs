@@ -0,0 +1,85 @@
+use manifest_parser::schema::{validate_schema, validate_schema_file, Violation};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_validate_schema_valid() {
+    // Test that a manifest following the DTD has no violations
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(
+        &file_path,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <notice>Builds need gbs</notice>
+  <remote name="origin" fetch="https://example.com"/>
+  <default remote="origin" revision="main" sync-j="4"/>
+  <project name="platform/core" path="core" groups="base">
+    <annotation name="owner" value="kernel"/>
+    <copyfile src="Makefile" dest="Makefile"/>
+  </project>
+  <project name="platform/app" remote="origin"/>
+  <include name="extra.xml"/>
+</manifest>
+"#,
+    )
+    .unwrap();
+
+    let violations = validate_schema_file(file_path.to_str().unwrap()).unwrap();
+    assert_eq!(violations, Vec::<Violation>::new());
+}
+
+#[test]
+fn test_validate_schema_violations() {
+    // Test that every violation is reported with its line
+    let violations = validate_schema(
+        r#"<manifest>
+  <remote name="origin"/>
+  <remote name="origin" fetch="https://example.com"/>
+  <default remote="tizen"/>
+  <default revision="main"/>
+  <project name="platform/core" sync_s="true">
+    <copyfile src="Makefile"/>
+    <include name="extra.xml"/>
+    text
+  </project>
+  <projects name="platform/app"/>
+</manifest>
+"#,
+    );
+
+    let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "line 2: Element 'remote' is missing required attribute 'fetch'",
+            "line 3: 'origin' of element 'remote' is defined twice",
+            "line 5: Element 'default' may appear only once in 'manifest'",
+            "line 6: Attribute 'sync_s' is not declared for element 'project'",
+            "line 7: Element 'copyfile' is missing required attribute 'dest'",
+            "line 8: Element 'include' is not allowed in 'project'",
+            "line 8: Element 'project' may not contain text",
+            "line 11: Element 'projects' is not allowed in 'manifest'",
+            "line 11: Element 'projects' is not declared",
+            "line 4: Attribute 'remote' of element 'default' refers to 'tizen', which is not defined",
+        ]
+    );
+}
+
+#[test]
+fn test_validate_schema_malformed() {
+    // Test that malformed documents and other root elements are reported
+    let violations = validate_schema("<manifest>\n  <project name=\"a\">\n</manifest>\n");
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].line, 3);
+
+    let violations = validate_schema("<west/>");
+    assert_eq!(
+        violations[0].message,
+        "Root element is 'west', expected 'manifest'"
+    );
+    assert_eq!(
+        validate_schema("")[0].message,
+        "Document has no root element"
+    );
+}
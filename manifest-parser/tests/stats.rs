@@ -0,0 +1,49 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::stats::RevisionKind;
+use manifest_parser::{DuplicatePolicy, Manifest};
+
+#[test]
+fn test_stats() {
+    // Test counting projects per remote, group and revision kind
+    let mut manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <remote name="tizen" fetch="https://review.tizen.org"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core" groups="base"/>
+        <project name="platform/app" remote="tizen" revision="refs/tags/v1.0" groups="base,apps"/>
+        <project name="platform/tools" groups="notdefault" clone-depth="1"
+                 revision="3f2c9e1a7b4d5c6e8f901234567890abcdef1234"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+    let mut duplicate = manifest.projects[0].clone();
+    duplicate.name = "vendor/core".to_string();
+    manifest
+        .add_project(duplicate, DuplicatePolicy::Error)
+        .unwrap();
+
+    let stats = manifest.stats();
+    assert_eq!(stats.projects, 4);
+    assert_eq!(stats.per_remote[&Some("origin".to_string())], 3);
+    assert_eq!(stats.per_remote[&Some("tizen".to_string())], 1);
+    assert_eq!(stats.per_group["base"], 3);
+    assert_eq!(stats.per_group["apps"], 1);
+    assert_eq!(stats.per_group["default"], 3);
+    assert_eq!(stats.per_group["notdefault"], 1);
+    assert!(!stats.per_group.contains_key("all"));
+    assert_eq!(stats.per_revision_kind[&RevisionKind::Branch], 2);
+    assert_eq!(stats.per_revision_kind[&RevisionKind::Tag], 1);
+    assert_eq!(stats.per_revision_kind[&RevisionKind::Commit], 1);
+    assert_eq!(stats.shallow, 1);
+    assert_eq!(stats.duplicate_paths.len(), 1);
+    assert_eq!(
+        stats.duplicate_paths["core"],
+        vec!["platform/core", "vendor/core"]
+    );
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "sync")]
+
+mod common;
+
+use common::git;
+use manifest_parser::status::{status, FileStatus};
+use tempfile::tempdir;
+
+#[test]
+fn test_status() {
+    // Test the branch, ahead/behind counts and changed files of checkouts
+    let dir = tempdir().unwrap();
+    let upstream = dir.path().join("upstream");
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    std::fs::write(upstream.join("README"), "core\n").unwrap();
+    git(&upstream, &["add", "README"]);
+    git(&upstream, &["commit", "-q", "-m", "one"]);
+
+    let target_dir = dir.path().join("target");
+    let checkout = target_dir.join("core");
+    std::fs::create_dir_all(&target_dir).unwrap();
+    git(
+        &target_dir,
+        &["clone", "-q", upstream.to_str().unwrap(), "core"],
+    );
+    git(&checkout, &["commit", "-q", "--allow-empty", "-m", "local"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "two"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "three"]);
+    git(&checkout, &["fetch", "-q"]);
+    std::fs::write(checkout.join("README"), "changed\n").unwrap();
+    std::fs::write(checkout.join("new file"), "new\n").unwrap();
+
+    let manifest_path = dir.path().join("manifest.xml");
+    std::fs::write(
+        &manifest_path,
+        r#"<manifest>
+        <remote name="origin" fetch="https://example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core"/>
+        <project name="platform/app"/>
+    </manifest>"#,
+    )
+    .unwrap();
+
+    let statuses = status(
+        manifest_path.to_str().unwrap(),
+        target_dir.to_str().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(statuses.len(), 2);
+
+    let core = &statuses[0];
+    assert_eq!(core.name, "platform/core");
+    assert!(core.checked_out);
+    assert_eq!(core.error, None);
+    assert_eq!(core.branch.as_deref(), Some("main"));
+    assert_eq!(core.upstream.as_deref(), Some("origin/main"));
+    assert_eq!((core.ahead, core.behind), (1, 2));
+    assert!(core.is_dirty());
+    assert_eq!(
+        core.files,
+        vec![
+            FileStatus {
+                path: "README".to_string(),
+                index: '.',
+                worktree: 'M',
+            },
+            FileStatus {
+                path: "new file".to_string(),
+                index: '?',
+                worktree: '?',
+            },
+        ]
+    );
+
+    let app = &statuses[1];
+    assert_eq!(app.path, "platform/app");
+    assert!(!app.checked_out);
+    assert!(!app.is_dirty());
+}
@@ -0,0 +1,58 @@
+use manifest_parser::{Manifest, SourceKind};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_load_submanifests() {
+    // Test that submanifests are read from their manifest-name, recursively
+    let dir = tempdir().unwrap();
+    let repo = dir.path().join(".repo");
+    let write = |path: &str, xml: &str| {
+        let path = repo.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, xml).unwrap();
+    };
+    write(
+        "manifests/default.xml",
+        r#"<manifest>
+            <submanifest name="vendor" path="vendor" manifest-name="vendor.xml" groups="vendor"/>
+        </manifest>"#,
+    );
+    write(
+        "submanifests/vendor/manifests/default.xml",
+        r#"<manifest><project name="wrong/file"/></manifest>"#,
+    );
+    write(
+        "submanifests/vendor/manifests/vendor.xml",
+        r#"<manifest>
+            <remote name="vendor" fetch="https://vendor.example.com"/>
+            <project name="vendor/hal" remote="vendor" revision="main"/>
+            <submanifest name="firmware"/>
+        </manifest>"#,
+    );
+    write(
+        "submanifests/vendor/submanifests/firmware/manifests/default.xml",
+        r#"<manifest><project name="firmware/blobs" remote="vendor" revision="main"/></manifest>"#,
+    );
+
+    let manifest = Manifest::from_file(
+        repo.join("manifests/default.xml").to_str().unwrap(),
+        None,
+        None,
+    )
+    .unwrap();
+    let loaded = manifest.load_submanifests(repo.to_str().unwrap()).unwrap();
+
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].submanifest.manifest_file(), "vendor.xml");
+    let hal = &loaded[0].manifest.projects[0];
+    assert_eq!(hal.name, "vendor/hal");
+    assert!(hal.in_group(&"vendor".parse().unwrap()));
+    assert_eq!(hal.source.as_ref().unwrap().kind, SourceKind::Submanifest);
+
+    let firmware = &loaded[0].submanifests[0];
+    assert_eq!(firmware.submanifest.manifest_file(), "default.xml");
+    let blobs = &firmware.manifest.projects[0];
+    assert_eq!(blobs.name, "firmware/blobs");
+    assert!(blobs.in_group(&"vendor".parse().unwrap()));
+}
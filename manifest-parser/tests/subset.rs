@@ -0,0 +1,63 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::subset::Selection;
+use manifest_parser::Manifest;
+
+const MANIFEST: &str = r#"
+<manifest>
+    <remote name="origin" fetch="https://example.com"/>
+    <remote name="vendor" fetch="https://vendor.example.com"/>
+    <remote name="internal" fetch="https://internal.example.com"/>
+    <default remote="origin" revision="main"/>
+    <project name="platform/core" groups="partner"/>
+    <project name="vendor/hal" remote="vendor" groups="partner"/>
+    <project name="internal/tools" remote="internal"/>
+    <repo-hooks in-project="internal/tools" enabled-list="pre-upload"/>
+    <remove-project name="platform/old" optional="true"/>
+</manifest>
+"#;
+
+#[test]
+fn test_subset_by_groups() {
+    // Test slicing a manifest down to the projects of a group filter
+    let manifest = Manifest::from_str(MANIFEST, None, None, &NoIncludes).unwrap();
+    let subset = manifest
+        .subset(&Selection::Groups("partner".to_string()))
+        .unwrap();
+
+    let names: Vec<&str> = subset.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["platform/core", "vendor/hal"]);
+    let remotes: Vec<&str> = subset.remotes.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(remotes, ["origin", "vendor"]);
+    assert!(subset.default.is_some());
+    assert!(subset.repo_hooks.is_none());
+    assert!(subset.remove_projects.is_empty());
+
+    let reparsed = Manifest::from_str(&subset.to_xml(), None, None, &NoIncludes).unwrap();
+    assert_eq!(reparsed.projects.len(), 2);
+}
+
+#[test]
+fn test_subset_by_projects() {
+    // Test slicing a manifest down to named projects
+    let manifest = Manifest::from_str(MANIFEST, None, None, &NoIncludes).unwrap();
+    let subset = manifest
+        .subset(&Selection::Projects(vec![
+            "internal/tools".to_string(),
+            "platform/core".to_string(),
+        ]))
+        .unwrap();
+
+    let names: Vec<&str> = subset.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["platform/core", "internal/tools"]);
+    let remotes: Vec<&str> = subset.remotes.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(remotes, ["origin", "internal"]);
+    assert!(subset.repo_hooks.is_some());
+
+    let err = manifest
+        .subset(&Selection::Projects(vec!["platform/missing".to_string()]))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Project 'platform/missing' is not in the manifest"
+    );
+}
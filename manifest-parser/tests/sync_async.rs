@@ -0,0 +1,158 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use common::git;
+use manifest_parser::sync::{
+    CancelToken, DefaultGitCommandRunner, GitCommandRunner, RetryPolicy, SyncEvent, SyncObserver,
+    SyncOptions,
+};
+use manifest_parser::sync_async::{sync_repos_async, sync_repos_async_with_runner};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+struct Recorder(Mutex<Vec<SyncEvent>>);
+
+impl SyncObserver for Recorder {
+    fn on_event(&self, event: &SyncEvent) {
+        self.0.lock().unwrap().push(event.clone());
+    }
+}
+
+#[tokio::test]
+async fn test_sync_repos_async() {
+    // Test syncing repositories on a tokio runtime
+    let dir = tempdir().unwrap();
+    let upstream = dir.path().join("remote/platform/core.git");
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    for message in ["one", "two", "three"] {
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", message]);
+    }
+    let file_path = dir.path().join("test_manifest.xml");
+    std::fs::write(
+        &file_path,
+        format!(
+            r#"<manifest>
+        <remote name="origin" fetch="file://{}"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core"/>
+        <project name="platform/core" path="missing" revision="nonexistent"/>
+    </manifest>"#,
+            dir.path().join("remote").display()
+        ),
+    )
+    .unwrap();
+    let target_dir = dir.path().join("target");
+    let options = SyncOptions {
+        jobs_network: Some(2),
+        jobs_checkout: Some(1),
+        quiet: true,
+        keep: true,
+        retry: RetryPolicy::none(),
+        ..Default::default()
+    };
+
+    let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+    let report = sync_repos_async(
+        file_path.to_str().unwrap(),
+        None,
+        options,
+        target_dir.to_str().unwrap(),
+        recorder.clone(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.projects.len(), 2);
+    assert_eq!(report.projects[0].path, "core");
+    let checkout = target_dir.join("core");
+    assert_eq!(git(&checkout, &["rev-list", "--count", "HEAD"]), "3");
+    assert_eq!(git(&checkout, &["branch", "--show-current"]), "main");
+
+    let events = recorder.0.lock().unwrap();
+    assert_eq!(events.first(), Some(&SyncEvent::Started { total: 2 }));
+    assert_eq!(events.last(), Some(&SyncEvent::Finished));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, SyncEvent::ProjectFinished { error: Some(_), .. })));
+}
+
+struct RecordingRunner(Mutex<Vec<String>>);
+
+impl GitCommandRunner for RecordingRunner {
+    fn run_git_command(
+        &self,
+        project_path: &Path,
+        args: &[&str],
+    ) -> Result<std::process::ExitStatus, Box<dyn std::error::Error>> {
+        self.0.lock().unwrap().push(args.join(" "));
+        DefaultGitCommandRunner.run_git_command(project_path, args)
+    }
+
+    fn run_remote_git_command(
+        &self,
+        project_path: &Path,
+        args: &[&str],
+        env: &[(&str, &str)],
+        cancel: Option<&CancelToken>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.0.lock().unwrap().push(args.join(" "));
+        DefaultGitCommandRunner.run_remote_git_command(project_path, args, env, cancel)
+    }
+}
+
+#[tokio::test]
+async fn test_sync_repos_async_with_runner() {
+    // Test that the local git commands of an async sync go through the given
+    // runner, and fetches run with tokio::process
+    let dir = tempdir().unwrap();
+    let upstream = dir.path().join("remote/platform/core.git");
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "one"]);
+    let file_path = dir.path().join("test_manifest.xml");
+    std::fs::write(
+        &file_path,
+        format!(
+            r#"<manifest>
+        <remote name="origin" fetch="file://{}"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core"/>
+    </manifest>"#,
+            dir.path().join("remote").display()
+        ),
+    )
+    .unwrap();
+    let options = SyncOptions {
+        detach: true,
+        quiet: true,
+        retry: RetryPolicy::none(),
+        no_tags: true,
+        ..Default::default()
+    };
+
+    let runner = Arc::new(RecordingRunner(Mutex::new(Vec::new())));
+    let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+    sync_repos_async_with_runner(
+        file_path.to_str().unwrap(),
+        None,
+        options,
+        dir.path().join("target").to_str().unwrap(),
+        recorder,
+        runner.clone(),
+    )
+    .await
+    .unwrap();
+
+    let commands = runner.0.lock().unwrap();
+    assert_eq!(
+        *commands,
+        [
+            "init".to_string(),
+            format!("remote add origin file://{}", upstream.display()),
+            "checkout FETCH_HEAD".to_string(),
+        ]
+    );
+}
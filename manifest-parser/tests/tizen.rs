@@ -0,0 +1,65 @@
+use manifest_parser::Manifest;
+
+const SNAPSHOT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <project name="platform/core/system/dlog" path="platform/core/system/dlog" revision="3f2c9e1a7b4d5c6e8f901234567890abcdef1234" />
+  <project name="platform/upstream/glib2" path="platform/upstream/glib2" revision="0123456789abcdef0123456789abcdef01234567" />
+</manifest>
+"#;
+
+#[test]
+fn test_tizen_snapshot_round_trip() {
+    // Test reading a snapshot manifest and writing it back unchanged
+    let manifest =
+        Manifest::from_tizen_snapshot_str(SNAPSHOT, "https://review.tizen.org/git").unwrap();
+    assert_eq!(manifest.remotes[0].name, "tizen");
+    assert_eq!(manifest.remotes[0].fetch, "https://review.tizen.org/git");
+    let default = manifest.default.as_ref().unwrap();
+    assert_eq!(default.remote.as_deref(), Some("tizen"));
+    assert_eq!(manifest.projects.len(), 2);
+
+    let xml = manifest.to_tizen_snapshot().unwrap();
+    assert_eq!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <project name="platform/core/system/dlog" path="platform/core/system/dlog" revision="3f2c9e1a7b4d5c6e8f901234567890abcdef1234"/>
+  <project name="platform/upstream/glib2" path="platform/upstream/glib2" revision="0123456789abcdef0123456789abcdef01234567"/>
+</manifest>
+"#
+    );
+}
+
+#[test]
+fn test_tizen_snapshot_requires_pinned_projects() {
+    // Test that projects not pinned to a commit are rejected
+    let xml = r#"<manifest><project name="platform/app" revision="tizen"/></manifest>"#;
+    let err = Manifest::from_tizen_snapshot_str(xml, "https://review.tizen.org/git").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Project 'platform/app' is at 'tizen', not pinned to a commit"
+    );
+
+    let mut manifest = Manifest::from_tizen_snapshot_str(SNAPSHOT, "").unwrap();
+    manifest.projects[1].revision = None;
+    let err = manifest.to_tizen_snapshot().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Project 'platform/upstream/glib2' is not pinned to a commit"
+    );
+}
+
+#[test]
+fn test_tizen_snapshot_default_without_remote() {
+    // Test that the added remote becomes the remote of a default that names none
+    let xml = SNAPSHOT.replace(
+        "<manifest>\n",
+        "<manifest>\n  <default revision=\"tizen\" sync-j=\"4\"/>\n",
+    );
+    let manifest = Manifest::from_tizen_snapshot_str(&xml, "https://review.tizen.org/git").unwrap();
+
+    let default = manifest.default.as_ref().unwrap();
+    assert_eq!(default.remote.as_deref(), Some("tizen"));
+    assert_eq!(default.revision.as_deref(), Some("tizen"));
+    assert_eq!(default.sync_j, Some(4));
+}
@@ -0,0 +1,162 @@
+#![cfg(feature = "sync")]
+
+mod common;
+
+use common::git;
+use manifest_parser::subset::Selection;
+use manifest_parser::upload::{review_url, upload, UploadOptions};
+use tempfile::tempdir;
+
+#[test]
+fn test_review_url() {
+    // Test the push URLs of review servers with and without a scheme
+    assert_eq!(
+        review_url("review.example.com", "platform/core"),
+        "ssh://review.example.com:29418/platform/core"
+    );
+    assert_eq!(
+        review_url("https://review.example.com/", "platform/core"),
+        "https://review.example.com/platform/core"
+    );
+}
+
+#[test]
+fn test_upload() {
+    // Test listing and pushing local commits for review
+    let dir = tempdir().unwrap();
+    let upstream = dir.path().join("upstream");
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "one"]);
+    let review = dir.path().join("review/platform/core");
+    std::fs::create_dir_all(&review).unwrap();
+    git(&review, &["init", "-q", "--bare"]);
+
+    let target_dir = dir.path().join("target");
+    std::fs::create_dir_all(&target_dir).unwrap();
+    for path in ["core", "app", "docs"] {
+        git(
+            &target_dir,
+            &["clone", "-q", upstream.to_str().unwrap(), path],
+        );
+    }
+    let core = target_dir.join("core");
+    git(&core, &["commit", "-q", "--allow-empty", "-m", "fix"]);
+    git(&core, &["commit", "-q", "--allow-empty", "-m", "feature"]);
+    git(
+        &target_dir.join("app"),
+        &["commit", "-q", "--allow-empty", "-m", "app"],
+    );
+
+    let manifest_path = dir.path().join("manifest.xml");
+    std::fs::write(
+        &manifest_path,
+        format!(
+            r#"<manifest>
+        <remote name="origin" fetch="https://example.com" review="file://{}"/>
+        <remote name="mirror" fetch="https://mirror.example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core"/>
+        <project name="platform/app" path="app" remote="mirror"/>
+        <project name="platform/docs" path="docs"/>
+    </manifest>"#,
+            dir.path().join("review").display()
+        ),
+    )
+    .unwrap();
+    let run = |options: &UploadOptions| {
+        upload(
+            manifest_path.to_str().unwrap(),
+            target_dir.to_str().unwrap(),
+            options,
+        )
+        .unwrap()
+    };
+
+    let options = UploadOptions {
+        reviewers: vec!["alice@example.com".to_string()],
+        cc: vec!["bob@example.com".to_string()],
+        topic: Some("fixes".to_string()),
+        dry_run: true,
+        ..UploadOptions::default()
+    };
+    let uploads = run(&options);
+    assert_eq!(uploads.len(), 2);
+    assert_eq!(uploads[0].name, "platform/core");
+    assert_eq!(uploads[0].commits.len(), 2);
+    assert!(uploads[0].commits[0].ends_with(" feature"));
+    assert_eq!(
+        uploads[0].refspec,
+        "HEAD:refs/for/main%r=alice@example.com,cc=bob@example.com,topic=fixes"
+    );
+    assert!(!uploads[0].uploaded);
+    assert_eq!(
+        uploads[1].error.as_deref(),
+        Some("Remote 'mirror' of project 'platform/app' has no review server")
+    );
+    assert!(git(&review, &["for-each-ref"]).is_empty());
+
+    let uploads = run(&UploadOptions::default());
+    assert!(uploads[0].uploaded);
+    assert_eq!(uploads[0].error, None);
+    assert_eq!(
+        git(&review, &["rev-parse", "refs/for/main"]),
+        git(&core, &["rev-parse", "HEAD"])
+    );
+}
+
+#[test]
+fn test_upload_pre_upload_hook() {
+    // Test that a failing pre-upload hook stops the upload
+    let dir = tempdir().unwrap();
+    let upstream = dir.path().join("upstream");
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "one"]);
+    let target_dir = dir.path().join("target");
+    std::fs::create_dir_all(target_dir.join("hooks/.git")).unwrap();
+    std::fs::write(
+        target_dir.join("hooks/pre-upload.py"),
+        "def main(project_list, **kwargs):\n    raise SystemExit('rejected ' + project_list[0])\n",
+    )
+    .unwrap();
+    git(
+        &target_dir,
+        &["clone", "-q", upstream.to_str().unwrap(), "core"],
+    );
+    git(
+        &target_dir.join("core"),
+        &["commit", "-q", "--allow-empty", "-m", "fix"],
+    );
+    let manifest_path = dir.path().join("manifest.xml");
+    std::fs::write(
+        &manifest_path,
+        r#"<manifest>
+        <remote name="origin" fetch="https://example.com" review="review.example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="tools/repohooks" path="hooks"/>
+        <project name="platform/core" path="core"/>
+        <repo-hooks in-project="tools/repohooks" enabled-list="pre-upload"/>
+    </manifest>"#,
+    )
+    .unwrap();
+    let mut options = UploadOptions {
+        selection: Some(Selection::Projects(vec!["core".to_string()])),
+        dry_run: true,
+        ..UploadOptions::default()
+    };
+    let run = |options: &UploadOptions| {
+        upload(
+            manifest_path.to_str().unwrap(),
+            target_dir.to_str().unwrap(),
+            options,
+        )
+    };
+
+    assert_eq!(
+        run(&options).unwrap_err().to_string(),
+        "Hook 'pre-upload' failed: rejected platform/core"
+    );
+    options.no_verify = true;
+    assert_eq!(run(&options).unwrap().len(), 1);
+}
@@ -0,0 +1,84 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::urls::resolve_url;
+use manifest_parser::Manifest;
+
+#[test]
+fn test_resolve_url() {
+    // Test resolving relative URLs against the manifest repository URL
+    let base = "https://android.googlesource.com/platform/manifest";
+    assert_eq!(resolve_url(base, ".."), "https://android.googlesource.com");
+    assert_eq!(
+        resolve_url(base, "../.."),
+        "https://android.googlesource.com"
+    );
+    assert_eq!(
+        resolve_url(base, "."),
+        "https://android.googlesource.com/platform"
+    );
+    assert_eq!(
+        resolve_url(base, "./"),
+        "https://android.googlesource.com/platform"
+    );
+    assert_eq!(
+        resolve_url(&format!("{}/", base), "../mirror/"),
+        "https://android.googlesource.com/mirror"
+    );
+    assert_eq!(
+        resolve_url(base, "tools"),
+        "https://android.googlesource.com/platform/tools"
+    );
+
+    assert_eq!(
+        resolve_url("ssh://git@host:29418/org/manifest", ".."),
+        "ssh://git@host:29418"
+    );
+    assert_eq!(
+        resolve_url("git@host:org/sub/manifest", ".."),
+        "git@host:org"
+    );
+    assert_eq!(
+        resolve_url("/srv/mirror/platform/manifest", ".."),
+        "/srv/mirror"
+    );
+
+    assert_eq!(
+        resolve_url(base, "https://github.com/org"),
+        "https://github.com/org"
+    );
+    assert_eq!(
+        resolve_url(base, "git@github.com:org"),
+        "git@github.com:org"
+    );
+    assert_eq!(resolve_url(base, "/srv/mirror"), "/srv/mirror");
+}
+
+#[test]
+fn test_resolve_fetch_urls() {
+    // Test that relative fetch URLs of remotes are made absolute
+    let mut manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="aosp" fetch=".."/>
+        <remote name="github" fetch="https://github.com/org"/>
+        <default remote="aosp" revision="main"/>
+        <project name="platform/build"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+
+    manifest.resolve_fetch_urls("https://android.googlesource.com/platform/manifest");
+
+    assert_eq!(
+        manifest.remotes[0].fetch,
+        "https://android.googlesource.com"
+    );
+    assert_eq!(manifest.remotes[1].fetch, "https://github.com/org");
+    assert_eq!(
+        manifest.resolve().unwrap()[0].url,
+        "https://android.googlesource.com/platform/build.git"
+    );
+}
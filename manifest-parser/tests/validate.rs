@@ -0,0 +1,87 @@
+use manifest_parser::include::NoIncludes;
+use manifest_parser::validate::check_relative_path;
+use manifest_parser::Manifest;
+
+#[test]
+fn test_check_relative_path() {
+    // Test which project names and paths are accepted
+    assert!(check_relative_path("platform/core").is_ok());
+    assert!(check_relative_path("core/").is_ok());
+    assert!(check_relative_path("tools/.config").is_ok());
+
+    assert_eq!(check_relative_path("").unwrap_err(), "path is empty");
+    assert_eq!(
+        check_relative_path("/srv/core").unwrap_err(),
+        "path is absolute"
+    );
+    assert_eq!(
+        check_relative_path("C:\\core").unwrap_err(),
+        "path is absolute"
+    );
+    assert_eq!(
+        check_relative_path("platform/../../etc").unwrap_err(),
+        "path contains a '..' component"
+    );
+    assert_eq!(
+        check_relative_path("./core").unwrap_err(),
+        "path contains a '.' component"
+    );
+}
+
+#[test]
+fn test_validate_paths() {
+    // Test that projects escaping the workspace are rejected
+    let parse = |xml: &str| Manifest::from_str(xml, None, None, &NoIncludes).unwrap();
+
+    let manifest = parse(
+        r#"
+    <manifest>
+        <project name="platform/core" path="core">
+            <copyfile src="Makefile" dest="Makefile"/>
+        </project>
+    </manifest>
+    "#,
+    );
+    assert!(manifest.validate_paths().is_ok());
+
+    let manifest = parse(r#"<manifest><project name="../core"/></manifest>"#);
+    assert_eq!(
+        manifest.validate_paths().unwrap_err().to_string(),
+        "Invalid name of project '../core': path contains a '..' component"
+    );
+
+    let manifest =
+        parse(r#"<manifest><project name="platform/core" path="/tmp/core"/></manifest>"#);
+    assert_eq!(
+        manifest.validate_paths().unwrap_err().to_string(),
+        "Invalid path '/tmp/core' of project 'platform/core': path is absolute"
+    );
+
+    let manifest = parse(
+        r#"
+    <manifest>
+        <project name="platform/core">
+            <linkfile src="tools" dest="../tools"/>
+        </project>
+    </manifest>
+    "#,
+    );
+    assert_eq!(
+        manifest.validate_paths().unwrap_err().to_string(),
+        "Invalid linkfile dest '../tools' of project 'platform/core': path contains a '..' component"
+    );
+
+    let manifest = parse(
+        r#"
+    <manifest>
+        <project name="platform/core">
+            <copyfile src="../../etc/passwd" dest="passwd"/>
+        </project>
+    </manifest>
+    "#,
+    );
+    assert_eq!(
+        manifest.validate_paths().unwrap_err().to_string(),
+        "Invalid copyfile src '../../etc/passwd' of project 'platform/core': path contains a '..' component"
+    );
+}
@@ -0,0 +1,47 @@
+use manifest_parser::view::ManifestView;
+use std::borrow::Cow;
+
+#[test]
+fn test_manifest_view() {
+    // Test parsing a manifest into views borrowing from the XML
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+    <manifest>
+        <remote name="origin" fetch="https://example.com" review="review.example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="core" groups="base,pdk">
+            <copyfile src="a" dest="b"/>
+        </project>
+        <project name="platform/app" revision="refs/tags/v1&amp;2"/>
+    </manifest>
+    "#;
+    let view = ManifestView::parse(xml).unwrap();
+
+    assert_eq!(view.remotes.len(), 1);
+    assert_eq!(view.remotes[0].name, "origin");
+    assert_eq!(
+        view.remotes[0].review.as_deref(),
+        Some("review.example.com")
+    );
+    let default = view.default.as_ref().unwrap();
+    assert_eq!(default.revision.as_deref(), Some("main"));
+
+    assert_eq!(view.projects.len(), 2);
+    let core = view.find_project("platform/core").unwrap();
+    assert!(matches!(core.name, Cow::Borrowed(_)));
+    assert_eq!(core.path_or_name(), "core");
+    assert_eq!(core.groups.as_deref(), Some("base,pdk"));
+    let app = view.find_project("platform/app").unwrap();
+    assert_eq!(app.path_or_name(), "platform/app");
+    assert!(matches!(app.revision, Some(Cow::Owned(_))));
+    assert_eq!(app.revision.as_deref(), Some("refs/tags/v1&2"));
+}
+
+#[test]
+fn test_manifest_view_requires_names() {
+    // Test that a project view needs a name
+    let err = ManifestView::parse(r#"<manifest><project path="core"/></manifest>"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Missing required attribute 'name' in project element"
+    );
+}
@@ -0,0 +1,307 @@
+use manifest_parser::groups::format_groups;
+use manifest_parser::include::NoIncludes;
+use manifest_parser::Manifest;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_to_west_yaml() {
+    // Test converting a manifest into a west manifest
+    let manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://git.example.com/"/>
+        <remote name="zephyr" fetch="https://github.com/zephyrproject-rtos" revision="v3.7.0"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="src/core" groups="base,notdefault"/>
+        <project name="zephyr" remote="zephyr" clone-depth="1"/>
+        <project name="tools" revision="1234567"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+
+    assert_eq!(
+        manifest.to_west_yaml().unwrap(),
+        "manifest:\n  \
+         defaults:\n    \
+         remote: origin\n    \
+         revision: main\n\
+         \n  \
+         remotes:\n    \
+         - name: origin\n      \
+         url-base: https://git.example.com\n    \
+         - name: zephyr\n      \
+         url-base: https://github.com/zephyrproject-rtos\n\
+         \n  \
+         projects:\n    \
+         - name: src-core\n      \
+         repo-path: platform/core\n      \
+         path: src/core\n      \
+         groups: [base, notdefault]\n    \
+         - name: zephyr\n      \
+         repo-path: zephyr\n      \
+         remote: zephyr\n      \
+         revision: v3.7.0\n      \
+         path: zephyr\n      \
+         clone-depth: 1\n    \
+         - name: tools\n      \
+         repo-path: tools\n      \
+         revision: '1234567'\n      \
+         path: tools\n\
+         \n  \
+         group-filter: [-notdefault]\n"
+    );
+}
+
+#[test]
+fn test_to_west_yaml_name_collisions() {
+    // Test that paths mapping to the same west name get distinct names
+    let manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://git.example.com/"/>
+        <default remote="origin" revision="main"/>
+        <project name="one" path="a/b-c"/>
+        <project name="two" path="a-b/c"/>
+        <project name="three" path="a-b-c-2"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+
+    let yaml = manifest.to_west_yaml().unwrap();
+    let names: Vec<&str> = yaml
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("- name: "))
+        .skip(1)
+        .collect();
+    assert_eq!(names, vec!["a-b-c", "a-b-c-3", "a-b-c-2"]);
+}
+
+#[test]
+fn test_to_west_yaml_relative_fetch() {
+    // Test that remotes relative to the manifest repository are rejected
+    let manifest = Manifest::from_str(
+        r#"<manifest><remote name="origin" fetch=".."/></manifest>"#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+
+    let err = manifest.to_west_yaml().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Remote 'origin' has a relative fetch URL '..', which west cannot express"
+    );
+}
+
+#[test]
+fn test_from_west_file() {
+    // Test reading a west manifest with imports from the workspace
+    let dir = tempdir().unwrap();
+    let manifest_dir = dir.path().join("zephyr");
+    let hal_dir = dir.path().join("modules/hal/nordic");
+    fs::create_dir_all(&manifest_dir).unwrap();
+    fs::create_dir_all(&hal_dir).unwrap();
+    fs::create_dir_all(manifest_dir.join("submanifests")).unwrap();
+
+    fs::write(
+        manifest_dir.join("west.yml"),
+        r#"
+manifest:
+  defaults:
+    remote: upstream
+  remotes:
+    - name: upstream
+      url-base: https://github.com/zephyrproject-rtos
+  group-filter: [-optional]
+  projects:
+    - name: hal_nordic
+      revision: 2.7.0
+      path: modules/hal/nordic
+      groups: [hal]
+      import:
+        name-allowlist: [nrfx]
+    - name: mcuboot
+      url: https://github.com/mcu-tools/mcuboot.git
+      revision: 1234567
+      clone-depth: 1
+    - name: tinycrypt
+      repo-path: tinycrypt-lib
+      path: modules/crypto/tinycrypt
+      groups:
+        - optional
+  self:
+    path: zephyr
+    import: submanifests
+"#,
+    )
+    .unwrap();
+    fs::write(
+        manifest_dir.join("submanifests/extra.yml"),
+        r#"
+manifest:
+  projects:
+    - name: mcuboot
+      remote: upstream
+      revision: main
+    - name: lvgl
+      remote: upstream
+"#,
+    )
+    .unwrap();
+    fs::write(
+        hal_dir.join("west.yml"),
+        r#"
+manifest:
+  remotes:
+    - name: nrf
+      url-base: https://github.com/nrfconnect
+  defaults:
+    remote: nrf
+    revision: v2.7-branch
+  projects:
+    - name: nrfx
+    - name: unused
+"#,
+    )
+    .unwrap();
+
+    let manifest =
+        Manifest::from_west_file(manifest_dir.join("west.yml").to_str().unwrap()).unwrap();
+
+    let projects: Vec<_> = manifest
+        .projects
+        .iter()
+        .map(|p| {
+            (
+                p.name.as_str(),
+                p.path.as_deref(),
+                p.remote.as_deref().unwrap(),
+                p.revision.as_deref().unwrap(),
+                &p.groups,
+            )
+        })
+        .collect();
+    let projects: Vec<_> = projects
+        .into_iter()
+        .map(|(a, b, c, d, groups)| (a, b, c, d, format_groups(groups)))
+        .collect();
+    let projects: Vec<_> = projects
+        .iter()
+        .map(|(a, b, c, d, groups)| {
+            (
+                *a,
+                *b,
+                *c,
+                *d,
+                (!groups.is_empty()).then_some(groups.as_str()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        projects,
+        vec![
+            (
+                "hal_nordic",
+                Some("modules/hal/nordic"),
+                "upstream",
+                "2.7.0",
+                Some("hal")
+            ),
+            ("mcuboot", None, "mcuboot", "1234567", None),
+            (
+                "tinycrypt-lib",
+                Some("modules/crypto/tinycrypt"),
+                "upstream",
+                "master",
+                Some("optional,notdefault")
+            ),
+            ("lvgl", None, "upstream", "master", None),
+            ("nrfx", None, "nrf", "v2.7-branch", None),
+        ]
+    );
+    assert_eq!(manifest.projects[1].clone_depth, Some(1));
+
+    let remotes: Vec<_> = manifest
+        .remotes
+        .iter()
+        .map(|r| (r.name.as_str(), r.fetch.as_str()))
+        .collect();
+    assert_eq!(
+        remotes,
+        vec![
+            ("upstream", "https://github.com/zephyrproject-rtos"),
+            ("mcuboot", "https://github.com/mcu-tools"),
+            ("nrf", "https://github.com/nrfconnect"),
+        ]
+    );
+    assert_eq!(
+        manifest.default.unwrap().remote.as_deref(),
+        Some("upstream")
+    );
+}
+
+#[test]
+fn test_from_west_str_round_trip() {
+    // Test that an exported west manifest reads back to the same projects
+    let manifest = Manifest::from_str(
+        r#"
+    <manifest>
+        <remote name="origin" fetch="https://git.example.com"/>
+        <default remote="origin" revision="main"/>
+        <project name="platform/core" path="src/core" groups="base"/>
+        <project name="tools" revision="v1.0"/>
+    </manifest>
+    "#,
+        None,
+        None,
+        &NoIncludes,
+    )
+    .unwrap();
+
+    let west = Manifest::from_west_str(&manifest.to_west_yaml().unwrap(), None).unwrap();
+
+    let projects: Vec<_> = west
+        .projects
+        .iter()
+        .map(|p| {
+            (
+                p.name.as_str(),
+                p.path.as_deref(),
+                p.revision.as_deref().unwrap(),
+                &p.groups,
+            )
+        })
+        .collect();
+    let projects: Vec<_> = projects
+        .into_iter()
+        .map(|(a, b, c, groups)| (a, b, c, format_groups(groups)))
+        .collect();
+    let projects: Vec<_> = projects
+        .iter()
+        .map(|(a, b, c, groups)| (*a, *b, *c, (!groups.is_empty()).then_some(groups.as_str())))
+        .collect();
+    assert_eq!(
+        projects,
+        vec![
+            ("platform/core", Some("src/core"), "main", Some("base")),
+            ("tools", None, "v1.0", None),
+        ]
+    );
+
+    let err =
+        Manifest::from_west_str("manifest:\n  self:\n    import: west.d\n", None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Cannot import from 'self' without the manifest directory"
+    );
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::git;
+use manifest_parser::Manifest;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+// Creates a repository with one commit at `dir`, returning that commit.
+fn repository(dir: &Path, origin: Option<&str>) -> String {
+    fs::create_dir_all(dir).unwrap();
+    git(dir, &["init", "-q", "-b", "main"]);
+    git(dir, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+    if let Some(origin) = origin {
+        git(dir, &["remote", "add", "origin", origin]);
+    }
+    git(dir, &["rev-parse", "HEAD"])
+}
+
+#[test]
+fn test_from_workspace() {
+    // Test describing the repositories of a directory tree as a manifest
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    let core = repository(
+        &root.join("platform/core"),
+        Some("https://review.tizen.org/git/platform/core.git"),
+    );
+    let app = repository(&root.join("apps/app"), Some("https://github.com/org/app"));
+    repository(&root.join("scratch"), None);
+    fs::create_dir_all(root.join("docs")).unwrap();
+
+    let manifest = Manifest::from_workspace(root).unwrap();
+    let remotes: Vec<(&str, &str)> = manifest
+        .remotes
+        .iter()
+        .map(|r| (r.name.as_str(), r.fetch.as_str()))
+        .collect();
+    assert_eq!(
+        remotes,
+        [
+            ("github.com", "https://github.com/org"),
+            ("review.tizen.org", "https://review.tizen.org/git/platform"),
+        ]
+    );
+    let default = manifest.default.as_ref().unwrap();
+    assert_eq!(default.remote.as_deref(), Some("github.com"));
+
+    assert_eq!(manifest.projects.len(), 2);
+    let project = &manifest.projects[0];
+    assert_eq!(project.name, "app");
+    assert_eq!(project.path.as_deref(), Some("apps/app"));
+    assert_eq!(project.remote, None);
+    assert_eq!(project.revision.as_deref(), Some(app.as_str()));
+    let project = &manifest.projects[1];
+    assert_eq!(project.name, "core");
+    assert_eq!(project.path.as_deref(), Some("platform/core"));
+    assert_eq!(project.remote.as_deref(), Some("review.tizen.org"));
+    assert_eq!(project.revision.as_deref(), Some(core.as_str()));
+}
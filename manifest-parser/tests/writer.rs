@@ -0,0 +1,164 @@
+use manifest_parser::writer::WriteOptions;
+use manifest_parser::{CopyFile, Manifest, ParseOptions, Project};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_to_xml_round_trip() {
+    // Test that a written manifest parses back to the same manifest
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    fs::write(
+        &file_path,
+        r#"
+    <manifest>
+        <notice>Builds need "gbs" &amp; a fast disk</notice>
+        <remote name="origin" fetch="https://example.com" review="https://review.example.com"/>
+        <default remote="origin" revision="main" sync-j="4"/>
+        <project name="platform/core" path="core" groups="base,notdefault" sync-s="true">
+            <copyfile src="Makefile" dest="Makefile"/>
+            <linkfile src="tools" dest="tools"/>
+            <annotation name="owner" value="kernel" keep="false"/>
+        </project>
+        <project name="platform/app"/>
+        <extend-project name="platform/app" revision="develop"/>
+        <repo-hooks in-project="platform/core" enabled-list="pre-upload"/>
+    </manifest>
+    "#,
+    )
+    .unwrap();
+    let manifest = Manifest::from_file(file_path.to_str().unwrap(), None, None).unwrap();
+
+    let xml = manifest.to_xml();
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest>\n"));
+    assert!(xml.contains("  <project name=\"platform/app\"/>\n"));
+    assert!(xml.contains("<notice>Builds need &quot;gbs&quot; &amp; a fast disk</notice>"));
+
+    let written_path = dir.path().join("written.xml");
+    manifest
+        .write_to_file(written_path.to_str().unwrap())
+        .unwrap();
+    let written = Manifest::from_file(written_path.to_str().unwrap(), None, None).unwrap();
+
+    assert_eq!(written.to_xml(), xml);
+    assert_eq!(
+        written.notice.as_deref(),
+        Some(r#"Builds need "gbs" & a fast disk"#)
+    );
+    assert_eq!(written.default.unwrap().sync_j, Some(4));
+    let core = &written.projects[0];
+    assert_eq!(core.sync_s, Some(true));
+    assert_eq!(core.copyfiles[0].dest, "Makefile");
+    assert_eq!(core.linkfiles[0].src, "tools");
+    assert!(!core.annotations[0].keep);
+    assert_eq!(
+        written.extend_projects[0].revision.as_deref(),
+        Some("develop")
+    );
+}
+
+#[test]
+fn test_to_xml_generated_manifest() {
+    // Test writing a manifest built in memory
+    let mut manifest = Manifest::new();
+    manifest.projects.push(Project {
+        name: "platform/core".to_string(),
+        path: None,
+        remote: None,
+        revision: Some("3f2c9e1".into()),
+        dest_branch: None,
+        groups: Vec::new(),
+        sync_c: None,
+        sync_s: None,
+        sync_tags: None,
+        upstream: None,
+        clone_depth: None,
+        force_path: None,
+        copyfiles: Vec::new(),
+        linkfiles: Vec::new(),
+        annotations: Vec::new(),
+        source: None,
+    });
+
+    assert_eq!(
+        manifest.to_xml(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <manifest>\n  \
+         <project name=\"platform/core\" revision=\"3f2c9e1\"/>\n\
+         </manifest>\n"
+    );
+}
+
+#[test]
+fn test_to_xml_with_options() {
+    // Test indentation and attribute wrapping of generated manifests
+    let mut manifest = Manifest::new();
+    manifest.projects.push(Project {
+        name: "platform/core".to_string(),
+        path: Some("core".to_string()),
+        remote: None,
+        revision: Some("main".into()),
+        dest_branch: None,
+        groups: Vec::new(),
+        sync_c: None,
+        sync_s: None,
+        sync_tags: None,
+        upstream: None,
+        clone_depth: None,
+        force_path: None,
+        copyfiles: vec![CopyFile {
+            src: "Makefile".to_string(),
+            dest: "Makefile".to_string(),
+        }],
+        linkfiles: Vec::new(),
+        annotations: Vec::new(),
+        source: None,
+    });
+
+    let options = WriteOptions::new().indent(4).wrap_attributes(60);
+    assert_eq!(
+        manifest.to_xml_with(&options),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <manifest>\n    \
+         <project name=\"platform/core\"\n             \
+         path=\"core\"\n             \
+         revision=\"main\">\n        \
+         <copyfile src=\"Makefile\" dest=\"Makefile\"/>\n    \
+         </project>\n\
+         </manifest>\n"
+    );
+}
+
+#[test]
+fn test_to_xml_keeps_comments() {
+    // Test that comments kept while parsing are written before their elements
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("default.xml");
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- SPDX-License-Identifier: Apache-2.0 -->
+<manifest>
+  <remote name="origin" fetch="https://example.com"/>
+  <!-- Owned by the core team -->
+  <project name="platform/core"/>
+  <project name="platform/app"/>
+  <!-- end of projects -->
+</manifest>
+"#;
+    fs::write(&file_path, xml).unwrap();
+    let file_path = file_path.to_str().unwrap();
+
+    let options = ParseOptions::new().comments(true);
+    let mut manifest = Manifest::from_file_with(file_path, None, None, &options).unwrap();
+    manifest.default = None;
+    assert_eq!(manifest.comments.len(), 3);
+    assert_eq!(manifest.to_xml(), xml);
+
+    manifest.projects.swap(0, 1);
+    let reordered = manifest.to_xml();
+    assert!(reordered.contains(
+        "  <project name=\"platform/app\"/>\n  <!-- Owned by the core team -->\n  <project name=\"platform/core\"/>\n"
+    ));
+
+    let manifest = Manifest::from_file(file_path, None, None).unwrap();
+    assert!(manifest.comments.is_empty());
+}
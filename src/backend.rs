@@ -0,0 +1,270 @@
+//! Execution backends for gbs commands.
+//!
+//! A backend turns a composed gbs invocation into a [`Command`]. The native
+//! backend runs `gbs` from the host, while [`ContainerBackend`] runs it
+//! inside a Docker or Podman image so hosts without gbs can still build.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A fully composed invocation, independent of where it runs.
+#[derive(Debug, Clone, Default)]
+pub struct CommandSpec {
+    /// The program to run, e.g. `gbs`.
+    pub program: String,
+    /// Arguments: those of gbs itself, then the gbs subcommand and its own.
+    pub args: Vec<OsString>,
+    /// Host paths the command needs access to (git tree, build root, ...).
+    pub mounts: Vec<PathBuf>,
+    /// Working directory of the command; the caller's when `None`.
+    pub current_dir: Option<PathBuf>,
+}
+
+/// Prepares the process that executes a [`CommandSpec`].
+pub trait ExecutionBackend {
+    fn command(&self, spec: &CommandSpec) -> Command;
+}
+
+impl<B: ExecutionBackend + ?Sized> ExecutionBackend for &B {
+    fn command(&self, spec: &CommandSpec) -> Command {
+        (**self).command(spec)
+    }
+}
+
+/// Runs the command directly on the host.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeBackend;
+
+impl ExecutionBackend for NativeBackend {
+    fn command(&self, spec: &CommandSpec) -> Command {
+        let mut command = Command::new(&spec.program);
+        command.args(&spec.args);
+        if let Some(dir) = &spec.current_dir {
+            command.current_dir(dir);
+        }
+        command
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContainerEngine {
+    #[default]
+    Docker,
+    Podman,
+}
+
+impl ContainerEngine {
+    pub fn program(&self) -> &'static str {
+        match self {
+            ContainerEngine::Docker => "docker",
+            ContainerEngine::Podman => "podman",
+        }
+    }
+}
+
+/// Runs the command inside a container image that provides gbs.
+///
+/// Every mount of the [`CommandSpec`] is bind-mounted at the same path
+/// inside the container, so paths in the arguments stay valid, and `HOME`
+/// is the host's, so `~/GBS-ROOT` and `~/.gbs.conf` are the mounted ones.
+/// The container runs privileged by default because gbs needs to set up a
+/// chroot in the build root.
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::backend::{ContainerBackend, ContainerEngine};
+///
+/// let backend = ContainerBackend::new("tizen/gbs:latest").engine(ContainerEngine::Podman);
+/// options.execute_with(&backend)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContainerBackend {
+    engine: ContainerEngine,
+    image: String,
+    privileged: bool,
+    extra_args: Vec<String>,
+}
+
+impl ContainerBackend {
+    pub fn new(image: &str) -> Self {
+        ContainerBackend {
+            engine: ContainerEngine::default(),
+            image: image.to_string(),
+            privileged: true,
+            extra_args: Vec::new(),
+        }
+    }
+
+    pub fn engine(mut self, engine: ContainerEngine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    pub fn privileged(mut self, privileged: bool) -> Self {
+        self.privileged = privileged;
+        self
+    }
+
+    /// Extra arguments passed to `docker run`/`podman run` before the image name.
+    pub fn extra_arg(mut self, arg: &str) -> Self {
+        self.extra_args.push(arg.to_string());
+        self
+    }
+}
+
+impl ExecutionBackend for ContainerBackend {
+    fn command(&self, spec: &CommandSpec) -> Command {
+        let mut command = Command::new(self.engine.program());
+        // An init process passes the signals of an abort on to gbs, which
+        // would ignore them as process 1.
+        command.args(["run", "--rm", "-i", "--init"]);
+        if self.privileged {
+            command.arg("--privileged");
+        }
+        let workdir = match &spec.current_dir {
+            Some(dir) => Some(absolute_path(dir)),
+            None => container_workdir(&spec.mounts),
+        };
+        let mut mounts = spec.mounts.clone();
+        if let Some(workdir) = &workdir {
+            if !mounts.iter().any(|m| workdir.starts_with(m)) {
+                mounts.push(workdir.clone());
+            }
+        }
+        for mount in &mounts {
+            command.arg("--mount").arg(bind_mount(mount));
+        }
+        if let Some(home) = std::env::var_os("HOME") {
+            let mut env = OsString::from("HOME=");
+            env.push(home);
+            command.arg("--env").arg(env);
+        }
+        if let Some(workdir) = workdir {
+            command.arg("-w").arg(workdir);
+        }
+        command.args(&self.extra_args);
+        command.arg(&self.image);
+        command.arg(&spec.program);
+        command.args(&spec.args);
+        command
+    }
+}
+
+// The `--mount` option mounting `path` at the same path. Its value is
+// parsed as CSV, so fields with commas or quotes are quoted.
+fn bind_mount(path: &Path) -> String {
+    let field = |field: String| {
+        if field.contains([',', '"']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field
+        }
+    };
+    let path = path.to_string_lossy();
+    format!(
+        "type=bind,{},{}",
+        field(format!("source={}", path)),
+        field(format!("target={}", path))
+    )
+}
+
+// Keeps the host working directory when it is visible inside the container,
+// so relative paths in the arguments resolve the same way.
+fn container_workdir(mounts: &[PathBuf]) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok();
+    match cwd {
+        Some(cwd) if mounts.iter().any(|m| cwd.starts_with(m)) => Some(cwd),
+        _ => mounts.first().cloned(),
+    }
+}
+
+/// Makes `path` absolute relative to the current directory.
+pub(crate) fn absolute_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> CommandSpec {
+        CommandSpec {
+            program: "gbs".to_string(),
+            args: vec!["build".into(), "-A".into(), "armv7l".into()],
+            mounts: vec![PathBuf::from("/src/pkg"), PathBuf::from("/data/GBS-ROOT")],
+            current_dir: None,
+        }
+    }
+
+    #[test]
+    fn test_native_backend() {
+        let command = NativeBackend.command(&spec());
+        assert_eq!(command.get_program(), "gbs");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["build", "-A", "armv7l"]
+        );
+    }
+
+    #[test]
+    fn test_container_backend() {
+        let backend = ContainerBackend::new("tizen/gbs:latest")
+            .engine(ContainerEngine::Podman)
+            .extra_arg("--network=host");
+        let command = backend.command(&spec());
+
+        assert_eq!(command.get_program(), "podman");
+        let args: Vec<_> = command
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(&args[..5], &["run", "--rm", "-i", "--init", "--privileged"]);
+        assert!(args.contains(&"type=bind,source=/src/pkg,target=/src/pkg".to_string()));
+        assert!(args.contains(&"type=bind,source=/data/GBS-ROOT,target=/data/GBS-ROOT".to_string()));
+        let env = args.iter().position(|a| a == "--env").unwrap();
+        assert!(args[env + 1].starts_with("HOME="));
+        let image = args.iter().position(|a| a == "tizen/gbs:latest").unwrap();
+        assert_eq!(args[image - 1], "--network=host");
+        assert_eq!(&args[image + 1..], &["gbs", "build", "-A", "armv7l"]);
+    }
+
+    #[test]
+    fn test_bind_mount() {
+        assert_eq!(
+            bind_mount(Path::new("/src/a:b")),
+            "type=bind,source=/src/a:b,target=/src/a:b"
+        );
+        assert_eq!(
+            bind_mount(Path::new("/src/a,\"b")),
+            r#"type=bind,"source=/src/a,""b","target=/src/a,""b""#
+        );
+    }
+
+    #[test]
+    fn test_current_dir() {
+        let spec = CommandSpec {
+            current_dir: Some(PathBuf::from("/work")),
+            ..spec()
+        };
+
+        let command = NativeBackend.command(&spec);
+        assert_eq!(command.get_current_dir(), Some(Path::new("/work")));
+
+        let command = ContainerBackend::new("tizen/gbs:latest").command(&spec);
+        let args: Vec<_> = command
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        assert!(args.contains(&"type=bind,source=/work,target=/work".to_string()));
+        let workdir = args.iter().position(|a| a == "-w").unwrap();
+        assert_eq!(args[workdir + 1], "/work");
+    }
+}
@@ -0,0 +1,187 @@
+//! Compatibility of build options with installed gbs versions.
+//!
+//! Newer options are rejected by older gbs releases with an "unrecognized
+//! arguments" error only once the build starts. [`GbsBuildOptions::check_compatibility`]
+//! reports them up front from the table below.
+
+use crate::runner::CommandRunner;
+use crate::GbsBuildOptions;
+use std::fmt;
+use std::io;
+use std::process::Command;
+
+/// A gbs release, as printed by `gbs --version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GbsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl GbsVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        GbsVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses `gbs 0.25.12` or `0.25.12`. A missing patch level is read as 0
+    /// and suffixes such as `-rc1` are ignored.
+    pub fn parse(version: &str) -> Option<GbsVersion> {
+        let version = version.trim();
+        let version = version.strip_prefix("gbs").unwrap_or(version).trim();
+        let version = version
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?;
+
+        let mut parts = version.split('.').map(|part| part.parse::<u32>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = match parts.next() {
+            Some(patch) => patch.ok()?,
+            None => 0,
+        };
+        Some(GbsVersion::new(major, minor, patch))
+    }
+
+    /// Runs `<program> --version` and parses its output.
+    pub fn detect(runner: &dyn CommandRunner, program: &str) -> io::Result<GbsVersion> {
+        let output = runner.output(Command::new(program).arg("--version"))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "{} --version failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        GbsVersion::parse(&stdout).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unexpected version output '{}'", stdout.trim()),
+            )
+        })
+    }
+}
+
+impl fmt::Display for GbsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// `gbs build` options and the first gbs release that accepts them. Options
+// missing here are supported by every release this crate targets.
+const OPTION_MIN_VERSIONS: &[(&str, GbsVersion)] = &[
+    ("--icecream", GbsVersion::new(0, 22, 0)),
+    ("--fail-fast", GbsVersion::new(0, 23, 0)),
+    ("--full-build", GbsVersion::new(0, 23, 0)),
+    ("--deps-build", GbsVersion::new(0, 23, 0)),
+    ("--style", GbsVersion::new(0, 24, 0)),
+    ("--export-only", GbsVersion::new(0, 24, 0)),
+    ("--pkg-ccache", GbsVersion::new(0, 24, 0)),
+    ("--skip-srcrpm", GbsVersion::new(0, 24, 0)),
+    ("--preordered-list", GbsVersion::new(0, 25, 0)),
+    ("--profiling", GbsVersion::new(0, 25, 0)),
+    ("--with-submodules", GbsVersion::new(0, 25, 4)),
+    ("--release", GbsVersion::new(0, 25, 10)),
+    ("--nocumulate", GbsVersion::new(0, 25, 12)),
+];
+
+/// Returns the first gbs release that accepts `option`, e.g. `--nocumulate`.
+pub fn min_version(option: &str) -> Option<GbsVersion> {
+    OPTION_MIN_VERSIONS
+        .iter()
+        .find(|(name, _)| *name == option)
+        .map(|(_, version)| *version)
+}
+
+/// An option that the checked gbs version does not know yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incompatibility {
+    pub option: &'static str,
+    pub required: GbsVersion,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} requires gbs {} or newer", self.option, self.required)
+    }
+}
+
+impl GbsBuildOptions {
+    /// Lists the configured options that `version` will reject.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let version = GbsVersion::detect(&*runner::default_runner(), "gbs")?;
+    /// for problem in options.check_compatibility(&version) {
+    ///     eprintln!("{}", problem);
+    /// }
+    /// ```
+    pub fn check_compatibility(&self, version: &GbsVersion) -> Vec<Incompatibility> {
+        let args = self.to_args();
+        OPTION_MIN_VERSIONS
+            .iter()
+            .filter(|(option, required)| required > version && args.iter().any(|a| a == option))
+            .map(|(option, required)| Incompatibility {
+                option,
+                required: *required,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            GbsVersion::parse("gbs 0.25.12\n"),
+            Some(GbsVersion::new(0, 25, 12))
+        );
+        assert_eq!(GbsVersion::parse("0.24"), Some(GbsVersion::new(0, 24, 0)));
+        assert_eq!(
+            GbsVersion::parse("gbs 0.25.1-rc1"),
+            Some(GbsVersion::new(0, 25, 1))
+        );
+        assert_eq!(GbsVersion::parse("gbs"), None);
+    }
+
+    #[test]
+    fn test_check_compatibility() {
+        let options = GbsBuildOptions::builder()
+            .arch("armv7l".to_string())
+            .with_submodules(true)
+            .release("1".to_string())
+            .nocumulate(true)
+            .build();
+
+        let problems = options.check_compatibility(&GbsVersion::new(0, 25, 10));
+        assert_eq!(
+            problems,
+            vec![Incompatibility {
+                option: "--nocumulate",
+                required: GbsVersion::new(0, 25, 12),
+            }]
+        );
+        assert_eq!(
+            problems[0].to_string(),
+            "--nocumulate requires gbs 0.25.12 or newer"
+        );
+        assert_eq!(
+            options
+                .check_compatibility(&GbsVersion::new(0, 24, 0))
+                .len(),
+            3
+        );
+        assert!(options
+            .check_compatibility(&GbsVersion::new(0, 26, 0))
+            .is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+//! Reading of gbs configuration files (`~/.gbs.conf`).
+//!
+//! Only the parts that affect `gbs build` are interpreted: the profile's
+//! repositories, build root, build configuration and OBS settings. Values
+//! may refer to keys of the `[general]` section as `${key}`, as in gbs.
+
+use crate::GbsBuildOptions;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A parsed configuration file, sections and keys in file order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GbsConf {
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+/// The OBS server a profile builds against remotely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObsSettings {
+    pub url: String,
+    pub user: Option<String>,
+    pub base_prj: Option<String>,
+    pub target_prj: Option<String>,
+}
+
+/// A repository of a profile.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ConfRepo {
+    /// Section name, e.g. `repo.base`.
+    pub name: String,
+    /// The URL as configured, without credentials.
+    pub url: String,
+    pub user: Option<String>,
+    /// The `passwd`, or the decoded `passwdx`.
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for ConfRepo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConfRepo")
+            .field("name", &self.name)
+            .field("url", &self.url)
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+/// The effective settings of one profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfProfile {
+    /// Full section name, e.g. `profile.tizen`.
+    pub name: String,
+    /// Repositories, with the user and password of the repository, or else
+    /// of the profile or the `[general]` section.
+    pub repos: Vec<ConfRepo>,
+    pub buildroot: Option<PathBuf>,
+    pub buildconf: Option<PathBuf>,
+    pub obs: Option<ObsSettings>,
+}
+
+impl GbsConf {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<GbsConf> {
+        GbsConf::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> io::Result<GbsConf> {
+        let mut conf = GbsConf::default();
+        let mut last_key: Option<usize> = None;
+
+        for (index, raw) in content.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if raw.starts_with(char::is_whitespace) {
+                // Continuation of the previous value.
+                if let (Some((_, keys)), Some(key)) = (conf.sections.last_mut(), last_key) {
+                    keys[key].1.push('\n');
+                    keys[key].1.push_str(line);
+                    continue;
+                }
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                conf.sections.push((name.trim().to_string(), Vec::new()));
+                last_key = None;
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once(['=', ':'])
+                .ok_or_else(|| invalid(format!("line {}: expected 'key = value'", index + 1)))?;
+            let (_, keys) = conf
+                .sections
+                .last_mut()
+                .ok_or_else(|| invalid(format!("line {}: key outside of a section", index + 1)))?;
+            keys.push((key.trim().to_lowercase(), value.trim().to_string()));
+            last_key = Some(keys.len() - 1);
+        }
+        Ok(conf)
+    }
+
+    /// Returns the value of `key` in `section`, with `${...}` references expanded.
+    pub fn get(&self, section: &str, key: &str) -> Option<String> {
+        self.raw(section, key).map(|value| self.expand(value))
+    }
+
+    fn raw(&self, section: &str, key: &str) -> Option<&str> {
+        let (_, keys) = self.sections.iter().find(|(name, _)| name == section)?;
+        keys.iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn expand(&self, value: &str) -> String {
+        let mut expanded = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find("${") {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 2..start + end];
+            expanded.push_str(&rest[..start]);
+            match self.raw("general", &name.to_lowercase()) {
+                Some(general) if !general.contains("${") => expanded.push_str(general),
+                _ => expanded.push_str(&rest[start..start + end + 1]),
+            }
+            rest = &rest[start + end + 1..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+
+    /// Resolves a profile by its section name (`profile.tizen`) or short name (`tizen`).
+    ///
+    /// Settings missing in the profile fall back to the `[general]` section.
+    pub fn profile(&self, name: &str) -> io::Result<ConfProfile> {
+        let section = if name.starts_with("profile.") {
+            name.to_string()
+        } else {
+            format!("profile.{}", name)
+        };
+        if !self.sections.iter().any(|(s, _)| *s == section) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Profile '{}' not found", section),
+            ));
+        }
+
+        let setting = |key: &str| {
+            self.get(&section, key)
+                .or_else(|| self.get("general", key))
+                .filter(|value| !value.is_empty())
+        };
+
+        let mut repos = Vec::new();
+        for repo in self.list(&section, "repos") {
+            let url = self
+                .get(&repo, "url")
+                .ok_or_else(|| invalid(format!("Repository '{}' has no url", repo)))?;
+            let user = self.get(&repo, "user").or_else(|| setting("user"));
+            let mut password = self.password(&repo)?;
+            for section in [section.as_str(), "general"] {
+                if password.is_none() {
+                    password = self.password(section)?;
+                }
+            }
+            repos.push(ConfRepo {
+                name: repo,
+                url,
+                user,
+                password,
+            });
+        }
+
+        let obs = match self.get(&section, "obs") {
+            Some(obs) => Some(ObsSettings {
+                url: self
+                    .get(&obs, "url")
+                    .ok_or_else(|| invalid(format!("OBS '{}' has no url", obs)))?,
+                user: self.get(&obs, "user").or_else(|| setting("user")),
+                base_prj: self.get(&obs, "base_prj"),
+                target_prj: self.get(&obs, "target_prj"),
+            }),
+            None => None,
+        };
+
+        Ok(ConfProfile {
+            name: section.clone(),
+            repos,
+            buildroot: setting("buildroot").map(|p| expand_home(&p)),
+            buildconf: self.get(&section, "buildconf").map(|p| expand_home(&p)),
+            obs,
+        })
+    }
+
+    // The `passwdx` of `section` decoded, or else its `passwd`, like gbs.
+    fn password(&self, section: &str) -> io::Result<Option<String>> {
+        if let Some(passwdx) = self.get(section, "passwdx").filter(|p| !p.is_empty()) {
+            return decode_passwdx(&passwdx).map(Some);
+        }
+        Ok(self.get(section, "passwd").filter(|p| !p.is_empty()))
+    }
+
+    fn list(&self, section: &str, key: &str) -> Vec<String> {
+        self.get(section, key)
+            .map(|value| {
+                value
+                    .split([',', '\n'])
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl GbsBuildOptions {
+    /// Reads `profile_name` from the gbs configuration file at `conf_path` and
+    /// pre-fills profile, repositories, build root, build configuration and
+    /// OBS settings, as gbs sees them.
+    ///
+    /// The file is passed to gbs as well, see [`GbsBuildOptions::conf`], so
+    /// that gbs reads the credentials of the repositories from it: the
+    /// repositories are listed without them.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let options = GbsBuildOptions::from_conf_profile("/home/user/.gbs.conf", "tizen")?;
+    /// println!("{:?}", options.buildroot);
+    /// ```
+    pub fn from_conf_profile<P: AsRef<Path>>(
+        conf_path: P,
+        profile_name: &str,
+    ) -> io::Result<GbsBuildOptions> {
+        let profile = GbsConf::load(&conf_path)?.profile(profile_name)?;
+
+        let mut builder = GbsBuildOptions::builder()
+            .conf(conf_path.as_ref())
+            .profile(profile.name);
+        if !profile.repos.is_empty() {
+            builder =
+                builder.repositories(profile.repos.into_iter().map(|repo| repo.url).collect());
+        }
+        if let Some(buildroot) = profile.buildroot {
+            builder = builder.buildroot(buildroot);
+        }
+        if let Some(buildconf) = profile.buildconf {
+            builder = builder.dist(buildconf.to_string_lossy().into_owned());
+        }
+        let mut options = builder.build();
+        options.obs = profile.obs;
+        Ok(options)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// gbs stores passwords as `passwdx`: compressed with zlib, then encoded
+// in base64.
+fn decode_passwdx(passwdx: &str) -> io::Result<String> {
+    let compressed = BASE64_STANDARD
+        .decode(passwdx)
+        .map_err(|e| invalid(format!("Invalid passwdx: {}", e)))?;
+    let passwd = miniz_oxide::inflate::decompress_to_vec_zlib(&compressed)
+        .map_err(|e| invalid(format!("Invalid passwdx: {}", e)))?;
+    String::from_utf8(passwd).map_err(|e| invalid(format!("Invalid passwdx: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &str = r#"
+[general]
+profile = profile.tizen
+work_dir = /src
+# shared download server
+mirror = http://download.tizen.org
+
+[profile.tizen]
+obs = obs.tizen
+repos = repo.base,
+    repo.local
+buildroot = /data/GBS-ROOT-${profile}
+buildconf = ${work_dir}/build.conf
+user = builder
+passwd = secret
+
+[obs.tizen]
+url = https://api.tizen.org
+base_prj = Tizen:Base
+
+[repo.base]
+url = ${mirror}/snapshots/base/latest/repos/standard/packages/
+
+[repo.local]
+url = ${work_dir}/local/repos
+
+[profile.private]
+repos = repo.private
+passwdx = eJwrcCgutirXL0oBABCjA00=
+
+[repo.private]
+url = https://private.example.com/repos/
+user = builder
+"#;
+
+    #[test]
+    fn test_profile() {
+        let conf = GbsConf::parse(CONF).unwrap();
+        let profile = conf.profile("tizen").unwrap();
+
+        assert_eq!(profile.name, "profile.tizen");
+        let urls: Vec<_> = profile.repos.iter().map(|repo| repo.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "http://download.tizen.org/snapshots/base/latest/repos/standard/packages/",
+                "/src/local/repos",
+            ]
+        );
+        assert_eq!(profile.repos[0].name, "repo.base");
+        assert_eq!(profile.repos[0].user.as_deref(), Some("builder"));
+        assert_eq!(profile.repos[0].password.as_deref(), Some("secret"));
+        assert!(!format!("{:?}", profile.repos[0]).contains("secret"));
+        assert_eq!(
+            profile.buildroot,
+            Some(PathBuf::from("/data/GBS-ROOT-profile.tizen"))
+        );
+        assert_eq!(profile.buildconf, Some(PathBuf::from("/src/build.conf")));
+        let obs = profile.obs.unwrap();
+        assert_eq!(obs.url, "https://api.tizen.org");
+        assert_eq!(obs.user.as_deref(), Some("builder"));
+        assert_eq!(obs.base_prj.as_deref(), Some("Tizen:Base"));
+
+        assert!(conf.profile("missing").is_err());
+    }
+
+    #[test]
+    fn test_passwdx() {
+        let conf = GbsConf::parse(CONF).unwrap();
+        let profile = conf.profile("private").unwrap();
+        assert_eq!(profile.repos[0].password.as_deref(), Some("p@ss:w/rd"));
+
+        let conf = GbsConf::parse(
+            "[profile.broken]\npasswdx = not base64\nrepos = repo.x\n[repo.x]\nurl = /x\n",
+        )
+        .unwrap();
+        assert!(conf.profile("broken").is_err());
+    }
+
+    #[test]
+    fn test_from_conf_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".gbs.conf");
+        fs::write(&path, CONF).unwrap();
+
+        let options = GbsBuildOptions::from_conf_profile(&path, "profile.tizen").unwrap();
+        assert_eq!(options.profile.as_deref(), Some("profile.tizen"));
+        assert_eq!(options.dist.as_deref(), Some("/src/build.conf"));
+        assert_eq!(
+            options.buildroot,
+            Some(PathBuf::from("/data/GBS-ROOT-profile.tizen"))
+        );
+        assert_eq!(
+            options.repositories,
+            Some(vec![
+                "http://download.tizen.org/snapshots/base/latest/repos/standard/packages/"
+                    .to_string(),
+                "/src/local/repos".to_string(),
+            ])
+        );
+        let obs = options.obs.as_ref().unwrap();
+        assert_eq!(obs.url, "https://api.tizen.org");
+        assert_eq!(obs.base_prj.as_deref(), Some("Tizen:Base"));
+        assert_eq!(options.conf.as_deref(), Some(path.as_path()));
+
+        let spec = crate::GbsInvocation::new(&options).spec();
+        assert_eq!(
+            spec.args[..3],
+            ["-c".into(), path.into_os_string(), "build".into()]
+        );
+        assert!(spec.mounts.contains(&options.conf.clone().unwrap()));
+    }
+}
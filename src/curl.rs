@@ -0,0 +1,26 @@
+//! Helpers for the requests made through `curl`.
+
+use crate::runner::CommandRunner;
+use std::io;
+use std::process::{Command, Output};
+
+/// Runs the curl `command` through `runner`, passing `credentials` to curl
+/// as a config read from its stdin, so that they are not on its command
+/// line where other users and traces see them.
+pub(crate) fn output(
+    runner: &dyn CommandRunner,
+    command: &mut Command,
+    credentials: Option<&(String, String)>,
+) -> io::Result<Output> {
+    let Some((user, password)) = credentials else {
+        return runner.output(command);
+    };
+    command.args(["--config", "-"]);
+    let config = format!("user = \"{}:{}\"\n", quote(user), quote(password));
+    runner.output_with_stdin(command, config.as_bytes())
+}
+
+// Escapes `value` for a double-quoted curl config parameter.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
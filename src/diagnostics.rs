@@ -0,0 +1,224 @@
+//! Classification of failed package builds from their rpmbuild logs.
+//!
+//! Large rebuilds fail for a handful of recurring reasons. [`classify`]
+//! recognizes them in a build log so failures can be triaged automatically
+//! instead of by reading every log.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Why a package failed to build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Build dependencies that could not be installed.
+    MissingBuildRequires {
+        packages: Vec<String>,
+    },
+    /// The `%check` section failed.
+    CheckFailed,
+    /// The compiler reported an error, e.g. `foo.c:12:5: error: ...`.
+    CompilerError {
+        location: String,
+        message: String,
+    },
+    /// Files were installed into the buildroot but no package owns them.
+    UnpackagedFiles {
+        files: Vec<String>,
+    },
+    Unknown,
+}
+
+/// A classified failure of one package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub package: String,
+    pub kind: FailureKind,
+    /// The last lines of the build log.
+    pub log_tail: Vec<String>,
+}
+
+/// Number of log lines kept in [`Diagnostic::log_tail`].
+pub const DEFAULT_TAIL_LINES: usize = 30;
+
+/// Reads the build log of `package` and classifies its failure.
+pub fn diagnose(package: &str, log_path: &Path, tail_lines: usize) -> io::Result<Diagnostic> {
+    let log = String::from_utf8_lossy(&fs::read(log_path)?).into_owned();
+    Ok(Diagnostic {
+        package: package.to_string(),
+        kind: classify(&log),
+        log_tail: log_tail(&log, tail_lines),
+    })
+}
+
+/// Returns the last `lines` lines of `log`, without build time stamps.
+pub fn log_tail(log: &str, lines: usize) -> Vec<String> {
+    let all: Vec<&str> = log.lines().collect();
+    all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| strip_timestamp(line).to_string())
+        .collect()
+}
+
+/// Classifies a build log. When several causes show up, the one that
+/// stopped the build first in rpmbuild's order wins.
+pub fn classify(log: &str) -> FailureKind {
+    let lines: Vec<&str> = log.lines().map(strip_timestamp).collect();
+
+    let mut missing = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        // OBS build: "nothing provides foo-devel needed by bar"
+        if let Some(rest) = line.split_once("nothing provides ").map(|(_, r)| r) {
+            if let Some(package) = rest.split(" needed by").next() {
+                missing.push(package.trim().to_string());
+            }
+        }
+        // rpmbuild: "error: Failed build dependencies:" then "\tfoo is needed by bar"
+        if line.contains("Failed build dependencies:") {
+            for dep in &lines[index + 1..] {
+                match dep.trim().split_once(" is needed by ") {
+                    Some((package, _)) => missing.push(package.trim().to_string()),
+                    None => break,
+                }
+            }
+        }
+    }
+    if !missing.is_empty() {
+        missing.dedup();
+        return FailureKind::MissingBuildRequires { packages: missing };
+    }
+
+    if let Some(index) = lines
+        .iter()
+        .position(|l| l.contains("Installed (but unpackaged) file(s) found:"))
+    {
+        let files = lines[index + 1..]
+            .iter()
+            .map(|l| l.trim())
+            .take_while(|l| l.starts_with('/'))
+            .map(String::from)
+            .collect();
+        return FailureKind::UnpackagedFiles { files };
+    }
+
+    if lines
+        .iter()
+        .any(|l| l.contains("Bad exit status from") && l.contains("(%check)"))
+    {
+        return FailureKind::CheckFailed;
+    }
+
+    for line in &lines {
+        if let Some((location, message)) = compiler_error(line) {
+            return FailureKind::CompilerError {
+                location: location.to_string(),
+                message: message.to_string(),
+            };
+        }
+    }
+
+    FailureKind::Unknown
+}
+
+// Matches gcc/clang "<file>:<line>:<column>: error: <message>" and
+// "<file>:<line>: error: <message>".
+fn compiler_error(line: &str) -> Option<(&str, &str)> {
+    let (location, message) = line.split_once(": error: ")?;
+    let last = location.rsplit(':').next()?;
+    if last.is_empty() || !last.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((location.trim(), message.trim()))
+}
+
+// Build logs prefix every line with the elapsed time, e.g. "[  123s] ".
+fn strip_timestamp(line: &str) -> &str {
+    if let Some(rest) = line.strip_prefix('[') {
+        if let Some((stamp, rest)) = rest.split_once("] ") {
+            let stamp = stamp.trim();
+            if stamp.ends_with('s') && stamp[..stamp.len() - 1].chars().all(|c| c.is_ascii_digit())
+            {
+                return rest;
+            }
+        }
+    }
+    line
+}
+
+#[cfg(feature = "report")]
+impl crate::report::DepanneurReport {
+    /// Classifies every failed package that has a build log.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.failed()
+            .filter_map(|package| {
+                let log_path = package.log_path.as_ref()?;
+                diagnose(&package.name, log_path, DEFAULT_TAIL_LINES).ok()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_build_requires() {
+        let log = "\
+[    5s] error: Failed build dependencies:
+[    5s] \tpkgconfig(glib-2.0) is needed by foo-1.0-1.armv7l
+[    5s] \tlibfoo-devel is needed by foo-1.0-1.armv7l
+[    5s] exit ...";
+        assert_eq!(
+            classify(log),
+            FailureKind::MissingBuildRequires {
+                packages: vec![
+                    "pkgconfig(glib-2.0)".to_string(),
+                    "libfoo-devel".to_string()
+                ]
+            }
+        );
+        assert_eq!(
+            classify("unresolvable: nothing provides python3-foo needed by bar"),
+            FailureKind::MissingBuildRequires {
+                packages: vec!["python3-foo".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_build_failures() {
+        let log = "\
+[  120s] src/main.c:42:7: error: 'foo' undeclared (first use in this function)
+[  120s] make: *** [Makefile:10: main.o] Error 1
+[  120s] error: Bad exit status from /var/tmp/rpm-tmp.Xy1 (%build)";
+        assert_eq!(
+            classify(log),
+            FailureKind::CompilerError {
+                location: "src/main.c:42:7".to_string(),
+                message: "'foo' undeclared (first use in this function)".to_string()
+            }
+        );
+
+        let log = "[  300s] error: Bad exit status from /var/tmp/rpm-tmp.Xy1 (%check)";
+        assert_eq!(classify(log), FailureKind::CheckFailed);
+
+        let log = "\
+[  400s] error: Installed (but unpackaged) file(s) found:
+[  400s]    /usr/lib/libfoo.so.1
+[  400s]    /usr/share/foo/data
+[  400s] RPM build errors:";
+        assert_eq!(
+            classify(log),
+            FailureKind::UnpackagedFiles {
+                files: vec![
+                    "/usr/lib/libfoo.so.1".to_string(),
+                    "/usr/share/foo/data".to_string()
+                ]
+            }
+        );
+
+        assert_eq!(classify("Killed"), FailureKind::Unknown);
+        assert_eq!(log_tail(log, 1), vec!["RPM build errors:"]);
+    }
+}
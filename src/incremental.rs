@@ -0,0 +1,175 @@
+//! Options that speed up rebuilds.
+//!
+//! `gbs build` accepts several flags that only make sense together, e.g.
+//! `--no-configure` is ignored unless `--incremental` is given as well.
+//! [`IncrementalOptions`] groups them so these rules are checked in one
+//! place, both by [`IncrementalOptionsBuilder::build`] and before a build is
+//! executed.
+
+use crate::subcommand::ValidationError;
+
+/// The "speed up building" options of `gbs build`.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalOptions {
+    pub incremental: bool,
+    pub no_configure: bool,
+    pub noinit: bool,
+    pub ccache: bool,
+    pub pkg_ccache: Option<String>,
+    pub icecream: Option<u32>,
+}
+
+impl IncrementalOptions {
+    pub fn builder() -> IncrementalOptionsBuilder {
+        IncrementalOptionsBuilder::default()
+    }
+
+    /// Checks the dependencies between the options.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.no_configure && !self.incremental {
+            return Err(ValidationError::new(
+                "--no-configure can only be used together with --incremental",
+            ));
+        }
+        if let Some(pkg_ccache) = &self.pkg_ccache {
+            if pkg_ccache.is_empty() {
+                return Err(ValidationError::new("--pkg-ccache must not be empty"));
+            }
+        }
+        if self.icecream == Some(0) {
+            return Err(ValidationError::new(
+                "--icecream needs at least one parallel job",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Layers `overlay` on top of these options, see [`crate::GbsBuildOptions::merge`].
+    pub fn merge(&self, overlay: &IncrementalOptions) -> IncrementalOptions {
+        IncrementalOptions {
+            incremental: self.incremental || overlay.incremental,
+            no_configure: self.no_configure || overlay.no_configure,
+            noinit: self.noinit || overlay.noinit,
+            ccache: self.ccache || overlay.ccache,
+            pkg_ccache: overlay
+                .pkg_ccache
+                .clone()
+                .or_else(|| self.pkg_ccache.clone()),
+            icecream: overlay.icecream.or(self.icecream),
+        }
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if self.incremental {
+            args.push("--incremental".to_string());
+        }
+
+        if self.no_configure {
+            args.push("--no-configure".to_string());
+        }
+
+        if self.noinit {
+            args.push("--noinit".to_string());
+        }
+
+        if self.ccache {
+            args.push("--ccache".to_string());
+        }
+
+        if let Some(pkg_ccache) = &self.pkg_ccache {
+            args.push("--pkg-ccache".to_string());
+            args.push(pkg_ccache.clone());
+        }
+
+        if let Some(icecream) = self.icecream {
+            args.push("--icecream".to_string());
+            args.push(icecream.to_string());
+        }
+
+        args
+    }
+}
+
+#[derive(Default)]
+pub struct IncrementalOptionsBuilder {
+    options: IncrementalOptions,
+}
+
+impl IncrementalOptionsBuilder {
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.options.incremental = incremental;
+        self
+    }
+
+    pub fn no_configure(mut self, no_configure: bool) -> Self {
+        self.options.no_configure = no_configure;
+        self
+    }
+
+    pub fn noinit(mut self, noinit: bool) -> Self {
+        self.options.noinit = noinit;
+        self
+    }
+
+    pub fn ccache(mut self, ccache: bool) -> Self {
+        self.options.ccache = ccache;
+        self
+    }
+
+    pub fn pkg_ccache(mut self, pkg_ccache: String) -> Self {
+        self.options.pkg_ccache = Some(pkg_ccache);
+        self
+    }
+
+    pub fn icecream(mut self, icecream: u32) -> Self {
+        self.options.icecream = Some(icecream);
+        self
+    }
+
+    /// Returns the options, or the first violated invariant.
+    pub fn build(self) -> Result<IncrementalOptions, ValidationError> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_configure_requires_incremental() {
+        let err = IncrementalOptions::builder()
+            .no_configure(true)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "--no-configure can only be used together with --incremental"
+        );
+
+        let options = IncrementalOptions::builder()
+            .incremental(true)
+            .no_configure(true)
+            .build()
+            .unwrap();
+        assert_eq!(options.to_args(), vec!["--incremental", "--no-configure"]);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(IncrementalOptions::builder().icecream(0).build().is_err());
+        assert!(IncrementalOptions::builder()
+            .pkg_ccache(String::new())
+            .build()
+            .is_err());
+        assert!(IncrementalOptions::builder()
+            .ccache(true)
+            .pkg_ccache("ccache.tar".to_string())
+            .icecream(4)
+            .build()
+            .is_ok());
+    }
+}
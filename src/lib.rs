@@ -1,5 +1,44 @@
+use backend::{ExecutionBackend, NativeBackend};
+use incremental::IncrementalOptions;
 use std::collections::HashMap;
-use std::process::{Command, ExitStatus, Stdio};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use subcommand::{GbsInvocation, GbsSubcommand, ValidationError};
+
+// The backends, the runner and the invocation are what `execute` runs
+// through, so they are part of the minimal build.
+pub mod backend;
+pub mod compat;
+#[cfg(feature = "conf")]
+pub mod conf;
+#[cfg(feature = "http")]
+mod curl;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod incremental;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "http")]
+pub mod preflight;
+pub mod presets;
+#[cfg(feature = "http")]
+pub mod publish;
+#[cfg(feature = "release")]
+pub mod release;
+#[cfg(feature = "report")]
+pub mod report;
+pub mod runner;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+pub mod script;
+pub mod subcommand;
+pub mod summary;
+#[cfg(feature = "watchdog")]
+pub mod watchdog;
+
+#[cfg(feature = "manifest")]
+pub use manifest_parser as manifest;
 
 // positional arguments:
 //   gitdir                git repository path, which can contain multiple packages, in this case, all packages will be
@@ -127,11 +166,28 @@ use std::process::{Command, ExitStatus, Stdio};
 //   --release RELEASE     Override Release in spec file
 //   --nocumulate          without cumulative build
 
+/// Whether gbs continues building the remaining packages after one fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keepgoing {
+    On,
+    Off,
+}
+
+impl Keepgoing {
+    /// The value `gbs build --keepgoing` expects.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Keepgoing::On => "on",
+            Keepgoing::Off => "off",
+        }
+    }
+}
+
 /// Represents the options for the `gbs build` command.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct GbsBuildOptions {
     // Positional arguments
-    pub gitdir: Option<String>,
+    pub gitdir: Option<PathBuf>,
 
     // Build configuration options
     pub arch: Option<String>,
@@ -144,21 +200,16 @@ pub struct GbsBuildOptions {
     pub debug: bool,
     pub baselibs: bool,
     pub clean: bool,
-    pub incremental: bool,
-    pub no_configure: bool,
-    pub noinit: bool,
-    pub ccache: bool,
-    pub pkg_ccache: Option<String>,
-    pub icecream: Option<u32>,
+    pub incremental_options: IncrementalOptions,
     pub threads: Option<u32>,
     pub skip_srcrpm: bool,
 
     // Build environment options
-    pub buildroot: Option<String>,
+    pub buildroot: Option<PathBuf>,
     pub clean_once: bool,
     pub clean_repos: bool,
     pub fail_fast: bool,
-    pub keepgoing: Option<u32>,
+    pub keepgoing: Option<Keepgoing>,
     pub extra_packs: Option<Vec<String>>,
     pub keep_packs: bool,
     pub use_higher_deps: bool,
@@ -180,7 +231,7 @@ pub struct GbsBuildOptions {
     pub commit: Option<String>,
     pub include_all: bool,
     pub packaging_dir: Option<String>,
-    pub spec: Option<String>,
+    pub spec: Option<PathBuf>,
     pub upstream_branch: Option<String>,
     pub upstream_tag: Option<String>,
     pub fallback_to_native: bool,
@@ -204,6 +255,15 @@ pub struct GbsBuildOptions {
     pub with_submodules: bool,
     pub release: Option<String>,
     pub nocumulate: bool,
+
+    // Execution options (not passed to gbs build)
+    pub current_dir: Option<PathBuf>,
+    /// gbs configuration file, passed as `gbs -c <conf>` before the verb.
+    pub conf: Option<PathBuf>,
+    /// OBS server of the configuration profile, see
+    /// [`GbsBuildOptions::from_conf_profile`]. `gbs build` does not use it.
+    #[cfg(feature = "conf")]
+    pub obs: Option<conf::ObsSettings>,
 }
 
 /// Represents the options for building with GBS (Git Build System).
@@ -267,7 +327,7 @@ pub struct GbsBuildOptions {
 ///
 ///   Includes base libraries in the build.
 ///
-/// - `buildroot: Option<String>`
+/// - `buildroot: Option<PathBuf>`
 ///
 ///   Specifies the build root directory.
 ///
@@ -287,9 +347,10 @@ pub struct GbsBuildOptions {
 ///
 ///   Fails the build immediately on the first error.
 ///
-/// - `keepgoing: Option<u32>`
+/// - `keepgoing: Option<Keepgoing>`
 ///
-///   Specifies the number of errors to tolerate before failing.
+///   Continues with the other packages when one fails. Unset leaves the
+///   choice to gbs; `Keepgoing::On` conflicts with `fail_fast`.
 ///
 /// - `extra_packs: Option<Vec<String>>`
 ///
@@ -347,29 +408,10 @@ pub struct GbsBuildOptions {
 ///
 ///   Specifies the snapshot to use for the build.
 ///
-/// - `incremental: bool`
-///
-///   Enables incremental builds.
-///
-/// - `no_configure: bool`
-///
-///   Skips the configure step.
-///
-/// - `noinit: bool`
+/// - `incremental_options: IncrementalOptions`
 ///
-///   Skips the initialization step.
-///
-/// - `ccache: bool`
-///
-///   Enables ccache support.
-///
-/// - `pkg_ccache: Option<String>`
-///
-///   Specifies the ccache package.
-///
-/// - `icecream: Option<u32>`
-///
-///   Enables icecream distributed compilation.
+///   Incremental build, ccache and icecream options, see
+///   [`incremental::IncrementalOptions`].
 ///
 /// - `skip_srcrpm: bool`
 ///
@@ -391,7 +433,7 @@ pub struct GbsBuildOptions {
 ///
 ///   Specifies the packaging directory.
 ///
-/// - `spec: Option<String>`
+/// - `spec: Option<PathBuf>`
 ///
 ///   Specifies the spec file.
 ///
@@ -479,339 +521,597 @@ pub struct GbsBuildOptions {
 ///
 ///   Disables cumulative builds.
 ///
-/// - `gitdir: Option<String>`
+/// - `gitdir: Option<PathBuf>`
 ///
 ///   Specifies the git directory.
+///
+/// - `current_dir: Option<PathBuf>`
+///
+///   Specifies the working directory of the gbs process. Relative paths in
+///   `gitdir`, `spec`, `packaging_dir` and the package list files are
+///   resolved against it.
+///
+/// - `conf: Option<PathBuf>`
+///
+///   Specifies the gbs configuration file, instead of `~/.gbs.conf`.
 impl GbsBuildOptions {
     /// Builder pattern for GbsBuildOptions
     pub fn builder() -> GbsBuildOptionsBuilder {
         GbsBuildOptionsBuilder::default()
     }
 
-    /// Converts the options into a vector of command-line arguments.
+    /// Layers `overlay` on top of these options and returns the result.
+    ///
+    /// Fields set in the overlay win: `Some` values replace the base value
+    /// as a whole (lists and defines are not concatenated) and `true` flags
+    /// are turned on. A flag cannot be turned off by an overlay, since an
+    /// unset flag is indistinguishable from `false`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let options = site_defaults.merge(&profile_defaults).merge(&job_overrides);
+    /// ```
+    pub fn merge(&self, overlay: &GbsBuildOptions) -> GbsBuildOptions {
+        GbsBuildOptions {
+            gitdir: overlay.gitdir.clone().or_else(|| self.gitdir.clone()),
+            arch: overlay.arch.clone().or_else(|| self.arch.clone()),
+            dist: overlay.dist.clone().or_else(|| self.dist.clone()),
+            profile: overlay.profile.clone().or_else(|| self.profile.clone()),
+            repositories: overlay
+                .repositories
+                .clone()
+                .or_else(|| self.repositories.clone()),
+            skip_conf_repos: self.skip_conf_repos || overlay.skip_conf_repos,
+            overwrite: self.overwrite || overlay.overwrite,
+            define: overlay.define.clone().or_else(|| self.define.clone()),
+            debug: self.debug || overlay.debug,
+            baselibs: self.baselibs || overlay.baselibs,
+            clean: self.clean || overlay.clean,
+            incremental_options: self.incremental_options.merge(&overlay.incremental_options),
+            threads: overlay.threads.or(self.threads),
+            skip_srcrpm: self.skip_srcrpm || overlay.skip_srcrpm,
+            buildroot: overlay.buildroot.clone().or_else(|| self.buildroot.clone()),
+            clean_once: self.clean_once || overlay.clean_once,
+            clean_repos: self.clean_repos || overlay.clean_repos,
+            fail_fast: self.fail_fast || overlay.fail_fast,
+            keepgoing: overlay.keepgoing.or(self.keepgoing),
+            extra_packs: overlay
+                .extra_packs
+                .clone()
+                .or_else(|| self.extra_packs.clone()),
+            keep_packs: self.keep_packs || overlay.keep_packs,
+            use_higher_deps: self.use_higher_deps || overlay.use_higher_deps,
+            kvm: self.kvm || overlay.kvm,
+            vm_memory: overlay.vm_memory.clone().or_else(|| self.vm_memory.clone()),
+            vm_disk: overlay.vm_disk.clone().or_else(|| self.vm_disk.clone()),
+            vm_swap: overlay.vm_swap.clone().or_else(|| self.vm_swap.clone()),
+            vm_diskfilesystem: overlay
+                .vm_diskfilesystem
+                .clone()
+                .or_else(|| self.vm_diskfilesystem.clone()),
+            vm_initrd: overlay.vm_initrd.clone().or_else(|| self.vm_initrd.clone()),
+            vm_kernel: overlay.vm_kernel.clone().or_else(|| self.vm_kernel.clone()),
+            not_export_source: self.not_export_source || overlay.not_export_source,
+            full_build: self.full_build || overlay.full_build,
+            deps_build: self.deps_build || overlay.deps_build,
+            snapshot: overlay.snapshot.clone().or_else(|| self.snapshot.clone()),
+            commit: overlay.commit.clone().or_else(|| self.commit.clone()),
+            include_all: self.include_all || overlay.include_all,
+            packaging_dir: overlay
+                .packaging_dir
+                .clone()
+                .or_else(|| self.packaging_dir.clone()),
+            spec: overlay.spec.clone().or_else(|| self.spec.clone()),
+            upstream_branch: overlay
+                .upstream_branch
+                .clone()
+                .or_else(|| self.upstream_branch.clone()),
+            upstream_tag: overlay
+                .upstream_tag
+                .clone()
+                .or_else(|| self.upstream_tag.clone()),
+            fallback_to_native: self.fallback_to_native || overlay.fallback_to_native,
+            squash_patches_until: overlay
+                .squash_patches_until
+                .clone()
+                .or_else(|| self.squash_patches_until.clone()),
+            no_patch_export: self.no_patch_export || overlay.no_patch_export,
+            package_list: overlay
+                .package_list
+                .clone()
+                .or_else(|| self.package_list.clone()),
+            package_from_file: overlay
+                .package_from_file
+                .clone()
+                .or_else(|| self.package_from_file.clone()),
+            binary_list: overlay
+                .binary_list
+                .clone()
+                .or_else(|| self.binary_list.clone()),
+            binary_from_file: overlay
+                .binary_from_file
+                .clone()
+                .or_else(|| self.binary_from_file.clone()),
+            exclude: overlay.exclude.clone().or_else(|| self.exclude.clone()),
+            exclude_from_file: overlay
+                .exclude_from_file
+                .clone()
+                .or_else(|| self.exclude_from_file.clone()),
+            deps: self.deps || overlay.deps,
+            rdeps: self.rdeps || overlay.rdeps,
+            disable_debuginfo: self.disable_debuginfo || overlay.disable_debuginfo,
+            style: overlay.style.clone().or_else(|| self.style.clone()),
+            export_only: self.export_only || overlay.export_only,
+            preordered_list: overlay
+                .preordered_list
+                .clone()
+                .or_else(|| self.preordered_list.clone()),
+            profiling: overlay.profiling.clone().or_else(|| self.profiling.clone()),
+            with_submodules: self.with_submodules || overlay.with_submodules,
+            release: overlay.release.clone().or_else(|| self.release.clone()),
+            nocumulate: self.nocumulate || overlay.nocumulate,
+            current_dir: overlay
+                .current_dir
+                .clone()
+                .or_else(|| self.current_dir.clone()),
+            conf: overlay.conf.clone().or_else(|| self.conf.clone()),
+            #[cfg(feature = "conf")]
+            obs: overlay.obs.clone().or_else(|| self.obs.clone()),
+        }
+    }
+
+    /// Returns the arguments for `gbs build`, converted to strings lossily.
+    /// Use [`GbsBuildOptions::to_os_args`] to keep non-UTF-8 paths intact.
     pub fn to_args(&self) -> Vec<String> {
-        let mut args = Vec::new();
+        self.to_os_args()
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Returns the arguments for `gbs build`.
+    pub fn to_os_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = Vec::new();
 
         // Build configuration options
         if let Some(arch) = &self.arch {
-            args.push("-A".to_string());
-            args.push(arch.clone());
+            args.push("-A".into());
+            args.push(arch.clone().into());
         }
 
         if let Some(dist) = &self.dist {
-            args.push("-D".to_string());
-            args.push(dist.clone());
+            args.push("-D".into());
+            args.push(dist.clone().into());
         }
 
         if let Some(profile) = &self.profile {
-            args.push("-P".to_string());
-            args.push(profile.clone());
+            args.push("-P".into());
+            args.push(profile.clone().into());
         }
 
         if let Some(repositories) = &self.repositories {
             for repo in repositories {
-                args.push("-R".to_string());
-                args.push(repo.clone());
+                args.push("-R".into());
+                args.push(repo.clone().into());
             }
         }
 
         if self.skip_conf_repos {
-            args.push("--skip-conf-repos".to_string());
+            args.push("--skip-conf-repos".into());
         }
 
         if self.overwrite {
-            args.push("--overwrite".to_string());
+            args.push("--overwrite".into());
         }
 
         if let Some(define) = &self.define {
             let mut define_vec: Vec<_> = define.iter().collect();
             define_vec.sort_by_key(|&(key, _)| key);
             for (key, value) in define_vec {
-                args.push("--define".to_string());
-                args.push(format!("{} {}", key, value));
+                args.push("--define".into());
+                args.push(format!("{} {}", key, value).into());
             }
         }
 
         if self.debug {
-            args.push("--debug".to_string());
+            args.push("--debug".into());
         }
 
         if self.baselibs {
-            args.push("--baselibs".to_string());
+            args.push("--baselibs".into());
         }
 
         // Build env options
         if let Some(buildroot) = &self.buildroot {
-            args.push("-B".to_string());
-            args.push(buildroot.clone());
+            args.push("-B".into());
+            args.push(buildroot.clone().into());
         }
 
         if self.clean {
-            args.push("-C".to_string());
+            args.push("-C".into());
         }
 
         if self.clean_once {
-            args.push("--clean-once".to_string());
+            args.push("--clean-once".into());
         }
 
         if self.clean_repos {
-            args.push("--clean-repos".to_string());
+            args.push("--clean-repos".into());
         }
 
         if self.fail_fast {
-            args.push("--fail-fast".to_string());
+            args.push("--fail-fast".into());
         }
 
         if let Some(keepgoing) = self.keepgoing {
-            args.push("--keepgoing".to_string());
-            args.push(keepgoing.to_string());
+            args.push("--keepgoing".into());
+            args.push(keepgoing.as_str().into());
         }
 
         if let Some(extra_packs) = &self.extra_packs {
-            args.push("--extra-packs".to_string());
-            args.push(extra_packs.join(","));
+            args.push("--extra-packs".into());
+            args.push(extra_packs.join(",").into());
         }
 
         if self.keep_packs {
-            args.push("--keep-packs".to_string());
+            args.push("--keep-packs".into());
         }
 
         if self.use_higher_deps {
-            args.push("--use-higher-deps".to_string());
+            args.push("--use-higher-deps".into());
         }
 
         if self.kvm {
-            args.push("--kvm".to_string());
+            args.push("--kvm".into());
         }
 
         if let Some(vm_memory) = &self.vm_memory {
-            args.push("--vm-memory".to_string());
-            args.push(vm_memory.clone());
+            args.push("--vm-memory".into());
+            args.push(vm_memory.clone().into());
         }
 
         if let Some(vm_disk) = &self.vm_disk {
-            args.push("--vm-disk".to_string());
-            args.push(vm_disk.clone());
+            args.push("--vm-disk".into());
+            args.push(vm_disk.clone().into());
         }
 
         if let Some(vm_swap) = &self.vm_swap {
-            args.push("--vm-swap".to_string());
-            args.push(vm_swap.clone());
+            args.push("--vm-swap".into());
+            args.push(vm_swap.clone().into());
         }
 
         if let Some(vm_diskfilesystem) = &self.vm_diskfilesystem {
-            args.push("--vm-diskfilesystem".to_string());
-            args.push(vm_diskfilesystem.clone());
+            args.push("--vm-diskfilesystem".into());
+            args.push(vm_diskfilesystem.clone().into());
         }
 
         if let Some(vm_initrd) = &self.vm_initrd {
-            args.push("--vm-initrd".to_string());
-            args.push(vm_initrd.clone());
+            args.push("--vm-initrd".into());
+            args.push(vm_initrd.clone().into());
         }
 
         if let Some(vm_kernel) = &self.vm_kernel {
-            args.push("--vm-kernel".to_string());
-            args.push(vm_kernel.clone());
+            args.push("--vm-kernel".into());
+            args.push(vm_kernel.clone().into());
         }
 
         if self.not_export_source {
-            args.push("--not-export-source".to_string());
+            args.push("--not-export-source".into());
         }
 
         if self.full_build {
-            args.push("--full-build".to_string());
+            args.push("--full-build".into());
         }
 
         if self.deps_build {
-            args.push("--deps-build".to_string());
+            args.push("--deps-build".into());
         }
 
         if let Some(snapshot) = &self.snapshot {
-            args.push("--snapshot".to_string());
-            args.push(snapshot.clone());
+            args.push("--snapshot".into());
+            args.push(snapshot.clone().into());
         }
 
         // Speed up building options
-        if self.incremental {
-            args.push("--incremental".to_string());
-        }
-
-        if self.no_configure {
-            args.push("--no-configure".to_string());
-        }
-
-        if self.noinit {
-            args.push("--noinit".to_string());
-        }
-
-        if self.ccache {
-            args.push("--ccache".to_string());
-        }
-
-        if let Some(pkg_ccache) = &self.pkg_ccache {
-            args.push("--pkg-ccache".to_string());
-            args.push(pkg_ccache.clone());
-        }
-
-        if let Some(icecream) = self.icecream {
-            args.push("--icecream".to_string());
-            args.push(icecream.to_string());
-        }
+        args.extend(
+            self.incremental_options
+                .to_args()
+                .into_iter()
+                .map(OsString::from),
+        );
 
         if self.skip_srcrpm {
-            args.push("--skip-srcrpm".to_string());
+            args.push("--skip-srcrpm".into());
         }
 
         if let Some(threads) = self.threads {
-            args.push("--threads".to_string());
-            args.push(threads.to_string());
+            args.push("--threads".into());
+            args.push(threads.to_string().into());
         }
 
         // Git-tree options
         if let Some(commit) = &self.commit {
-            args.push("-c".to_string());
-            args.push(commit.clone());
+            args.push("-c".into());
+            args.push(commit.clone().into());
         }
 
         if self.include_all {
-            args.push("--include-all".to_string());
+            args.push("--include-all".into());
         }
 
         if let Some(packaging_dir) = &self.packaging_dir {
-            args.push("--packaging-dir".to_string());
-            args.push(packaging_dir.clone());
+            args.push("--packaging-dir".into());
+            args.push(packaging_dir.clone().into());
         }
 
         if let Some(spec) = &self.spec {
-            args.push("--spec".to_string());
-            args.push(spec.clone());
+            args.push("--spec".into());
+            args.push(spec.clone().into());
         }
 
         if let Some(upstream_branch) = &self.upstream_branch {
-            args.push("--upstream-branch".to_string());
-            args.push(upstream_branch.clone());
+            args.push("--upstream-branch".into());
+            args.push(upstream_branch.clone().into());
         }
 
         if let Some(upstream_tag) = &self.upstream_tag {
-            args.push("--upstream-tag".to_string());
-            args.push(upstream_tag.clone());
+            args.push("--upstream-tag".into());
+            args.push(upstream_tag.clone().into());
         }
 
         if self.fallback_to_native {
-            args.push("--fallback-to-native".to_string());
+            args.push("--fallback-to-native".into());
         }
 
         if let Some(squash_patches_until) = &self.squash_patches_until {
-            args.push("--squash-patches-until".to_string());
-            args.push(squash_patches_until.clone());
+            args.push("--squash-patches-until".into());
+            args.push(squash_patches_until.clone().into());
         }
 
         if self.no_patch_export {
-            args.push("--no-patch-export".to_string());
+            args.push("--no-patch-export".into());
         }
 
         // Package selection options
         if let Some(package_list) = &self.package_list {
             for package in package_list {
-                args.push("--package".to_string());
-                args.push(package.clone());
+                args.push("--package".into());
+                args.push(package.clone().into());
             }
         }
 
         if let Some(package_from_file) = &self.package_from_file {
-            args.push("--package-from-file".to_string());
-            args.push(package_from_file.clone());
+            args.push("--package-from-file".into());
+            args.push(package_from_file.clone().into());
         }
 
         if let Some(binary_list) = &self.binary_list {
             for binary in binary_list {
-                args.push("--binary".to_string());
-                args.push(binary.clone());
+                args.push("--binary".into());
+                args.push(binary.clone().into());
             }
         }
 
         if let Some(binary_from_file) = &self.binary_from_file {
-            args.push("--binary-from-file".to_string());
-            args.push(binary_from_file.clone());
+            args.push("--binary-from-file".into());
+            args.push(binary_from_file.clone().into());
         }
 
         if let Some(exclude) = &self.exclude {
             for exclude in exclude {
-                args.push("--exclude".to_string());
-                args.push(exclude.clone());
+                args.push("--exclude".into());
+                args.push(exclude.clone().into());
             }
         }
 
         if let Some(exclude_from_file) = &self.exclude_from_file {
-            args.push("--exclude-from-file".to_string());
-            args.push(exclude_from_file.clone());
+            args.push("--exclude-from-file".into());
+            args.push(exclude_from_file.clone().into());
         }
 
         if self.deps {
-            args.push("--deps".to_string());
+            args.push("--deps".into());
         }
 
         if self.rdeps {
-            args.push("--rdeps".to_string());
+            args.push("--rdeps".into());
         }
 
         if self.disable_debuginfo {
-            args.push("--disable-debuginfo".to_string());
+            args.push("--disable-debuginfo".into());
         }
 
         if let Some(style) = &self.style {
-            args.push("--style".to_string());
-            args.push(style.clone());
+            args.push("--style".into());
+            args.push(style.clone().into());
         }
 
         if self.export_only {
-            args.push("--export-only".to_string());
+            args.push("--export-only".into());
         }
 
         if let Some(preordered_list) = &self.preordered_list {
-            args.push("--preordered-list".to_string());
-            args.push(preordered_list.clone());
+            args.push("--preordered-list".into());
+            args.push(preordered_list.clone().into());
         }
 
         if let Some(profiling) = &self.profiling {
-            args.push("--profiling".to_string());
-            args.push(profiling.clone());
+            args.push("--profiling".into());
+            args.push(profiling.clone().into());
         }
 
         if self.with_submodules {
-            args.push("--with-submodules".to_string());
+            args.push("--with-submodules".into());
         }
 
         if let Some(release) = &self.release {
-            args.push("--release".to_string());
-            args.push(release.clone());
+            args.push("--release".into());
+            args.push(release.clone().into());
         }
 
         if self.nocumulate {
-            args.push("--nocumulate".to_string());
+            args.push("--nocumulate".into());
         }
 
         // Positional arguments
         // keep last
         if let Some(gitdir) = &self.gitdir {
-            args.push(gitdir.clone());
+            args.push(gitdir.clone().into());
         }
 
         args
     }
 
+    /// Checks `arch` against [`SUPPORTED_ARCHS`]. Not part of `validate`:
+    /// otherwise gbs decides which architectures its configuration supports.
+    pub fn validate_arch(&self) -> Result<(), ValidationError> {
+        match &self.arch {
+            Some(arch) if !SUPPORTED_ARCHS.contains(&arch.as_str()) => {
+                Err(ValidationError::new(&format!(
+                    "arch {} not supported, supported archs are: {}",
+                    arch,
+                    SUPPORTED_ARCHS.join(" ")
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the host paths `gbs build` reads from or writes to: the git
+    /// tree (the working directory when `gitdir` is unset), the build root
+    /// (`~/GBS-ROOT` when `buildroot` is unset), the local `-R`
+    /// repositories and the configuration file: `conf`, or else
+    /// `~/.gbs.conf`, if there is one.
+    pub fn mount_paths(&self) -> Vec<PathBuf> {
+        let gitdir = self.gitdir.as_deref().unwrap_or(Path::new("."));
+        let mut paths = vec![self.resolve(gitdir)];
+        paths.extend(self.buildroot_path());
+        for repo in self.repositories.iter().flatten() {
+            if !repo.contains("://") || repo.starts_with("file://") {
+                paths.push(self.resolve(Path::new(repo.trim_start_matches("file://"))));
+            }
+        }
+        match &self.conf {
+            Some(conf) => paths.push(self.resolve(conf)),
+            None => {
+                let conf =
+                    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".gbs.conf"));
+                paths.extend(conf.filter(|conf| conf.is_file()));
+            }
+        }
+        paths
+    }
+
+    /// Returns the build root gbs uses: `buildroot`, or `~/GBS-ROOT` when unset.
+    pub fn buildroot_path(&self) -> Option<PathBuf> {
+        match &self.buildroot {
+            Some(buildroot) => Some(self.resolve(buildroot)),
+            None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join("GBS-ROOT")),
+        }
+    }
+
+    // Makes `path` absolute the way gbs sees it, i.e. relative to `current_dir`.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.current_dir {
+            Some(cwd) => backend::absolute_path(cwd).join(path),
+            None => backend::absolute_path(path),
+        }
+    }
+
+    /// Returns a bash script running the same `gbs build` invocation on the
+    /// host, for people who only have a shell on the build host.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// std::fs::write("build.sh", options.to_shell_script()?)?;
+    /// ```
+    pub fn to_shell_script(&self) -> Result<String, ValidationError> {
+        GbsInvocation::new(self).to_shell_script()
+    }
+
     /// Executes the `gbs build` command with the specified options.
     pub fn execute(&self) -> Result<ExitStatus, std::io::Error> {
-        let mut command = Command::new("gbs");
-        command.arg("build");
-        command.args(self.to_args());
+        self.execute_with(&NativeBackend)
+    }
+
+    /// Executes the `gbs build` command through the given backend.
+    pub fn execute_with(
+        &self,
+        backend: &dyn ExecutionBackend,
+    ) -> Result<ExitStatus, std::io::Error> {
+        GbsInvocation::new(self).backend(backend).execute()
+    }
+}
 
-        let mut child = command
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .spawn()?;
+/// Architectures `gbs build -A` knows out of the box. Build configurations
+/// may add others, e.g. `riscv64`, so [`GbsBuildOptions::validate_arch`]
+/// checks against this list only on request.
+pub const SUPPORTED_ARCHS: &[&str] = &[
+    "x86_64", "i586", "armv6l", "armv7hl", "armv7l", "aarch64", "mips", "mipsel",
+];
 
-        child.wait()
+impl GbsSubcommand for GbsBuildOptions {
+    fn name(&self) -> &str {
+        "build"
+    }
+
+    fn to_args(&self) -> Vec<String> {
+        GbsBuildOptions::to_args(self)
+    }
+
+    fn to_os_args(&self) -> Vec<OsString> {
+        GbsBuildOptions::to_os_args(self)
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.incremental_options.validate()?;
+        if self.incremental_options.noinit && (self.clean || self.clean_once) {
+            return Err(ValidationError::new(
+                "--noinit can't be specified together with --clean or --clean-once",
+            ));
+        }
+        if self.fail_fast && self.keepgoing == Some(Keepgoing::On) {
+            return Err(ValidationError::new(
+                "--fail-fast can't be specified together with --keepgoing on",
+            ));
+        }
+        Ok(())
+    }
+
+    fn mount_paths(&self, current_dir: Option<&Path>) -> Vec<PathBuf> {
+        match current_dir {
+            Some(current_dir) => GbsBuildOptions {
+                current_dir: Some(current_dir.to_path_buf()),
+                ..self.clone()
+            }
+            .mount_paths(),
+            None => GbsBuildOptions::mount_paths(self),
+        }
+    }
+
+    fn created_paths(&self, current_dir: Option<&Path>) -> Vec<PathBuf> {
+        let buildroot = match current_dir {
+            Some(current_dir) => GbsBuildOptions {
+                current_dir: Some(current_dir.to_path_buf()),
+                ..self.clone()
+            }
+            .buildroot_path(),
+            None => self.buildroot_path(),
+        };
+        buildroot.into_iter().collect()
+    }
+
+    fn current_dir(&self) -> Option<PathBuf> {
+        self.current_dir.clone()
+    }
+
+    fn global_args(&self) -> Vec<OsString> {
+        match &self.conf {
+            Some(conf) => vec!["-c".into(), conf.clone().into()],
+            None => Vec::new(),
+        }
     }
 }
 
@@ -876,8 +1176,8 @@ impl GbsBuildOptionsBuilder {
     }
 
     // Build env options
-    pub fn buildroot(mut self, buildroot: String) -> Self {
-        self.options.buildroot = Some(buildroot);
+    pub fn buildroot<P: Into<PathBuf>>(mut self, buildroot: P) -> Self {
+        self.options.buildroot = Some(buildroot.into());
         self
     }
     pub fn clean(mut self, clean: bool) -> Self {
@@ -900,7 +1200,7 @@ impl GbsBuildOptionsBuilder {
         self
     }
 
-    pub fn keepgoing(mut self, keepgoing: u32) -> Self {
+    pub fn keepgoing(mut self, keepgoing: Keepgoing) -> Self {
         self.options.keepgoing = Some(keepgoing);
         self
     }
@@ -976,33 +1276,39 @@ impl GbsBuildOptionsBuilder {
     }
 
     // Speed up building options
+    /// Replaces all speed up options with a validated set.
+    pub fn incremental_options(mut self, incremental_options: IncrementalOptions) -> Self {
+        self.options.incremental_options = incremental_options;
+        self
+    }
+
     pub fn incremental(mut self, incremental: bool) -> Self {
-        self.options.incremental = incremental;
+        self.options.incremental_options.incremental = incremental;
         self
     }
 
     pub fn no_configure(mut self, no_configure: bool) -> Self {
-        self.options.no_configure = no_configure;
+        self.options.incremental_options.no_configure = no_configure;
         self
     }
 
     pub fn noinit(mut self, noinit: bool) -> Self {
-        self.options.noinit = noinit;
+        self.options.incremental_options.noinit = noinit;
         self
     }
 
     pub fn ccache(mut self, ccache: bool) -> Self {
-        self.options.ccache = ccache;
+        self.options.incremental_options.ccache = ccache;
         self
     }
 
     pub fn pkg_ccache(mut self, pkg_ccache: String) -> Self {
-        self.options.pkg_ccache = Some(pkg_ccache);
+        self.options.incremental_options.pkg_ccache = Some(pkg_ccache);
         self
     }
 
     pub fn icecream(mut self, icecream: u32) -> Self {
-        self.options.icecream = Some(icecream);
+        self.options.incremental_options.icecream = Some(icecream);
         self
     }
 
@@ -1032,8 +1338,8 @@ impl GbsBuildOptionsBuilder {
         self
     }
 
-    pub fn spec(mut self, spec: String) -> Self {
-        self.options.spec = Some(spec);
+    pub fn spec<P: Into<PathBuf>>(mut self, spec: P) -> Self {
+        self.options.spec = Some(spec.into());
         self
     }
 
@@ -1143,8 +1449,19 @@ impl GbsBuildOptionsBuilder {
         self
     }
 
-    pub fn gitdir(mut self, gitdir: String) -> Self {
-        self.options.gitdir = Some(gitdir);
+    pub fn gitdir<P: Into<PathBuf>>(mut self, gitdir: P) -> Self {
+        self.options.gitdir = Some(gitdir.into());
+        self
+    }
+
+    // Execution options
+    pub fn current_dir<P: Into<PathBuf>>(mut self, current_dir: P) -> Self {
+        self.options.current_dir = Some(current_dir.into());
+        self
+    }
+
+    pub fn conf<P: Into<PathBuf>>(mut self, conf: P) -> Self {
+        self.options.conf = Some(conf.into());
         self
     }
 
@@ -1156,6 +1473,11 @@ impl GbsBuildOptionsBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use backend::ContainerBackend;
+    use runner::CommandRunner;
+    use std::io;
+    use std::process::Command;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_builder_with_clean() {
@@ -1327,4 +1649,196 @@ mod tests {
         assert!(args.contains(&"--define".to_string()));
         assert!(args.contains(&"BAZ qux".to_string()));
     }
+
+    #[test]
+    fn test_validate_build_options() {
+        let options = GbsBuildOptions::builder()
+            .arch("armv7l".to_string())
+            .build();
+        assert!(options.validate().is_ok());
+
+        assert!(options.validate_arch().is_ok());
+
+        // Architectures added by a build configuration are left to gbs.
+        let options = GbsBuildOptions::builder()
+            .arch("riscv64".to_string())
+            .build();
+        assert!(options.validate().is_ok());
+        assert!(options.validate_arch().is_err());
+
+        let options = GbsBuildOptions::builder().noinit(true).clean(true).build();
+        assert!(options.validate().is_err());
+
+        let options = GbsBuildOptions::builder().no_configure(true).build();
+        assert!(options.validate().is_err());
+
+        let options = GbsBuildOptions::builder()
+            .fail_fast(true)
+            .keepgoing(Keepgoing::On)
+            .build();
+        assert!(options.validate().is_err());
+
+        let options = GbsBuildOptions::builder()
+            .fail_fast(true)
+            .keepgoing(Keepgoing::Off)
+            .build();
+        assert!(options.validate().is_ok());
+        assert_eq!(options.to_args(), vec!["--fail-fast", "--keepgoing", "off"]);
+    }
+
+    #[test]
+    fn test_merge_overlay_wins() {
+        let site = GbsBuildOptions::builder()
+            .arch("x86_64".to_string())
+            .buildroot("/data/GBS-ROOT".to_string())
+            .repository("http://site/repo".to_string())
+            .ccache(true)
+            .build();
+        let job = GbsBuildOptions::builder()
+            .arch("aarch64".to_string())
+            .repository("http://job/repo".to_string())
+            .threads(8)
+            .build();
+
+        let merged = site.merge(&job);
+
+        assert_eq!(merged.arch.as_deref(), Some("aarch64"));
+        assert_eq!(
+            merged.buildroot.as_deref(),
+            Some(Path::new("/data/GBS-ROOT"))
+        );
+        assert_eq!(
+            merged.repositories,
+            Some(vec!["http://job/repo".to_string()])
+        );
+        assert_eq!(merged.threads, Some(8));
+        assert!(merged.incremental_options.ccache);
+        assert_eq!(site.merge(&GbsBuildOptions::default()), site);
+    }
+
+    #[test]
+    fn test_to_shell_script() {
+        let options = GbsBuildOptions::builder()
+            .arch("armv7l".to_string())
+            .current_dir("/src/pkg")
+            .spec("my package.spec")
+            .build();
+
+        let script = options.to_shell_script().unwrap();
+        assert!(script.contains("\ncd /src/pkg\n"));
+        assert!(script.ends_with("\nexec gbs build -A armv7l --spec 'my package.spec'\n"));
+
+        let options = GbsBuildOptions::builder().noinit(true).clean(true).build();
+        assert!(options.to_shell_script().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let gitdir = PathBuf::from(std::ffi::OsStr::from_bytes(b"/src/caf\xe9 pkg"));
+        let options = GbsBuildOptions::builder()
+            .spec("my package.spec")
+            .gitdir(gitdir.clone())
+            .build();
+
+        assert_eq!(
+            options.to_os_args(),
+            vec![
+                OsString::from("--spec"),
+                OsString::from("my package.spec"),
+                gitdir.into_os_string()
+            ]
+        );
+        assert_eq!(options.to_args()[2], "/src/caf\u{FFFD} pkg");
+    }
+
+    #[test]
+    fn test_current_dir_is_not_an_argument() {
+        let options = GbsBuildOptions::builder()
+            .current_dir("/work".to_string())
+            .gitdir("pkg".to_string())
+            .buildroot("/data/GBS-ROOT".to_string())
+            .build();
+
+        assert_eq!(
+            options.to_args(),
+            vec![
+                "-B".to_string(),
+                "/data/GBS-ROOT".to_string(),
+                "pkg".to_string()
+            ]
+        );
+        assert_eq!(
+            options.mount_paths()[..2],
+            [PathBuf::from("/work/pkg"), PathBuf::from("/data/GBS-ROOT")]
+        );
+    }
+
+    // Records whether the build root exists when gbs runs.
+    struct BuildrootRunner(PathBuf, Mutex<Option<bool>>);
+
+    impl CommandRunner for BuildrootRunner {
+        fn output(&self, _command: &mut Command) -> io::Result<std::process::Output> {
+            *self.1.lock().unwrap() = Some(self.0.is_dir());
+            Command::new("true").output()
+        }
+
+        fn status(&self, command: &mut Command) -> io::Result<ExitStatus> {
+            Ok(self.output(command)?.status)
+        }
+    }
+
+    #[test]
+    fn test_container_build_creates_buildroot() {
+        let dir = tempfile::tempdir().unwrap();
+        let buildroot = dir.path().join("GBS-ROOT");
+        let options = GbsBuildOptions::builder()
+            .gitdir(dir.path().to_str().unwrap().to_string())
+            .buildroot(buildroot.to_str().unwrap().to_string())
+            .build();
+        let runner = Arc::new(BuildrootRunner(buildroot.clone(), Mutex::new(None)));
+
+        let status = GbsInvocation::new(&options)
+            .backend(ContainerBackend::new("tizen/gbs:latest"))
+            .runner(runner.clone())
+            .execute()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(*runner.1.lock().unwrap(), Some(true));
+    }
+
+    #[test]
+    fn test_invocation_current_dir_is_mounted() {
+        let options = GbsBuildOptions::builder()
+            .current_dir("/work")
+            .gitdir("pkg")
+            .build();
+
+        let spec = GbsInvocation::new(&options).current_dir("/other").spec();
+        assert_eq!(spec.current_dir, Some(PathBuf::from("/other")));
+        assert_eq!(spec.mounts[0], PathBuf::from("/other/pkg"));
+    }
+
+    #[test]
+    fn test_local_repositories_are_mounted() {
+        let options = GbsBuildOptions::builder()
+            .current_dir("/work")
+            .buildroot("/data/GBS-ROOT".to_string())
+            .repository("repos/local".to_string())
+            .repository("file:///srv/repo".to_string())
+            .repository("http://download.tizen.org/repo".to_string())
+            .build();
+
+        assert_eq!(
+            options.mount_paths()[..4],
+            [
+                PathBuf::from("/work"),
+                PathBuf::from("/data/GBS-ROOT"),
+                PathBuf::from("/work/repos/local"),
+                PathBuf::from("/srv/repo")
+            ]
+        );
+    }
 }
@@ -0,0 +1,278 @@
+//! Performance metrics of a build.
+//!
+//! [`MetricsCollector`] follows the progress messages depanneur prints while
+//! a build runs to time every package, samples the disk usage of the build
+//! root and measures the CPU time spent by gbs and the processes it waited
+//! for. The resulting
+//! [`BuildMetrics`] are meant to be stored and trended over time.
+
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::runner::{ChildIo, CpuTime, LineObserver};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageTiming {
+    /// Package as announced by depanneur, e.g. `acl-2.2.52-1`.
+    pub name: String,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildMetrics {
+    pub wall_time: Duration,
+    /// Packages that finished building, in completion order.
+    pub packages: Vec<PackageTiming>,
+    /// Highest disk usage of the build root seen, in bytes.
+    pub peak_disk_usage: Option<u64>,
+    /// User and system CPU time of the measured command and the processes
+    /// it waited for. Only measured on Unix.
+    pub child_cpu_time: Option<Duration>,
+}
+
+/// Collects [`BuildMetrics`] while a build runs.
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::metrics::MetricsCollector;
+///
+/// let collector = MetricsCollector::new().disk_path(options.buildroot_path().unwrap());
+/// let (status, metrics) = GbsInvocation::new(&options).execute_with_metrics(&collector)?;
+/// println!("{:?} spent building", metrics.wall_time);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MetricsCollector {
+    disk_path: Option<PathBuf>,
+    interval: Duration,
+}
+
+impl Default for MetricsCollector {
+    fn default() -> Self {
+        MetricsCollector {
+            disk_path: None,
+            interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl MetricsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directory whose disk usage is sampled, usually the build root.
+    pub fn disk_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.disk_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Time between two disk usage samples.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Runs `run` and measures it. `run` installs the given hooks, an
+    /// observer for the output lines and a receiver for the CPU time, on
+    /// the command it runs.
+    pub fn measure<F>(&self, run: F) -> io::Result<(ExitStatus, BuildMetrics)>
+    where
+        F: FnOnce(ChildIo) -> io::Result<ExitStatus>,
+    {
+        let progress = Arc::new(Mutex::new(ProgressTracker::default()));
+        let tracker = progress.clone();
+        let observer: LineObserver = Arc::new(move |line| tracker.lock().unwrap().feed(line));
+        let cpu_time = CpuTime::new();
+        let hooks = ChildIo {
+            on_line: Some(observer),
+            cpu_time: Some(cpu_time.clone()),
+            ..ChildIo::default()
+        };
+
+        let start = Instant::now();
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let (status, peak_disk_usage) = thread::scope(|s| {
+            let sampler = self.disk_path.as_deref().map(|path| {
+                s.spawn(move || {
+                    let mut peak = disk_usage(path).ok();
+                    while let Err(mpsc::RecvTimeoutError::Timeout) =
+                        stopped.recv_timeout(self.interval)
+                    {
+                        peak = peak.max(disk_usage(path).ok());
+                    }
+                    peak.max(disk_usage(path).ok())
+                })
+            });
+            let status = run(hooks);
+            let _ = stop.send(());
+            let peak = sampler.and_then(|sampler| sampler.join().ok().flatten());
+            (status, peak)
+        });
+        let status = status?;
+
+        let packages = std::mem::take(&mut progress.lock().unwrap().finished);
+
+        Ok((
+            status,
+            BuildMetrics {
+                wall_time: start.elapsed(),
+                packages,
+                peak_disk_usage,
+                child_cpu_time: cpu_time.get(),
+            },
+        ))
+    }
+}
+
+// Times packages from the progress messages of depanneur:
+//
+//   info: *** [1/12] building acl-2.2.52-1 armv7l tizen (worker: 0) ***
+//   info: finished building acl
+//   error: failed to build acl
+#[derive(Default)]
+struct ProgressTracker {
+    started: HashMap<String, Instant>,
+    finished: Vec<PackageTiming>,
+}
+
+impl ProgressTracker {
+    fn feed(&mut self, line: &str) {
+        if let Some(rest) = line.split_once("] building ").map(|(_, rest)| rest) {
+            if let Some(name) = rest.split_whitespace().next() {
+                self.started.insert(name.to_string(), Instant::now());
+            }
+            return;
+        }
+
+        let done = ["finished building ", "failed to build "]
+            .iter()
+            .find_map(|marker| line.split_once(marker).map(|(_, rest)| rest));
+        let Some(name) = done.and_then(|rest| rest.split_whitespace().next()) else {
+            return;
+        };
+        // The start message carries version and release, the end message may not.
+        let key = self
+            .started
+            .keys()
+            .find(|key| {
+                *key == name
+                    || key
+                        .strip_prefix(name)
+                        .and_then(|rest| rest.strip_prefix('-'))
+                        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            })
+            .cloned();
+        if let Some(key) = key {
+            let started = self.started.remove(&key).unwrap();
+            self.finished.push(PackageTiming {
+                name: key,
+                duration: started.elapsed(),
+            });
+        }
+    }
+}
+
+/// Returns the space `path` takes on disk, without crossing file systems.
+pub fn disk_usage(path: &Path) -> io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    let mut total = allocated(&metadata);
+    if metadata.is_dir() {
+        let dev = device(&metadata);
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            match fs::symlink_metadata(entry.path()) {
+                Ok(child) if child.is_dir() && device(&child) == dev => {
+                    total += disk_usage(&entry.path()).unwrap_or(0)
+                }
+                Ok(child) if !child.is_dir() => total += allocated(&child),
+                // Mount points, and entries removed while walking.
+                _ => (),
+            }
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(unix)]
+fn allocated(metadata: &Metadata) -> u64 {
+    metadata.blocks() * 512
+}
+
+// Without block counts, the apparent size is the closest estimate.
+#[cfg(not(unix))]
+fn allocated(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> Option<u64> {
+    Some(metadata.dev())
+}
+
+// Mount points cannot be told apart, the whole tree is walked.
+#[cfg(not(unix))]
+fn device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{CommandRunner, SystemRunner};
+    use std::process::Command;
+
+    #[test]
+    fn test_progress_tracker() {
+        let mut tracker = ProgressTracker::default();
+        for line in [
+            "info: *** [1/3] building acl-2.2.52-1 armv7l tizen (worker: 0) ***",
+            "info: *** [2/3] building acl-devel-1.0-1 armv7l tizen (worker: 1) ***",
+            "info: finished building acl-devel",
+            "error: failed to build acl",
+            "info: finished building unknown",
+        ] {
+            tracker.feed(line);
+        }
+
+        let names: Vec<_> = tracker.finished.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["acl-devel-1.0-1", "acl-2.2.52-1"]);
+        assert!(tracker.started.is_empty());
+    }
+
+    #[test]
+    fn test_measure() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("data"), vec![1u8; 64 * 1024]).unwrap();
+
+        let collector = MetricsCollector::new()
+            .disk_path(dir.path())
+            .interval(Duration::from_millis(10));
+        let (status, metrics) = collector
+            .measure(|hooks| {
+                let script = r#"
+                    echo "info: *** [1/1] building foo-1.0-1 x86_64 tizen (worker: 0) ***"
+                    i=0; while [ $i -lt 20000 ]; do i=$((i + 1)); done
+                    echo "info: finished building foo"
+                "#;
+                SystemRunner.status_with(Command::new("sh").args(["-c", script]), &hooks)
+            })
+            .unwrap();
+
+        assert!(status.success());
+        assert_eq!(metrics.packages.len(), 1);
+        assert!(metrics.peak_disk_usage.unwrap() >= 64 * 1024);
+        // Measured for the command alone, not for the whole test process.
+        let cpu_time = metrics.child_cpu_time.unwrap();
+        assert!(cpu_time > Duration::ZERO && cpu_time < metrics.wall_time + Duration::from_secs(1));
+    }
+}
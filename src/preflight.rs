@@ -0,0 +1,307 @@
+//! Reachability checks for the `-R` repositories of a build.
+//!
+//! Every repository is expected to serve `repodata/repomd.xml`. Remote
+//! repositories are probed with a `HEAD` request through `curl`, which
+//! honors the usual `http_proxy`/`https_proxy`/`no_proxy` environment
+//! variables as well as credentials embedded in the URL. Local
+//! repositories (plain paths or `file://` URLs) are checked on disk.
+
+use crate::curl;
+use crate::runner::{self, CommandRunner};
+use crate::GbsBuildOptions;
+use log::warn;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// What to do when a repository is not healthy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreflightPolicy {
+    /// Log problems as warnings and carry on.
+    #[default]
+    Warn,
+    /// Return an error so the build is not started.
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoStatus {
+    Ok,
+    /// The server answered with a redirect to `location`.
+    Redirected {
+        location: String,
+    },
+    Dead {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoCheck {
+    pub url: String,
+    pub status: RepoStatus,
+}
+
+/// Checks the repositories of a build before it starts.
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::preflight::{Preflight, PreflightPolicy};
+///
+/// let checks = Preflight::new()
+///     .policy(PreflightPolicy::Fail)
+///     .timeout(std::time::Duration::from_secs(5))
+///     .run(&options)?;
+/// ```
+#[derive(Clone)]
+pub struct Preflight {
+    policy: PreflightPolicy,
+    timeout: Duration,
+    jobs: usize,
+    credentials: Option<(String, String)>,
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Default for Preflight {
+    fn default() -> Self {
+        Preflight {
+            policy: PreflightPolicy::default(),
+            timeout: Duration::from_secs(10),
+            jobs: 8,
+            credentials: None,
+            runner: runner::default_runner(),
+        }
+    }
+}
+
+impl Preflight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn policy(mut self, policy: PreflightPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Maximum number of repositories probed concurrently.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Credentials used for repositories that do not embed them in the URL,
+    /// passed to curl on its stdin rather than its command line.
+    pub fn credentials(mut self, user: &str, password: &str) -> Self {
+        self.credentials = Some((user.to_string(), password.to_string()));
+        self
+    }
+
+    pub fn runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Checks all repositories configured in `options` and applies the policy.
+    pub fn run(&self, options: &GbsBuildOptions) -> io::Result<Vec<RepoCheck>> {
+        let repos = options.repositories.clone().unwrap_or_default();
+        let checks = self.check(&repos);
+
+        let unhealthy: Vec<_> = checks
+            .iter()
+            .filter(|c| c.status != RepoStatus::Ok)
+            .collect();
+        for check in &unhealthy {
+            match &check.status {
+                RepoStatus::Redirected { location } => {
+                    warn!("Repository '{}' redirects to '{}'", check.url, location)
+                }
+                RepoStatus::Dead { reason } => {
+                    warn!("Repository '{}' is unreachable: {}", check.url, reason)
+                }
+                RepoStatus::Ok => (),
+            }
+        }
+
+        if self.policy == PreflightPolicy::Fail && !unhealthy.is_empty() {
+            let urls: Vec<_> = unhealthy.iter().map(|c| c.url.as_str()).collect();
+            return Err(io::Error::other(format!(
+                "Repository preflight failed for: {}",
+                urls.join(", ")
+            )));
+        }
+
+        Ok(checks)
+    }
+
+    /// Probes `repos` in parallel and returns one result per repository, in input order.
+    ///
+    /// Up to `jobs` workers take the next repository as soon as they are
+    /// done with one, so a slow repository only holds up its own worker.
+    pub fn check(&self, repos: &[String]) -> Vec<RepoCheck> {
+        let next = AtomicUsize::new(0);
+        let statuses = Mutex::new(vec![None; repos.len()]);
+        thread::scope(|s| {
+            let workers: Vec<_> = (0..self.jobs.min(repos.len()))
+                .map(|_| {
+                    s.spawn(|| loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(url) = repos.get(index) else {
+                            break;
+                        };
+                        let status = self.check_one(url);
+                        statuses.lock().unwrap()[index] = Some(status);
+                    })
+                })
+                .collect();
+            // A panicking check leaves its repository without a status.
+            for worker in workers {
+                let _ = worker.join();
+            }
+        });
+        let statuses = statuses.into_inner().unwrap_or_else(|e| e.into_inner());
+        repos
+            .iter()
+            .zip(statuses)
+            .map(|(url, status)| RepoCheck {
+                url: url.clone(),
+                status: status.unwrap_or_else(|| RepoStatus::Dead {
+                    reason: "check panicked".to_string(),
+                }),
+            })
+            .collect()
+    }
+
+    fn check_one(&self, url: &str) -> RepoStatus {
+        let repomd = format!("{}/repodata/repomd.xml", url.trim_end_matches('/'));
+
+        if !url.contains("://") || url.starts_with("file://") {
+            let path = repomd.trim_start_matches("file://");
+            return if Path::new(path).is_file() {
+                RepoStatus::Ok
+            } else {
+                RepoStatus::Dead {
+                    reason: format!("'{}' does not exist", path),
+                }
+            };
+        }
+
+        let mut cmd = Command::new("curl");
+        cmd.args([
+            "--head",
+            "--silent",
+            "--show-error",
+            "--output",
+            "/dev/null",
+        ])
+        .arg("--max-time")
+        .arg(self.timeout.as_secs().max(1).to_string())
+        .args(["--write-out", "%{http_code} %{redirect_url}"]);
+        cmd.arg(&repomd);
+
+        match curl::output(&*self.runner, &mut cmd, self.credentials.as_ref()) {
+            Ok(output) if output.status.success() => {
+                parse_curl_write_out(&String::from_utf8_lossy(&output.stdout))
+            }
+            Ok(output) => RepoStatus::Dead {
+                reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            },
+            Err(e) => RepoStatus::Dead {
+                reason: format!("Failed to execute curl: {}", e),
+            },
+        }
+    }
+}
+
+/// Interprets curl's `%{http_code} %{redirect_url}` output.
+fn parse_curl_write_out(out: &str) -> RepoStatus {
+    let (code, location) = out.trim().split_once(' ').unwrap_or((out.trim(), ""));
+    match code.parse::<u16>() {
+        Ok(200..=299) => RepoStatus::Ok,
+        Ok(300..=399) => RepoStatus::Redirected {
+            location: location.trim().to_string(),
+        },
+        Ok(code) => RepoStatus::Dead {
+            reason: format!("HTTP {}", code),
+        },
+        Err(_) => RepoStatus::Dead {
+            reason: format!("Unexpected curl output '{}'", out.trim()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_curl_write_out() {
+        assert_eq!(parse_curl_write_out("200 "), RepoStatus::Ok);
+        assert_eq!(
+            parse_curl_write_out("301 https://new.example.com/repodata/repomd.xml"),
+            RepoStatus::Redirected {
+                location: "https://new.example.com/repodata/repomd.xml".to_string()
+            }
+        );
+        assert_eq!(
+            parse_curl_write_out("404 "),
+            RepoStatus::Dead {
+                reason: "HTTP 404".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_keeps_input_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let repos: Vec<String> = (0..5)
+            .map(|i| {
+                let repo = dir.path().join(i.to_string());
+                if i % 2 == 0 {
+                    fs::create_dir_all(repo.join("repodata")).unwrap();
+                    fs::write(repo.join("repodata/repomd.xml"), "<repomd/>").unwrap();
+                }
+                repo.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let checks = Preflight::new().jobs(2).check(&repos);
+        assert_eq!(checks.len(), 5);
+        for (i, check) in checks.iter().enumerate() {
+            assert_eq!(check.url, repos[i]);
+            assert_eq!(check.status == RepoStatus::Ok, i % 2 == 0);
+        }
+    }
+
+    #[test]
+    fn test_local_repositories() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good");
+        fs::create_dir_all(good.join("repodata")).unwrap();
+        fs::write(good.join("repodata/repomd.xml"), "<repomd/>").unwrap();
+        let bad = dir.path().join("bad");
+
+        let options = GbsBuildOptions::builder()
+            .repository(good.to_str().unwrap().to_string())
+            .repository(format!("file://{}", bad.display()))
+            .build();
+
+        let checks = Preflight::new().run(&options).unwrap();
+        assert_eq!(checks[0].status, RepoStatus::Ok);
+        assert!(matches!(checks[1].status, RepoStatus::Dead { .. }));
+
+        let result = Preflight::new().policy(PreflightPolicy::Fail).run(&options);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Ready-made option sets for well-known Tizen build targets.
+//!
+//! Presets are plain data: the built-in table can be inspected with
+//! [`tizen_presets`] and callers can describe their own targets with
+//! [`BuildPreset`] values. Repository URLs may contain an `{arch}`
+//! placeholder that is replaced with the requested architecture.
+
+use crate::subcommand::ValidationError;
+use crate::{GbsBuildOptions, GbsBuildOptionsBuilder};
+
+const TIZEN_SNAPSHOTS: &str = "http://download.tizen.org/snapshots/TIZEN/Tizen";
+
+// (name, profile, repositories relative to TIZEN_SNAPSHOTS, supported archs)
+const TIZEN_PRESETS: &[(&str, &str, &[&str], &[&str])] = &[
+    (
+        "tizen-base",
+        "profile.tizen_base",
+        &["Tizen-Base/reference/repos/standard/packages/"],
+        &["armv7l", "aarch64", "i586", "x86_64"],
+    ),
+    (
+        "tizen-unified",
+        "profile.tizen_unified",
+        &[
+            "Tizen-Base/reference/repos/standard/packages/",
+            "Tizen-Unified/reference/repos/standard/packages/",
+        ],
+        &["armv7l", "aarch64", "i586", "x86_64"],
+    ),
+    (
+        "tizen-unified-emulator",
+        "profile.tizen_unified_emulator",
+        &[
+            "Tizen-Base/reference/repos/standard/packages/",
+            "Tizen-Unified/reference/repos/emulator/packages/",
+        ],
+        &["i586", "x86_64"],
+    ),
+];
+
+/// Describes the dist/profile/repositories of a build target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildPreset {
+    pub name: String,
+    /// Build configuration file. When unset gbs uses the one published
+    /// with the repositories.
+    pub dist: Option<String>,
+    pub profile: Option<String>,
+    pub repositories: Vec<String>,
+    /// Architectures the target is published for; empty means any.
+    pub archs: Vec<String>,
+}
+
+impl BuildPreset {
+    pub fn supports_arch(&self, arch: &str) -> bool {
+        self.archs.is_empty() || self.archs.iter().any(|a| a == arch)
+    }
+
+    /// Returns a builder pre-populated for `arch`, or an error when the
+    /// target is not published for `arch`.
+    pub fn builder(&self, arch: &str) -> Result<GbsBuildOptionsBuilder, ValidationError> {
+        if !self.supports_arch(arch) {
+            return Err(ValidationError::new(&format!(
+                "preset {} does not support arch {}, supported archs are: {}",
+                self.name,
+                arch,
+                self.archs.join(" ")
+            )));
+        }
+        let mut builder = GbsBuildOptions::builder().arch(arch.to_string());
+        if let Some(dist) = &self.dist {
+            builder = builder.dist(dist.clone());
+        }
+        if let Some(profile) = &self.profile {
+            builder = builder.profile(profile.clone());
+        }
+        if !self.repositories.is_empty() {
+            builder = builder.repositories(
+                self.repositories
+                    .iter()
+                    .map(|repo| repo.replace("{arch}", arch))
+                    .collect(),
+            );
+        }
+        Ok(builder)
+    }
+}
+
+/// The built-in Tizen presets.
+pub fn tizen_presets() -> Vec<BuildPreset> {
+    TIZEN_PRESETS
+        .iter()
+        .map(|(name, profile, repositories, archs)| BuildPreset {
+            name: name.to_string(),
+            dist: None,
+            profile: Some(profile.to_string()),
+            repositories: repositories
+                .iter()
+                .map(|repo| format!("{}/{}", TIZEN_SNAPSHOTS, repo))
+                .collect(),
+            archs: archs.iter().map(|a| a.to_string()).collect(),
+        })
+        .collect()
+}
+
+/// Looks up a preset by name in `presets`.
+pub fn find_preset<'a>(presets: &'a [BuildPreset], name: &str) -> Option<&'a BuildPreset> {
+    presets.iter().find(|p| p.name == name)
+}
+
+impl GbsBuildOptions {
+    /// Returns a builder for one of the built-in presets, see [`tizen_presets`].
+    /// Fails for unknown presets and archs the preset does not support.
+    pub fn preset(name: &str, arch: &str) -> Result<GbsBuildOptionsBuilder, ValidationError> {
+        match find_preset(&tizen_presets(), name) {
+            Some(preset) => preset.builder(arch),
+            None => Err(ValidationError::new(&format!("unknown preset {}", name))),
+        }
+    }
+
+    /// Tizen Unified reference snapshot (base + unified standard repositories).
+    pub fn tizen_unified(arch: &str) -> Result<GbsBuildOptionsBuilder, ValidationError> {
+        Self::preset("tizen-unified", arch)
+    }
+
+    /// Tizen Unified reference snapshot with the emulator repository.
+    pub fn tizen_unified_emulator(arch: &str) -> Result<GbsBuildOptionsBuilder, ValidationError> {
+        Self::preset("tizen-unified-emulator", arch)
+    }
+
+    /// Tizen Base reference snapshot.
+    pub fn tizen_base(arch: &str) -> Result<GbsBuildOptionsBuilder, ValidationError> {
+        Self::preset("tizen-base", arch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tizen_unified_preset() {
+        let options = GbsBuildOptions::tizen_unified("aarch64")
+            .unwrap()
+            .clean(true)
+            .build();
+
+        assert_eq!(options.to_args(), vec![
+            "-A".to_string(), "aarch64".to_string(),
+            "-P".to_string(), "profile.tizen_unified".to_string(),
+            "-R".to_string(), "http://download.tizen.org/snapshots/TIZEN/Tizen/Tizen-Base/reference/repos/standard/packages/".to_string(),
+            "-R".to_string(), "http://download.tizen.org/snapshots/TIZEN/Tizen/Tizen-Unified/reference/repos/standard/packages/".to_string(),
+            "-C".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_custom_preset_table() {
+        let presets = vec![BuildPreset {
+            name: "vendor".to_string(),
+            dist: Some("vendor.conf".to_string()),
+            profile: None,
+            repositories: vec!["http://mirror/{arch}/packages/".to_string()],
+            archs: vec!["armv7l".to_string()],
+        }];
+
+        let preset = find_preset(&presets, "vendor").unwrap();
+        assert!(preset.supports_arch("armv7l"));
+        assert!(!preset.supports_arch("x86_64"));
+
+        let options = preset.builder("armv7l").unwrap().build();
+        assert_eq!(options.dist.as_deref(), Some("vendor.conf"));
+        assert_eq!(
+            options.repositories,
+            Some(vec!["http://mirror/armv7l/packages/".to_string()])
+        );
+        assert!(GbsBuildOptions::preset("unknown", "armv7l").is_err());
+    }
+
+    #[test]
+    fn test_preset_unsupported_arch() {
+        let err = GbsBuildOptions::tizen_unified_emulator("armv7l")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.message,
+            "preset tizen-unified-emulator does not support arch armv7l, supported archs are: i586 x86_64"
+        );
+        assert!(GbsBuildOptions::tizen_unified_emulator("x86_64").is_ok());
+    }
+}
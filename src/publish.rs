@@ -0,0 +1,890 @@
+//! Publishing of build artifacts and logs to remote storage.
+//!
+//! Files are uploaded with HTTP `PUT` requests through `curl`, a limited
+//! number at a time. Server errors (5xx), throttling (429) and connection
+//! failures are retried with an exponential backoff. With a journal
+//! configured every finished file is recorded with its size and
+//! modification time, so an interrupted publish resumes with the files it
+//! has not uploaded, or that changed since.
+//!
+//! Plain HTTP, WebDAV and artifact servers take each `PUT` as the whole
+//! file, so large files can instead be sent in parts with the
+//! [tus](https://tus.io/protocols/resumable-upload) resumable upload
+//! protocol, see [`Publisher::resumable`]. The journal then also records
+//! the upload URL the server gave, and an interrupted upload continues
+//! from the offset the server has.
+
+use crate::curl;
+use crate::runner::{self, CommandRunner};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// A local file and where it goes, relative to the publisher's base URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadItem {
+    pub local: PathBuf,
+    pub remote: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadStatus {
+    Uploaded {
+        attempts: u32,
+    },
+    /// The journal already records the file, at its current size and
+    /// modification time, as uploaded.
+    Skipped,
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadResult {
+    pub item: UploadItem,
+    pub status: UploadStatus,
+}
+
+// Outcome of a single request.
+enum Attempt<T = ()> {
+    Done(T),
+    Retry(String),
+    Fatal(String),
+}
+
+impl<T> Attempt<T> {
+    fn and_then<U>(self, f: impl FnOnce(T) -> Attempt<U>) -> Attempt<U> {
+        match self {
+            Attempt::Done(value) => f(value),
+            Attempt::Retry(reason) => Attempt::Retry(reason),
+            Attempt::Fatal(reason) => Attempt::Fatal(reason),
+        }
+    }
+}
+
+// The status code and headers of a response, from `--dump-header -`.
+struct Response {
+    code: u16,
+    headers: String,
+}
+
+impl Response {
+    // The value of header `name` in the last response curl got.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.lines().rev().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    fn offset(&self) -> Option<u64> {
+        self.header("Upload-Offset")?.parse().ok()
+    }
+}
+
+// Where the tus uploads of a publisher are created, and their part size.
+#[derive(Clone)]
+struct Resumable {
+    endpoint: String,
+    part_size: u64,
+}
+
+// What the journal records: the uploaded files, and the upload URLs of
+// the resumable uploads started.
+#[derive(Default)]
+struct Journal {
+    done: HashSet<String>,
+    uploads: HashMap<String, String>,
+}
+
+/// Uploads files to a base URL.
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::publish::Publisher;
+///
+/// let publisher = Publisher::new("https://storage.example.com/snapshots/20240101.1")
+///     .jobs(4)
+///     .journal("/var/tmp/publish-20240101.1.journal");
+/// let items = Publisher::items_from_dir(&report_dir, "logs")?;
+/// let results = publisher.publish(&items)?;
+/// ```
+#[derive(Clone)]
+pub struct Publisher {
+    base_url: String,
+    jobs: usize,
+    retries: u32,
+    retry_delay: Duration,
+    timeout: Duration,
+    credentials: Option<(String, String)>,
+    journal: Option<PathBuf>,
+    resumable: Option<Resumable>,
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Publisher {
+    pub fn new(base_url: &str) -> Self {
+        Publisher {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            jobs: 4,
+            retries: 5,
+            retry_delay: Duration::from_secs(2),
+            timeout: Duration::from_secs(600),
+            credentials: None,
+            journal: None,
+            resumable: None,
+            runner: runner::default_runner(),
+        }
+    }
+
+    /// Maximum number of concurrent uploads.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Number of retries after a retryable failure.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Delay before the first retry; doubled for every further retry.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Timeout of a single request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Credentials for the server, passed to curl on its stdin rather than
+    /// its command line.
+    pub fn credentials(mut self, user: &str, password: &str) -> Self {
+        self.credentials = Some((user.to_string(), password.to_string()));
+        self
+    }
+
+    /// Records finished uploads in `path` and skips them on the next run.
+    pub fn journal<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.journal = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Uploads files larger than `part_size` to the tus server creating
+    /// uploads at `endpoint`, in `PATCH` requests of up to `part_size`
+    /// bytes. The remote path of a file is given as its `filename`
+    /// metadata. A failed part is retried from the offset the server has.
+    pub fn resumable(mut self, endpoint: &str, part_size: u64) -> Self {
+        self.resumable = Some(Resumable {
+            endpoint: endpoint.to_string(),
+            part_size: part_size.max(1),
+        });
+        self
+    }
+
+    pub fn runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Lists every file below `dir`, to be uploaded below `prefix`.
+    pub fn items_from_dir(dir: &Path, prefix: &str) -> io::Result<Vec<UploadItem>> {
+        let mut items = Vec::new();
+        collect_files(dir, dir, prefix.trim_matches('/'), &mut items)?;
+        items.sort_by(|a, b| a.remote.cmp(&b.remote));
+        Ok(items)
+    }
+
+    /// Uploads `items` and returns one result per item, in input order.
+    ///
+    /// Up to `jobs` workers take the next file as soon as they are done
+    /// with one, so a large file only holds up its own worker. Failed
+    /// uploads do not stop the others; an error is only returned when the
+    /// journal cannot be read or opened.
+    pub fn publish(&self, items: &[UploadItem]) -> io::Result<Vec<UploadResult>> {
+        let done = match &self.journal {
+            Some(path) => read_journal(path)?,
+            None => Journal::default(),
+        };
+        let journal = match &self.journal {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+        let journal = journal.as_ref();
+
+        let next = AtomicUsize::new(0);
+        let statuses = Mutex::new(vec![None; items.len()]);
+        thread::scope(|s| {
+            let workers: Vec<_> = (0..self.jobs.min(items.len()))
+                .map(|_| {
+                    s.spawn(|| loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        let status = self.upload(item, &done, journal);
+                        statuses.lock().unwrap()[index] = Some(status);
+                    })
+                })
+                .collect();
+            // A panicking upload leaves its file without a status.
+            for worker in workers {
+                let _ = worker.join();
+            }
+        });
+        let statuses = statuses.into_inner().unwrap_or_else(|e| e.into_inner());
+        Ok(items
+            .iter()
+            .zip(statuses)
+            .map(|(item, status)| UploadResult {
+                item: item.clone(),
+                status: status.unwrap_or_else(|| UploadStatus::Failed {
+                    reason: "upload panicked".to_string(),
+                }),
+            })
+            .collect())
+    }
+
+    fn url(&self, item: &UploadItem) -> String {
+        format!("{}/{}", self.base_url, item.remote.trim_start_matches('/'))
+    }
+
+    fn upload(
+        &self,
+        item: &UploadItem,
+        done: &Journal,
+        journal: Option<&Mutex<File>>,
+    ) -> UploadStatus {
+        let url = self.url(item);
+        let (key, size) = match journal_key(&item.local, &url) {
+            Ok(key) => key,
+            Err(e) => {
+                return UploadStatus::Failed {
+                    reason: format!("Failed to read '{}': {}", item.local.display(), e),
+                }
+            }
+        };
+        if done.done.contains(&key) {
+            return UploadStatus::Skipped;
+        }
+
+        let result = match &self.resumable {
+            Some(resumable) if size > resumable.part_size => {
+                self.upload_resumable(resumable, item, &key, size, done, journal)
+            }
+            _ => self
+                .with_retries(|| self.put(&item.local, &url))
+                .map(|((), attempts)| attempts),
+        };
+        match result {
+            Ok(attempts) => match record(journal, &key) {
+                Ok(()) => UploadStatus::Uploaded { attempts },
+                Err(e) => UploadStatus::Failed {
+                    reason: format!("Failed to update journal: {}", e),
+                },
+            },
+            Err(reason) => UploadStatus::Failed { reason },
+        }
+    }
+
+    // Sends `item` in parts, continuing the upload the journal records for
+    // `key` if the server still has it. Returns the number of requests it
+    // took, or the last failure.
+    fn upload_resumable(
+        &self,
+        resumable: &Resumable,
+        item: &UploadItem,
+        key: &str,
+        size: u64,
+        done: &Journal,
+        journal: Option<&Mutex<File>>,
+    ) -> Result<u32, String> {
+        let mut attempts = 0;
+        let mut location = done.uploads.get(key).cloned();
+        let mut created = false;
+        loop {
+            let upload = match &location {
+                Some(location) => location.clone(),
+                None => {
+                    let (upload, n) =
+                        self.with_retries(|| self.create(&resumable.endpoint, item, size))?;
+                    attempts += n;
+                    record(journal, &format!("tus {} {}", upload, key))
+                        .map_err(|e| format!("Failed to update journal: {}", e))?;
+                    created = true;
+                    location = Some(upload.clone());
+                    upload
+                }
+            };
+            let (offset, n) = self
+                .with_retries(|| self.send_part(&item.local, &upload, size, resumable.part_size))?;
+            attempts += n;
+            match offset {
+                Some(offset) if offset >= size => return Ok(attempts),
+                Some(_) => {}
+                None if created => {
+                    return Err(format!("Upload '{}' disappeared from the server", upload))
+                }
+                // The server dropped the upload of an earlier run.
+                None => location = None,
+            }
+        }
+    }
+
+    // Returns the value and the number of attempts it took, or the last
+    // failure.
+    fn with_retries<T, F: Fn() -> Attempt<T>>(&self, request: F) -> Result<(T, u32), String> {
+        let mut delay = self.retry_delay;
+        for attempt in 1.. {
+            match request() {
+                Attempt::Done(value) => return Ok((value, attempt)),
+                Attempt::Fatal(reason) => return Err(reason),
+                Attempt::Retry(reason) if attempt > self.retries => return Err(reason),
+                Attempt::Retry(_) => {
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    fn put(&self, file: &Path, url: &str) -> Attempt {
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--output", "/dev/null"])
+            .arg("--max-time")
+            .arg(self.timeout.as_secs().max(1).to_string())
+            .args(["--write-out", "%{http_code}"])
+            .arg("--upload-file")
+            .arg(file);
+        cmd.arg(url);
+
+        match curl::output(&*self.runner, &mut cmd, self.credentials.as_ref()) {
+            Ok(output) if output.status.success() => {
+                classify_http_code(&String::from_utf8_lossy(&output.stdout))
+            }
+            // curl itself failed: DNS, connection reset, timeout, ...
+            Ok(output) => {
+                Attempt::Retry(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            }
+            Err(e) => Attempt::Fatal(format!("Failed to execute curl: {}", e)),
+        }
+    }
+
+    // Creates a tus upload of `size` bytes for `item`, returning its URL.
+    fn create(&self, endpoint: &str, item: &UploadItem, size: u64) -> Attempt<String> {
+        let mut cmd = self.tus_command();
+        cmd.args(["--request", "POST", "--header", "Content-Length: 0"])
+            .arg("--header")
+            .arg(format!("Upload-Length: {}", size))
+            .arg("--header")
+            .arg(format!(
+                "Upload-Metadata: filename {}",
+                BASE64_STANDARD.encode(&item.remote)
+            ));
+        self.send(cmd, endpoint).and_then(|response| {
+            match (response.code, response.header("Location")) {
+                (201, Some(location)) => Attempt::Done(resolve_url(endpoint, location)),
+                (code, _) => Attempt::Fatal(format!("HTTP {}", code)),
+            }
+        })
+    }
+
+    // Asks the server for the offset of `upload` and sends the next part of
+    // `file` from there. Returns the offset the server has after it, or
+    // `None` if the server no longer has the upload.
+    fn send_part(
+        &self,
+        file: &Path,
+        upload: &str,
+        size: u64,
+        part_size: u64,
+    ) -> Attempt<Option<u64>> {
+        let mut cmd = self.tus_command();
+        cmd.arg("--head");
+        self.send(cmd, upload).and_then(|response| {
+            let offset = match (response.code, response.offset()) {
+                (404 | 410, _) => return Attempt::Done(None),
+                (200 | 204, Some(offset)) if offset >= size => return Attempt::Done(Some(offset)),
+                (200 | 204, Some(offset)) => offset,
+                (code, _) => return Attempt::Fatal(format!("HTTP {} for '{}'", code, upload)),
+            };
+            let end = (offset + part_size).min(size);
+            let name = upload.rsplit('/').next().unwrap_or_default();
+            let part = match write_part(file, name, offset, end) {
+                Ok(part) => part,
+                Err(e) => {
+                    return Attempt::Fatal(format!(
+                        "Failed to read part of '{}': {}",
+                        file.display(),
+                        e
+                    ))
+                }
+            };
+            let mut cmd = self.tus_command();
+            cmd.args(["--request", "PATCH"])
+                .args(["--header", "Content-Type: application/offset+octet-stream"])
+                .arg("--header")
+                .arg(format!("Upload-Offset: {}", offset))
+                .arg("--upload-file")
+                .arg(&part);
+            let attempt = self.send(cmd, upload);
+            let _ = fs::remove_file(&part);
+            attempt.and_then(|response| match (response.code, response.offset()) {
+                (204, Some(next)) if next > offset => Attempt::Done(Some(next)),
+                // Another request moved the offset; the retry asks again.
+                (409, _) => Attempt::Retry("HTTP 409".to_string()),
+                (code, _) => Attempt::Fatal(format!("HTTP {} for '{}'", code, upload)),
+            })
+        })
+    }
+
+    fn tus_command(&self) -> Command {
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--output", "/dev/null"])
+            .args(["--dump-header", "-"])
+            .arg("--max-time")
+            .arg(self.timeout.as_secs().max(1).to_string())
+            .args(["--write-out", "\\n%{http_code}"])
+            .args(["--header", "Tus-Resumable: 1.0.0"]);
+        cmd
+    }
+
+    fn send(&self, mut cmd: Command, url: &str) -> Attempt<Response> {
+        cmd.arg(url);
+        match curl::output(&*self.runner, &mut cmd, self.credentials.as_ref()) {
+            Ok(output) if output.status.success() => {
+                let out = String::from_utf8_lossy(&output.stdout);
+                let (headers, code) = out.rsplit_once('\n').unwrap_or(("", &out));
+                match code.trim().parse::<u16>() {
+                    Ok(code @ (429 | 500..=599)) => Attempt::Retry(format!("HTTP {}", code)),
+                    Ok(code) => Attempt::Done(Response {
+                        code,
+                        headers: headers.to_string(),
+                    }),
+                    Err(_) => Attempt::Fatal(format!("Unexpected curl output '{}'", code.trim())),
+                }
+            }
+            Ok(output) => {
+                Attempt::Retry(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            }
+            Err(e) => Attempt::Fatal(format!("Failed to execute curl: {}", e)),
+        }
+    }
+}
+
+// Resolves the `Location` a server answered with against `url`.
+fn resolve_url(url: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    if location.starts_with('/') {
+        let host = rest.split('/').next().unwrap_or(rest);
+        return format!("{}://{}{}", scheme, host, location);
+    }
+    match url.rsplit_once('/') {
+        Some((dir, _)) if dir.len() > scheme.len() + 2 => format!("{}/{}", dir, location),
+        _ => format!("{}/{}", url, location),
+    }
+}
+
+fn classify_http_code(out: &str) -> Attempt {
+    match out.trim().parse::<u16>() {
+        Ok(200..=299) => Attempt::Done(()),
+        Ok(code @ (429 | 500..=599)) => Attempt::Retry(format!("HTTP {}", code)),
+        Ok(code) => Attempt::Fatal(format!("HTTP {}", code)),
+        Err(_) => Attempt::Fatal(format!("Unexpected curl output '{}'", out.trim())),
+    }
+}
+
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    prefix: &str,
+    items: &mut Vec<UploadItem>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, prefix, items)?;
+        } else {
+            let relative = path.strip_prefix(root).unwrap().to_string_lossy();
+            let remote = if prefix.is_empty() {
+                relative.into_owned()
+            } else {
+                format!("{}/{}", prefix, relative)
+            };
+            items.push(UploadItem {
+                local: path.clone(),
+                remote,
+            });
+        }
+    }
+    Ok(())
+}
+
+// The journal line of `local` uploaded to `url`, and its size: a file
+// changed since it was uploaded is uploaded again.
+fn journal_key(local: &Path, url: &str) -> io::Result<(String, u64)> {
+    let metadata = fs::metadata(local)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let key = format!("{} {} {}", url, metadata.len(), modified.as_nanos());
+    Ok((key, metadata.len()))
+}
+
+// Reads the journal at `path`, where `tus <upload> <key>` lines record the
+// upload started for a file and other lines the files uploaded.
+fn read_journal(path: &Path) -> io::Result<Journal> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Journal::default()),
+        Err(e) => return Err(e),
+    };
+    let mut journal = Journal::default();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        match line
+            .strip_prefix("tus ")
+            .and_then(|upload| upload.split_once(' '))
+        {
+            Some((upload, key)) => {
+                journal.uploads.insert(key.to_string(), upload.to_string());
+            }
+            None => {
+                journal.done.insert(line);
+            }
+        }
+    }
+    Ok(journal)
+}
+
+// Copies bytes `start..end` of `path` next to the system temp files, as
+// part `name`, since curl can only upload whole files.
+fn write_part(path: &Path, name: &str, start: u64, end: u64) -> io::Result<PathBuf> {
+    let part = std::env::temp_dir().join(format!(
+        "gbsw-{}-{}.{}-{}.part",
+        std::process::id(),
+        name,
+        start,
+        end
+    ));
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut out = File::create(&part)?;
+    io::copy(&mut file.take(end - start), &mut out)?;
+    Ok(part)
+}
+
+fn record(journal: Option<&Mutex<File>>, key: &str) -> io::Result<()> {
+    match journal {
+        Some(journal) => {
+            let mut journal = journal.lock().unwrap();
+            writeln!(journal, "{}", key)?;
+            journal.flush()
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "trace")]
+    use crate::runner::{Replayer, TraceEntry};
+
+    #[cfg(feature = "trace")]
+    fn curl_put(file: &Path, url: &str, http_code: &str) -> TraceEntry {
+        TraceEntry {
+            program: "curl".to_string(),
+            args: [
+                "--silent",
+                "--show-error",
+                "--output",
+                "/dev/null",
+                "--max-time",
+                "600",
+                "--write-out",
+                "%{http_code}",
+                "--upload-file",
+                file.to_str().unwrap(),
+                url,
+            ]
+            .iter()
+            .map(|a| a.to_string())
+            .collect(),
+            current_dir: None,
+            env: Vec::new(),
+            stdin: None,
+            status: 0,
+            stdout: http_code.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_retry_on_server_error() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("out/x86_64")).unwrap();
+        let rpm = dir.path().join("out/x86_64/foo-1.0-1.x86_64.rpm");
+        let log = dir.path().join("out/log.txt");
+        fs::write(&rpm, "rpm").unwrap();
+        fs::write(&log, "log").unwrap();
+
+        let items = Publisher::items_from_dir(&dir.path().join("out"), "repos").unwrap();
+        assert_eq!(items[0].remote, "repos/log.txt");
+        assert_eq!(items[1].remote, "repos/x86_64/foo-1.0-1.x86_64.rpm");
+
+        let rpm_url = "https://storage/snap/repos/x86_64/foo-1.0-1.x86_64.rpm";
+        let log_url = "https://storage/snap/repos/log.txt";
+        let replayer = Replayer::from_entries(vec![
+            curl_put(&rpm, rpm_url, "503"),
+            curl_put(&rpm, rpm_url, "201"),
+            curl_put(&log, log_url, "403"),
+        ]);
+        let journal = dir.path().join("journal");
+        let publisher = Publisher::new("https://storage/snap/")
+            .retry_delay(Duration::ZERO)
+            .journal(&journal)
+            .runner(Arc::new(replayer));
+
+        let results = publisher.publish(&items).unwrap();
+        assert_eq!(
+            results[0].status,
+            UploadStatus::Failed {
+                reason: "HTTP 403".to_string()
+            }
+        );
+        assert_eq!(results[1].status, UploadStatus::Uploaded { attempts: 2 });
+
+        // Only the failed file is uploaded again.
+        let replayer = Replayer::from_entries(vec![curl_put(&log, log_url, "200")]);
+        let results = publisher
+            .clone()
+            .runner(Arc::new(replayer))
+            .publish(&items)
+            .unwrap();
+        assert_eq!(results[0].status, UploadStatus::Uploaded { attempts: 1 });
+        assert_eq!(results[1].status, UploadStatus::Skipped);
+
+        // Files changed since are uploaded again.
+        fs::write(&rpm, "rebuilt rpm").unwrap();
+        let replayer = Replayer::from_entries(vec![curl_put(&rpm, rpm_url, "200")]);
+        let results = publisher
+            .runner(Arc::new(replayer))
+            .publish(&items)
+            .unwrap();
+        assert_eq!(results[0].status, UploadStatus::Skipped);
+        assert_eq!(results[1].status, UploadStatus::Uploaded { attempts: 1 });
+    }
+
+    #[cfg(feature = "trace")]
+    fn curl_tus(request: &[&str], url: &str, response: &str) -> TraceEntry {
+        let mut args = vec![
+            "--silent",
+            "--show-error",
+            "--output",
+            "/dev/null",
+            "--dump-header",
+            "-",
+            "--max-time",
+            "600",
+            "--write-out",
+            "\\n%{http_code}",
+            "--header",
+            "Tus-Resumable: 1.0.0",
+        ];
+        args.extend(request);
+        args.push(url);
+        TraceEntry {
+            program: "curl".to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            current_dir: None,
+            env: Vec::new(),
+            stdin: None,
+            status: 0,
+            stdout: response.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    #[cfg(feature = "trace")]
+    fn tus_offset(offset: u64, code: u16) -> String {
+        format!(
+            "HTTP/1.1 {} \r\nUpload-Offset: {}\r\n\r\n\n{}",
+            code, offset, code
+        )
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_resumable_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("big.rpm");
+        fs::write(&file, "0123456789").unwrap();
+        let items = [UploadItem {
+            local: file.clone(),
+            remote: "repos/big.rpm".to_string(),
+        }];
+        let upload = "https://storage/files/abc";
+        let part = |start: u64, end: u64| {
+            std::env::temp_dir()
+                .join(format!(
+                    "gbsw-{}-abc.{}-{}.part",
+                    std::process::id(),
+                    start,
+                    end
+                ))
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        let patch = |start: u64, end: u64, response: &str| {
+            let offset = format!("Upload-Offset: {}", start);
+            let part = part(start, end);
+            curl_tus(
+                &[
+                    "--request",
+                    "PATCH",
+                    "--header",
+                    "Content-Type: application/offset+octet-stream",
+                    "--header",
+                    &offset,
+                    "--upload-file",
+                    &part,
+                ],
+                upload,
+                response,
+            )
+        };
+        let head = |offset: u64| curl_tus(&["--head"], upload, &tus_offset(offset, 200));
+
+        // The second part fails, after the server got the first one.
+        let replayer = Replayer::from_entries(vec![
+            curl_tus(
+                &[
+                    "--request",
+                    "POST",
+                    "--header",
+                    "Content-Length: 0",
+                    "--header",
+                    "Upload-Length: 10",
+                    "--header",
+                    "Upload-Metadata: filename cmVwb3MvYmlnLnJwbQ==",
+                ],
+                "https://storage/files/",
+                "HTTP/1.1 201 Created\r\nLocation: /files/abc\r\n\r\n\n201",
+            ),
+            head(0),
+            patch(0, 4, &tus_offset(4, 204)),
+            head(4),
+            patch(4, 8, "HTTP/1.1 403 Forbidden\r\n\r\n\n403"),
+        ]);
+        let journal = dir.path().join("journal");
+        let publisher = Publisher::new("https://storage/snap")
+            .resumable("https://storage/files/", 4)
+            .retry_delay(Duration::ZERO)
+            .journal(&journal)
+            .runner(Arc::new(replayer));
+        let results = publisher.publish(&items).unwrap();
+        assert_eq!(
+            results[0].status,
+            UploadStatus::Failed {
+                reason: format!("HTTP 403 for '{}'", upload)
+            }
+        );
+        assert!(!Path::new(&part(0, 4)).exists());
+
+        // The next run continues from the offset the server has.
+        let replayer = Replayer::from_entries(vec![
+            head(4),
+            patch(4, 8, &tus_offset(8, 204)),
+            head(8),
+            patch(8, 10, &tus_offset(10, 204)),
+        ]);
+        let results = publisher
+            .clone()
+            .runner(Arc::new(replayer))
+            .publish(&items)
+            .unwrap();
+        assert_eq!(results[0].status, UploadStatus::Uploaded { attempts: 2 });
+
+        let results = publisher
+            .runner(Arc::new(Replayer::from_entries(Vec::new())))
+            .publish(&items)
+            .unwrap();
+        assert_eq!(results[0].status, UploadStatus::Skipped);
+    }
+
+    // Answers every request with HTTP 200, keeping what it was given.
+    #[derive(Default)]
+    struct CapturingRunner(Mutex<Vec<(Vec<String>, String)>>);
+
+    impl CommandRunner for CapturingRunner {
+        fn output(&self, command: &mut Command) -> io::Result<std::process::Output> {
+            self.output_with_stdin(command, b"")
+        }
+
+        fn output_with_stdin(
+            &self,
+            command: &mut Command,
+            input: &[u8],
+        ) -> io::Result<std::process::Output> {
+            let args = command
+                .get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect();
+            let input = String::from_utf8_lossy(input).into_owned();
+            self.0.lock().unwrap().push((args, input));
+            Command::new("echo").args(["-n", "200"]).output()
+        }
+
+        fn status(&self, command: &mut Command) -> io::Result<std::process::ExitStatus> {
+            Ok(self.output(command)?.status)
+        }
+    }
+
+    #[test]
+    fn test_credentials_on_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("foo.rpm");
+        fs::write(&file, "rpm").unwrap();
+        let items = [UploadItem {
+            local: file,
+            remote: "foo.rpm".to_string(),
+        }];
+
+        let runner = Arc::new(CapturingRunner::default());
+        let results = Publisher::new("https://storage/snap")
+            .credentials("builder", r#"p"a:ss"#)
+            .runner(runner.clone())
+            .publish(&items)
+            .unwrap();
+        assert_eq!(results[0].status, UploadStatus::Uploaded { attempts: 1 });
+
+        let calls = runner.0.lock().unwrap();
+        let (args, input) = &calls[0];
+        assert!(args.iter().all(|arg| !arg.contains("ss")));
+        assert!(args.ends_with(&["--config".to_string(), "-".to_string()]));
+        assert_eq!(input, "user = \"builder:p\\\"a:ss\"\n");
+    }
+}
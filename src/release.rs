@@ -0,0 +1,278 @@
+use crate::runner::{self, CommandRunner};
+use git_utils::GitCommand;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Strategy used by [`ReleaseGenerator`] to compute a `Release` value.
+#[derive(Debug, Clone)]
+pub enum ReleaseStrategy {
+    /// `<YYYYMMDD>.<counter>`, where the counter is taken from a state file
+    /// and increases with every build of the same day. The state file is
+    /// locked while it is updated, so concurrent builds get their own
+    /// counter.
+    DateCounter { state_path: PathBuf },
+    /// `<commit count>.g<short sha>`, where the count is that of every
+    /// commit reachable from `HEAD`, so that it keeps increasing across
+    /// tags.
+    GitDescribe { gitdir: PathBuf },
+}
+
+/// Generates values for `gbs build --release`.
+///
+/// Generated releases increase monotonically across CI runs. When a build
+/// key (e.g. the CI pipeline id) is passed to [`ReleaseGenerator::generate`],
+/// re-running the same build yields the same release again.
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::release::ReleaseGenerator;
+/// use gbsw::GbsBuildOptions;
+///
+/// let generator = ReleaseGenerator::date_counter("/var/lib/ci/release.db");
+/// let options = GbsBuildOptions::builder()
+///     .release(generator.generate(Some("pipeline-1234"))?)
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct ReleaseGenerator {
+    strategy: ReleaseStrategy,
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl ReleaseGenerator {
+    pub fn new(strategy: ReleaseStrategy) -> Self {
+        ReleaseGenerator {
+            strategy,
+            runner: runner::default_runner(),
+        }
+    }
+
+    /// Runs git through `runner` instead of spawning it directly.
+    pub fn runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    pub fn date_counter<P: AsRef<Path>>(state_path: P) -> Self {
+        Self::new(ReleaseStrategy::DateCounter {
+            state_path: state_path.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn git_describe<P: AsRef<Path>>(gitdir: P) -> Self {
+        Self::new(ReleaseStrategy::GitDescribe {
+            gitdir: gitdir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Generates a release string for today's date (UTC).
+    pub fn generate(&self, build_key: Option<&str>) -> io::Result<String> {
+        self.generate_for_date(&today(), build_key)
+    }
+
+    /// Generates a release string for the given `YYYYMMDD` date.
+    ///
+    /// The date is ignored by the git describe strategy.
+    pub fn generate_for_date(&self, date: &str, build_key: Option<&str>) -> io::Result<String> {
+        match &self.strategy {
+            ReleaseStrategy::DateCounter { state_path } => {
+                next_date_counter(state_path, date, build_key)
+            }
+            ReleaseStrategy::GitDescribe { gitdir } => {
+                git_describe_release(self.runner.as_ref(), gitdir)
+            }
+        }
+    }
+}
+
+// The state file holds one line per generated release: `<date> <counter> [<build key>]`.
+fn next_date_counter(state_path: &Path, date: &str, build_key: Option<&str>) -> io::Result<String> {
+    if let Some(parent) = state_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(state_path)?;
+    // Released when the file is closed.
+    file.lock()?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+
+    let mut counter = 0;
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(line_date), Some(line_counter)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let line_counter: u32 = line_counter.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid counter in release state file: '{}'", line),
+            )
+        })?;
+        if build_key.is_some() && fields.next() == build_key {
+            return Ok(format!("{}.{}", line_date, line_counter));
+        }
+        if line_date == date {
+            counter = counter.max(line_counter);
+        }
+    }
+    counter += 1;
+
+    file.seek(SeekFrom::End(0))?;
+    match build_key {
+        Some(key) => writeln!(file, "{} {} {}", date, counter, key)?,
+        None => writeln!(file, "{} {}", date, counter)?,
+    }
+
+    Ok(format!("{}.{}", date, counter))
+}
+
+fn git_describe_release(runner: &dyn CommandRunner, gitdir: &Path) -> io::Result<String> {
+    let count = run_git(runner, gitdir, &["rev-list", "--count", "HEAD"])?;
+    let sha = run_git(runner, gitdir, &["rev-parse", "--short", "HEAD"])?;
+    Ok(format!("{}.g{}", count.trim(), sha.trim()))
+}
+
+fn run_git(runner: &dyn CommandRunner, gitdir: &Path, args: &[&str]) -> io::Result<String> {
+    let mut command = GitCommand::new("git")
+        .arg("-C")
+        .arg(gitdir)
+        .args(args)
+        .to_command();
+    let output = runner.output(&mut command)?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{:04}{:02}{:02}", year, month, day)
+}
+
+// Converts days since 1970-01-01 into a (year, month, day) proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_counter_increments_and_is_reproducible() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state").join("release.db");
+        let generator = ReleaseGenerator::date_counter(&state);
+
+        assert_eq!(
+            generator.generate_for_date("20250101", None).unwrap(),
+            "20250101.1"
+        );
+        assert_eq!(
+            generator
+                .generate_for_date("20250101", Some("ci-7"))
+                .unwrap(),
+            "20250101.2"
+        );
+        assert_eq!(
+            generator.generate_for_date("20250101", None).unwrap(),
+            "20250101.3"
+        );
+        assert_eq!(
+            generator.generate_for_date("20250102", None).unwrap(),
+            "20250102.1"
+        );
+        // Re-running a known build returns the release recorded for it.
+        assert_eq!(
+            generator
+                .generate_for_date("20250102", Some("ci-7"))
+                .unwrap(),
+            "20250101.2"
+        );
+    }
+
+    #[test]
+    fn test_date_counter_concurrent_builds() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("release.db");
+
+        let mut releases: Vec<String> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| {
+                        ReleaseGenerator::date_counter(&state)
+                            .generate_for_date("20250101", None)
+                            .unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        releases.sort_by_key(|release| release[9..].parse::<u32>().unwrap());
+        let expected: Vec<_> = (1..=8).map(|n| format!("20250101.{}", n)).collect();
+        assert_eq!(releases, expected);
+    }
+
+    #[test]
+    fn test_git_describe_release_keeps_increasing() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = GitCommand::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(&["-c", "user.name=gbsw", "-c", "user.email=gbsw@example.com"])
+                .args(args)
+                .to_command()
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+        };
+        git(&["init", "-q"]);
+        let generator = ReleaseGenerator::git_describe(dir.path());
+        let count = |release: String| release.split('.').next().unwrap().to_string();
+
+        git(&["commit", "-q", "--allow-empty", "-m", "one"]);
+        assert_eq!(count(generator.generate(None).unwrap()), "1");
+        git(&["commit", "-q", "--allow-empty", "-m", "two"]);
+        git(&["tag", "v1.0"]);
+        assert_eq!(count(generator.generate(None).unwrap()), "2");
+        git(&["commit", "-q", "--allow-empty", "-m", "three"]);
+        let release = generator.generate(None).unwrap();
+        assert_eq!(count(release.clone()), "3");
+        assert!(release.starts_with("3.g"));
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19358), (2023, 1, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+    }
+}
@@ -0,0 +1,264 @@
+//! Parsing of the reports depanneur leaves behind after a build.
+//!
+//! For every profile and architecture depanneur keeps a local repository
+//! under the build root, e.g. `~/GBS-ROOT/local/repos/tizen/armv7l/`. Next to
+//! the generated `RPMS`/`SRPMS` it writes:
+//!
+//! - `report.json`: a summary plus one entry per package, in build order:
+//!
+//!   ```text
+//!   {
+//!     "summary": { "packages_total": 2, ... },
+//!     "build_details": [
+//!       { "package_name": "foo", "package_version": "1.0-1", "status": "succeeded",
+//!         "log_path": "logs/success/foo-1.0-1/log.txt", "build_time": 12.5 },
+//!       ...
+//!     ]
+//!   }
+//!   ```
+//!
+//! - `logs/success/<package>-<version>/log.txt` and
+//!   `logs/fail/<package>-<version>/log.txt`.
+//!
+//! [`DepanneurReport::load`] reads `report.json` when present and otherwise
+//! reconstructs what it can from the `logs` directory.
+
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Outcome of a single package in a multi-package build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackageStatus {
+    Succeeded,
+    ExportError,
+    ExpansionError,
+    BuildError,
+    NotBuilt,
+    /// A status this crate does not know, as depanneur reported it.
+    Unknown(String),
+}
+
+impl PackageStatus {
+    fn from_report(status: &str) -> Self {
+        match status {
+            "succeeded" | "success" => PackageStatus::Succeeded,
+            "export_error" => PackageStatus::ExportError,
+            "expansion_error" => PackageStatus::ExpansionError,
+            "failed" | "build_error" | "fail" => PackageStatus::BuildError,
+            "" => PackageStatus::NotBuilt,
+            status => PackageStatus::Unknown(status.to_string()),
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            PackageStatus::ExportError | PackageStatus::ExpansionError | PackageStatus::BuildError
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageReport {
+    pub name: String,
+    pub version: Option<String>,
+    pub status: PackageStatus,
+    /// Absolute path of the package build log, if one was written.
+    pub log_path: Option<PathBuf>,
+    pub duration: Option<Duration>,
+}
+
+/// Typed view of a depanneur build report.
+#[derive(Debug, Clone, Default)]
+pub struct DepanneurReport {
+    /// Packages in the order depanneur built them.
+    pub packages: Vec<PackageReport>,
+}
+
+#[derive(Deserialize)]
+struct RawReport {
+    #[serde(default)]
+    build_details: Vec<RawPackage>,
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    package_name: String,
+    #[serde(default)]
+    package_version: Option<String>,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    log_path: Option<String>,
+    #[serde(default)]
+    build_time: Option<f64>,
+}
+
+impl DepanneurReport {
+    /// Returns the local repository directory depanneur reports into.
+    pub fn report_dir(buildroot: &Path, profile: &str, arch: &str) -> PathBuf {
+        buildroot
+            .join("local")
+            .join("repos")
+            .join(profile)
+            .join(arch)
+    }
+
+    /// Loads the report found in `report_dir`.
+    pub fn load(report_dir: &Path) -> io::Result<Self> {
+        let json_path = report_dir.join("report.json");
+        if json_path.is_file() {
+            let content = fs::read_to_string(&json_path)?;
+            return Self::from_json_str(&content, report_dir);
+        }
+        Self::from_logs_dir(&report_dir.join("logs"))
+    }
+
+    /// Parses the content of a `report.json`. Relative log paths are
+    /// resolved against `report_dir`.
+    pub fn from_json_str(content: &str, report_dir: &Path) -> io::Result<Self> {
+        let raw: RawReport = serde_json::from_str(content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let packages = raw
+            .build_details
+            .into_iter()
+            .map(|p| PackageReport {
+                name: p.package_name,
+                version: p.package_version,
+                status: PackageStatus::from_report(&p.status),
+                log_path: p.log_path.map(|log| report_dir.join(log)),
+                duration: p
+                    .build_time
+                    .filter(|t| t.is_finite() && *t >= 0.0)
+                    .map(Duration::from_secs_f64),
+            })
+            .collect();
+
+        Ok(DepanneurReport { packages })
+    }
+
+    /// Reconstructs a report from the `logs/success` and `logs/fail`
+    /// directories, splitting the `<package>-<version>-<release>` names of
+    /// their subdirectories. Build order and timing are not available this
+    /// way.
+    pub fn from_logs_dir(logs_dir: &Path) -> io::Result<Self> {
+        let mut packages = Vec::new();
+        for (subdir, status) in [
+            ("success", PackageStatus::Succeeded),
+            ("fail", PackageStatus::BuildError),
+        ] {
+            let dir = logs_dir.join(subdir);
+            if !dir.is_dir() {
+                continue;
+            }
+            let mut entries: Vec<_> = fs::read_dir(&dir)?.collect::<Result<_, _>>()?;
+            entries.sort_by_key(|e| e.file_name());
+            for entry in entries {
+                let log = entry.path().join("log.txt");
+                let (name, version) = split_version(&entry.file_name().to_string_lossy());
+                packages.push(PackageReport {
+                    name,
+                    version,
+                    status: status.clone(),
+                    log_path: log.is_file().then_some(log),
+                    duration: None,
+                });
+            }
+        }
+        Ok(DepanneurReport { packages })
+    }
+
+    pub fn succeeded(&self) -> impl Iterator<Item = &PackageReport> {
+        self.packages
+            .iter()
+            .filter(|p| p.status == PackageStatus::Succeeded)
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &PackageReport> {
+        self.packages.iter().filter(|p| p.status.is_failure())
+    }
+
+    pub fn not_built(&self) -> impl Iterator<Item = &PackageReport> {
+        self.packages
+            .iter()
+            .filter(|p| p.status == PackageStatus::NotBuilt)
+    }
+
+    /// Names of the packages in build order.
+    pub fn build_order(&self) -> Vec<&str> {
+        self.packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    /// Sum of the per-package build times that were reported.
+    pub fn total_duration(&self) -> Duration {
+        self.packages.iter().filter_map(|p| p.duration).sum()
+    }
+}
+
+// Splits a `<package>-<version>-<release>` log directory name into the
+// package name and `<version>-<release>`, keeping names without both.
+fn split_version(dir_name: &str) -> (String, Option<String>) {
+    let mut parts = dir_name.rsplitn(3, '-');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(release), Some(version), Some(name)) if !name.is_empty() => {
+            (name.to_string(), Some(format!("{}-{}", version, release)))
+        }
+        _ => (dir_name.to_string(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report_json() {
+        let json = r#"{
+            "summary": { "packages_total": 3 },
+            "build_details": [
+                { "package_name": "libfoo", "package_version": "1.0-1", "status": "succeeded",
+                  "log_path": "logs/success/libfoo-1.0-1/log.txt", "build_time": 12.5 },
+                { "package_name": "bar", "status": "failed", "build_time": 3 },
+                { "package_name": "baz", "status": "" },
+                { "package_name": "qux", "status": "cancelled" }
+            ]
+        }"#;
+        let report = DepanneurReport::from_json_str(json, Path::new("/repo")).unwrap();
+
+        assert_eq!(report.build_order(), vec!["libfoo", "bar", "baz", "qux"]);
+        assert_eq!(report.succeeded().count(), 1);
+        assert_eq!(report.failed().next().unwrap().name, "bar");
+        assert_eq!(report.not_built().next().unwrap().name, "baz");
+        assert_eq!(
+            report.packages[3].status,
+            PackageStatus::Unknown("cancelled".to_string())
+        );
+        assert_eq!(
+            report.packages[0].log_path.as_deref(),
+            Some(Path::new("/repo/logs/success/libfoo-1.0-1/log.txt"))
+        );
+        assert_eq!(report.total_duration(), Duration::from_secs_f64(15.5));
+    }
+
+    #[test]
+    fn test_load_from_logs_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = dir.path().join("logs");
+        fs::create_dir_all(logs.join("success/foo-1.0-1")).unwrap();
+        fs::write(logs.join("success/foo-1.0-1/log.txt"), "ok").unwrap();
+        fs::create_dir_all(logs.join("fail/bar-devel-2.0-1")).unwrap();
+
+        let report = DepanneurReport::load(dir.path()).unwrap();
+
+        assert_eq!(report.build_order(), vec!["foo", "bar-devel"]);
+        assert_eq!(report.packages[1].version.as_deref(), Some("2.0-1"));
+        assert_eq!(report.packages[0].status, PackageStatus::Succeeded);
+        assert!(report.packages[0].log_path.is_some());
+        assert_eq!(report.packages[1].status, PackageStatus::BuildError);
+        assert!(report.packages[1].log_path.is_none());
+    }
+}
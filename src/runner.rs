@@ -0,0 +1,468 @@
+//! Execution of external processes, with optional record and replay.
+//!
+//! Everything this crate runs (gbs, git, curl, ...) goes through a
+//! [`CommandRunner`]. With the `trace` feature, `Recorder` captures every
+//! command, its inputs and its results to a trace file, and `Replayer`
+//! later serves those results without starting any process. This makes
+//! higher-level tooling built on this crate testable deterministically.
+
+use std::io::{self, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "trace")]
+mod trace;
+#[cfg(feature = "trace")]
+pub use trace::{Recorder, Replayer, TraceEntry};
+
+/// Runs prepared commands.
+pub trait CommandRunner: Send + Sync {
+    /// Runs the command with its stdout/stderr captured.
+    fn output(&self, command: &mut Command) -> io::Result<Output>;
+
+    /// Runs the command with `input` written to its stdin and its
+    /// stdout/stderr captured.
+    fn output_with_stdin(&self, command: &mut Command, input: &[u8]) -> io::Result<Output> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that does not read its stdin closes it early.
+            let _ = stdin.write_all(input);
+        }
+        child.wait_with_output()
+    }
+
+    /// Runs the command with the stdio configured on it and waits for it.
+    fn status(&self, command: &mut Command) -> io::Result<ExitStatus>;
+
+    /// Like [`CommandRunner::status`], with the stdio hooks of `child_io`.
+    fn status_with(&self, command: &mut Command, child_io: &ChildIo) -> io::Result<ExitStatus> {
+        if child_io.stdin.is_some() {
+            command.stdin(Stdio::piped());
+        }
+        if child_io.on_line.is_some() {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        own_process_group(command, child_io);
+        let mut child = command.spawn()?;
+        if let (Some(responder), Some(stdin)) = (&child_io.stdin, child.stdin.take()) {
+            responder.attach(stdin);
+        }
+
+        let on_line = &child_io.on_line;
+        let stdout = child
+            .stdout
+            .take()
+            .map(|out| tee(out, io::stdout, on_line.clone(), false));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|err| tee(err, io::stderr, on_line.clone(), false));
+        let status = wait(&mut child, child_io);
+        for handle in stdout.into_iter().chain(stderr) {
+            let _ = handle.join();
+        }
+        status
+    }
+}
+
+/// Receives the output of a command line by line.
+pub type LineObserver = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Hooks into the stdio of a command run by [`CommandRunner::status_with`].
+#[derive(Clone, Default)]
+pub struct ChildIo {
+    /// Answers prompts on stdin; stdin is inherited when `None`.
+    pub stdin: Option<StdinResponder>,
+    /// Called for every line written to stdout or stderr. The output is
+    /// still forwarded to our own stdout/stderr.
+    pub on_line: Option<LineObserver>,
+    /// Kills the command once triggered.
+    pub abort: Option<AbortSignal>,
+    /// Receives the CPU time of the command once it exits.
+    pub cpu_time: Option<CpuTime>,
+}
+
+impl ChildIo {
+    pub fn is_empty(&self) -> bool {
+        self.stdin.is_none()
+            && self.on_line.is_none()
+            && self.abort.is_none()
+            && self.cpu_time.is_none()
+    }
+}
+
+/// User and system CPU time of a command and of the processes it waited
+/// for. Only measured on Unix.
+#[derive(Debug, Clone, Default)]
+pub struct CpuTime(Arc<Mutex<Option<Duration>>>);
+
+impl CpuTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The CPU time, once the command exited.
+    pub fn get(&self) -> Option<Duration> {
+        *self.0.lock().unwrap()
+    }
+
+    #[cfg(unix)]
+    fn set(&self, time: Duration) {
+        *self.0.lock().unwrap() = Some(time);
+    }
+}
+
+/// Asks a running command to be killed, e.g. from a watchdog thread.
+///
+/// On Unix a command that can be aborted runs in its own process group, so
+/// that the processes it started are stopped with it. A Ctrl-C in the
+/// terminal then no longer reaches it directly.
+#[derive(Debug, Clone, Default)]
+pub struct AbortSignal(Arc<Mutex<Option<String>>>);
+
+impl AbortSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kills the command; `reason` becomes the error returned by the runner.
+    pub fn abort(&self, reason: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| reason.to_string());
+    }
+
+    pub fn reason(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+// How long an aborted command has to exit after SIGTERM before it is
+// killed.
+#[cfg(unix)]
+const ABORT_GRACE: Duration = Duration::from_secs(10);
+
+// Starts the command in a process group of its own when it can be aborted,
+// see [`AbortSignal`].
+pub(crate) fn own_process_group(command: &mut Command, child_io: &ChildIo) {
+    #[cfg(unix)]
+    if child_io.abort.is_some() {
+        std::os::unix::process::CommandExt::process_group(command, 0);
+    }
+    #[cfg(not(unix))]
+    let _ = (command, child_io);
+}
+
+// Waits for `child`, killing it when the abort signal of `child_io` is
+// triggered first.
+pub(crate) fn wait(child: &mut Child, child_io: &ChildIo) -> io::Result<ExitStatus> {
+    let cpu_time = child_io.cpu_time.as_ref();
+    let Some(abort) = &child_io.abort else {
+        return reap(child, cpu_time, false).map(|status| status.expect("blocking wait"));
+    };
+    loop {
+        if let Some(status) = reap(child, cpu_time, true)? {
+            return Ok(status);
+        }
+        if let Some(reason) = abort.reason() {
+            terminate(child, cpu_time)?;
+            return Err(io::Error::new(io::ErrorKind::Interrupted, reason));
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+// Stops `child` and the processes it started. On Unix its process group
+// gets SIGTERM, which `docker run` and `podman run` pass on to the
+// container, then SIGKILL once `child` exited or ABORT_GRACE passed.
+fn terminate(child: &mut Child, cpu_time: Option<&CpuTime>) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let group = child.id() as libc::pid_t;
+        // SAFETY: killpg only sends a signal.
+        unsafe { libc::killpg(group, libc::SIGTERM) };
+        let deadline = std::time::Instant::now() + ABORT_GRACE;
+        let mut exited = false;
+        while !exited && std::time::Instant::now() < deadline {
+            exited = reap(child, cpu_time, true)?.is_some();
+            if !exited {
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+        // Children ignoring SIGTERM may outlive `child`.
+        // SAFETY: as above.
+        unsafe { libc::killpg(group, libc::SIGKILL) };
+        if exited {
+            return Ok(());
+        }
+    }
+    #[cfg(not(unix))]
+    child.kill()?;
+    reap(child, cpu_time, false)?;
+    Ok(())
+}
+
+// Waits for `child` to exit, or only checks whether it did with `nohang`.
+// The standard library does not report the resource usage of a child, so
+// it is waited for with wait4(2) when its CPU time is wanted.
+fn reap(
+    child: &mut Child,
+    cpu_time: Option<&CpuTime>,
+    nohang: bool,
+) -> io::Result<Option<ExitStatus>> {
+    #[cfg(unix)]
+    if let Some(cpu_time) = cpu_time {
+        use std::os::unix::process::ExitStatusExt;
+
+        let flags = if nohang { libc::WNOHANG } else { 0 };
+        let mut status = 0;
+        // SAFETY: rusage is plain data, and both pointers outlive the call.
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            let pid =
+                unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, flags, &mut usage) };
+            match pid {
+                0 => return Ok(None),
+                -1 => {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+                _ => {
+                    let time = |tv: libc::timeval| {
+                        Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+                    };
+                    cpu_time.set(time(usage.ru_utime) + time(usage.ru_stime));
+                    return Ok(Some(ExitStatus::from_raw(status)));
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = cpu_time;
+
+    if nohang {
+        child.try_wait()
+    } else {
+        child.wait().map(Some)
+    }
+}
+
+/// Answers interactive prompts, so unattended runs do not hang on stdin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StdinResponder {
+    /// Answers `y` to every prompt.
+    AutoYes,
+    /// Answers `n` to every prompt.
+    AutoNo,
+    /// Answers the prompts in order, then closes stdin.
+    Scripted(Vec<String>),
+}
+
+impl StdinResponder {
+    // The writer is detached: gbs may hand its stdin down to long-running
+    // children, so it is not known when the pipe closes. Once it does the
+    // write fails and the thread ends.
+    pub(crate) fn attach(&self, mut stdin: ChildStdin) {
+        let responder = self.clone();
+        thread::spawn(move || match responder {
+            StdinResponder::AutoYes => while stdin.write_all(b"y\n").is_ok() {},
+            StdinResponder::AutoNo => while stdin.write_all(b"n\n").is_ok() {},
+            StdinResponder::Scripted(answers) => {
+                for answer in answers {
+                    if writeln!(stdin, "{}", answer).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Runs commands as regular child processes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, command: &mut Command) -> io::Result<Output> {
+        command.output()
+    }
+
+    fn status(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        command.status()
+    }
+}
+
+// Forwards everything read from `reader` to `sink`. Complete lines are
+// passed to `on_line` as they arrive. With `capture` the whole output is
+// returned once the reader is closed; otherwise only the current line is
+// kept in memory and an empty string is returned.
+pub(crate) fn tee<R, W, F>(
+    mut reader: R,
+    sink: F,
+    on_line: Option<LineObserver>,
+    capture: bool,
+) -> thread::JoinHandle<String>
+where
+    R: Read + Send + 'static,
+    W: Write,
+    F: Fn() -> W + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut captured = Vec::new();
+        let mut line = Vec::new();
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            let chunk = &buf[..n];
+            let _ = sink().write_all(chunk);
+            if capture {
+                captured.extend_from_slice(chunk);
+            }
+            if let Some(on_line) = &on_line {
+                let mut rest = chunk;
+                while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+                    line.extend_from_slice(&rest[..end]);
+                    on_line(&String::from_utf8_lossy(&line));
+                    line.clear();
+                    rest = &rest[end + 1..];
+                }
+                line.extend_from_slice(rest);
+            }
+        }
+        if let Some(on_line) = &on_line {
+            if !line.is_empty() {
+                on_line(&String::from_utf8_lossy(&line));
+            }
+        }
+        String::from_utf8_lossy(&captured).into_owned()
+    })
+}
+
+/// Returns the runner used when none is configured.
+pub fn default_runner() -> Arc<dyn CommandRunner> {
+    Arc::new(SystemRunner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stdin_responder() {
+        let script = r#"read a; read b; test "$a$b" = "$0""#;
+        let run = |expected: &str, responder: StdinResponder| {
+            let child_io = ChildIo {
+                stdin: Some(responder),
+                ..ChildIo::default()
+            };
+            SystemRunner
+                .status_with(Command::new("sh").args(["-c", script, expected]), &child_io)
+                .unwrap()
+                .success()
+        };
+
+        assert!(run("yy", StdinResponder::AutoYes));
+        assert!(run("nn", StdinResponder::AutoNo));
+        assert!(run(
+            "yesno",
+            StdinResponder::Scripted(vec!["yes".to_string(), "no".to_string()])
+        ));
+        assert!(!run("yy", StdinResponder::AutoNo));
+    }
+
+    #[test]
+    fn test_abort_signal() {
+        let abort = AbortSignal::new();
+        let child_io = ChildIo {
+            abort: Some(abort.clone()),
+            ..ChildIo::default()
+        };
+        abort.abort("disk full");
+
+        let err = SystemRunner
+            .status_with(Command::new("sleep").arg("10"), &child_io)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert_eq!(err.to_string(), "disk full");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_abort_stops_the_processes_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let abort = AbortSignal::new();
+        let child_io = ChildIo {
+            abort: Some(abort.clone()),
+            ..ChildIo::default()
+        };
+        let watcher = {
+            let pid_file = pid_file.clone();
+            thread::spawn(move || loop {
+                match std::fs::read_to_string(&pid_file) {
+                    Ok(pid) if pid.ends_with('\n') => {
+                        abort.abort("disk full");
+                        return pid.trim().to_string();
+                    }
+                    _ => thread::sleep(Duration::from_millis(10)),
+                }
+            })
+        };
+
+        let script = format!("sleep 60 & echo $! > {}; wait", pid_file.display());
+        let started = std::time::Instant::now();
+        let err = SystemRunner
+            .status_with(Command::new("sh").args(["-c", &script]), &child_io)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(started.elapsed() < Duration::from_secs(30));
+        // The sleep is gone, or a zombie nobody reaped yet.
+        let stat = format!("/proc/{}/stat", watcher.join().unwrap());
+        let running = || {
+            std::fs::read_to_string(&stat)
+                .is_ok_and(|stat| !stat.rsplit(')').next().unwrap().starts_with(" Z"))
+        };
+        for _ in 0..100 {
+            if !running() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!running());
+    }
+
+    #[test]
+    fn test_line_observer() {
+        let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = lines.clone();
+        let child_io = ChildIo {
+            on_line: Some(Arc::new(move |line: &str| {
+                seen.lock().unwrap().push(line.to_string())
+            })),
+            ..ChildIo::default()
+        };
+
+        let status = SystemRunner
+            .status_with(
+                Command::new("sh").args(["-c", "echo one; echo two >&2; printf three"]),
+                &child_io,
+            )
+            .unwrap();
+        assert!(status.success());
+
+        let mut lines = lines.lock().unwrap().clone();
+        lines.sort();
+        assert_eq!(lines, vec!["one", "three", "two"]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Concurrent builds of several independent git trees.
+//!
+//! gbs `--threads` only builds packages of one tree in parallel. The
+//! [`MultiBuildScheduler`] runs whole `gbs build` invocations side by side,
+//! each in its own build root, since gbs locks the build root it uses.
+
+use crate::backend::{ExecutionBackend, NativeBackend};
+use crate::runner::{self, CommandRunner};
+use crate::subcommand::GbsInvocation;
+use crate::GbsBuildOptions;
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The result of one tree's build.
+#[derive(Debug)]
+pub struct BuildOutcome {
+    pub gitdir: PathBuf,
+    /// The build root the tree was built in.
+    pub buildroot: Option<PathBuf>,
+    pub result: io::Result<ExitStatus>,
+}
+
+impl BuildOutcome {
+    pub fn succeeded(&self) -> bool {
+        matches!(&self.result, Ok(status) if status.success())
+    }
+}
+
+/// Builds several git trees concurrently.
+///
+/// Trees without a build root of their own, or whose build root is already
+/// used by an earlier tree, are built in `<buildroot>-<tree name>` next to
+/// the build root they would have used.
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::scheduler::MultiBuildScheduler;
+///
+/// let outcomes = MultiBuildScheduler::new()
+///     .workers(2)
+///     .add("/src/libfoo", options.clone())
+///     .add("/src/libbar", options)
+///     .run();
+/// assert!(outcomes.iter().all(|o| o.succeeded()));
+/// ```
+pub struct MultiBuildScheduler {
+    jobs: Vec<(PathBuf, GbsBuildOptions)>,
+    workers: usize,
+    backend: Box<dyn ExecutionBackend + Send + Sync>,
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Default for MultiBuildScheduler {
+    fn default() -> Self {
+        MultiBuildScheduler {
+            jobs: Vec::new(),
+            workers: 2,
+            backend: Box::new(NativeBackend),
+            runner: runner::default_runner(),
+        }
+    }
+}
+
+impl MultiBuildScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tree to build with `options`; the gitdir of `options` is replaced.
+    pub fn add<P: Into<PathBuf>>(mut self, gitdir: P, options: GbsBuildOptions) -> Self {
+        self.jobs.push((gitdir.into(), options));
+        self
+    }
+
+    /// Maximum number of builds running at the same time.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    pub fn backend<B: ExecutionBackend + Send + Sync + 'static>(mut self, backend: B) -> Self {
+        self.backend = Box::new(backend);
+        self
+    }
+
+    pub fn runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// The options each tree is built with, in the order the trees were added.
+    pub fn planned_options(&self) -> Vec<GbsBuildOptions> {
+        let mut used = HashSet::new();
+        self.jobs
+            .iter()
+            .map(|(gitdir, options)| {
+                let mut options = options.clone();
+                options.gitdir = Some(gitdir.clone());
+
+                let buildroot = options.buildroot_path();
+                let own = options.buildroot.is_some()
+                    && buildroot.as_ref().is_some_and(|b| used.insert(b.clone()));
+                if !own {
+                    if let Some(buildroot) = buildroot {
+                        let name = gitdir
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| "tree".to_string());
+                        let mut unique = PathBuf::from(format!("{}-{}", buildroot.display(), name));
+                        let mut n = 2;
+                        while !used.insert(unique.clone()) {
+                            unique =
+                                PathBuf::from(format!("{}-{}-{}", buildroot.display(), name, n));
+                            n += 1;
+                        }
+                        options.buildroot = Some(unique);
+                    }
+                }
+                options
+            })
+            .collect()
+    }
+
+    /// Runs all builds and returns their outcomes in the order the trees were added.
+    ///
+    /// A failing build does not stop the others.
+    pub fn run(&self) -> Vec<BuildOutcome> {
+        let planned = self.planned_options();
+        let next = AtomicUsize::new(0);
+        let outcomes: Mutex<Vec<Option<BuildOutcome>>> =
+            Mutex::new(planned.iter().map(|_| None).collect());
+
+        thread::scope(|s| {
+            for _ in 0..self.workers.min(planned.len()) {
+                s.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(options) = planned.get(index) else {
+                        break;
+                    };
+                    let result = GbsInvocation::new(options)
+                        .backend(&*self.backend)
+                        .runner(self.runner.clone())
+                        .execute();
+                    outcomes.lock().unwrap()[index] = Some(BuildOutcome {
+                        gitdir: options.gitdir.clone().unwrap_or_default(),
+                        buildroot: options.buildroot.clone(),
+                        result,
+                    });
+                });
+            }
+        });
+
+        outcomes
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_buildroots() {
+        let shared = GbsBuildOptions::builder()
+            .arch("x86_64".to_string())
+            .buildroot("/data/GBS-ROOT")
+            .build();
+        let scheduler = MultiBuildScheduler::new()
+            .add("/src/foo", shared.clone())
+            .add("/src/bar", shared.clone())
+            .add("/other/bar", shared)
+            .add(
+                "/src/baz",
+                GbsBuildOptions::builder().buildroot("/data/baz").build(),
+            );
+
+        let buildroots: Vec<_> = scheduler
+            .planned_options()
+            .into_iter()
+            .map(|o| o.buildroot.unwrap())
+            .collect();
+        assert_eq!(
+            buildroots,
+            vec![
+                PathBuf::from("/data/GBS-ROOT"),
+                PathBuf::from("/data/GBS-ROOT-bar"),
+                PathBuf::from("/data/GBS-ROOT-bar-2"),
+                PathBuf::from("/data/baz"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_run_keeps_order() {
+        use crate::runner::{Replayer, TraceEntry};
+        use crate::Keepgoing;
+
+        let gbs = |tree: &str, status: i32| TraceEntry {
+            program: "gbs".to_string(),
+            args: [
+                "build",
+                "-B",
+                &format!("/data/{}", tree),
+                &format!("/src/{}", tree),
+            ]
+            .iter()
+            .map(|a| a.to_string())
+            .collect(),
+            current_dir: None,
+            env: Vec::new(),
+            stdin: None,
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        let replayer = Replayer::from_entries(vec![gbs("b", 256), gbs("a", 0)]);
+        let options = |tree: &str| {
+            GbsBuildOptions::builder()
+                .buildroot(format!("/data/{}", tree))
+                .build()
+        };
+
+        let outcomes = MultiBuildScheduler::new()
+            .workers(3)
+            .runner(Arc::new(replayer))
+            .add("/src/a", options("a"))
+            .add("/src/b", options("b"))
+            .add(
+                "/src/c",
+                GbsBuildOptions::builder()
+                    .fail_fast(true)
+                    .keepgoing(Keepgoing::On)
+                    .build(),
+            )
+            .run();
+
+        let gitdirs: Vec<_> = outcomes.iter().map(|o| o.gitdir.clone()).collect();
+        assert_eq!(
+            gitdirs,
+            vec![
+                PathBuf::from("/src/a"),
+                PathBuf::from("/src/b"),
+                PathBuf::from("/src/c")
+            ]
+        );
+        assert!(outcomes[0].succeeded());
+        assert!(!outcomes[1].succeeded());
+        assert_eq!(
+            outcomes[2].result.as_ref().unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
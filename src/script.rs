@@ -0,0 +1,147 @@
+//! Rendering of invocations as standalone shell scripts.
+//!
+//! Builds configured in Rust sometimes have to be reproduced by hand on a
+//! build host that only has a shell. [`shell_script`] writes out a prepared
+//! [`Command`] as a bash script that runs the same process in the same
+//! directory with the same environment overrides.
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Renders `command` as a commented bash script.
+///
+/// The script changes to the command's working directory, or to the
+/// directory of the calling process when the command has none, since
+/// relative paths in the arguments are resolved against it.
+pub fn shell_script(command: &Command) -> String {
+    let mut script = String::from("#!/bin/bash\n");
+    script.push_str(&format!(
+        "# Generated by gbsw {}.\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    script.push_str("set -euo pipefail\n\n");
+
+    let dir = command
+        .get_current_dir()
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_dir().ok());
+    if let Some(dir) = dir {
+        script.push_str("# Working directory of the invocation.\n");
+        script.push_str(&format!("cd {}\n\n", quote(dir.as_os_str())));
+    }
+
+    let mut envs: Vec<_> = command.get_envs().collect();
+    if !envs.is_empty() {
+        envs.sort();
+        script.push_str("# Environment set for the invocation.\n");
+        for (key, value) in envs {
+            match value {
+                Some(value) => script.push_str(&format!(
+                    "export {}={}\n",
+                    key.to_string_lossy(),
+                    quote(value)
+                )),
+                None => script.push_str(&format!("unset {}\n", key.to_string_lossy())),
+            }
+        }
+        script.push('\n');
+    }
+
+    script.push_str("exec");
+    for word in std::iter::once(command.get_program()).chain(command.get_args()) {
+        script.push(' ');
+        script.push_str(&quote(word));
+    }
+    script.push('\n');
+    script
+}
+
+/// Quotes `word` for bash, leaving simple words as they are.
+pub fn quote(word: &OsStr) -> String {
+    let bytes = &*bytes(word);
+    let plain = |b: &u8| b.is_ascii_alphanumeric() || b"-_./=:,+@%".contains(b);
+    if !bytes.is_empty() && bytes.iter().all(plain) {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(word) => format!("'{}'", word.replace('\'', r"'\''")),
+        // ANSI-C quoting keeps bytes that are not valid UTF-8.
+        Err(_) => {
+            let mut quoted = String::from("$'");
+            for &b in bytes {
+                match b {
+                    b'\'' | b'\\' => quoted.push_str(&format!("\\{}", b as char)),
+                    0x20..=0x7e => quoted.push(b as char),
+                    _ => quoted.push_str(&format!("\\x{:02x}", b)),
+                }
+            }
+            quoted.push('\'');
+            quoted
+        }
+    }
+}
+
+#[cfg(unix)]
+fn bytes(word: &OsStr) -> Cow<'_, [u8]> {
+    Cow::Borrowed(word.as_bytes())
+}
+
+// Elsewhere, words that are not valid Unicode cannot be passed on as they
+// are; they are written with replacement characters.
+#[cfg(not(unix))]
+fn bytes(word: &OsStr) -> Cow<'_, [u8]> {
+    match word.to_string_lossy() {
+        Cow::Borrowed(word) => Cow::Borrowed(word.as_bytes()),
+        Cow::Owned(word) => Cow::Owned(word.into_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(OsStr::new("armv7l")), "armv7l");
+        assert_eq!(quote(OsStr::new("my package.spec")), "'my package.spec'");
+        assert_eq!(quote(OsStr::new("it's")), r"'it'\''s'");
+        assert_eq!(quote(OsStr::new("")), "''");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_quote_non_utf8() {
+        assert_eq!(quote(OsStr::from_bytes(b"caf\xe9")), r"$'caf\xe9'");
+    }
+
+    #[test]
+    fn test_shell_script() {
+        let mut command = Command::new("gbs");
+        command
+            .args(["build", "-A", "armv7l", "--ccache", "/src/my pkg"])
+            .current_dir("/src")
+            .env("TMPDIR", "/data/tmp");
+
+        assert_eq!(
+            shell_script(&command),
+            format!(
+                "#!/bin/bash\n\
+                 # Generated by gbsw {}.\n\
+                 set -euo pipefail\n\
+                 \n\
+                 # Working directory of the invocation.\n\
+                 cd /src\n\
+                 \n\
+                 # Environment set for the invocation.\n\
+                 export TMPDIR=/data/tmp\n\
+                 \n\
+                 exec gbs build -A armv7l --ccache '/src/my pkg'\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Generic plumbing shared by all gbs verbs.
+//!
+//! Every verb (`build`, `export`, vendor specific ones, ...) implements
+//! [`GbsSubcommand`] and is executed through [`GbsInvocation`], so new verbs
+//! can live in downstream crates without changes to this one.
+
+use crate::backend::{CommandSpec, ExecutionBackend, NativeBackend};
+#[cfg(feature = "metrics")]
+use crate::metrics::{BuildMetrics, MetricsCollector};
+use crate::runner::{self, ChildIo, CommandRunner, LineObserver, StdinResponder};
+use crate::script;
+use crate::summary::{self, BuildSummary, SummaryParser};
+#[cfg(feature = "watchdog")]
+use crate::watchdog::DiskWatchdog;
+use std::error::Error;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Returned when a subcommand is configured with an invalid combination of options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(message: &str) -> Self {
+        ValidationError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ValidationError {}
+
+impl From<ValidationError> for io::Error {
+    fn from(e: ValidationError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+/// A gbs verb together with its options.
+pub trait GbsSubcommand {
+    /// The verb passed to gbs, e.g. `build`.
+    fn name(&self) -> &str;
+
+    /// The arguments following the verb.
+    fn to_args(&self) -> Vec<String>;
+
+    /// The arguments following the verb, as passed to the process.
+    ///
+    /// Override this when arguments may hold paths that are not valid UTF-8.
+    fn to_os_args(&self) -> Vec<OsString> {
+        self.to_args().into_iter().map(OsString::from).collect()
+    }
+
+    /// Checks the options before anything is executed.
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+
+    /// Host paths the command needs access to, see [`CommandSpec::mounts`].
+    /// Relative paths are resolved against `current_dir` when it is set, see
+    /// [`GbsInvocation::current_dir`].
+    fn mount_paths(&self, current_dir: Option<&Path>) -> Vec<PathBuf> {
+        let _ = current_dir;
+        Vec::new()
+    }
+
+    /// Host paths among the [`mount_paths`](Self::mount_paths) the command
+    /// writes to, created before it runs when missing, as container engines
+    /// refuse to mount paths that do not exist.
+    fn created_paths(&self, current_dir: Option<&Path>) -> Vec<PathBuf> {
+        let _ = current_dir;
+        Vec::new()
+    }
+
+    /// Working directory of the gbs process; the caller's when `None`.
+    fn current_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Arguments of gbs itself, passed before the verb, e.g. `-c <conf>`.
+    fn global_args(&self) -> Vec<OsString> {
+        Vec::new()
+    }
+}
+
+impl<T: GbsSubcommand + ?Sized> GbsSubcommand for &T {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn to_args(&self) -> Vec<String> {
+        (**self).to_args()
+    }
+
+    fn to_os_args(&self) -> Vec<OsString> {
+        (**self).to_os_args()
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        (**self).validate()
+    }
+
+    fn mount_paths(&self, current_dir: Option<&Path>) -> Vec<PathBuf> {
+        (**self).mount_paths(current_dir)
+    }
+
+    fn created_paths(&self, current_dir: Option<&Path>) -> Vec<PathBuf> {
+        (**self).created_paths(current_dir)
+    }
+
+    fn current_dir(&self) -> Option<PathBuf> {
+        (**self).current_dir()
+    }
+
+    fn global_args(&self) -> Vec<OsString> {
+        (**self).global_args()
+    }
+}
+
+/// Executes a [`GbsSubcommand`].
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::subcommand::GbsInvocation;
+/// use gbsw::backend::ContainerBackend;
+///
+/// let status = GbsInvocation::new(&options)
+///     .backend(ContainerBackend::new("tizen/gbs:latest"))
+///     .execute()?;
+/// ```
+pub struct GbsInvocation<'a, T: GbsSubcommand> {
+    subcommand: T,
+    program: String,
+    backend: Box<dyn ExecutionBackend + 'a>,
+    runner: Arc<dyn CommandRunner>,
+    current_dir: Option<PathBuf>,
+    child_io: ChildIo,
+    #[cfg(feature = "watchdog")]
+    watchdog: Option<DiskWatchdog>,
+}
+
+impl<'a, T: GbsSubcommand> GbsInvocation<'a, T> {
+    pub fn new(subcommand: T) -> Self {
+        GbsInvocation {
+            subcommand,
+            program: "gbs".to_string(),
+            backend: Box::new(NativeBackend),
+            runner: runner::default_runner(),
+            current_dir: None,
+            child_io: ChildIo::default(),
+            #[cfg(feature = "watchdog")]
+            watchdog: None,
+        }
+    }
+
+    /// Overrides the gbs executable, e.g. for vendor forks.
+    pub fn program(mut self, program: &str) -> Self {
+        self.program = program.to_string();
+        self
+    }
+
+    pub fn backend<B: ExecutionBackend + 'a>(mut self, backend: B) -> Self {
+        self.backend = Box::new(backend);
+        self
+    }
+
+    /// Runs the command through `runner`, e.g. a [`runner::Recorder`].
+    pub fn runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Overrides the working directory requested by the subcommand.
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Answers prompts of gbs on stdin instead of inheriting it.
+    pub fn stdin(mut self, responder: StdinResponder) -> Self {
+        self.child_io.stdin = Some(responder);
+        self
+    }
+
+    /// Passes every output line of gbs to `on_line`, e.g. to follow progress.
+    pub fn on_line(mut self, on_line: LineObserver) -> Self {
+        self.child_io.on_line = Some(on_line);
+        self
+    }
+
+    /// Monitors disk usage while gbs runs.
+    #[cfg(feature = "watchdog")]
+    pub fn watchdog(mut self, watchdog: DiskWatchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    pub fn subcommand(&self) -> &T {
+        &self.subcommand
+    }
+
+    /// The composed invocation, without validating it.
+    pub fn spec(&self) -> CommandSpec {
+        let mut args = self.subcommand.global_args();
+        args.push(OsString::from(self.subcommand.name()));
+        args.extend(self.subcommand.to_os_args());
+        CommandSpec {
+            program: self.program.clone(),
+            args,
+            mounts: self.subcommand.mount_paths(self.current_dir.as_deref()),
+            current_dir: self
+                .current_dir
+                .clone()
+                .or_else(|| self.subcommand.current_dir()),
+        }
+    }
+
+    /// Validates the subcommand and prepares the process through the backend.
+    pub fn command(&self) -> Result<Command, ValidationError> {
+        self.subcommand.validate()?;
+        Ok(self.backend.command(&self.spec()))
+    }
+
+    /// Validates the subcommand and renders the process the backend would
+    /// start as a bash script, see [`script::shell_script`].
+    pub fn to_shell_script(&self) -> Result<String, ValidationError> {
+        Ok(script::shell_script(&self.command()?))
+    }
+
+    /// Runs the command with inherited stdout/stderr and waits for it.
+    pub fn execute(&self) -> io::Result<ExitStatus> {
+        self.execute_with_io(&self.child_io)
+    }
+
+    /// Like [`GbsInvocation::execute`], measuring the run with `collector`.
+    #[cfg(feature = "metrics")]
+    pub fn execute_with_metrics(
+        &self,
+        collector: &MetricsCollector,
+    ) -> io::Result<(ExitStatus, BuildMetrics)> {
+        self.subcommand.validate()?;
+        collector.measure(|hooks| {
+            let child_io = ChildIo {
+                cpu_time: hooks.cpu_time,
+                ..self.observed_by(hooks.on_line.expect("progress observer"))
+            };
+            self.execute_with_io(&child_io)
+        })
+    }
+
+    /// Like [`GbsInvocation::execute`], also returning the build summary gbs
+    /// printed. The summary is `None` when gbs stopped before printing it.
+    pub fn execute_with_summary(&self) -> io::Result<(ExitStatus, Option<BuildSummary>)> {
+        let parser = Arc::new(Mutex::new(SummaryParser::default()));
+        let status = self.execute_with_io(&self.observed_by(summary::observer(&parser)))?;
+        let summary = parser.lock().unwrap().summary();
+        Ok((status, summary))
+    }
+
+    // The configured stdio hooks, with `observer` receiving the output as well.
+    fn observed_by(&self, observer: LineObserver) -> ChildIo {
+        let on_line: LineObserver = match self.child_io.on_line.clone() {
+            Some(on_line) => Arc::new(move |line| {
+                observer(line);
+                on_line(line);
+            }),
+            None => observer,
+        };
+        ChildIo {
+            on_line: Some(on_line),
+            ..self.child_io.clone()
+        }
+    }
+
+    fn execute_with_io(&self, child_io: &ChildIo) -> io::Result<ExitStatus> {
+        #[cfg(feature = "watchdog")]
+        if let Some(watchdog) = &self.watchdog {
+            return watchdog.watch(|abort| {
+                self.run_with_io(&ChildIo {
+                    abort: abort.or_else(|| child_io.abort.clone()),
+                    ..child_io.clone()
+                })
+            });
+        }
+        self.run_with_io(child_io)
+    }
+
+    fn run_with_io(&self, child_io: &ChildIo) -> io::Result<ExitStatus> {
+        let mut command = self.command()?;
+        command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+        for path in self.subcommand.created_paths(self.current_dir.as_deref()) {
+            std::fs::create_dir_all(path)?;
+        }
+
+        if child_io.is_empty() {
+            self.runner.status(&mut command)
+        } else {
+            self.runner.status_with(&mut command, child_io)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Export {
+        outdir: Option<String>,
+    }
+
+    impl GbsSubcommand for Export {
+        fn name(&self) -> &str {
+            "export"
+        }
+
+        fn to_args(&self) -> Vec<String> {
+            match &self.outdir {
+                Some(outdir) => vec!["-o".to_string(), outdir.clone()],
+                None => Vec::new(),
+            }
+        }
+
+        fn validate(&self) -> Result<(), ValidationError> {
+            match &self.outdir {
+                Some(outdir) if outdir.is_empty() => {
+                    Err(ValidationError::new("outdir must not be empty"))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_downstream_subcommand() {
+        let export = Export {
+            outdir: Some("/tmp/out".to_string()),
+        };
+        let invocation = GbsInvocation::new(&export).program("gbs-vendor");
+        let command = invocation.command().unwrap();
+
+        assert_eq!(command.get_program(), "gbs-vendor");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["export", "-o", "/tmp/out"]
+        );
+    }
+
+    #[test]
+    fn test_validation_runs_before_execution() {
+        let export = Export {
+            outdir: Some(String::new()),
+        };
+        let err = GbsInvocation::new(export).execute().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "outdir must not be empty");
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_execute_with_summary() {
+        let replayer = runner::Replayer::from_entries(vec![runner::TraceEntry {
+            program: "gbs".to_string(),
+            args: vec!["export".to_string()],
+            current_dir: None,
+            env: Vec::new(),
+            stdin: None,
+            status: 256,
+            stdout: "info: *** Build Status Summary ***\n\
+                     === Total succeeded built packages: (1) ===\n\
+                     === the following packages failed to build due to rpmbuild issue (1) ===\n\
+                     foo\n"
+                .to_string(),
+            stderr: String::new(),
+        }]);
+        let (status, summary) = GbsInvocation::new(Export { outdir: None })
+            .runner(Arc::new(replayer))
+            .execute_with_summary()
+            .unwrap();
+        assert!(!status.success());
+        let summary = summary.unwrap();
+        assert_eq!(summary.succeeded, 1);
+        assert_eq!(summary.build_errors, vec!["foo"]);
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_execute_through_replayer() {
+        let replayer = runner::Replayer::from_entries(vec![runner::TraceEntry {
+            program: "gbs".to_string(),
+            args: vec!["export".to_string()],
+            current_dir: None,
+            env: Vec::new(),
+            stdin: None,
+            status: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        }]);
+        let status = GbsInvocation::new(Export { outdir: None })
+            .runner(Arc::new(replayer))
+            .execute()
+            .unwrap();
+        assert!(status.success());
+    }
+}
@@ -0,0 +1,150 @@
+//! The build status summary gbs prints when a build ends.
+//!
+//! ```text
+//! info: *** Build Status Summary ***
+//! === Total succeeded built packages: (12) ===
+//! === the following packages failed to build because export source files to build environment failed (1) ===
+//! bar
+//! === the following packages failed to build because dependency expansion failed (1) ===
+//! baz
+//! === the following packages failed to build due to rpmbuild issue (1) ===
+//! qux
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use crate::runner::LineObserver;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildSummary {
+    pub succeeded: usize,
+    pub export_errors: Vec<String>,
+    pub expansion_errors: Vec<String>,
+    pub build_errors: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Section {
+    Export,
+    Expansion,
+    Build,
+    Other,
+}
+
+impl BuildSummary {
+    /// Number of packages gbs tried to build.
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.export_errors.len() + self.expansion_errors.len() + self.build_errors.len()
+    }
+
+    /// Parses the summary out of the complete output of a build, or returns
+    /// `None` when the output has no summary, e.g. because gbs failed early.
+    pub fn parse(output: &str) -> Option<BuildSummary> {
+        let mut parser = SummaryParser::default();
+        output.lines().for_each(|line| parser.feed(line));
+        parser.summary
+    }
+}
+
+/// Collects the summary from output lines as they arrive.
+#[derive(Default)]
+pub(crate) struct SummaryParser {
+    summary: Option<BuildSummary>,
+    section: Option<Section>,
+}
+
+impl SummaryParser {
+    pub(crate) fn feed(&mut self, line: &str) {
+        let line = line.trim();
+        if line.contains("*** Build Status Summary ***") {
+            self.summary = Some(BuildSummary::default());
+            self.section = None;
+            return;
+        }
+        let Some(summary) = self.summary.as_mut() else {
+            return;
+        };
+
+        if let Some(header) = line.strip_prefix("===") {
+            self.section = Some(if header.contains("succeeded") {
+                summary.succeeded = count(header).unwrap_or(0);
+                Section::Other
+            } else if header.contains("export") {
+                Section::Export
+            } else if header.contains("dependency expansion") {
+                Section::Expansion
+            } else if header.contains("rpmbuild") {
+                Section::Build
+            } else {
+                Section::Other
+            });
+            return;
+        }
+        if line.is_empty() || line.contains(':') {
+            // "info: ..." lines end the package lists.
+            self.section = None;
+            return;
+        }
+
+        let list = match self.section {
+            Some(Section::Export) => &mut summary.export_errors,
+            Some(Section::Expansion) => &mut summary.expansion_errors,
+            Some(Section::Build) => &mut summary.build_errors,
+            _ => return,
+        };
+        list.push(line.to_string());
+    }
+
+    pub(crate) fn summary(&self) -> Option<BuildSummary> {
+        self.summary.clone()
+    }
+}
+
+// Reads the "(12)" in "Total succeeded built packages: (12) ===".
+fn count(header: &str) -> Option<usize> {
+    let start = header.rfind('(')?;
+    let end = header[start..].find(')')? + start;
+    header[start + 1..end].trim().parse().ok()
+}
+
+/// Returns an observer feeding `parser`, for [`crate::runner::ChildIo::on_line`].
+pub(crate) fn observer(parser: &Arc<Mutex<SummaryParser>>) -> LineObserver {
+    let parser = parser.clone();
+    Arc::new(move |line| parser.lock().unwrap().feed(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_summary() {
+        let output = "\
+info: finished building qux
+info: *** Build Status Summary ***
+=== Total succeeded built packages: (12) ===
+=== the following packages failed to build because export source files to build environment failed (1) ===
+bar
+=== the following packages failed to build because dependency expansion failed (1) ===
+baz
+=== the following packages failed to build due to rpmbuild issue (2) ===
+qux
+quux
+info: generated html format report:
+     /home/user/GBS-ROOT/local/repos/tizen/armv7l/index.html
+info: Done
+";
+        let summary = BuildSummary::parse(output).unwrap();
+        assert_eq!(summary.succeeded, 12);
+        assert_eq!(summary.export_errors, vec!["bar"]);
+        assert_eq!(summary.expansion_errors, vec!["baz"]);
+        assert_eq!(summary.build_errors, vec!["qux", "quux"]);
+        assert_eq!(summary.total(), 16);
+
+        assert_eq!(BuildSummary::parse("error: no spec file found"), None);
+    }
+}
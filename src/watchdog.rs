@@ -0,0 +1,243 @@
+//! Disk space monitoring while a build runs.
+//!
+//! A full disk surfaces as a cryptic rpmbuild failure hours into a build.
+//! [`DiskWatchdog`] samples the build root while gbs runs and warns, or
+//! kills gbs, as soon as a threshold is crossed.
+
+use crate::metrics::disk_usage;
+use crate::runner::{self, AbortSignal, CommandRunner};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// What to do when a threshold is crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatchdogAction {
+    /// Log the problem as a warning once and let the build go on.
+    #[default]
+    Warn,
+    /// Kill gbs; the execution returns an `Interrupted` error describing
+    /// the problem.
+    Abort,
+}
+
+/// Watches the disk usage of a build root.
+///
+/// # Example
+///
+/// ```ignore
+/// use gbsw::watchdog::{DiskWatchdog, WatchdogAction};
+///
+/// let watchdog = DiskWatchdog::new(options.buildroot_path().unwrap())
+///     .min_free(10 * 1024 * 1024 * 1024)
+///     .action(WatchdogAction::Abort);
+/// GbsInvocation::new(&options).watchdog(watchdog).execute()?;
+/// ```
+#[derive(Clone)]
+pub struct DiskWatchdog {
+    path: PathBuf,
+    max_usage: Option<u64>,
+    min_free: Option<u64>,
+    interval: Duration,
+    action: WatchdogAction,
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl DiskWatchdog {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        DiskWatchdog {
+            path: path.as_ref().to_path_buf(),
+            max_usage: None,
+            min_free: None,
+            interval: Duration::from_secs(60),
+            action: WatchdogAction::default(),
+            runner: runner::default_runner(),
+        }
+    }
+
+    /// Maximum size of the watched directory, in bytes.
+    pub fn max_usage(mut self, bytes: u64) -> Self {
+        self.max_usage = Some(bytes);
+        self
+    }
+
+    /// Minimum free space on the file system of the watched directory, in bytes.
+    pub fn min_free(mut self, bytes: u64) -> Self {
+        self.min_free = Some(bytes);
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn action(mut self, action: WatchdogAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Runs `df` through `runner`.
+    pub fn runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Samples the directory once and describes the crossed threshold, if any.
+    pub fn check(&self) -> Option<String> {
+        if let Some(max_usage) = self.max_usage {
+            if let Ok(usage) = disk_usage(&self.path) {
+                if usage > max_usage {
+                    return Some(format!(
+                        "'{}' uses {} bytes, more than the allowed {}",
+                        self.path.display(),
+                        usage,
+                        max_usage
+                    ));
+                }
+            }
+        }
+        if let Some(min_free) = self.min_free {
+            if let Some(free) = self.free_space() {
+                if free < min_free {
+                    return Some(format!(
+                        "Only {} bytes left on the file system of '{}', less than the required {}",
+                        free,
+                        self.path.display(),
+                        min_free
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    // The build root may not exist before gbs creates it, so the closest
+    // existing ancestor is asked instead.
+    fn free_space(&self) -> Option<u64> {
+        let existing = self.path.ancestors().find(|p| p.exists())?;
+        let output = self
+            .runner
+            .output(Command::new("df").arg("-Pk").arg(existing))
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        parse_df_available(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Samples the directory while `run` executes. `run` receives the
+    /// signal to pass to the runner when the action is [`WatchdogAction::Abort`].
+    pub(crate) fn watch<F>(&self, run: F) -> io::Result<ExitStatus>
+    where
+        F: FnOnce(Option<AbortSignal>) -> io::Result<ExitStatus>,
+    {
+        let abort = match self.action {
+            WatchdogAction::Abort => Some(AbortSignal::new()),
+            WatchdogAction::Warn => None,
+        };
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        thread::scope(|s| {
+            let signal = abort.clone();
+            s.spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(self.interval)
+                {
+                    let Some(problem) = self.check() else {
+                        continue;
+                    };
+                    match &signal {
+                        Some(signal) => {
+                            log::error!("Aborting build: {}", problem);
+                            signal.abort(&problem);
+                        }
+                        None => log::warn!("{}", problem),
+                    }
+                    // Report a crossed threshold once.
+                    break;
+                }
+            });
+            let status = run(abort.clone());
+            let _ = stop.send(());
+            status
+        })
+        .and_then(|status| {
+            // The build may have ended on its own before it was killed,
+            // or run through a runner that cannot kill it.
+            match abort.and_then(|abort| abort.reason()) {
+                Some(reason) => Err(io::Error::new(io::ErrorKind::Interrupted, reason)),
+                None => Ok(status),
+            }
+        })
+    }
+}
+
+// Reads the "Available" column of `df -Pk`, in bytes.
+fn parse_df_available(out: &str) -> Option<u64> {
+    let fields: Vec<&str> = out.lines().nth(1)?.split_whitespace().collect();
+    let available: u64 = fields.get(3)?.parse().ok()?;
+    Some(available * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_df_available() {
+        let out = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                   /dev/sda1        102400000  90000000  12400000      88% /\n";
+        assert_eq!(parse_df_available(out), Some(12400000 * 1024));
+        assert_eq!(parse_df_available("garbage"), None);
+    }
+
+    #[test]
+    fn test_abort_on_max_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("rpm"), vec![0u8; 64 * 1024]).unwrap();
+
+        let watchdog = DiskWatchdog::new(dir.path())
+            .max_usage(1024)
+            .interval(Duration::from_millis(10))
+            .action(WatchdogAction::Abort);
+        assert!(watchdog.check().is_some());
+
+        let err = watchdog
+            .watch(|abort| {
+                let child_io = runner::ChildIo {
+                    abort,
+                    ..runner::ChildIo::default()
+                };
+                runner::SystemRunner.status_with(Command::new("sleep").arg("10"), &child_io)
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_abort_is_reported_when_the_build_ends_first() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("rpm"), vec![0u8; 64 * 1024]).unwrap();
+
+        let watchdog = DiskWatchdog::new(dir.path())
+            .max_usage(1024)
+            .interval(Duration::from_millis(10))
+            .action(WatchdogAction::Abort);
+
+        // The command is not killed, like with a replaying runner.
+        let err = watchdog
+            .watch(|abort| {
+                while abort.as_ref().unwrap().reason().is_none() {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                runner::SystemRunner.status(&mut Command::new("true"))
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(err.to_string().contains("more than the allowed 1024"));
+    }
+}